@@ -1,84 +1,409 @@
 use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use std::sync::{Arc, Mutex};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use crate::feedback::{Monitor, MonitorFeed};
+use crate::recovery::Spool;
 
 pub struct AudioRecorder {
     samples: Arc<Mutex<Vec<f32>>>,
     stream: Option<cpal::Stream>,
+    /// Declared after `stream` so the stream (and its sender) is dropped first.
+    spool: Option<Spool>,
     device_sample_rate: u32,
+    /// Name of the device to record from; empty = the default input.
+    device_name: String,
+    /// The device and its format, looked up once in `new` and reused by
+    /// every `start`, so a recorder kept between recordings starts without
+    /// probing the device again.
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    /// Raw samples copied out by `snapshot_since_into` for resampling,
+    /// kept so streaming ticks don't allocate a fresh copy each time.
+    scratch: Mutex<Vec<f32>>,
+    resample: ResampleQuality,
+    /// The current recording's options, for `reopen`.
+    options: CaptureOptions,
+    /// Times `reopen` carried on the current recording.
+    reopens: u32,
+    /// Plays the input back while recording, if `options.monitor` asks.
+    monitor: Option<Monitor>,
 }
 
-const TARGET_SAMPLE_RATE: u32 = 16_000;
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
 
-impl AudioRecorder {
-    pub fn new() -> Result<Self> {
-        let host = cpal::default_host();
+/// Peak level auto-gain aims for: -3 dBFS.
+const AUTO_GAIN_TARGET: f32 = 0.708;
+/// Below this peak (-40 dBFS) the buffer is treated as silence/noise and left alone.
+const AUTO_GAIN_FLOOR: f32 = 0.01;
+/// Never amplify by more than this (+26 dB), so faint noise isn't blown up.
+const AUTO_GAIN_MAX: f32 = 20.0;
+/// Range of the manual `input_gain`: -20 dB to +20 dB.
+const INPUT_GAIN_MIN: f32 = 0.1;
+const INPUT_GAIN_MAX: f32 = 10.0;
+/// Samples this close to full scale count as clipped; resampling rounds
+/// off the flat tops a little.
+const CLIP_LEVEL: f32 = 0.99;
+
+/// Times a recording is carried on after its stream failed; a device that
+/// keeps failing ends it after all.
+const MAX_STREAM_REOPENS: u32 = 3;
+
+/// Cutoff of the optional rumble filter (desk bumps, HVAC, mic handling).
+const HIGHPASS_CUTOFF_HZ: f32 = 80.0;
+
+/// Frame length for the energy-based voice activity check.
+const VAD_FRAME: Duration = Duration::from_millis(30);
+/// Frames with an RMS above this (about -36 dBFS) count as speech.
+const VAD_RMS_THRESHOLD: f32 = 0.015;
+/// `gate_quiet` measures against the level this share of a buffer's speech
+/// frames stays below, so a few shouts don't set it.
+const GATE_REFERENCE_PERCENTILE: f32 = 0.9;
+/// Frames `gate_quiet` keeps on either side of a loud one (150ms), so the
+/// quieter onsets and tails of words aren't cut off.
+const GATE_HANGOVER_FRAMES: usize = 5;
+
+/// Which input channel(s) make up the mono signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputChannel {
+    /// Average all channels.
+    #[default]
+    Mono,
+    /// Use only this channel (0 = first/left), e.g. the one input of an
+    /// audio interface that has the mic plugged in.
+    Channel(u16),
+}
+
+/// Per-session capture options.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CaptureOptions {
+    /// Pass samples through a high-pass filter as they arrive, so snapshots,
+    /// the VAD and the final buffer all see the same filtered signal.
+    pub highpass: bool,
+    /// Also write samples to a WAV file as they arrive (see `recovery`),
+    /// which `stop` deletes again.
+    pub spool: bool,
+    pub channel: InputChannel,
+    /// How snapshots and the final buffer are brought to 16kHz.
+    pub resample: ResampleQuality,
+    /// Play the input on the default output at this volume while
+    /// recording (see `feedback::Monitor`).
+    pub monitor: Option<f32>,
+}
+
+/// Resampler used to bring audio to 16kHz.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResampleQuality {
+    /// Linear interpolation: two multiply-adds per output sample, but
+    /// anything above 8kHz in the capture aliases down into the speech band.
+    #[default]
+    Fast,
+    /// Windowed sinc with its cutoff just below 8kHz: no aliasing, at
+    /// around 100 multiply-adds per output sample from a 48kHz device.
+    High,
+}
+
+/// How much speech the recording holds and how long it has been quiet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VoiceActivity {
+    pub speech: Duration,
+    pub trailing_silence: Duration,
+}
+
+/// Finds pauses in a recording as it grows, scanning each stretch of
+/// 16kHz audio once. A silence that runs across several scans counts as
+/// one pause.
+#[derive(Debug, Clone, Default)]
+pub struct PauseDetector {
+    /// Recording position (16kHz samples) scanned up to, a frame boundary.
+    scanned: usize,
+    /// Silence at the end of what was scanned.
+    silence: Duration,
+}
+
+impl PauseDetector {
+    /// Scan the part of `audio` not seen before, where `audio` begins at
+    /// `offset` in the recording. True if speech resumed after at least
+    /// `gap` of silence somewhere in it.
+    pub fn resumed_after(&mut self, audio: &[f32], offset: usize, gap: Duration) -> bool {
+        let frame_len = vad_frame_len(TARGET_SAMPLE_RATE);
+        let from = self.scanned.saturating_sub(offset).min(audio.len());
+        let frames = audio[from..].chunks_exact(frame_len);
+        self.scanned = offset + from + frames.len() * frame_len;
+        let mut resumed = false;
+        for frame in frames {
+            if rms(frame) > VAD_RMS_THRESHOLD {
+                resumed |= self.silence >= gap;
+                self.silence = Duration::ZERO;
+            } else {
+                self.silence += VAD_FRAME;
+            }
+        }
+        resumed
+    }
+}
+
+/// An input device and the format it captures in.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// cpal's name for it, e.g. `"f32"` or `"i16"`.
+    pub sample_format: String,
+}
+
+/// A device that can be recorded from.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputDevice {
+    pub name: String,
+    /// Captures what the system plays rather than a microphone: a
+    /// PulseAudio/PipeWire monitor source, or an output device on Windows
+    /// (WASAPI loopback).
+    pub loopback: bool,
+}
+
+/// PulseAudio/PipeWire name monitor sources by the sink they capture.
+fn is_monitor(name: &str) -> bool {
+    name.to_lowercase().contains("monitor")
+}
+
+/// Whether `device`, opened for input, captures what the system plays
+/// (see `InputDevice::loopback`).
+fn is_loopback(device: &cpal::Device) -> bool {
+    let Ok(name) = device.name() else {
+        return false;
+    };
+    if is_monitor(&name) {
+        return true;
+    }
+    cfg!(target_os = "windows")
+        && cpal::default_host()
+            .output_devices()
+            .is_ok_and(|mut outputs| outputs.any(|d| d.name().is_ok_and(|n| n == name)))
+}
+
+/// Devices `AudioRecorder::new` can open, by name.
+pub fn input_devices() -> Result<Vec<InputDevice>> {
+    let host = cpal::default_host();
+    let mut devices: Vec<InputDevice> = host
+        .input_devices()?
+        .filter_map(|device| device.name().ok())
+        .map(|name| InputDevice {
+            loopback: is_monitor(&name),
+            name,
+        })
+        .collect();
+    if cfg!(target_os = "windows") {
+        let outputs = host
+            .output_devices()?
+            .filter_map(|device| device.name().ok());
+        devices.extend(outputs.map(|name| InputDevice {
+            name,
+            loopback: true,
+        }));
+    }
+    Ok(devices)
+}
+
+/// The input device called `name` (the default one if it's empty) and its
+/// capture format.
+fn open_device(name: &str) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    let host = cpal::default_host();
+    if name.is_empty() {
         let device = host
             .default_input_device()
             .context("No input audio device found")?;
-
         let config = device.default_input_config()?;
+        return Ok((device, config));
+    }
+    let named = |device: &cpal::Device| device.name().is_ok_and(|n| n == name);
+    if let Some(device) = host.input_devices()?.find(named) {
+        let config = device.default_input_config()?;
+        return Ok((device, config));
+    }
+    // WASAPI records an output device's mix when an input stream is built
+    // on it.
+    if cfg!(target_os = "windows") {
+        if let Some(device) = host.output_devices()?.find(named) {
+            let config = device.default_output_config()?;
+            return Ok((device, config));
+        }
+    }
+    anyhow::bail!("Input device {name:?} not found")
+}
+
+/// Fail unless `channel` exists on a device with `channels` channels.
+fn check_channel(channel: InputChannel, channels: u16) -> Result<()> {
+    if let InputChannel::Channel(n) = channel {
+        if n >= channels {
+            anyhow::bail!(
+                "Input channel {n} doesn't exist: the device has {channels} channel(s), numbered from 0"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// What a pre-roll capture records; a change means restarting it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreRollConfig {
+    /// As for `AudioRecorder::new`.
+    pub device: String,
+    pub channel: InputChannel,
+    pub highpass: bool,
+    /// How much audio to keep.
+    pub length: Duration,
+}
+
+/// Capture that runs while idle and keeps only its last `length` of
+/// audio, so a recording can begin with what was said just before it was
+/// started (see `AudioRecorder::prepend`).
+pub struct PreRoll {
+    config: PreRollConfig,
+    samples: Arc<Mutex<Vec<f32>>>,
+    /// `config.length` in device samples.
+    limit: usize,
+    sample_rate: u32,
+    _stream: cpal::Stream,
+}
+
+impl PreRoll {
+    pub fn start(config: PreRollConfig) -> Result<Self> {
+        let (device, supported) = open_device(&config.device)?;
+        check_channel(config.channel, supported.channels())?;
+        let sample_rate = supported.sample_rate().0;
+        let limit = (sample_rate as f64 * config.length.as_secs_f64()) as usize;
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        let sink = Sink {
+            samples: samples.clone(),
+            channel: config.channel,
+            filter: config
+                .highpass
+                .then(|| HighPass::new(sample_rate, HIGHPASS_CUTOFF_HZ)),
+            spool: None,
+            monitor: None,
+            limit: Some(limit),
+            // Logged by the stream; a dead pre-roll just stays empty.
+            on_error: Box::new(|_| {}),
+        };
+        let stream = open_stream(&device, &supported, sink)?;
+        stream.play()?;
+        log::info!("Pre-roll capture started ({:?})", config.length);
+        Ok(Self {
+            config,
+            samples,
+            limit,
+            sample_rate,
+            _stream: stream,
+        })
+    }
+
+    pub fn config(&self) -> &PreRollConfig {
+        &self.config
+    }
+}
+
+/// Query the input device called `name` (the default one if it's empty)
+/// without opening a stream.
+pub fn input_info(name: &str) -> Result<DeviceInfo> {
+    let (device, config) = open_device(name)?;
+    Ok(device_info(&device, &config))
+}
+
+fn device_info(device: &cpal::Device, config: &cpal::SupportedStreamConfig) -> DeviceInfo {
+    DeviceInfo {
+        name: device.name().unwrap_or_default(),
+        sample_rate: config.sample_rate().0,
+        channels: config.channels(),
+        sample_format: config.sample_format().to_string(),
+    }
+}
+
+impl AudioRecorder {
+    /// Recorder for the input device called `device_name` (see
+    /// `input_devices`), or the default one if it's empty.
+    pub fn new(device_name: &str) -> Result<Self> {
+        let (device, config) = open_device(device_name)?;
+        let info = device_info(&device, &config);
         log::info!(
-            "Audio device: {}, sample rate: {}, channels: {}",
-            device.name().unwrap_or_default(),
-            config.sample_rate().0,
-            config.channels()
+            "Audio device: {}, sample rate: {}, channels: {}, format: {}",
+            info.name,
+            info.sample_rate,
+            info.channels,
+            info.sample_format
         );
 
         Ok(Self {
             samples: Arc::new(Mutex::new(Vec::new())),
             stream: None,
-            device_sample_rate: config.sample_rate().0,
+            spool: None,
+            device_sample_rate: info.sample_rate,
+            device_name: device_name.to_string(),
+            device,
+            config,
+            scratch: Mutex::new(Vec::new()),
+            resample: ResampleQuality::default(),
+            options: CaptureOptions::default(),
+            reopens: 0,
+            monitor: None,
         })
     }
 
-    pub fn start(&mut self) -> Result<()> {
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .context("No input audio device found")?;
-
-        let config = device.default_input_config()?;
-        self.device_sample_rate = config.sample_rate().0;
-        let channels = config.channels() as usize;
+    /// The `device_name` this recorder was made for.
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
 
-        let samples = self.samples.clone();
-        samples.lock().unwrap().clear();
+    /// Whether the device looked up in `new` is still the one to record
+    /// from. Only the default input can change under a recorder; this asks
+    /// for its name, which is much cheaper than probing its format. A named
+    /// device that is gone shows up as `start` failing.
+    pub fn is_current(&self) -> bool {
+        if !self.device_name.is_empty() {
+            return true;
+        }
+        let default = cpal::default_host()
+            .default_input_device()
+            .and_then(|device| device.name().ok());
+        default.is_some() && default == self.device.name().ok()
+    }
 
-        let err_fn = |err: cpal::StreamError| {
-            log::error!("Audio stream error: {err}");
-        };
+    /// Start capturing. Fails if `options.channel` doesn't exist on the
+    /// device. Can be called again after `stop`, for the next recording.
+    ///
+    /// `on_error` is called once, from the audio thread, if the stream dies
+    /// mid-recording (e.g. the USB mic was unplugged). Samples captured
+    /// until then stay in the buffer.
+    pub fn start<F>(&mut self, options: CaptureOptions, on_error: F) -> Result<()>
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        self.device_sample_rate = self.config.sample_rate().0;
+        self.resample = options.resample;
+        check_channel(options.channel, self.config.channels())?;
+        self.options = options;
+        self.reopens = 0;
+        self.samples.lock().clear();
 
-        let stream = match config.sample_format() {
-            cpal::SampleFormat::F32 => device.build_input_stream(
-                &config.into(),
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    let mono: Vec<f32> = data
-                        .chunks(channels)
-                        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-                        .collect();
-                    samples.lock().unwrap().extend_from_slice(&mono);
-                },
-                err_fn,
-                None,
-            )?,
-            cpal::SampleFormat::I16 => device.build_input_stream(
-                &config.into(),
-                move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                    let mono: Vec<f32> = data
-                        .chunks(channels)
-                        .map(|frame| {
-                            frame.iter().map(|&s| s as f32 / i16::MAX as f32).sum::<f32>()
-                                / channels as f32
-                        })
-                        .collect();
-                    samples.lock().unwrap().extend_from_slice(&mono);
-                },
-                err_fn,
-                None,
-            )?,
-            format => anyhow::bail!("Unsupported sample format: {format:?}"),
+        let rate = self.device_sample_rate;
+        // Recording without a spool beats not recording at all.
+        self.spool = match options.spool.then(|| Spool::create(rate)) {
+            Some(Ok(spool)) => Some(spool),
+            Some(Err(e)) => {
+                log::error!("Recording spool disabled: {e}");
+                None
+            }
+            None => None,
         };
+        self.start_monitor();
+        let stream = open_stream(&self.device, &self.config, self.sink(on_error))?;
 
         stream.play()?;
         self.stream = Some(stream);
@@ -86,20 +411,165 @@ impl AudioRecorder {
         Ok(())
     }
 
+    /// Carry on a recording whose stream failed (see `start`) on the device
+    /// as it is now: the named one again, or whatever the default input is
+    /// by now, e.g. a headset that just connected. What was captured stays.
+    /// If the new stream runs at another rate, the buffer is resampled to
+    /// it first, so the whole recording is always at `device_sample_rate`.
+    /// Gives up after `MAX_STREAM_REOPENS` in one recording.
+    pub fn reopen<F>(&mut self, on_error: F) -> Result<DeviceInfo>
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        self.stream.take();
+        if self.reopens >= MAX_STREAM_REOPENS {
+            anyhow::bail!("The audio stream failed {MAX_STREAM_REOPENS} times");
+        }
+        self.reopens += 1;
+        let (device, config) = open_device(&self.device_name)?;
+        check_channel(self.options.channel, config.channels())?;
+        let rate = config.sample_rate().0;
+        if rate != self.device_sample_rate {
+            log::warn!(
+                "Input switched from {}Hz to {rate}Hz mid-recording",
+                self.device_sample_rate
+            );
+            switch_rate(
+                &mut self.samples.lock(),
+                self.device_sample_rate,
+                rate,
+                self.resample,
+            );
+            // Its header has the old rate; what it holds is in memory too.
+            if self.spool.take().is_some() {
+                log::warn!("Recording spool stopped: the sample rate changed");
+            }
+        }
+        let rate_changed = rate != self.device_sample_rate;
+        self.device = device;
+        self.config = config;
+        self.device_sample_rate = rate;
+        if rate_changed {
+            self.start_monitor();
+        }
+        let stream = open_stream(&self.device, &self.config, self.sink(on_error))?;
+        stream.play()?;
+        self.stream = Some(stream);
+        let info = device_info(&self.device, &self.config);
+        log::info!("Recording continues on {} at {rate}Hz", info.name);
+        Ok(info)
+    }
+
+    /// (Re)start monitoring for the current recording at the device's rate,
+    /// if its options ask for it. Without an output to play on, the
+    /// recording goes on unmonitored. A loopback input isn't monitored: it
+    /// would record its own playback.
+    fn start_monitor(&mut self) {
+        self.monitor = None;
+        let Some(volume) = self.options.monitor else {
+            return;
+        };
+        if is_loopback(&self.device) {
+            log::warn!("Input monitoring skipped: the input records the output");
+            return;
+        }
+        match Monitor::start(self.device_sample_rate, volume) {
+            Ok(monitor) => self.monitor = Some(monitor),
+            Err(e) => log::warn!("Input monitoring unavailable: {e:#}"),
+        }
+    }
+
+    /// Where a stream of the current recording delivers its samples, with
+    /// a fresh filter state for every stream.
+    fn sink<F>(&self, on_error: F) -> Sink
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        let rate = self.device_sample_rate;
+        Sink {
+            samples: self.samples.clone(),
+            channel: self.options.channel,
+            filter: self
+                .options
+                .highpass
+                .then(|| HighPass::new(rate, HIGHPASS_CUTOFF_HZ)),
+            spool: self.spool.as_ref().and_then(Spool::sender),
+            monitor: self.monitor.as_ref().map(Monitor::feed),
+            limit: None,
+            on_error: Box::new(on_error),
+        }
+    }
+
+    /// Put the audio `preroll` captured before `start` at the front of the
+    /// recording. Both streams ran side by side for a moment, so as many
+    /// samples as the recording already holds are dropped from the end of
+    /// the pre-roll; the seam is then off by a callback's worth at most.
+    /// The pre-roll isn't spooled for crash recovery.
+    pub fn prepend(&self, preroll: PreRoll) {
+        if preroll.sample_rate != self.device_sample_rate {
+            log::warn!("Pre-roll dropped: it was captured at a different sample rate");
+            return;
+        }
+        let before = preroll.samples.lock();
+        let mut samples = self.samples.lock();
+        let end = before.len().saturating_sub(samples.len());
+        let start = end.saturating_sub(preroll.limit);
+        samples.splice(0..0, before[start..end].iter().copied());
+    }
+
     /// Snapshot current audio buffer (for streaming) — returns 16kHz mono f32.
     pub fn snapshot(&self) -> Vec<f32> {
-        let raw = self.samples.lock().unwrap().clone();
+        self.snapshot_since(0)
+    }
+
+    /// Like `snapshot`, but only from `start` (a 16kHz sample offset) on, so
+    /// streaming ticks on a long recording don't copy and resample all of it.
+    pub fn snapshot_since(&self, start: usize) -> Vec<f32> {
+        let mut out = Vec::new();
+        self.snapshot_since_into(start, &mut out);
+        out
+    }
+
+    /// Like `snapshot_since`, but into `out` (replacing its contents), so a
+    /// caller that snapshots repeatedly can keep reusing one buffer. Once
+    /// the buffers have grown to the window size, a tick allocates nothing.
+    pub fn snapshot_since_into(&self, start: usize, out: &mut Vec<f32>) {
+        let from =
+            (start as u64 * self.device_sample_rate as u64 / TARGET_SAMPLE_RATE as u64) as usize;
+        let copy = |dst: &mut Vec<f32>| {
+            let samples = self.samples.lock();
+            dst.clear();
+            dst.extend_from_slice(&samples[from.min(samples.len())..]);
+        };
         if self.device_sample_rate == TARGET_SAMPLE_RATE {
-            raw
+            copy(out);
         } else {
-            resample(&raw, self.device_sample_rate, TARGET_SAMPLE_RATE)
+            // Copy out first so the audio callback isn't blocked while resampling.
+            let mut raw = self.scratch.lock();
+            copy(&mut raw);
+            let (rate, quality) = (self.device_sample_rate, self.resample);
+            resample_into(&raw, rate, TARGET_SAMPLE_RATE, quality, out);
         }
     }
 
+    /// A handle to read the buffer's size from other threads.
+    pub fn gauge(&self) -> BufferGauge {
+        BufferGauge(self.samples.clone())
+    }
+
+    /// Energy VAD over the raw buffer, without resampling or copying it, so
+    /// it's cheap enough to poll several times a second.
+    pub fn voice_activity(&self) -> VoiceActivity {
+        let raw = self.samples.lock();
+        voice_activity(&raw, self.device_sample_rate)
+    }
+
     /// Stop recording and return 16kHz mono f32 samples.
     pub fn stop(&mut self) -> Vec<f32> {
         self.stream.take(); // drops the stream, stopping recording
-        let raw = std::mem::take(&mut *self.samples.lock().unwrap());
+        self.spool.take(); // clean stop: nothing to recover
+        self.monitor.take();
+        let raw = std::mem::take(&mut *self.samples.lock());
         log::info!(
             "Recording stopped: {} samples at {}Hz",
             raw.len(),
@@ -109,24 +579,691 @@ impl AudioRecorder {
         if self.device_sample_rate == TARGET_SAMPLE_RATE {
             raw
         } else {
-            resample(&raw, self.device_sample_rate, TARGET_SAMPLE_RATE)
+            resample(
+                &raw,
+                self.device_sample_rate,
+                TARGET_SAMPLE_RATE,
+                self.resample,
+            )
         }
     }
 }
 
-fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-    let ratio = from_rate as f64 / to_rate as f64;
-    let output_len = (input.len() as f64 / ratio) as usize;
-    (0..output_len)
-        .map(|i| {
-            let src = i as f64 * ratio;
-            let idx = src as usize;
-            let frac = src - idx as f64;
-            if idx + 1 < input.len() {
-                (input[idx] as f64 * (1.0 - frac) + input[idx + 1] as f64 * frac) as f32
-            } else {
-                input[idx.min(input.len() - 1)]
+/// Read-only view of a recorder's buffer (see `AudioRecorder::gauge`). It
+/// stays valid after the recorder is gone, reading empty.
+#[derive(Clone)]
+pub struct BufferGauge(Arc<Mutex<Vec<f32>>>);
+
+impl BufferGauge {
+    /// Samples held (at the device's rate) and bytes allocated for them.
+    pub fn usage(&self) -> (usize, usize) {
+        let samples = self.0.lock();
+        (
+            samples.len(),
+            samples.capacity() * std::mem::size_of::<f32>(),
+        )
+    }
+}
+
+/// Where a stream delivers its samples and reports failure.
+struct Sink {
+    samples: Arc<Mutex<Vec<f32>>>,
+    channel: InputChannel,
+    filter: Option<HighPass>,
+    spool: Option<mpsc::Sender<Vec<f32>>>,
+    monitor: Option<MonitorFeed>,
+    /// Keep only about this many samples (a pre-roll's length).
+    limit: Option<usize>,
+    on_error: Box<dyn FnOnce(String) + Send>,
+}
+
+/// Open an input stream on `device` in its native sample format.
+fn open_stream(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    sink: Sink,
+) -> Result<cpal::Stream> {
+    let stream_config: cpal::StreamConfig = config.clone().into();
+    let stream = match config.sample_format() {
+        SampleFormat::I8 => build_stream::<i8>(device, &stream_config, sink)?,
+        SampleFormat::I16 => build_stream::<i16>(device, &stream_config, sink)?,
+        SampleFormat::I32 => build_stream::<i32>(device, &stream_config, sink)?,
+        SampleFormat::U8 => build_stream::<u8>(device, &stream_config, sink)?,
+        SampleFormat::U16 => build_stream::<u16>(device, &stream_config, sink)?,
+        SampleFormat::U32 => build_stream::<u32>(device, &stream_config, sink)?,
+        SampleFormat::F32 => build_stream::<f32>(device, &stream_config, sink)?,
+        SampleFormat::F64 => build_stream::<f64>(device, &stream_config, sink)?,
+        format => anyhow::bail!("Unsupported sample format: {format:?}"),
+    };
+    Ok(stream)
+}
+
+/// Open an input stream delivering `T` samples and append them, converted to
+/// mono f32 in [-1, 1] (downmixed or one channel picked), to the shared buffer.
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    sink: Sink,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let Sink {
+        samples,
+        channel,
+        mut filter,
+        spool,
+        monitor,
+        limit,
+        on_error,
+    } = sink;
+    let mut on_error = Some(on_error);
+    let err_fn = move |err: cpal::StreamError| {
+        log::error!("Audio stream error: {err}");
+        // cpal may keep reporting the same failure; the worker needs it once.
+        if let Some(on_error) = on_error.take() {
+            on_error(err.to_string());
+        }
+    };
+
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let mut mono = match channel {
+                InputChannel::Mono => downmix(data, channels),
+                InputChannel::Channel(n) => extract_channel(data, channels, n.into()),
+            };
+            if let Some(filter) = filter.as_mut() {
+                filter.process(&mut mono);
             }
-        })
+            // parking_lot's Mutex doesn't poison, so a panic elsewhere while
+            // holding the buffer can't wedge every later callback.
+            let mut buffer = samples.lock();
+            buffer.extend_from_slice(&mono);
+            // Trimmed only once it's twice the limit, so most callbacks
+            // don't shift the whole buffer.
+            if let Some(limit) = limit.filter(|&limit| buffer.len() > 2 * limit) {
+                let excess = buffer.len() - limit;
+                buffer.drain(..excess);
+            }
+            drop(buffer);
+            if let Some(monitor) = &monitor {
+                monitor.push(&mono);
+            }
+            if let Some(spool) = &spool {
+                // Unbounded channel: never blocks the audio thread.
+                let _ = spool.send(mono);
+            }
+        },
+        err_fn,
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Convert interleaved frames to mono f32 by averaging channels. Integer
+/// formats map their full range onto [-1, 1] (unsigned ones are re-centred
+/// around zero) via cpal's sample conversions.
+fn downmix<T>(data: &[T], channels: usize) -> Vec<f32>
+where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    data.chunks(channels)
+        .map(|frame| frame.iter().map(|&s| s.to_sample::<f32>()).sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Take channel `index` of every interleaved frame, converted to f32 like
+/// `downmix` does.
+fn extract_channel<T>(data: &[T], channels: usize, index: usize) -> Vec<f32>
+where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    data.chunks(channels)
+        .filter_map(|frame| frame.get(index))
+        .map(|&s| s.to_sample::<f32>())
         .collect()
 }
+
+/// First-order IIR high-pass filter. Keeps its state between calls so a
+/// stream processed in blocks is filtered as one continuous signal.
+struct HighPass {
+    alpha: f32,
+    prev_in: f32,
+    prev_out: f32,
+}
+
+impl HighPass {
+    fn new(sample_rate: u32, cutoff_hz: f32) -> Self {
+        let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff_hz);
+        let dt = 1.0 / sample_rate as f32;
+        Self {
+            alpha: rc / (rc + dt),
+            prev_in: 0.0,
+            prev_out: 0.0,
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for s in samples.iter_mut() {
+            let out = self.alpha * (self.prev_out + *s - self.prev_in);
+            self.prev_in = *s;
+            self.prev_out = out;
+            *s = out;
+        }
+    }
+}
+
+/// Decode a WAV file (integer or float PCM, any rate and channel count) to
+/// 16kHz mono f32.
+pub fn load_wav(path: &std::path::Path, quality: ResampleQuality) -> Result<Vec<f32>> {
+    let mut reader = hound::WavReader::open(path).context("Not a readable WAV file")?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let mono = downmix(&interleaved, spec.channels.max(1) as usize);
+    log::info!(
+        "Loaded {}: {} samples at {}Hz, {} channel(s)",
+        path.display(),
+        mono.len(),
+        spec.sample_rate,
+        spec.channels
+    );
+    Ok(if spec.sample_rate == TARGET_SAMPLE_RATE {
+        mono
+    } else {
+        resample(&mono, spec.sample_rate, TARGET_SAMPLE_RATE, quality)
+    })
+}
+
+/// Scale `samples` so their peak lands at -3 dBFS. Quiet-but-present speech
+/// is boosted; buffers whose peak is below the noise floor are left as-is,
+/// and loud buffers are never attenuated.
+pub fn normalize_peak(samples: &mut [f32]) {
+    let peak = samples.iter().fold(0.0f32, |m, &s| m.max(s.abs()));
+    if peak < AUTO_GAIN_FLOOR {
+        return;
+    }
+    let gain = (AUTO_GAIN_TARGET / peak).min(AUTO_GAIN_MAX);
+    if gain <= 1.0 {
+        return;
+    }
+    for s in samples.iter_mut() {
+        *s *= gain;
+    }
+}
+
+/// `samples` with `pad` samples of silence on either side, so whisper
+/// doesn't clip the first and last phonemes of a buffer that was cut off
+/// mid-sound.
+pub fn pad_silence(samples: &[f32], pad: usize) -> Vec<f32> {
+    let mut padded = Vec::with_capacity(samples.len() + 2 * pad);
+    padded.resize(pad, 0.0);
+    padded.extend_from_slice(samples);
+    padded.resize(samples.len() + 2 * pad, 0.0);
+    padded
+}
+
+/// Multiply `samples` by a fixed `gain`, clipping at full scale.
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 {
+        return;
+    }
+    for s in samples.iter_mut() {
+        *s = (*s * gain).clamp(-1.0, 1.0);
+    }
+}
+
+/// Share of `samples` at full scale (0.0–1.0): how much of the input an
+/// overdriven mic or preamp clipped.
+pub fn clipped_fraction(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let clipped = samples.iter().filter(|s| s.abs() >= CLIP_LEVEL).count();
+    clipped as f32 / samples.len() as f32
+}
+
+/// Check a `Settings::clipping_warning_percent` is within `0.0..=100.0`.
+pub fn validate_clipping_threshold(percent: f32) -> Result<()> {
+    if !(0.0..=100.0).contains(&percent) {
+        anyhow::bail!("Clipping threshold must be between 0 and 100 %, got {percent}");
+    }
+    Ok(())
+}
+
+/// Check a manual gain is within `INPUT_GAIN_MIN..=INPUT_GAIN_MAX`.
+pub fn validate_gain(gain: f32) -> Result<()> {
+    if !(INPUT_GAIN_MIN..=INPUT_GAIN_MAX).contains(&gain) {
+        anyhow::bail!(
+            "Input gain must be between {INPUT_GAIN_MIN} and {INPUT_GAIN_MAX}, got {gain}"
+        );
+    }
+    Ok(())
+}
+
+/// Check a `gate_quiet` threshold is within `0.0..=1.0`.
+pub fn validate_gate(relative: f32) -> Result<()> {
+    if !(0.0..=1.0).contains(&relative) {
+        anyhow::bail!("Loudness gate must be between 0 and 1, got {relative}");
+    }
+    Ok(())
+}
+
+/// Keep only the near-field speaker: mute every stretch of `samples` (16kHz
+/// mono) quieter than `relative` times the level of the buffer's speech,
+/// such as a TV or people talking across the room. The buffer keeps its
+/// length, so timings are unaffected. 0 leaves it as is.
+pub fn gate_quiet(samples: &mut [f32], relative: f32) {
+    if relative <= 0.0 {
+        return;
+    }
+    let frame_len = vad_frame_len(TARGET_SAMPLE_RATE);
+    let levels: Vec<f32> = samples.chunks(frame_len).map(rms).collect();
+    let mut speech: Vec<f32> = levels
+        .iter()
+        .copied()
+        .filter(|&level| level > VAD_RMS_THRESHOLD)
+        .collect();
+    if speech.is_empty() {
+        return;
+    }
+    speech.sort_by(f32::total_cmp);
+    let reference = speech[((speech.len() - 1) as f32 * GATE_REFERENCE_PERCENTILE) as usize];
+    let loud: Vec<bool> = levels
+        .iter()
+        .map(|&level| level >= reference * relative)
+        .collect();
+    for (i, frame) in samples.chunks_mut(frame_len).enumerate() {
+        let start = i.saturating_sub(GATE_HANGOVER_FRAMES);
+        let end = (i + GATE_HANGOVER_FRAMES + 1).min(loud.len());
+        if !loud[start..end].contains(&true) {
+            frame.fill(0.0);
+        }
+    }
+}
+
+fn vad_frame_len(sample_rate: u32) -> usize {
+    (sample_rate as usize * VAD_FRAME.as_millis() as usize / 1000).max(1)
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+fn voice_activity(samples: &[f32], sample_rate: u32) -> VoiceActivity {
+    let mut speech_frames = 0u32;
+    let mut silent_tail = 0u32;
+    for frame in samples.chunks(vad_frame_len(sample_rate)) {
+        if rms(frame) > VAD_RMS_THRESHOLD {
+            speech_frames += 1;
+            silent_tail = 0;
+        } else {
+            silent_tail += 1;
+        }
+    }
+    VoiceActivity {
+        speech: VAD_FRAME * speech_frames,
+        trailing_silence: VAD_FRAME * silent_tail,
+    }
+}
+
+/// Bring what was recorded at `from_rate` to `to_rate`, so what the new
+/// stream appends continues it at one rate.
+fn switch_rate(samples: &mut Vec<f32>, from_rate: u32, to_rate: u32, quality: ResampleQuality) {
+    *samples = resample(samples, from_rate, to_rate, quality);
+}
+
+pub fn resample(input: &[f32], from_rate: u32, to_rate: u32, quality: ResampleQuality) -> Vec<f32> {
+    let mut output = Vec::new();
+    resample_into(input, from_rate, to_rate, quality, &mut output);
+    output
+}
+
+/// `resample` into `output`, replacing its contents. Both qualities give
+/// the same number of samples.
+pub fn resample_into(
+    input: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+    output: &mut Vec<f32>,
+) {
+    let ratio = from_rate as f64 / to_rate as f64;
+    let output_len = (input.len() as u64 * u64::from(to_rate) / u64::from(from_rate)) as usize;
+    output.clear();
+    if quality == ResampleQuality::High {
+        output.extend((0..output_len).map(|i| sinc_sample(input, i as f64 * ratio, ratio)));
+        return;
+    }
+    output.extend((0..output_len).map(|i| {
+        let src = i as f64 * ratio;
+        let idx = src as usize;
+        let frac = src - idx as f64;
+        if idx + 1 < input.len() {
+            (input[idx] as f64 * (1.0 - frac) + input[idx + 1] as f64 * frac) as f32
+        } else {
+            input[idx.min(input.len() - 1)]
+        }
+    }));
+}
+
+/// Zero crossings of the sinc kernel on each side, at the lower rate.
+const SINC_ZERO_CROSSINGS: f64 = 16.0;
+/// Cutoff as a fraction of the lower rate's Nyquist frequency, leaving room
+/// for the kernel's transition band.
+const SINC_CUTOFF: f64 = 0.95;
+
+/// The band-limited value of `input` at fractional position `center`, for
+/// resampling by `ratio` (input rate / output rate): a Hann-windowed sinc
+/// low-pass, normalized so a constant signal stays constant.
+fn sinc_sample(input: &[f32], center: f64, ratio: f64) -> f32 {
+    let cutoff = SINC_CUTOFF * (1.0 / ratio).min(1.0);
+    let half_width = SINC_ZERO_CROSSINGS / cutoff;
+    let first = (center - half_width).ceil().max(0.0) as usize;
+    let last = ((center + half_width).floor() as usize).min(input.len() - 1);
+    let (mut sum, mut weight) = (0.0, 0.0);
+    for (j, &sample) in input.iter().enumerate().take(last + 1).skip(first) {
+        let x = j as f64 - center;
+        let arg = std::f64::consts::PI * cutoff * x;
+        let sinc = if arg == 0.0 { 1.0 } else { arg.sin() / arg };
+        let window = 0.5 * (1.0 + (std::f64::consts::PI * x / half_width).cos());
+        sum += sample as f64 * sinc * window;
+        weight += sinc * window;
+    }
+    if weight == 0.0 {
+        0.0
+    } else {
+        (sum / weight) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, amplitude: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                amplitude * (2.0 * std::f32::consts::PI * freq * t).sin()
+            })
+            .collect()
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0f32, |m, &s| m.max(s.abs()))
+    }
+
+    #[test]
+    fn normalize_peak_boosts_quiet_speech_to_minus_3_dbfs() {
+        let mut samples = sine(440.0, 0.1, TARGET_SAMPLE_RATE, 16_000);
+        normalize_peak(&mut samples);
+        let dbfs = 20.0 * peak(&samples).log10();
+        assert!((dbfs + 3.0).abs() < 0.1, "peak at {dbfs} dBFS");
+    }
+
+    #[test]
+    fn normalize_peak_leaves_noise_below_the_floor() {
+        let original = sine(440.0, AUTO_GAIN_FLOOR / 2.0, TARGET_SAMPLE_RATE, 16_000);
+        let mut samples = original.clone();
+        normalize_peak(&mut samples);
+        assert_eq!(samples, original);
+    }
+
+    #[test]
+    fn normalize_peak_never_attenuates() {
+        let original = sine(440.0, 0.9, TARGET_SAMPLE_RATE, 16_000);
+        let mut samples = original.clone();
+        normalize_peak(&mut samples);
+        assert_eq!(samples, original);
+    }
+
+    #[test]
+    fn downmix_averages_channels() {
+        let stereo = [0.5f32, -0.5, 1.0, 0.0];
+        assert_eq!(downmix(&stereo, 2), [0.0, 0.5]);
+    }
+
+    #[test]
+    fn downmix_recentres_unsigned_samples() {
+        let mono = downmix(&[0u16, 32768, u16::MAX], 1);
+        assert_eq!(mono[0], -1.0);
+        assert_eq!(mono[1], 0.0);
+        assert!((mono[2] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn downmix_converts_f64() {
+        assert_eq!(downmix(&[0.25f64, 0.75], 2), [0.5]);
+    }
+
+    /// RMS of the filtered `freq` sine relative to the input's, after the
+    /// filter has settled.
+    fn highpass_gain(freq: f32) -> f32 {
+        let input = sine(freq, 0.5, TARGET_SAMPLE_RATE, 16_000);
+        let mut output = input.clone();
+        HighPass::new(TARGET_SAMPLE_RATE, HIGHPASS_CUTOFF_HZ).process(&mut output);
+        rms(&output[8_000..]) / rms(&input[8_000..])
+    }
+
+    #[test]
+    fn highpass_cuts_rumble_and_keeps_speech() {
+        let rumble = highpass_gain(20.0);
+        assert!(rumble < 0.3, "20Hz at {rumble}");
+        let cutoff = highpass_gain(HIGHPASS_CUTOFF_HZ);
+        assert!((cutoff - 0.707).abs() < 0.05, "cutoff at {cutoff}");
+        let speech = highpass_gain(1000.0);
+        assert!(speech > 0.95, "1kHz at {speech}");
+    }
+
+    #[test]
+    fn highpass_filters_blocks_as_one_signal() {
+        let input = sine(50.0, 0.5, TARGET_SAMPLE_RATE, 4_000);
+        let mut whole = input.clone();
+        HighPass::new(TARGET_SAMPLE_RATE, HIGHPASS_CUTOFF_HZ).process(&mut whole);
+        let mut blocks = input;
+        let mut filter = HighPass::new(TARGET_SAMPLE_RATE, HIGHPASS_CUTOFF_HZ);
+        for block in blocks.chunks_mut(480) {
+            filter.process(block);
+        }
+        assert_eq!(blocks, whole);
+    }
+
+    #[test]
+    fn load_wav_downmixes_and_resamples_a_stereo_file() {
+        // 0.1 s of 16-bit stereo at 44.1 kHz, left at 0.6 and right at 0.2.
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/stereo-44k.wav");
+        for quality in [ResampleQuality::Fast, ResampleQuality::High] {
+            let samples = load_wav(&path, quality).unwrap();
+            assert_eq!(samples.len(), 1_600, "{quality:?}");
+            // The windowed sinc rings against the file's edges.
+            for &s in &samples[100..1_500] {
+                assert!((s - 0.4).abs() < 1e-3, "{quality:?} gave {s}");
+            }
+        }
+    }
+
+    /// `speech` seconds of a loud tone, then `silence` seconds of nothing,
+    /// at 16kHz.
+    fn speech_then_silence(speech: f32, silence: f32) -> Vec<f32> {
+        let rate = TARGET_SAMPLE_RATE as f32;
+        let mut audio = sine(220.0, 0.3, TARGET_SAMPLE_RATE, (speech * rate) as usize);
+        audio.resize(audio.len() + (silence * rate) as usize, 0.0);
+        audio
+    }
+
+    #[test]
+    fn pause_detector_finds_speech_resuming_after_a_gap() {
+        let gap = Duration::from_secs(2);
+        let mut audio = speech_then_silence(1.0, 3.0);
+        audio.extend(speech_then_silence(1.0, 0.0));
+        let mut pauses = PauseDetector::default();
+        assert!(pauses.resumed_after(&audio, 0, gap));
+        // Audio scanned before doesn't report the pause again.
+        assert!(!pauses.resumed_after(&audio, 0, gap));
+    }
+
+    #[test]
+    fn pause_detector_ignores_short_gaps() {
+        let mut audio = speech_then_silence(1.0, 1.0);
+        audio.extend(speech_then_silence(1.0, 0.0));
+        let mut pauses = PauseDetector::default();
+        assert!(!pauses.resumed_after(&audio, 0, Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn pause_detector_joins_a_gap_across_ticks() {
+        let gap = Duration::from_secs(2);
+        let mut audio = speech_then_silence(1.0, 1.5);
+        let mut pauses = PauseDetector::default();
+        assert!(!pauses.resumed_after(&audio, 0, gap));
+        audio.extend(speech_then_silence(0.0, 1.5));
+        assert!(!pauses.resumed_after(&audio, 0, gap));
+        audio.extend(speech_then_silence(1.0, 0.0));
+        assert!(pauses.resumed_after(&audio, 0, gap));
+    }
+
+    #[test]
+    fn pause_detector_follows_a_sliding_window() {
+        let gap = Duration::from_secs(2);
+        let second = TARGET_SAMPLE_RATE as usize;
+        let mut recording = speech_then_silence(2.0, 0.0);
+        let mut pauses = PauseDetector::default();
+        assert!(!pauses.resumed_after(&recording, 0, gap));
+        recording.extend(speech_then_silence(0.0, 3.0));
+        recording.extend(speech_then_silence(1.0, 0.0));
+        // The window has moved one second into the recording.
+        assert!(pauses.resumed_after(&recording[second..], second, gap));
+    }
+
+    #[test]
+    fn gain_doubles_and_clips_at_the_rails() {
+        let mut samples = [0.1, -0.25, 0.4, 0.6, -0.8, 1.0];
+        apply_gain(&mut samples, 2.0);
+        assert_eq!(samples, [0.2, -0.5, 0.8, 1.0, -1.0, 1.0]);
+    }
+
+    #[test]
+    fn gain_must_be_in_range() {
+        for gain in [INPUT_GAIN_MIN, 1.0, 2.0, INPUT_GAIN_MAX] {
+            assert!(validate_gain(gain).is_ok(), "{gain}");
+        }
+        for gain in [0.0, -1.0, INPUT_GAIN_MAX + 0.5, f32::NAN] {
+            assert!(validate_gain(gain).is_err(), "{gain}");
+        }
+    }
+
+    #[test]
+    fn both_resamplers_give_the_expected_length() {
+        for quality in [ResampleQuality::Fast, ResampleQuality::High] {
+            for (rate, len, expected) in [
+                (48_000, 48_000, 16_000),
+                (44_100, 44_100, 16_000),
+                (22_050, 11_025, 8_000),
+                (16_000, 1234, 1234),
+                (8_000, 800, 1600),
+            ] {
+                let input = vec![0.25; len];
+                let output = resample(&input, rate, TARGET_SAMPLE_RATE, quality);
+                assert_eq!(output.len(), expected, "{quality:?} from {rate}Hz");
+                let middle = output[output.len() / 2];
+                assert!((middle - 0.25).abs() < 1e-3, "{quality:?}: {middle}");
+            }
+        }
+    }
+
+    #[test]
+    fn gate_mutes_quiet_speech_and_keeps_loud() {
+        let loud = sine(300.0, 0.5, TARGET_SAMPLE_RATE, 16_000);
+        let quiet = sine(300.0, 0.05, TARGET_SAMPLE_RATE, 16_000);
+        let mut samples = [loud.clone(), quiet].concat();
+        gate_quiet(&mut samples, 0.3);
+        assert_eq!(samples[..16_000], loud[..]);
+        // Past the hangover after the loud part, nothing is left.
+        let hangover = (GATE_HANGOVER_FRAMES + 1) * vad_frame_len(TARGET_SAMPLE_RATE);
+        assert_eq!(peak(&samples[16_000 + hangover..]), 0.0);
+    }
+
+    #[test]
+    fn gate_leaves_audio_without_speech_alone() {
+        let original = sine(300.0, 0.01, TARGET_SAMPLE_RATE, 8000);
+        let mut samples = original.clone();
+        gate_quiet(&mut samples, 0.3);
+        assert_eq!(samples, original);
+
+        let mut samples = sine(300.0, 0.5, TARGET_SAMPLE_RATE, 8000);
+        samples.extend(sine(300.0, 0.05, TARGET_SAMPLE_RATE, 8000));
+        let ungated = samples.clone();
+        gate_quiet(&mut samples, 0.0);
+        assert_eq!(samples, ungated);
+    }
+
+    #[test]
+    fn padding_adds_silence_on_both_sides() {
+        let samples = [0.5, -0.5, 0.25];
+        let padded = pad_silence(&samples, 4);
+        assert_eq!(padded.len(), samples.len() + 8);
+        assert_eq!(padded[..4], [0.0; 4]);
+        assert_eq!(padded[4..7], samples);
+        assert_eq!(padded[7..], [0.0; 4]);
+        assert_eq!(pad_silence(&samples, 0), samples);
+    }
+
+    #[test]
+    fn clipped_fraction_counts_samples_at_full_scale() {
+        let clean = sine(440.0, 0.5, TARGET_SAMPLE_RATE, 1600);
+        assert_eq!(clipped_fraction(&clean), 0.0);
+
+        let clipped: Vec<f32> = sine(440.0, 2.0, TARGET_SAMPLE_RATE, 1600)
+            .into_iter()
+            .map(|s| s.clamp(-1.0, 1.0))
+            .collect();
+        let fraction = clipped_fraction(&clipped);
+        // A sine driven to twice full scale is cut off for about two
+        // thirds of the time.
+        assert!((0.6..0.72).contains(&fraction), "{fraction}");
+        assert_eq!(clipped_fraction(&[]), 0.0);
+    }
+
+    #[test]
+    fn a_rate_change_mid_buffer_keeps_the_signal_continuous() {
+        let phase_shifted = |rate: u32, from: f32, len: usize| -> Vec<f32> {
+            (0..len)
+                .map(|i| {
+                    let t = from + i as f32 / rate as f32;
+                    0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                })
+                .collect()
+        };
+        let expected = phase_shifted(TARGET_SAMPLE_RATE, 0.0, 16_000);
+        for quality in [ResampleQuality::Fast, ResampleQuality::High] {
+            // Half a second at 48kHz, then the device switches to 44.1kHz.
+            let mut samples = phase_shifted(48_000, 0.0, 24_000);
+            switch_rate(&mut samples, 48_000, 44_100, quality);
+            assert_eq!(samples.len(), 22_050);
+            samples.extend(phase_shifted(44_100, 0.5, 22_050));
+
+            let output = resample(&samples, 44_100, TARGET_SAMPLE_RATE, quality);
+            assert_eq!(output.len(), 16_000, "{quality:?}");
+            // Away from the buffer's ends, seam included, it is the same sine.
+            for i in 1000..15_000 {
+                let error = (output[i] - expected[i]).abs();
+                assert!(error < 0.02, "{quality:?}: off by {error} at {i}");
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Transcription latency self-test, to help pick a model and check whether
+//! the hardware keeps up with the streaming interval.
+
+use crate::audio::TARGET_SAMPLE_RATE;
+use crate::transcribe::{Transcribe, TranscribeConfig};
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Length of the synthetic clip used when no recording is available.
+const SYNTHETIC_SECS: f32 = 5.0;
+
+/// Bundled resource with a few seconds of known speech ("And so my fellow
+/// Americans", from whisper.cpp's JFK sample), benchmarked when there's no
+/// recording yet.
+pub const SPEECH_SAMPLE: &str = "resources/speech.wav";
+
+/// Audio the benchmark ran on.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipSource {
+    /// The most recent recording of this session, i.e. real speech.
+    LastRecording,
+    /// `SPEECH_SAMPLE`.
+    SpeechSample,
+    /// Generated speech-like tone bursts, if `SPEECH_SAMPLE` can't be
+    /// loaded; timings are indicative only, as whisper may decode it faster
+    /// than real speech.
+    Synthetic,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub source: ClipSource,
+    pub audio_secs: f32,
+    pub runs: u32,
+    pub min_ms: u64,
+    pub median_ms: u64,
+    pub max_ms: u64,
+    /// Median transcription time divided by audio length (< 1 = faster than
+    /// real time).
+    pub realtime_factor: f32,
+    /// Whether a median run fits within the streaming interval.
+    pub keeps_up: bool,
+    /// Whether the model was loaded with GPU offload requested.
+    pub gpu_requested: bool,
+    /// whisper.cpp's build/backend summary (lists CUDA when compiled in).
+    pub system_info: String,
+    /// Output of the last run, to sanity-check that something was heard.
+    pub sample_output: String,
+}
+
+/// A speech-like clip: tone bursts at syllable rate with a few harmonics,
+/// separated by short gaps.
+pub fn synthetic_clip() -> Vec<f32> {
+    let rate = TARGET_SAMPLE_RATE as f32;
+    let len = (SYNTHETIC_SECS * rate) as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / rate;
+            // ~4 syllables per second, pitch wandering around 140 Hz
+            let envelope = (std::f32::consts::PI * 4.0 * t).sin().max(0.0);
+            let pitch = 140.0 + 30.0 * (std::f32::consts::PI * 0.7 * t).sin();
+            let voice: f32 = (1..=4)
+                .map(|h| {
+                    let h = h as f32;
+                    (2.0 * std::f32::consts::PI * pitch * h * t).sin() / h
+                })
+                .sum();
+            0.2 * envelope * voice
+        })
+        .collect()
+}
+
+/// Payload of the `benchmark-progress` event.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BenchmarkProgress {
+    pub done: u32,
+    pub runs: u32,
+}
+
+/// Transcribe `audio` `runs` times, reporting progress after each run.
+pub fn run(
+    transcriber: &dyn Transcribe,
+    audio: &[f32],
+    source: ClipSource,
+    config: &TranscribeConfig,
+    runs: u32,
+    stream_interval: Duration,
+    mut on_progress: impl FnMut(BenchmarkProgress),
+) -> Result<BenchmarkReport> {
+    let runs = runs.max(1);
+    let mut timings = Vec::with_capacity(runs as usize);
+    let mut sample_output = String::new();
+    for done in 1..=runs {
+        let started = Instant::now();
+        sample_output = transcriber.transcribe(audio, config)?;
+        timings.push(started.elapsed());
+        on_progress(BenchmarkProgress { done, runs });
+    }
+    timings.sort();
+
+    let audio_secs = audio.len() as f32 / TARGET_SAMPLE_RATE as f32;
+    let median = timings[timings.len() / 2];
+    Ok(BenchmarkReport {
+        source,
+        audio_secs,
+        runs,
+        min_ms: timings[0].as_millis() as u64,
+        median_ms: median.as_millis() as u64,
+        max_ms: timings[timings.len() - 1].as_millis() as u64,
+        realtime_factor: median.as_secs_f32() / audio_secs.max(f32::EPSILON),
+        keeps_up: median < stream_interval,
+        gpu_requested: transcriber.gpu_requested(),
+        system_info: whisper_rs::print_system_info().to_string(),
+        sample_output,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::audio::{self, ResampleQuality};
+    use crate::transcribe::{self, Transcriber};
+    use std::path::Path;
+
+    pub(crate) fn speech_sample() -> Vec<f32> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SPEECH_SAMPLE);
+        audio::load_wav(&path, ResampleQuality::default()).unwrap()
+    }
+
+    #[test]
+    fn speech_sample_decodes() {
+        let samples = speech_sample();
+        assert_eq!(samples.len(), 41_600);
+        assert!(samples.iter().any(|s| s.abs() > 0.1));
+    }
+
+    /// `VTT_TEST_MODEL=path/to/ggml-base.bin cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs a whisper model (VTT_TEST_MODEL)"]
+    fn benchmark_hears_the_speech_sample() {
+        let model = transcribe::tests::test_model();
+        let transcriber = Transcriber::new(&model, false, None).unwrap();
+        let report = run(
+            &transcriber,
+            &speech_sample(),
+            ClipSource::SpeechSample,
+            &transcribe::tests::config(),
+            1,
+            Duration::from_secs(3),
+            |_| {},
+        )
+        .unwrap();
+        let heard = report.sample_output.to_lowercase();
+        assert!(heard.contains("americans"), "heard {heard:?}");
+    }
+}
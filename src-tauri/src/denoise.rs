@@ -0,0 +1,89 @@
+//! RNNoise noise suppression for 16kHz buffers.
+//!
+//! RNNoise only runs at 48kHz, so the buffer is upsampled, denoised in
+//! 10ms frames and brought back down. Speech above 8kHz is already gone
+//! at 16kHz, so nothing is lost by not denoising the original capture.
+
+use crate::audio::{self, TARGET_SAMPLE_RATE};
+use nnnoiseless::DenoiseState;
+
+const RNNOISE_RATE: u32 = 48_000;
+const FACTOR: usize = (RNNOISE_RATE / TARGET_SAMPLE_RATE) as usize;
+/// RNNoise works on i16-scaled samples.
+const SCALE: f32 = i16::MAX as f32;
+
+/// Denoise `samples` (16kHz mono) in place.
+pub fn denoise(samples: &mut [f32]) {
+    if samples.is_empty() {
+        return;
+    }
+    let frame = DenoiseState::FRAME_SIZE;
+    // Upsampling adds nothing to alias, so the linear resampler will do.
+    let mut input: Vec<f32> = audio::resample(
+        samples,
+        TARGET_SAMPLE_RATE,
+        RNNOISE_RATE,
+        audio::ResampleQuality::Fast,
+    )
+    .into_iter()
+    .map(|s| s * SCALE)
+    .collect();
+    // RNNoise's output lags by one frame: pad by an extra frame and drop
+    // the first one, so the result lines up with the input. The first
+    // frame is also just the model warming up.
+    let padded = (input.len().div_ceil(frame) + 1) * frame;
+    input.resize(padded, 0.0);
+
+    let mut state = DenoiseState::new();
+    let mut output = vec![0.0f32; padded];
+    let frames = output
+        .chunks_exact_mut(frame)
+        .zip(input.chunks_exact(frame));
+    for (out, chunk) in frames {
+        state.process_frame(out, chunk);
+    }
+
+    // Average each group of three: a crude low-pass, so whatever RNNoise
+    // left above 8kHz doesn't alias back down.
+    let denoised = output[frame..].chunks_exact(FACTOR);
+    for (sample, group) in samples.iter_mut().zip(denoised) {
+        *sample = group.iter().sum::<f32>() / (FACTOR as f32 * SCALE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `len` samples of white noise in [-amplitude, amplitude].
+    fn noise(amplitude: f32, len: usize) -> Vec<f32> {
+        let mut seed = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                amplitude * (seed as f32 / u32::MAX as f32 * 2.0 - 1.0)
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn suppresses_noise() {
+        let second = TARGET_SAMPLE_RATE as usize;
+        let original = noise(0.1, 3 * second);
+        let mut samples = original.clone();
+        denoise(&mut samples);
+        assert_eq!(samples.len(), original.len());
+        // Past the first second, by which RNNoise has its noise estimate.
+        let (before, after) = (rms(&original[second..]), rms(&samples[second..]));
+        assert!(after < before / 4.0, "RMS {before} -> {after}");
+    }
+
+    #[test]
+    fn empty_buffer_is_a_no_op() {
+        denoise(&mut []);
+    }
+}
@@ -0,0 +1,227 @@
+//! Process figures for `get_diagnostics`, read from the OS, and the report
+//! of `health_check`.
+
+use crate::audio::DeviceInfo;
+use crate::transcribe::{ModelCheck, ModelInfo};
+use serde::Serialize;
+
+/// Resident memory of this process in bytes, or `None` where it can't be
+/// read (so far only Linux is supported, through `/proc`).
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    // "VmRSS:     123456 kB"
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// A part of the app `health_check` looks at.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheck {
+    AudioDevice,
+    Model,
+    Transcription,
+    Typing,
+    Hotkey,
+}
+
+/// One line of the report: whether that part works, and what was found.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthItem {
+    pub check: HealthCheck,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl HealthItem {
+    fn new(check: HealthCheck, ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Every check passed.
+    pub ok: bool,
+    pub items: Vec<HealthItem>,
+}
+
+impl HealthReport {
+    pub fn new(items: Vec<HealthItem>) -> Self {
+        Self {
+            ok: items.iter().all(|item| item.ok),
+            items,
+        }
+    }
+}
+
+/// The input device's probe (`audio::input_info`).
+pub fn audio_item(device: &anyhow::Result<DeviceInfo>) -> HealthItem {
+    match device {
+        Ok(info) => HealthItem::new(
+            HealthCheck::AudioDevice,
+            true,
+            format!("{}, {} Hz", info.name, info.sample_rate),
+        ),
+        Err(e) => HealthItem::new(HealthCheck::AudioDevice, false, e.to_string()),
+    }
+}
+
+/// The model file's header (`transcribe::inspect_model`).
+pub fn model_item(model: &ModelCheck) -> HealthItem {
+    match model {
+        ModelCheck::Missing => HealthItem::new(HealthCheck::Model, false, "Model file not found"),
+        ModelCheck::Invalid { reason } => HealthItem::new(HealthCheck::Model, false, reason),
+        ModelCheck::Valid { model_type, .. } => {
+            HealthItem::new(HealthCheck::Model, true, format!("{model_type} model"))
+        }
+    }
+}
+
+/// The API backend stands in for the model file and whisper.
+pub fn api_items(url: &str) -> [HealthItem; 2] {
+    let url = url.trim();
+    [
+        HealthItem::new(HealthCheck::Model, true, "Not used with the API backend"),
+        if url.is_empty() {
+            HealthItem::new(HealthCheck::Transcription, false, "No API URL set")
+        } else {
+            HealthItem::new(HealthCheck::Transcription, true, format!("API at {url}"))
+        },
+    ]
+}
+
+/// Whether whisper has a model loaded (`model_info`), and where it runs.
+pub fn transcription_item(loaded: Option<&ModelInfo>, loading: bool) -> HealthItem {
+    let (ok, detail) = match loaded {
+        Some(info) if info.gpu_requested => (true, "whisper with GPU offload"),
+        Some(_) => (true, "whisper on the CPU"),
+        None if loading => (false, "Model still loading"),
+        None => (false, "No model loaded"),
+    };
+    HealthItem::new(HealthCheck::Transcription, ok, detail)
+}
+
+/// The typing tools found missing (`typing::recheck_dependencies`).
+pub fn typing_item(missing: &[String]) -> HealthItem {
+    if missing.is_empty() {
+        HealthItem::new(HealthCheck::Typing, true, "All typing tools found")
+    } else {
+        let detail = format!("Missing: {}", missing.join(", "));
+        HealthItem::new(HealthCheck::Typing, false, detail)
+    }
+}
+
+/// Why the global key listener stopped for good, if it did.
+pub fn hotkey_item(error: Option<&str>) -> HealthItem {
+    match error {
+        Some(error) => HealthItem::new(HealthCheck::Hotkey, false, error),
+        None => HealthItem::new(HealthCheck::Hotkey, true, "Listening"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Whether `item` passed, and its detail.
+    fn line(item: &HealthItem) -> (bool, &str) {
+        (item.ok, &item.detail)
+    }
+
+    fn loaded(gpu_requested: bool) -> ModelInfo {
+        ModelInfo {
+            path: PathBuf::from("ggml-base.bin"),
+            model_type: "base".to_string(),
+            multilingual: true,
+            vocab_size: 51865,
+            gpu_requested,
+            gpu_device: None,
+            word_timestamps: false,
+        }
+    }
+
+    #[test]
+    fn the_audio_item_names_the_device_or_the_error() {
+        let device = Ok(DeviceInfo {
+            name: "USB Mic".to_string(),
+            sample_rate: 48_000,
+            channels: 1,
+            sample_format: "f32".to_string(),
+        });
+        let item = audio_item(&device);
+        assert!(matches!(item.check, HealthCheck::AudioDevice));
+        assert_eq!(line(&item), (true, "USB Mic, 48000 Hz"));
+
+        let item = audio_item(&Err(anyhow::anyhow!("No input audio device found")));
+        assert_eq!(line(&item), (false, "No input audio device found"));
+    }
+
+    #[test]
+    fn the_model_item_follows_the_header_check() {
+        let item = model_item(&ModelCheck::Missing);
+        assert!(matches!(item.check, HealthCheck::Model));
+        assert_eq!(line(&item), (false, "Model file not found"));
+
+        let invalid = ModelCheck::Invalid {
+            reason: "Truncated".to_string(),
+        };
+        assert_eq!(line(&model_item(&invalid)), (false, "Truncated"));
+
+        let valid = ModelCheck::Valid {
+            model_type: "small",
+            vocab_size: 51865,
+            multilingual: true,
+        };
+        assert_eq!(line(&model_item(&valid)), (true, "small model"));
+    }
+
+    #[test]
+    fn the_api_backend_needs_only_a_url() {
+        let [model, transcription] = api_items(" http://localhost:8080 ");
+        assert!(matches!(model.check, HealthCheck::Model));
+        assert!(model.ok);
+        assert!(matches!(transcription.check, HealthCheck::Transcription));
+        assert_eq!(line(&transcription), (true, "API at http://localhost:8080"));
+
+        let [_, transcription] = api_items("  ");
+        assert_eq!(line(&transcription), (false, "No API URL set"));
+    }
+
+    #[test]
+    fn the_transcription_item_needs_a_loaded_model() {
+        let gpu = loaded(true);
+        let cpu = loaded(false);
+        assert_eq!(
+            line(&transcription_item(Some(&gpu), false)),
+            (true, "whisper with GPU offload")
+        );
+        assert_eq!(
+            line(&transcription_item(Some(&cpu), false)),
+            (true, "whisper on the CPU")
+        );
+        assert_eq!(
+            line(&transcription_item(None, true)),
+            (false, "Model still loading")
+        );
+        assert_eq!(
+            line(&transcription_item(None, false)),
+            (false, "No model loaded")
+        );
+    }
+
+    #[test]
+    fn the_report_fails_if_any_item_does() {
+        assert!(HealthReport::new(vec![hotkey_item(None), typing_item(&[])]).ok);
+        let missing = ["xdotool".to_string()];
+        let report = HealthReport::new(vec![hotkey_item(None), typing_item(&missing)]);
+        assert!(!report.ok);
+        assert_eq!(line(&report.items[1]), (false, "Missing: xdotool"));
+    }
+}
@@ -0,0 +1,49 @@
+/// Errors surfaced to the UI via the `error` event.
+///
+/// The event carries `code` so the frontend can branch on it, a summary
+/// from `i18n::error_summary` in the UI language, and `message` with the
+/// underlying detail.
+#[derive(Debug, Clone)]
+pub enum AppError {
+    /// No input device, or the recorder couldn't be initialized.
+    AudioDevice(String),
+    /// The input stream failed to start or died while recording.
+    AudioStream(String),
+    /// A transcription was requested but no model is loaded.
+    ModelNotLoaded,
+    /// The configured model file doesn't exist (yet), e.g. it is still
+    /// downloading.
+    ModelNotFound(String),
+    /// The model file exists but whisper couldn't load it.
+    ModelLoad(String),
+    /// whisper failed while transcribing.
+    Transcription(String),
+    /// Text couldn't be delivered to the focused window.
+    Typing(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AudioDevice(_) => "audio_device",
+            Self::AudioStream(_) => "audio_stream",
+            Self::ModelNotLoaded => "model_not_loaded",
+            Self::ModelNotFound(_) => "model_not_found",
+            Self::ModelLoad(_) => "model_load",
+            Self::Transcription(_) => "transcription",
+            Self::Typing(_) => "typing",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::ModelNotLoaded => "Model not loaded".to_string(),
+            Self::ModelNotFound(path) => format!("Model file not found: {path}"),
+            Self::AudioDevice(m)
+            | Self::AudioStream(m)
+            | Self::ModelLoad(m)
+            | Self::Transcription(m)
+            | Self::Typing(m) => m.clone(),
+        }
+    }
+}
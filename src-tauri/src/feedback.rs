@@ -0,0 +1,312 @@
+//! Sound output on the default output device: short confirmation beeps on
+//! recording start and stop, playback of the last recording, and
+//! monitoring of the input while recording.
+
+use crate::audio::{self, TARGET_SAMPLE_RATE};
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+const BEEP_LENGTH: Duration = Duration::from_millis(90);
+/// Peak amplitude; beeps should be noticeable, not startling.
+const VOLUME: f32 = 0.2;
+/// Kept playing after a recording's end, for the device's buffering.
+const PLAYBACK_TAIL: Duration = Duration::from_millis(300);
+/// Most input the monitor holds back; older samples are dropped (a short
+/// skip) rather than letting the delay grow when the two devices' clocks
+/// drift apart.
+const MONITOR_LATENCY: Duration = Duration::from_millis(60);
+/// Samples the output callback takes from the monitor's ring at a time.
+const MONITOR_CHUNK: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Beep {
+    /// Higher pitch: recording started.
+    Start,
+    /// Lower pitch: back to idle.
+    Stop,
+}
+
+impl Beep {
+    fn frequency(self) -> f32 {
+        match self {
+            Beep::Start => 880.0,
+            Beep::Stop => 587.0,
+        }
+    }
+}
+
+/// Play `beep` on a background thread; never blocks the caller.
+pub fn play(beep: Beep) {
+    std::thread::spawn(move || {
+        if let Err(e) = play_blocking(beep) {
+            log::warn!("Feedback sound failed: {e}");
+        }
+    });
+}
+
+fn play_blocking(beep: Beep) -> Result<()> {
+    let device = default_output_device()?;
+    let config = device.default_output_config()?;
+    let frequency = beep.frequency();
+    let sample_rate = config.sample_rate().0 as f32;
+    let total = (sample_rate * BEEP_LENGTH.as_secs_f32()) as usize;
+    let mut n = 0usize;
+    let tone = move || {
+        let value = if n < total {
+            // Sine envelope, so the beep starts and ends without a click.
+            let envelope = (std::f32::consts::PI * n as f32 / total as f32).sin();
+            let phase = 2.0 * std::f32::consts::PI * frequency * n as f32;
+            VOLUME * envelope * (phase / sample_rate).sin()
+        } else {
+            0.0
+        };
+        n += 1;
+        value
+    };
+    let stream = open_stream(&device, &config, tone)?;
+    stream.play()?;
+    // Some headroom for the device's buffering before the stream is dropped.
+    std::thread::sleep(BEEP_LENGTH * 3);
+    Ok(())
+}
+
+/// Play `samples` (16kHz mono) on a background thread, on a stream of its
+/// own, so a recording can go on meanwhile. Returns how long playback
+/// takes once it has started, or why it couldn't.
+pub fn play_recording(samples: Vec<f32>) -> Result<Duration> {
+    let (started_tx, started_rx) = mpsc::channel();
+    // The stream can't leave the thread that opened it on every platform,
+    // so that thread also keeps it alive until the end.
+    std::thread::spawn(move || match open_playback(&samples) {
+        Ok((stream, length)) => {
+            let _ = started_tx.send(Ok(length));
+            std::thread::sleep(length + PLAYBACK_TAIL);
+            drop(stream);
+        }
+        Err(e) => {
+            let _ = started_tx.send(Err(e));
+        }
+    });
+    started_rx.recv().context("Playback thread stopped")?
+}
+
+fn open_playback(samples: &[f32]) -> Result<(cpal::Stream, Duration)> {
+    let device = default_output_device()?;
+    let config = device.default_output_config()?;
+    let rate = config.sample_rate().0;
+    let samples = audio::resample(
+        samples,
+        TARGET_SAMPLE_RATE,
+        rate,
+        audio::ResampleQuality::Fast,
+    );
+    let length = Duration::from_secs_f32(samples.len() as f32 / rate as f32);
+    let mut samples = samples.into_iter();
+    let stream = open_stream(&device, &config, move || samples.next().unwrap_or(0.0))?;
+    stream.play()?;
+    Ok((stream, length))
+}
+
+/// Captured samples on their way to the monitor's output stream. Cloned
+/// into the capture callback, which only ever `push`es.
+#[derive(Clone)]
+pub struct MonitorFeed {
+    ring: Arc<Mutex<VecDeque<f32>>>,
+    /// `MONITOR_LATENCY` in input samples.
+    capacity: usize,
+}
+
+impl MonitorFeed {
+    fn new(input_rate: u32) -> Self {
+        let capacity = (input_rate as f32 * MONITOR_LATENCY.as_secs_f32()) as usize;
+        Self {
+            ring: Arc::new(Mutex::new(VecDeque::with_capacity(2 * capacity))),
+            capacity,
+        }
+    }
+
+    /// Queue captured (mono, device-rate) samples for the output. Never
+    /// waits: while the output side holds the ring, the block is skipped.
+    pub fn push(&self, samples: &[f32]) {
+        let Some(mut ring) = self.ring.try_lock() else {
+            return;
+        };
+        ring.extend(samples);
+        let excess = ring.len().saturating_sub(self.capacity);
+        ring.drain(..excess);
+    }
+
+    /// Move up to `MONITOR_CHUNK` queued samples into `out`.
+    fn take_into(&self, out: &mut VecDeque<f32>) {
+        let mut ring = self.ring.lock();
+        let n = ring.len().min(MONITOR_CHUNK);
+        out.extend(ring.drain(..n));
+    }
+}
+
+/// The live input played back on the default output while recording
+/// (sidetone), at `volume`. Stops when dropped.
+pub struct Monitor {
+    feed: MonitorFeed,
+    /// Dropping it ends the thread keeping the output stream.
+    _stop: mpsc::Sender<()>,
+}
+
+impl Monitor {
+    /// Monitor input captured at `input_rate`. Fails if there's no output
+    /// device or its stream can't be opened; capture doesn't depend on it.
+    pub fn start(input_rate: u32, volume: f32) -> Result<Self> {
+        let feed = MonitorFeed::new(input_rate);
+        let (started_tx, started_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let output = feed.clone();
+        // As in `play_recording`, the stream stays on the thread that opened it.
+        std::thread::spawn(move || match open_monitor(output, input_rate, volume) {
+            Ok(stream) => {
+                let _ = started_tx.send(Ok(()));
+                let _ = stop_rx.recv();
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = started_tx.send(Err(e));
+            }
+        });
+        started_rx.recv().context("Monitor thread stopped")??;
+        log::info!("Input monitoring started at volume {volume}");
+        Ok(Self {
+            feed,
+            _stop: stop_tx,
+        })
+    }
+
+    /// The end the capture callback feeds.
+    pub fn feed(&self) -> MonitorFeed {
+        self.feed.clone()
+    }
+}
+
+fn open_monitor(feed: MonitorFeed, input_rate: u32, volume: f32) -> Result<cpal::Stream> {
+    let device = default_output_device()?;
+    let config = device.default_output_config()?;
+    // Linear interpolation from the input rate to the output's: cheap,
+    // and plenty for hearing yourself.
+    let step = input_rate as f64 / config.sample_rate().0 as f64;
+    let stream = open_stream(&device, &config, monitor_reader(feed, step, volume))?;
+    stream.play()?;
+    Ok(stream)
+}
+
+/// Output samples from `feed`, `step` input samples apart, at `volume`.
+/// Each lags the input by one sample, interpolating towards the newest.
+fn monitor_reader(feed: MonitorFeed, step: f64, volume: f32) -> impl FnMut() -> f32 + Send {
+    let mut pending = VecDeque::with_capacity(MONITOR_CHUNK);
+    let (mut position, mut previous, mut current) = (0.0f64, 0.0f32, 0.0f32);
+    move || {
+        position += step;
+        while position >= 1.0 {
+            position -= 1.0;
+            if pending.is_empty() {
+                feed.take_into(&mut pending);
+            }
+            previous = current;
+            // Caught up with the input: silence until more arrives.
+            current = pending.pop_front().unwrap_or(0.0);
+        }
+        volume * (previous + (current - previous) * position as f32)
+    }
+}
+
+fn default_output_device() -> Result<cpal::Device> {
+    cpal::default_host()
+        .default_output_device()
+        .context("No output audio device found")
+}
+
+/// Output stream in the device's sample format, playing one value from
+/// `next` on all channels of each frame.
+fn open_stream(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    next: impl FnMut() -> f32 + Send + 'static,
+) -> Result<cpal::Stream> {
+    let stream_config: cpal::StreamConfig = config.clone().into();
+    let stream = match config.sample_format() {
+        SampleFormat::I8 => build_stream::<i8>(device, &stream_config, next)?,
+        SampleFormat::I16 => build_stream::<i16>(device, &stream_config, next)?,
+        SampleFormat::I32 => build_stream::<i32>(device, &stream_config, next)?,
+        SampleFormat::U8 => build_stream::<u8>(device, &stream_config, next)?,
+        SampleFormat::U16 => build_stream::<u16>(device, &stream_config, next)?,
+        SampleFormat::U32 => build_stream::<u32>(device, &stream_config, next)?,
+        SampleFormat::F32 => build_stream::<f32>(device, &stream_config, next)?,
+        SampleFormat::F64 => build_stream::<f64>(device, &stream_config, next)?,
+        format => anyhow::bail!("Unsupported sample format: {format:?}"),
+    };
+    Ok(stream)
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut next: impl FnMut() -> f32 + Send + 'static,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for frame in data.chunks_mut(channels) {
+                frame.fill(T::from_sample(next()));
+            }
+        },
+        |err| log::warn!("Output stream error: {err}"),
+        None,
+    )?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(len: usize) -> Vec<f32> {
+        (0..len).map(|i| i as f32).collect()
+    }
+
+    #[test]
+    fn the_feed_keeps_only_the_latest_samples() {
+        // 60ms at 1kHz.
+        let feed = MonitorFeed::new(1000);
+        feed.push(&ramp(100));
+        let mut out = VecDeque::new();
+        feed.take_into(&mut out);
+        assert_eq!(Vec::from(out), ramp(100)[40..]);
+    }
+
+    #[test]
+    fn the_output_takes_a_chunk_at_a_time() {
+        let feed = MonitorFeed::new(48_000);
+        feed.push(&ramp(1000));
+        let mut out = VecDeque::new();
+        feed.take_into(&mut out);
+        assert_eq!(Vec::from(out.clone()), ramp(MONITOR_CHUNK));
+        feed.take_into(&mut out);
+        assert_eq!(out.len(), 2 * MONITOR_CHUNK);
+        assert_eq!(out[MONITOR_CHUNK], MONITOR_CHUNK as f32);
+    }
+
+    #[test]
+    fn at_the_input_rate_samples_come_out_one_late_then_silence() {
+        let feed = MonitorFeed::new(48_000);
+        feed.push(&[0.2, 0.4, 0.6]);
+        let mut next = monitor_reader(feed, 1.0, 0.5);
+        let played: Vec<f32> = (0..6).map(|_| next()).collect();
+        assert_eq!(played, [0.0, 0.1, 0.2, 0.3, 0.0, 0.0]);
+    }
+}
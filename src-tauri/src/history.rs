@@ -0,0 +1,193 @@
+use crate::audio::TARGET_SAMPLE_RATE;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One completed (final-pass) transcription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time in milliseconds when the transcription finished.
+    pub timestamp_ms: u64,
+    pub text: String,
+    pub language: String,
+    /// Length of the recorded audio in seconds.
+    pub duration_secs: f32,
+    /// Missing in entries written before summaries existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SessionSummary>,
+}
+
+impl HistoryEntry {
+    pub fn new(text: String, language: String, summary: SessionSummary) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Self {
+            timestamp_ms,
+            text,
+            language,
+            duration_secs: summary.duration_secs,
+            summary: Some(summary),
+        }
+    }
+}
+
+/// Figures about one transcription, sent as `session-summary` after a
+/// recording and kept with its history entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// Length of the recorded audio in seconds.
+    pub duration_secs: f32,
+    /// Words in the final (post-processed) text.
+    pub words: usize,
+    /// Mean probability of whisper's words; `None` if it produced none.
+    pub avg_confidence: Option<f32>,
+    /// Wall time of the final pass.
+    pub transcribe_ms: u64,
+    /// Whether the model was loaded with GPU offload requested.
+    pub gpu: bool,
+}
+
+impl SessionSummary {
+    /// `samples` is the transcribed audio's length at 16kHz, `confidence`
+    /// the probabilities of the words whisper returned.
+    pub fn new(
+        text: &str,
+        samples: usize,
+        confidence: impl IntoIterator<Item = f32>,
+        elapsed: Duration,
+        gpu: bool,
+    ) -> Self {
+        let (sum, count) = confidence
+            .into_iter()
+            .fold((0.0, 0usize), |(sum, count), p| (sum + p, count + 1));
+        Self {
+            duration_secs: samples as f32 / TARGET_SAMPLE_RATE as f32,
+            words: text.split_whitespace().count(),
+            avg_confidence: (count > 0).then(|| sum / count as f32),
+            transcribe_ms: elapsed.as_millis() as u64,
+            gpu,
+        }
+    }
+}
+
+enum FileOp {
+    Append(HistoryEntry),
+    Clear,
+}
+
+/// Bounded in-memory log of recent transcriptions, optionally mirrored to a
+/// JSONL file. File I/O happens on a background thread so recording an entry
+/// never blocks the worker.
+pub struct History {
+    entries: Mutex<VecDeque<HistoryEntry>>,
+    file_tx: Mutex<mpsc::Sender<FileOp>>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        let (file_tx, file_rx) = mpsc::channel();
+        std::thread::spawn(move || run_file_writer(file_rx, history_file_path()));
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            file_tx: Mutex::new(file_tx),
+        }
+    }
+
+    /// Add an entry, dropping the oldest ones beyond `max_entries`.
+    pub fn record(&self, entry: HistoryEntry, max_entries: usize, persist: bool) {
+        if persist {
+            let _ = self.file_tx.lock().send(FileOp::Append(entry.clone()));
+        }
+        let mut entries = self.entries.lock();
+        entries.push_back(entry);
+        while entries.len() > max_entries {
+            entries.pop_front();
+        }
+    }
+
+    /// Up to `limit` most recent entries, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<HistoryEntry> {
+        self.entries
+            .lock()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Forget all entries and remove the history file.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+        let _ = self.file_tx.lock().send(FileOp::Clear);
+    }
+}
+
+pub fn history_file_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("voice-to-text")
+        .join("history.jsonl")
+}
+
+fn run_file_writer(rx: mpsc::Receiver<FileOp>, path: PathBuf) {
+    while let Ok(op) = rx.recv() {
+        let result = match op {
+            FileOp::Append(entry) => append_line(&path, &entry),
+            FileOp::Clear => match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            log::error!("History file {}: {e}", path.display());
+        }
+    }
+}
+
+fn append_line(path: &PathBuf, entry: &HistoryEntry) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_words_and_duration() {
+        let summary = SessionSummary::new(
+            "  Hello there,\nhow are you? ",
+            24_000,
+            [0.5, 1.0, 0.75],
+            Duration::from_millis(1234),
+            true,
+        );
+        assert_eq!(summary.words, 5);
+        assert_eq!(summary.duration_secs, 1.5);
+        assert_eq!(summary.avg_confidence, Some(0.75));
+        assert_eq!(summary.transcribe_ms, 1234);
+        assert!(summary.gpu);
+    }
+
+    #[test]
+    fn summary_without_words_has_no_confidence() {
+        let summary = SessionSummary::new("", 0, [], Duration::ZERO, false);
+        assert_eq!(summary.words, 0);
+        assert_eq!(summary.duration_secs, 0.0);
+        assert_eq!(summary.avg_confidence, None);
+    }
+}
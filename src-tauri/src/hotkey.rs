@@ -1,41 +1,939 @@
-use std::sync::mpsc;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+/// A key that gestures are made of: a modifier, tapped on its own or in a
+/// combo, or Space at the end of a combo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyKey {
+    /// Left Alt (AltGr is ignored, since it's used for typing).
+    Alt,
+    /// Either Ctrl.
+    Ctrl,
+    /// Either Shift.
+    Shift,
+    /// Either Super/Windows/Command key.
+    Super,
+    /// Only with modifiers held (`Binding::held`); on its own it's typing.
+    Space,
+}
+
+impl HotkeyKey {
+    fn from_rdev(key: &rdev::Key) -> Option<Self> {
+        match key {
+            rdev::Key::Alt => Some(HotkeyKey::Alt),
+            rdev::Key::ControlLeft | rdev::Key::ControlRight => Some(HotkeyKey::Ctrl),
+            rdev::Key::ShiftLeft | rdev::Key::ShiftRight => Some(HotkeyKey::Shift),
+            rdev::Key::MetaLeft | rdev::Key::MetaRight => Some(HotkeyKey::Super),
+            rdev::Key::Space => Some(HotkeyKey::Space),
+            _ => None,
+        }
+    }
+
+    fn is_modifier(self) -> bool {
+        self != HotkeyKey::Space
+    }
+}
+
+/// What a gesture does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Start or finish a recording.
+    Toggle,
+    /// Abort the recording without a final pass.
+    Cancel,
+    /// Like `Toggle`, but the recording is translated into English.
+    ToggleTranslate,
+    /// Swap the language and the secondary language.
+    SwitchLanguage,
+    /// Move on to the next of the cycle languages.
+    CycleLanguage,
+    /// Backspace over what the last dictation typed.
+    Undo,
+}
+
+/// `presses` taps of `key` in a row, with the `held` keys held down
+/// throughout, trigger `action`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub key: HotkeyKey,
+    /// 2 or 3, or 1 for a combo (with `held` keys); other counts never match.
+    pub presses: u32,
+    /// Keys that have to be down while `key` is tapped, and only those:
+    /// without any, `key` is tapped on its own. With one press it's a combo
+    /// like Ctrl+Alt+Space, whose keys may go down and up in any order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held: Vec<HotkeyKey>,
+    pub action: HotkeyAction,
+}
+
+impl Binding {
+    /// Whether tap number `presses` of `key`, released while exactly the
+    /// `held` keys were down, is this gesture.
+    fn matches(&self, key: HotkeyKey, presses: u32, held: &[HotkeyKey]) -> bool {
+        if presses != self.presses {
+            return false;
+        }
+        if presses == 1 {
+            // A combo: which of its keys comes up first doesn't matter.
+            let ours: BTreeSet<HotkeyKey> = self.held.iter().copied().chain([self.key]).collect();
+            let theirs: BTreeSet<HotkeyKey> = held.iter().copied().chain([key]).collect();
+            !self.held.is_empty() && ours == theirs
+        } else {
+            let ours: BTreeSet<HotkeyKey> = self.held.iter().copied().collect();
+            key == self.key && ours == held.iter().copied().collect::<BTreeSet<_>>()
+        }
+    }
+}
+
+/// How much the detector reports as `HotkeyEvent::Debug`, for "my hotkey
+/// doesn't trigger" reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyDebug {
+    #[default]
+    Off,
+    /// Gesture key releases and the detector's decisions; other keys show
+    /// up only as "another key".
+    Gestures,
+    /// Also which other key was pressed. It amounts to a keylogger while
+    /// on, so it has to be asked for explicitly.
+    AllKeys,
+}
+
+/// What the detector made of an input or a timeout.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Decision {
+    /// `key` was released: tap number `presses` of its gesture. `gap_ms` is
+    /// the time since its previous release; counting started over at 1 if
+    /// that was `window_ms` or more.
+    Counted {
+        key: HotkeyKey,
+        presses: u32,
+        /// The other gesture keys that were down.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        held: Vec<HotkeyKey>,
+        gap_ms: Option<u64>,
+        window_ms: u64,
+    },
+    /// A double press that waits for a possible third before it fires.
+    Waiting { key: HotkeyKey },
+    Fired {
+        key: HotkeyKey,
+        presses: u32,
+        action: HotkeyAction,
+    },
+    /// Another key was pressed, disarming the gestures of `keys`.
+    Disarmed { keys: Vec<HotkeyKey> },
+}
+
+/// One detector step, with `HotkeyDebug` on.
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugStep {
+    /// Since the detector started, so gaps can be read off.
+    pub at_ms: u64,
+    /// `"release alt"`, `"press other"` (or the key, with `AllKeys`) or
+    /// `"timeout"`.
+    pub input: String,
+    pub decisions: Vec<Decision>,
+}
+
 #[derive(Debug)]
 pub enum HotkeyEvent {
-    DoubleAlt,
+    Action(HotkeyAction),
+    Debug(DebugStep),
+    /// The OS refused to deliver global key events (macOS Accessibility).
+    PermissionRequired,
+    /// The listener stopped and was started again; the failure count so far.
+    Restarted(u32),
+    /// The listener kept failing and was given up on; the last error.
+    Failed(String),
 }
 
-const DOUBLE_PRESS_WINDOW: Duration = Duration::from_millis(400);
+/// First wait before restarting a listener that stopped; doubles with each
+/// failure in a row, up to `MAX_RESTART_BACKOFF`.
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+/// Failures in a row after which the listener isn't restarted any more.
+const MAX_RESTARTS: u32 = 5;
+/// A listener that ran this long before it failed was working: the failure
+/// count starts over.
+const STABLE_RUN: Duration = Duration::from_secs(60);
 
-pub fn start_listener(tx: mpsc::Sender<HotkeyEvent>) {
-    thread::spawn(move || {
-        let mut last_release = Instant::now() - Duration::from_secs(60);
-        let mut armed = false;
-
-        if let Err(e) = rdev::listen(move |event: rdev::Event| {
-            match event.event_type {
-                rdev::EventType::KeyRelease(rdev::Key::Alt) => {
-                    let now = Instant::now();
-                    if armed && now.duration_since(last_release) < DOUBLE_PRESS_WINDOW {
-                        let _ = tx.send(HotkeyEvent::DoubleAlt);
-                        armed = false;
-                    } else {
-                        armed = true;
-                    }
-                    last_release = now;
-                }
-                // Any non-Alt keypress disarms
-                rdev::EventType::KeyPress(key)
-                    if !matches!(key, rdev::Key::Alt | rdev::Key::AltGr) =>
-                {
-                    armed = false;
+/// Gesture timing and bindings, shared with the app so settings changes
+/// apply without restarting the listener.
+#[derive(Debug, Clone)]
+pub struct HotkeyConfig {
+    /// Maximum gap between consecutive releases of one gesture.
+    pub window: Duration,
+    /// A key with both a double and a triple press bound fires its double
+    /// press only once the window has passed without a third one, adding
+    /// that much latency.
+    pub bindings: Vec<Binding>,
+    pub debug: HotkeyDebug,
+}
+
+impl HotkeyConfig {
+    fn action(&self, key: HotkeyKey, presses: u32, held: &[HotkeyKey]) -> Option<HotkeyAction> {
+        self.bindings
+            .iter()
+            .find(|b| b.matches(key, presses, held))
+            .map(|b| b.action)
+    }
+
+    /// Whether a double press of `key` with `held` down has to wait for a
+    /// possible third.
+    fn waits_for_triple(&self, key: HotkeyKey, held: &[HotkeyKey]) -> bool {
+        self.action(key, 2, held).is_some() && self.action(key, 3, held).is_some()
+    }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(400),
+            bindings: default_bindings(),
+            debug: HotkeyDebug::Off,
+        }
+    }
+}
+
+/// Double Alt toggles recording.
+pub fn default_bindings() -> Vec<Binding> {
+    vec![Binding {
+        key: HotkeyKey::Alt,
+        presses: 2,
+        held: Vec::new(),
+        action: HotkeyAction::Toggle,
+    }]
+}
+
+/// Key events the detector cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyInput {
+    Press(HotkeyKey),
+    Release(HotkeyKey),
+    /// Any keypress other than a gesture key; which one only matters to
+    /// `HotkeyDebug::AllKeys`.
+    OtherPress(rdev::Key),
+}
+
+impl KeyInput {
+    fn describe(self, debug: HotkeyDebug) -> String {
+        match self {
+            // Space is mostly typing, so it's named only where other keys are.
+            KeyInput::Press(key) if !key.is_modifier() && debug != HotkeyDebug::AllKeys => {
+                "press other".to_string()
+            }
+            KeyInput::Release(key) if !key.is_modifier() && debug != HotkeyDebug::AllKeys => {
+                "release other".to_string()
+            }
+            KeyInput::Press(key) => format!("press {key:?}").to_lowercase(),
+            KeyInput::Release(key) => format!("release {key:?}").to_lowercase(),
+            KeyInput::OtherPress(key) if debug == HotkeyDebug::AllKeys => {
+                format!("press {key:?}")
+            }
+            KeyInput::OtherPress(_) => "press other".to_string(),
+        }
+    }
+
+    /// Typing rather than a gesture, as far as `HotkeyDebug::Gestures`
+    /// goes; a modifier's press only matters for what's held.
+    fn is_quiet(self) -> bool {
+        match self {
+            KeyInput::Press(_) | KeyInput::OtherPress(_) => true,
+            KeyInput::Release(key) => !key.is_modifier(),
+        }
+    }
+}
+
+/// Map an rdev event to detector input. AltGr is ignored, so it neither
+/// counts nor disarms.
+fn classify(event: &rdev::EventType) -> Option<KeyInput> {
+    match event {
+        rdev::EventType::KeyRelease(key) => HotkeyKey::from_rdev(key).map(KeyInput::Release),
+        rdev::EventType::KeyPress(rdev::Key::AltGr) => None,
+        rdev::EventType::KeyPress(key) => {
+            Some(HotkeyKey::from_rdev(key).map_or(KeyInput::OtherPress(*key), KeyInput::Press))
+        }
+        _ => None,
+    }
+}
+
+/// Taps of one key in the current gesture.
+#[derive(Debug, Default, Clone)]
+struct KeyPresses {
+    presses: u32,
+    last_release: Option<Instant>,
+    /// The other keys down during these taps; a tap with different ones
+    /// starts another gesture.
+    held: Vec<HotkeyKey>,
+}
+
+/// Press-counting state machine, tracking each key on its own: tapping
+/// Ctrl between two Alt taps doesn't disarm the Alt gesture. It never reads
+/// the clock itself: inputs carry their timestamp, and the caller asks for
+/// the `deadline` of a pending double press and reports `on_timeout` once
+/// it has passed.
+#[derive(Debug, Default)]
+struct PressDetector {
+    keys: HashMap<HotkeyKey, KeyPresses>,
+    /// Gesture keys down right now.
+    down: BTreeSet<HotkeyKey>,
+    /// Keys that were down while another key went up or was pressed: they
+    /// were held, so their own release isn't a tap.
+    chorded: HashSet<HotkeyKey>,
+    /// Decisions since the last `take_decisions`.
+    decisions: Vec<Decision>,
+}
+
+impl PressDetector {
+    /// When `key`'s completed double press fires unless a third press
+    /// arrives; `None` if nothing is pending for it.
+    fn key_deadline(&self, key: HotkeyKey, config: &HotkeyConfig) -> Option<Instant> {
+        let state = self.keys.get(&key)?;
+        if state.presses == 2 && config.waits_for_triple(key, &state.held) {
+            state.last_release.map(|at| at + config.window)
+        } else {
+            None
+        }
+    }
+
+    /// The earliest pending double press's deadline, if any.
+    fn deadline(&self, config: &HotkeyConfig) -> Option<Instant> {
+        self.keys
+            .keys()
+            .filter_map(|&key| self.key_deadline(key, config))
+            .min()
+    }
+
+    /// What the last inputs and timeouts were made of.
+    fn take_decisions(&mut self) -> Vec<Decision> {
+        std::mem::take(&mut self.decisions)
+    }
+
+    /// Fire `key`'s pending double press.
+    fn fire_double(&mut self, key: HotkeyKey, config: &HotkeyConfig) -> Option<HotkeyAction> {
+        let state = self.keys.remove(&key)?;
+        let action = config.action(key, 2, &state.held)?;
+        self.decisions.push(Decision::Fired {
+            key,
+            presses: 2,
+            action,
+        });
+        Some(action)
+    }
+
+    /// Fire the double presses whose deadline passed by `now` without a
+    /// third press.
+    fn on_timeout(&mut self, now: Instant, config: &HotkeyConfig) -> Vec<HotkeyAction> {
+        let due: Vec<HotkeyKey> = self
+            .keys
+            .keys()
+            .copied()
+            .filter(|&key| self.key_deadline(key, config).is_some_and(|at| at <= now))
+            .collect();
+        due.into_iter()
+            .filter_map(|key| self.fire_double(key, config))
+            .collect()
+    }
+
+    fn on_input(
+        &mut self,
+        input: KeyInput,
+        at: Instant,
+        config: &HotkeyConfig,
+    ) -> Vec<HotkeyAction> {
+        match input {
+            // Space without a modifier held is typing.
+            KeyInput::Press(key) if !key.is_modifier() && self.down.is_empty() => {
+                self.on_other_press(config)
+            }
+            KeyInput::Press(key) => {
+                self.down.insert(key);
+                Vec::new()
+            }
+            KeyInput::Release(key) => {
+                let was_down = self.down.remove(&key);
+                // The end of a hold, or of a space that was typed.
+                if self.chorded.remove(&key) || (!key.is_modifier() && !was_down) {
+                    return Vec::new();
                 }
-                _ => {}
+                let held: Vec<HotkeyKey> = self.down.iter().copied().collect();
+                self.chorded.extend(held.iter().copied());
+                self.on_tap(key, held, at, config)
+            }
+            KeyInput::OtherPress(_) => {
+                self.chorded.extend(self.down.iter().copied());
+                self.on_other_press(config)
+            }
+        }
+    }
+
+    fn on_tap(
+        &mut self,
+        key: HotkeyKey,
+        held: Vec<HotkeyKey>,
+        at: Instant,
+        config: &HotkeyConfig,
+    ) -> Vec<HotkeyAction> {
+        let state = self.keys.entry(key).or_default();
+        let gap = state
+            .last_release
+            .map(|last| at.saturating_duration_since(last));
+        let within = gap.is_some_and(|gap| gap < config.window);
+        state.presses = if state.presses > 0 && within && state.held == held {
+            state.presses + 1
+        } else {
+            1
+        };
+        state.last_release = Some(at);
+        state.held = held.clone();
+
+        let presses = state.presses;
+        self.decisions.push(Decision::Counted {
+            key,
+            presses,
+            held: held.clone(),
+            gap_ms: gap.map(|gap| gap.as_millis() as u64),
+            window_ms: config.window.as_millis() as u64,
+        });
+        let fired = match presses {
+            1 | 3 => config.action(key, presses, &held),
+            2 if !config.waits_for_triple(key, &held) => config.action(key, 2, &held),
+            2 => {
+                self.decisions.push(Decision::Waiting { key });
+                None
             }
-        }) {
-            log::error!("Hotkey listener failed: {e:?}");
+            _ => None,
+        };
+        if let Some(action) = fired {
+            self.decisions.push(Decision::Fired {
+                key,
+                presses,
+                action,
+            });
+        }
+        // Nothing more can follow a triple press, or a combo or double
+        // press that fired.
+        if fired.is_some() || presses >= 3 {
+            self.keys.remove(&key);
+        }
+        fired.into_iter().collect()
+    }
+
+    fn on_other_press(&mut self, config: &HotkeyConfig) -> Vec<HotkeyAction> {
+        // Typing after a completed double press means no third one is
+        // coming; before that, any other key disarms.
+        let (pending, disarmed): (Vec<HotkeyKey>, Vec<HotkeyKey>) = self
+            .keys
+            .keys()
+            .partition(|&&key| self.key_deadline(key, config).is_some());
+        for key in &disarmed {
+            self.keys.remove(key);
+        }
+        if !disarmed.is_empty() {
+            self.decisions.push(Decision::Disarmed { keys: disarmed });
+        }
+        pending
+            .into_iter()
+            .filter_map(|key| self.fire_double(key, config))
+            .collect()
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+}
+
+/// Whether the process may observe global key events.
+///
+/// On macOS both `rdev::listen` (CGEventTap) and synthesized paste keystrokes
+/// require the app to be listed under System Settings → Privacy & Security →
+/// Accessibility. The first time an untrusted binary creates an event tap,
+/// macOS shows its own prompt pointing the user there; the app has to be
+/// restarted after the permission is granted. Other platforms need nothing.
+fn has_input_permission() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        unsafe { AXIsProcessTrusted() }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        true
+    }
+}
+
+pub fn start_listener(tx: mpsc::Sender<HotkeyEvent>, config: Arc<Mutex<HotkeyConfig>>) {
+    let (key_tx, key_rx) = mpsc::channel();
+    let detector_tx = tx.clone();
+    thread::spawn(move || detect_presses(key_rx, detector_tx, config));
+
+    thread::spawn(move || {
+        if !has_input_permission() {
+            log::warn!("Accessibility permission missing, global hotkey unavailable");
+            let _ = tx.send(HotkeyEvent::PermissionRequired);
         }
+        supervise_listener(key_tx, tx);
     });
 }
+
+/// Run `rdev::listen`, restarting it with a growing backoff whenever it
+/// returns (on X11 it does when the input devices change under it), until
+/// it fails `MAX_RESTARTS` times in a row.
+fn supervise_listener(key_tx: mpsc::Sender<(KeyInput, Instant)>, tx: mpsc::Sender<HotkeyEvent>) {
+    let mut failures = 0;
+    loop {
+        let started = Instant::now();
+        let listener_tx = key_tx.clone();
+        let result = rdev::listen(move |event: rdev::Event| {
+            if let Some(input) = classify(&event.event_type) {
+                let _ = listener_tx.send((input, Instant::now()));
+            }
+        });
+        let error = match &result {
+            Err(e) => format!("{e:?}"),
+            Ok(()) => "stopped".to_string(),
+        };
+        log::error!("Hotkey listener failed: {error}");
+        // An event tap that can't be created is how a missing permission
+        // surfaces on macOS when the upfront check didn't catch it. It
+        // stays missing until the app is restarted.
+        #[cfg(target_os = "macos")]
+        if matches!(result, Err(rdev::ListenError::EventTapError)) {
+            let _ = tx.send(HotkeyEvent::PermissionRequired);
+            return;
+        }
+
+        if started.elapsed() >= STABLE_RUN {
+            failures = 0;
+        }
+        failures += 1;
+        if failures > MAX_RESTARTS {
+            log::error!("Hotkey listener failed {MAX_RESTARTS} times in a row; giving up");
+            let _ = tx.send(HotkeyEvent::Failed(error));
+            return;
+        }
+        let backoff = RESTART_BACKOFF * 2u32.pow(failures - 1);
+        thread::sleep(backoff.min(MAX_RESTART_BACKOFF));
+        log::info!("Restarting the hotkey listener (failure {failures})");
+        let _ = tx.send(HotkeyEvent::Restarted(failures));
+    }
+}
+
+/// Feed key events to a `PressDetector` and forward the gestures it
+/// recognizes, and with `HotkeyConfig::debug` what it made of each input.
+/// Runs on its own thread so a pending double press can fire on timeout,
+/// when no more key events arrive.
+fn detect_presses(
+    rx: mpsc::Receiver<(KeyInput, Instant)>,
+    tx: mpsc::Sender<HotkeyEvent>,
+    config: Arc<Mutex<HotkeyConfig>>,
+) {
+    let mut detector = PressDetector::default();
+    let started = Instant::now();
+    loop {
+        let config = config.lock().clone();
+        let received = match detector.deadline(&config) {
+            Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let (actions, input, at) = match received {
+            Ok((input, at)) => (detector.on_input(input, at, &config), Some(input), at),
+            Err(RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                (detector.on_timeout(now, &config), None, now)
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let decisions = detector.take_decisions();
+        // Short of `AllKeys`, typing that doesn't touch a gesture isn't
+        // reported at all, not even when it happened.
+        let report = match config.debug {
+            HotkeyDebug::Off => false,
+            HotkeyDebug::Gestures => {
+                !decisions.is_empty() || !input.is_some_and(KeyInput::is_quiet)
+            }
+            HotkeyDebug::AllKeys => true,
+        };
+        if report {
+            let step = DebugStep {
+                at_ms: at.saturating_duration_since(started).as_millis() as u64,
+                input: input.map_or_else(|| "timeout".to_string(), |i| i.describe(config.debug)),
+                decisions,
+            };
+            log::info!("Hotkey debug: {step:?}");
+            let _ = tx.send(HotkeyEvent::Debug(step));
+        }
+        for action in actions {
+            let _ = tx.send(HotkeyEvent::Action(action));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(key: HotkeyKey, presses: u32, action: HotkeyAction) -> Binding {
+        Binding {
+            key,
+            presses,
+            held: Vec::new(),
+            action,
+        }
+    }
+
+    /// A detector driven with inputs at synthetic times, in ms from `start`.
+    struct Keys {
+        detector: PressDetector,
+        config: HotkeyConfig,
+        start: Instant,
+    }
+
+    impl Keys {
+        fn new(bindings: Vec<Binding>) -> Self {
+            Self {
+                detector: PressDetector::default(),
+                config: HotkeyConfig {
+                    bindings,
+                    ..HotkeyConfig::default()
+                },
+                start: Instant::now(),
+            }
+        }
+
+        fn at(&self, ms: u64) -> Instant {
+            self.start + Duration::from_millis(ms)
+        }
+
+        fn input(&mut self, input: KeyInput, ms: u64) -> Vec<HotkeyAction> {
+            let at = self.at(ms);
+            self.detector.on_input(input, at, &self.config)
+        }
+
+        fn tap(&mut self, key: HotkeyKey, ms: u64) -> Vec<HotkeyAction> {
+            let mut actions = self.input(KeyInput::Press(key), ms);
+            actions.extend(self.input(KeyInput::Release(key), ms));
+            actions
+        }
+
+        fn timeout(&mut self, ms: u64) -> Vec<HotkeyAction> {
+            let now = self.at(ms);
+            self.detector.on_timeout(now, &self.config)
+        }
+    }
+
+    fn double_and_triple() -> Vec<Binding> {
+        vec![
+            binding(HotkeyKey::Alt, 2, HotkeyAction::Toggle),
+            binding(HotkeyKey::Alt, 3, HotkeyAction::Cancel),
+        ]
+    }
+
+    #[test]
+    fn window_is_configurable() {
+        let mut keys = Keys::new(default_bindings());
+        keys.config.window = Duration::from_millis(200);
+        assert!(keys.tap(HotkeyKey::Alt, 0).is_empty());
+        assert!(keys.tap(HotkeyKey::Alt, 250).is_empty());
+        assert_eq!(keys.tap(HotkeyKey::Alt, 400), [HotkeyAction::Toggle]);
+    }
+
+    #[test]
+    fn double_press_waits_for_a_possible_third() {
+        let mut keys = Keys::new(double_and_triple());
+        assert!(keys.tap(HotkeyKey::Alt, 0).is_empty());
+        assert!(keys.tap(HotkeyKey::Alt, 200).is_empty());
+        assert_eq!(keys.detector.deadline(&keys.config), Some(keys.at(600)));
+        assert!(keys.timeout(599).is_empty());
+        assert_eq!(keys.timeout(600), [HotkeyAction::Toggle]);
+        assert_eq!(keys.detector.deadline(&keys.config), None);
+    }
+
+    #[test]
+    fn triple_press_replaces_the_double() {
+        let mut keys = Keys::new(double_and_triple());
+        keys.tap(HotkeyKey::Alt, 0);
+        keys.tap(HotkeyKey::Alt, 200);
+        assert_eq!(keys.tap(HotkeyKey::Alt, 400), [HotkeyAction::Cancel]);
+        assert!(keys.timeout(1000).is_empty());
+    }
+
+    #[test]
+    fn typing_after_a_double_press_fires_it_at_once() {
+        let mut keys = Keys::new(double_and_triple());
+        keys.tap(HotkeyKey::Alt, 0);
+        keys.tap(HotkeyKey::Alt, 200);
+        let typed = KeyInput::OtherPress(rdev::Key::KeyA);
+        assert_eq!(keys.input(typed, 300), [HotkeyAction::Toggle]);
+    }
+
+    #[test]
+    fn two_releases_inside_the_window_fire() {
+        let mut keys = Keys::new(default_bindings());
+        assert!(keys.tap(HotkeyKey::Alt, 0).is_empty());
+        assert_eq!(keys.tap(HotkeyKey::Alt, 399), [HotkeyAction::Toggle]);
+    }
+
+    #[test]
+    fn two_releases_outside_the_window_do_not() {
+        let mut keys = Keys::new(default_bindings());
+        assert!(keys.tap(HotkeyKey::Alt, 0).is_empty());
+        assert!(keys.tap(HotkeyKey::Alt, 400).is_empty());
+    }
+
+    #[test]
+    fn a_keypress_in_between_disarms() {
+        let mut keys = Keys::new(default_bindings());
+        keys.tap(HotkeyKey::Alt, 0);
+        assert!(keys
+            .input(KeyInput::OtherPress(rdev::Key::KeyA), 100)
+            .is_empty());
+        assert!(keys.tap(HotkeyKey::Alt, 200).is_empty());
+    }
+
+    #[test]
+    fn alt_gr_is_ignored() {
+        use rdev::EventType::{KeyPress, KeyRelease};
+        use rdev::Key;
+
+        assert_eq!(classify(&KeyPress(Key::AltGr)), None);
+        assert_eq!(classify(&KeyRelease(Key::AltGr)), None);
+
+        let mut keys = Keys::new(default_bindings());
+        let events = [
+            (KeyPress(Key::Alt), 0),
+            (KeyRelease(Key::Alt), 50),
+            (KeyPress(Key::AltGr), 100),
+            (KeyRelease(Key::AltGr), 150),
+            (KeyPress(Key::Alt), 200),
+            (KeyRelease(Key::Alt), 250),
+        ];
+        let mut actions = Vec::new();
+        for (event, ms) in events {
+            if let Some(input) = classify(&event) {
+                actions.extend(keys.input(input, ms));
+            }
+        }
+        assert_eq!(actions, [HotkeyAction::Toggle]);
+    }
+
+    #[test]
+    fn one_keys_presses_do_not_disarm_another_keys_gesture() {
+        let mut keys = Keys::new(vec![
+            binding(HotkeyKey::Alt, 2, HotkeyAction::Toggle),
+            binding(HotkeyKey::Ctrl, 2, HotkeyAction::Cancel),
+        ]);
+        assert!(keys.tap(HotkeyKey::Alt, 0).is_empty());
+        assert!(keys.tap(HotkeyKey::Ctrl, 100).is_empty());
+        assert_eq!(keys.tap(HotkeyKey::Alt, 200), [HotkeyAction::Toggle]);
+        assert_eq!(keys.tap(HotkeyKey::Ctrl, 300), [HotkeyAction::Cancel]);
+    }
+
+    fn combo(key: HotkeyKey, held: &[HotkeyKey], action: HotkeyAction) -> Binding {
+        Binding {
+            held: held.to_vec(),
+            ..binding(key, 1, action)
+        }
+    }
+
+    #[test]
+    fn combo_fires_once_its_keys_are_down() {
+        use HotkeyKey::{Alt, Ctrl, Space};
+        let mut keys = Keys::new(vec![combo(Space, &[Ctrl, Alt], HotkeyAction::Toggle)]);
+        keys.input(KeyInput::Press(Ctrl), 0);
+        keys.input(KeyInput::Press(Alt), 10);
+        keys.input(KeyInput::Press(Space), 20);
+        assert_eq!(
+            keys.input(KeyInput::Release(Space), 30),
+            [HotkeyAction::Toggle]
+        );
+        // Letting go of the held keys isn't a tap of them.
+        assert!(keys.input(KeyInput::Release(Alt), 40).is_empty());
+        assert!(keys.input(KeyInput::Release(Ctrl), 50).is_empty());
+        assert!(keys.detector.keys.is_empty());
+    }
+
+    #[test]
+    fn combo_keys_may_come_up_in_any_order() {
+        use HotkeyKey::{Alt, Ctrl, Space};
+        let mut keys = Keys::new(vec![combo(Space, &[Ctrl, Alt], HotkeyAction::Toggle)]);
+        keys.input(KeyInput::Press(Ctrl), 0);
+        keys.input(KeyInput::Press(Space), 10);
+        keys.input(KeyInput::Press(Alt), 20);
+        assert_eq!(
+            keys.input(KeyInput::Release(Ctrl), 30),
+            [HotkeyAction::Toggle]
+        );
+        assert!(keys.input(KeyInput::Release(Space), 40).is_empty());
+        assert!(keys.input(KeyInput::Release(Alt), 50).is_empty());
+    }
+
+    #[test]
+    fn combo_needs_exactly_its_keys() {
+        use HotkeyKey::{Alt, Ctrl, Shift, Space};
+        let mut keys = Keys::new(vec![combo(Space, &[Ctrl, Alt], HotkeyAction::Toggle)]);
+        keys.input(KeyInput::Press(Ctrl), 0);
+        keys.input(KeyInput::Press(Alt), 10);
+        keys.input(KeyInput::Press(Shift), 20);
+        keys.input(KeyInput::Press(Space), 30);
+        assert!(keys.input(KeyInput::Release(Space), 40).is_empty());
+    }
+
+    #[test]
+    fn space_on_its_own_is_typing() {
+        let mut keys = Keys::new(default_bindings());
+        keys.tap(HotkeyKey::Alt, 0);
+        assert!(keys.tap(HotkeyKey::Space, 100).is_empty());
+        assert!(keys.tap(HotkeyKey::Alt, 200).is_empty());
+    }
+
+    #[test]
+    fn double_press_with_a_modifier_held() {
+        use HotkeyKey::{Alt, Ctrl};
+        let mut keys = Keys::new(vec![Binding {
+            held: vec![Ctrl],
+            ..binding(Alt, 2, HotkeyAction::Toggle)
+        }]);
+        keys.input(KeyInput::Press(Ctrl), 0);
+        assert!(keys.tap(Alt, 100).is_empty());
+        assert_eq!(keys.tap(Alt, 200), [HotkeyAction::Toggle]);
+        assert!(keys.input(KeyInput::Release(Ctrl), 300).is_empty());
+        // Without Ctrl it's another gesture.
+        keys.tap(Alt, 400);
+        assert!(keys.tap(Alt, 500).is_empty());
+    }
+
+    /// The kinds of `decisions`, in order.
+    fn kinds(decisions: &[Decision]) -> Vec<&'static str> {
+        decisions
+            .iter()
+            .map(|decision| match decision {
+                Decision::Counted { .. } => "counted",
+                Decision::Waiting { .. } => "waiting",
+                Decision::Fired { .. } => "fired",
+                Decision::Disarmed { .. } => "disarmed",
+            })
+            .collect()
+    }
+
+    /// What `detect_presses` reports for `inputs` (each at its ms), as
+    /// `"<input>: <decision kinds>"` lines and fired actions.
+    fn debug_report(debug: HotkeyDebug, inputs: &[(KeyInput, u64)]) -> Vec<String> {
+        let (key_tx, key_rx) = mpsc::channel();
+        let (tx, rx) = mpsc::channel();
+        let start = Instant::now();
+        for &(input, ms) in inputs {
+            key_tx
+                .send((input, start + Duration::from_millis(ms)))
+                .unwrap();
+        }
+        drop(key_tx);
+        let config = HotkeyConfig {
+            debug,
+            ..HotkeyConfig::default()
+        };
+        // Returns once it has gone through the queued inputs.
+        detect_presses(key_rx, tx, Arc::new(Mutex::new(config)));
+        rx.try_iter()
+            .map(|event| match event {
+                HotkeyEvent::Debug(step) => {
+                    format!("{}: {}", step.input, kinds(&step.decisions).join(" "))
+                }
+                other => format!("{other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decisions_follow_the_detector() {
+        let mut keys = Keys::new(double_and_triple());
+        keys.tap(HotkeyKey::Alt, 0);
+        let decisions = keys.detector.take_decisions();
+        assert!(matches!(
+            decisions[..],
+            [Decision::Counted {
+                presses: 1,
+                gap_ms: None,
+                window_ms: 400,
+                ..
+            }]
+        ));
+        keys.tap(HotkeyKey::Alt, 150);
+        let decisions = keys.detector.take_decisions();
+        assert!(matches!(
+            decisions[..],
+            [
+                Decision::Counted {
+                    presses: 2,
+                    gap_ms: Some(150),
+                    ..
+                },
+                Decision::Waiting {
+                    key: HotkeyKey::Alt
+                }
+            ]
+        ));
+        keys.timeout(550);
+        assert_eq!(kinds(&keys.detector.take_decisions()), ["fired"]);
+
+        keys.tap(HotkeyKey::Alt, 1000);
+        keys.input(KeyInput::OtherPress(rdev::Key::KeyA), 1100);
+        let decisions = keys.detector.take_decisions();
+        assert_eq!(kinds(&decisions), ["counted", "disarmed"]);
+        assert!(matches!(&decisions[1], Decision::Disarmed { keys } if keys == &[HotkeyKey::Alt]));
+    }
+
+    #[test]
+    fn gesture_debugging_leaves_typing_out() {
+        let alt_tap = |ms| {
+            [
+                (KeyInput::Press(HotkeyKey::Alt), ms),
+                (KeyInput::Release(HotkeyKey::Alt), ms),
+            ]
+        };
+        let inputs = [
+            &alt_tap(0)[..],
+            &[(KeyInput::OtherPress(rdev::Key::KeyA), 50)],
+            // Typing with nothing armed.
+            &[(KeyInput::OtherPress(rdev::Key::KeyB), 60)],
+            &alt_tap(100),
+            &alt_tap(200),
+        ]
+        .concat();
+        assert_eq!(
+            debug_report(HotkeyDebug::Gestures, &inputs),
+            [
+                "release alt: counted",
+                "press other: disarmed",
+                "release alt: counted",
+                "release alt: counted fired",
+                "Action(Toggle)",
+            ]
+        );
+    }
+
+    #[test]
+    fn all_keys_debugging_names_every_key() {
+        let inputs = [
+            (KeyInput::OtherPress(rdev::Key::KeyA), 0),
+            (KeyInput::Press(HotkeyKey::Space), 10),
+            (KeyInput::Release(HotkeyKey::Space), 20),
+        ];
+        assert_eq!(
+            debug_report(HotkeyDebug::AllKeys, &inputs),
+            ["press KeyA: ", "press space: ", "release space: "]
+        );
+        assert!(debug_report(HotkeyDebug::Off, &inputs).is_empty());
+    }
+}
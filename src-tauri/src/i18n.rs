@@ -0,0 +1,121 @@
+//! Message catalog for text the backend shows the user: error summaries
+//! (one per `AppError` code) and the tray's status line.
+
+use crate::error::AppError;
+use crate::AppStatus;
+use serde::{Deserialize, Serialize};
+
+/// Language of the messages in this catalog (`Settings::ui_language`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UiLanguage {
+    #[default]
+    #[serde(rename = "pl")]
+    Polish,
+    #[serde(rename = "en")]
+    English,
+}
+
+/// What went wrong, in a few words. The error's own message (from the OS,
+/// whisper or xdotool, usually in English) is the detail that goes with it.
+pub fn error_summary(err: &AppError, language: UiLanguage) -> &'static str {
+    use UiLanguage::{English, Polish};
+    match (err, language) {
+        (AppError::AudioDevice(_), Polish) => "Nie znaleziono mikrofonu",
+        (AppError::AudioDevice(_), English) => "No microphone found",
+        (AppError::AudioStream(_), Polish) => "Błąd strumienia audio",
+        (AppError::AudioStream(_), English) => "Audio stream failed",
+        (AppError::ModelNotLoaded, Polish) => "Model nie jest załadowany",
+        (AppError::ModelNotLoaded, English) => "No model is loaded",
+        (AppError::ModelNotFound(_), Polish) => {
+            "Nie znaleziono pliku modelu (czy pobieranie się zakończyło?)"
+        }
+        (AppError::ModelNotFound(_), English) => {
+            "Model file not found (has the download finished?)"
+        }
+        (AppError::ModelLoad(_), Polish) => "Nie udało się załadować modelu",
+        (AppError::ModelLoad(_), English) => "Failed to load the model",
+        (AppError::Transcription(_), Polish) => "Błąd transkrypcji",
+        (AppError::Transcription(_), English) => "Transcription failed",
+        (AppError::Typing(_), Polish) => "Nie udało się wpisać tekstu (xdotool/xclip?)",
+        (AppError::Typing(_), English) => "Failed to type the text (xdotool/xclip?)",
+    }
+}
+
+/// The tray tooltip's description of `status`.
+pub fn status_text(status: AppStatus, paused: bool, language: UiLanguage) -> &'static str {
+    use UiLanguage::{English, Polish};
+    match (status, language) {
+        (AppStatus::Idle, Polish) if paused => "Wstrzymano",
+        (AppStatus::Idle, English) if paused => "Paused",
+        (AppStatus::Idle, Polish) => "Naciśnij dwa razy Alt",
+        (AppStatus::Idle, English) => "Double-press Alt",
+        (AppStatus::Recording, Polish) => "Nagrywanie…",
+        (AppStatus::Recording, English) => "Recording…",
+        (AppStatus::Transcribing, Polish) => "Transkrypcja…",
+        (AppStatus::Transcribing, English) => "Transcribing…",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LANGUAGES: [UiLanguage; 2] = [UiLanguage::Polish, UiLanguage::English];
+
+    fn errors() -> Vec<AppError> {
+        let detail = || "detail".to_string();
+        vec![
+            AppError::AudioDevice(detail()),
+            AppError::AudioStream(detail()),
+            AppError::ModelNotLoaded,
+            AppError::ModelNotFound(detail()),
+            AppError::ModelLoad(detail()),
+            AppError::Transcription(detail()),
+            AppError::Typing(detail()),
+        ]
+    }
+
+    #[test]
+    fn every_error_code_has_a_summary_in_every_language() {
+        for err in errors() {
+            for language in LANGUAGES {
+                assert!(!error_summary(&err, language).is_empty(), "{err:?}");
+            }
+            assert_ne!(
+                error_summary(&err, UiLanguage::Polish),
+                error_summary(&err, UiLanguage::English),
+                "{} isn't translated",
+                err.code()
+            );
+        }
+    }
+
+    #[test]
+    fn every_status_has_a_text_in_every_language() {
+        for status in [
+            AppStatus::Idle,
+            AppStatus::Recording,
+            AppStatus::Transcribing,
+        ] {
+            for paused in [false, true] {
+                for language in LANGUAGES {
+                    assert!(!status_text(status, paused, language).is_empty());
+                }
+            }
+        }
+        assert_eq!(
+            status_text(AppStatus::Idle, true, UiLanguage::English),
+            "Paused"
+        );
+    }
+
+    #[test]
+    fn ui_language_uses_language_codes() {
+        let parsed: UiLanguage = serde_json::from_str("\"en\"").unwrap();
+        assert_eq!(parsed, UiLanguage::English);
+        assert_eq!(
+            serde_json::to_string(&UiLanguage::Polish).unwrap(),
+            "\"pl\""
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Recording indicator outside the app's windows, for tray-only setups:
+//! the Scroll Lock LED (X11, via `xset`) or a desktop notification (via
+//! `notify-send`) that stays up until the recording stops.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const LED_NAME: &str = "Scroll Lock";
+const NOTIFICATION_TITLE: &str = "Voice to Text";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorKind {
+    #[default]
+    Off,
+    /// Light Scroll Lock while recording; a notification where `xset`
+    /// doesn't work (no X display, not installed).
+    Led,
+    Notification,
+}
+
+enum Signal {
+    Show {
+        kind: IndicatorKind,
+        message: String,
+    },
+    Hide {
+        done: Option<mpsc::Sender<()>>,
+    },
+}
+
+/// Shows and hides the indicator on a thread of its own, so a slow or hung
+/// `notify-send` (no notification daemon) never holds up a status change.
+pub struct Indicator {
+    tx: Mutex<mpsc::Sender<Signal>>,
+}
+
+impl Indicator {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut state = State::default();
+            for signal in rx {
+                match signal {
+                    Signal::Show { kind, message } => state.show(kind, &message),
+                    Signal::Hide { done } => {
+                        state.hide();
+                        if let Some(done) = done {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            }
+        });
+        Self { tx: Mutex::new(tx) }
+    }
+
+    /// Recording started: show `kind` (nothing for `Off`), with `message`
+    /// as the notification's text.
+    pub fn show(&self, kind: IndicatorKind, message: &str) {
+        let message = message.to_string();
+        let _ = self.tx.lock().send(Signal::Show { kind, message });
+    }
+
+    /// Recording stopped: put the LED back as it was and close the
+    /// notification.
+    pub fn hide(&self) {
+        let _ = self.tx.lock().send(Signal::Hide { done: None });
+    }
+
+    /// Like `hide`, but wait up to `timeout` until it's done, e.g. before
+    /// quitting.
+    pub fn hide_and_wait(&self, timeout: Duration) {
+        let (done, wait) = mpsc::channel();
+        let _ = self.tx.lock().send(Signal::Hide { done: Some(done) });
+        let _ = wait.recv_timeout(timeout);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Whether Scroll Lock was already lit before recording, while we have
+    /// it lit.
+    led_before: Option<bool>,
+    /// The notification shown, while it's up.
+    notification: Option<u32>,
+    /// A tool already failed once; later failures are only debug-logged.
+    led_failed: bool,
+    notify_failed: bool,
+}
+
+impl State {
+    fn show(&mut self, kind: IndicatorKind, message: &str) {
+        self.hide();
+        if kind == IndicatorKind::Off {
+            return;
+        }
+        if kind == IndicatorKind::Led {
+            match light_led() {
+                Ok(before) => {
+                    self.led_before = Some(before);
+                    return;
+                }
+                Err(e) => warn_once(&mut self.led_failed, "LED indicator", &e),
+            }
+        }
+        match notify(message) {
+            Ok(id) => self.notification = Some(id),
+            Err(e) => warn_once(&mut self.notify_failed, "Notification indicator", &e),
+        }
+    }
+
+    fn hide(&mut self) {
+        // An LED that was lit before is left lit.
+        if self.led_before.take() == Some(false) {
+            if let Err(e) = set_led(false) {
+                log::warn!("Failed to turn {LED_NAME} off: {e}");
+            }
+        }
+        if let Some(id) = self.notification.take() {
+            if let Err(e) = close_notification(id) {
+                log::warn!("Failed to close the recording notification: {e}");
+            }
+        }
+    }
+}
+
+fn warn_once(failed: &mut bool, what: &str, e: &anyhow::Error) {
+    if std::mem::replace(failed, true) {
+        log::debug!("{what} unavailable: {e}");
+    } else {
+        log::warn!("{what} unavailable: {e}");
+    }
+}
+
+/// Run `program`, failing on a non-zero exit; returns its stdout.
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{program} failed: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Light the LED; returns whether it was lit already.
+fn light_led() -> Result<bool> {
+    let before = led_state()?;
+    if !before {
+        set_led(true)?;
+    }
+    Ok(before)
+}
+
+/// Scroll Lock's state from `xset q`, whose keyboard section reads
+/// `... 02: Scroll Lock: off ...`.
+fn led_state() -> Result<bool> {
+    let query = run("xset", &["q"])?;
+    let label = format!("{LED_NAME}:");
+    let state = query
+        .split(&label)
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .context("xset reports no Scroll Lock LED")?;
+    Ok(state == "on")
+}
+
+fn set_led(on: bool) -> Result<()> {
+    let flag = if on { "led" } else { "-led" };
+    run("xset", &[flag, "named", LED_NAME]).map(drop)
+}
+
+/// Show a notification that doesn't time out; returns its id.
+fn notify(message: &str) -> Result<u32> {
+    let id = run(
+        "notify-send",
+        &[
+            "--print-id",
+            "--expire-time=0",
+            "--urgency=low",
+            NOTIFICATION_TITLE,
+            message,
+        ],
+    )?;
+    id.trim()
+        .parse()
+        .context("notify-send printed no notification id")
+}
+
+/// notify-send can't close notifications; the notification service can.
+fn close_notification(id: u32) -> Result<()> {
+    run(
+        "gdbus",
+        &[
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.Notifications",
+            "--object-path",
+            "/org/freedesktop/Notifications",
+            "--method",
+            "org.freedesktop.Notifications.CloseNotification",
+            &id.to_string(),
+        ],
+    )
+    .map(drop)
+}
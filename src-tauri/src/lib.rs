@@ -1,186 +1,4110 @@
 mod audio;
+mod benchmark;
+mod denoise;
+mod diagnostics;
+mod error;
+mod feedback;
+mod history;
 mod hotkey;
+mod i18n;
+mod indicator;
+mod logging;
+mod notes;
+mod numbers;
+mod pipe;
+mod postprocess;
+mod recovery;
+mod remote;
+mod session;
+mod streaming;
+mod subtitles;
 mod transcribe;
 mod typing;
+mod whisper_api;
 
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, RecvTimeoutError};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tauri::{
     image::Image,
-    menu::{MenuBuilder, MenuItemBuilder},
+    menu::{
+        CheckMenuItemBuilder, Menu, MenuBuilder, MenuItem, MenuItemBuilder, Submenu, SubmenuBuilder,
+    },
     tray::TrayIconBuilder,
     AppHandle, Emitter, Manager,
 };
-use tauri_plugin_autostart::MacosLauncher;
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
 
+use error::AppError;
+
+/// Time between streaming ticks, unless a quality preset sets it.
 const STREAM_INTERVAL: Duration = Duration::from_secs(3);
-const MIN_AUDIO_SAMPLES: usize = 16_000; // 1 second at 16kHz
+/// whisper returns nothing for less than a second of audio, so
+/// `min_audio_ms` can't go lower.
+const MIN_AUDIO_MS: u32 = 1000;
+/// Toggles this soon after a state transition are treated as accidental
+/// double-fires. Because the final pass runs on the worker thread, toggles
+/// sent while transcribing are delivered right after returning to Idle and
+/// would fall inside this window too; those go through `send_toggle`, which
+/// marks them as queued so they skip it.
+const TOGGLE_COOLDOWN: Duration = Duration::from_millis(300);
+/// How often the worker checks for trailing silence while auto-stop is on,
+/// and for speech until a recording's first streaming tick.
+const SILENCE_POLL: Duration = Duration::from_millis(250);
+/// Longest recording `record_and_transcribe` makes.
+const MAX_ONE_SHOT: Duration = Duration::from_secs(300);
+/// Silence that ends a `record_and_transcribe` recording after some speech,
+/// unless auto-stop sets another.
+const ONE_SHOT_SILENCE: Duration = Duration::from_millis(1500);
+/// Least `Settings::onset_speech_ms` taken when it's on, so a click can't
+/// set off the first tick.
+const MIN_ONSET_SPEECH_MS: u32 = 100;
+/// Longest pre-roll kept (see `Settings::preroll_ms`).
+const MAX_PREROLL_MS: u32 = 5000;
+/// Most silence `Settings::pad_ms` adds at each end.
+const MAX_PAD_MS: u32 = 1000;
+/// The first 3 s of a recording are checked for one of
+/// `Settings::language_prefixes`.
+const LANGUAGE_PREFIX_SAMPLES: usize = 3 * audio::TARGET_SAMPLE_RATE as usize;
+/// Auto-stop only once this much speech was heard, so a slow start or a
+/// breath before the first word doesn't end the session.
+const AUTO_STOP_MIN_SPEECH: Duration = Duration::from_secs(1);
+/// Shorter silence settings are raised to this; natural mid-sentence pauses
+/// run up to roughly half a second.
+const AUTO_STOP_MIN_SILENCE: Duration = Duration::from_millis(800);
+/// Words of committed text passed to whisper as context once the streaming
+/// window has moved past them.
+const WINDOW_CONTEXT_WORDS: usize = 30;
+/// Minimum gap between `partial-transcript` events.
+const PARTIAL_MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// Consecutive chunks of a long final pass share this much audio, so no
+/// word is lost to a cut (see `streaming::stitch`).
+const CHUNK_OVERLAP: usize = 2 * audio::TARGET_SAMPLE_RATE as usize;
+/// Shorter chunk settings are raised to this, so the overlap stays a small
+/// part of each chunk.
+const MIN_CHUNK_SECS: u32 = 10;
+/// Most words two chunks can have in common; 2s of fast speech.
+const STITCH_MAX_WORDS: usize = 10;
+/// Words of clipboard text used as the prompt; whisper only looks at about
+/// the last 224 tokens of it.
+const CLIPBOARD_PROMPT_WORDS: usize = 100;
+/// Models kept loaded for quick switching (see `transcribe::ModelCache`).
+const MODEL_CACHE_SIZE: usize = 2;
+/// Default number of runs for `benchmark_model`.
+const BENCHMARK_RUNS: u32 = 5;
+/// Seconds `test_typing` waits so the user can focus a target window.
+const TYPING_TEST_COUNTDOWN: u32 = 3;
+/// How long quitting waits for the worker to finish a recording.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long quitting waits for the recording indicator to be taken down.
+const INDICATOR_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppStatus {
+    Idle,
+    Recording,
+    Transcribing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscribeMode {
+    /// Transcribe every few seconds while recording and type confirmed
+    /// text as it arrives; the final pass fixes up the rest on stop.
+    Streaming,
+    /// No ticks at all: the worker just waits for the stop, then runs the
+    /// final pass and types its text once.
+    Batch,
+    /// Like `Batch`, but the final text is typed one sentence at a time,
+    /// `sentence_delay_ms` apart.
+    Typewriter,
+}
+
+/// Where finished text goes.
+///
+/// Streaming only inserts text in the modes that insert at the cursor; in
+/// `ClipboardOnly` and `PreviewOnly` ticks just update the preview, since
+/// rewriting the clipboard every few seconds would clobber it for nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Type into the focused window, by whatever method the backend prefers.
+    Type,
+    /// Only show the text in the UI (`transcript` event); nothing is typed.
+    PreviewOnly,
+    /// Put the final text on the clipboard without pasting.
+    ClipboardOnly,
+    /// Paste into the focused window via the clipboard and leave the full
+    /// final text on it afterwards.
+    TypeAndClipboard,
+}
+
+impl OutputMode {
+    fn inserts(self) -> bool {
+        matches!(self, OutputMode::Type | OutputMode::TypeAndClipboard)
+    }
+}
+
+/// How the final pass deals with what streaming already transcribed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalPass {
+    /// Transcribe the whole recording again: the best text, as whisper
+    /// hears it all at once, but the longest wait after stopping.
+    #[default]
+    FullRetranscribe,
+    /// Keep the text streaming committed and transcribe only the audio
+    /// after it.
+    ExtendOnly,
+    /// Keep the last streaming hypothesis as it is; nothing is transcribed
+    /// after stopping, so words spoken after the last tick are lost.
+    None,
+}
+
+/// When the settings window comes up by itself (see `update_main_window`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowBehavior {
+    /// Only when opened from the tray, or for a confirmation.
+    #[default]
+    Manual,
+    /// Also while recording and transcribing, for the live text; hidden
+    /// again afterwards, unless it was open already.
+    ShowWhileRecording,
+    /// Hidden whenever a recording starts, even if it was open.
+    AlwaysHidden,
+}
+
+/// What a triple Alt press does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriplePressAction {
+    /// Not recognized; double presses fire without waiting for a third.
+    Disabled,
+    /// Abort the recording without a final pass. Already streamed text stays.
+    Cancel,
+    /// Swap `language` and `secondary_language`.
+    SwitchLanguage,
+}
+
+/// How much confirmed text a streaming tick types at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitGranularity {
+    /// Every word as soon as it's confirmed.
+    Word,
+    /// Whole sentences: a sentence in progress waits until it ends (or
+    /// the final pass).
+    Sentence,
+    /// Everything confirmed, at most every `commit_interval_ms`.
+    Interval,
+}
+
+/// What transcribes the audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// whisper.cpp with the configured model, on this machine.
+    #[default]
+    Local,
+    /// An OpenAI-compatible transcription endpoint (`api_url`). Streaming
+    /// ticks send a request each.
+    Api,
+}
+
+/// What happens to text meant for the focused window when that's one of
+/// the app's own, e.g. the settings window during configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnWindowPolicy {
+    /// Type it anyway, into whichever of the app's inputs has focus.
+    Type,
+    /// Don't type it; an `own-window-focused` event says so.
+    Refuse,
+    /// Show it in the settings window's scratchpad instead (sent with the
+    /// `own-window-focused` event).
+    Scratchpad,
+}
+
+/// What happens to final text awaiting confirmation when nobody answers
+/// within `Settings::confirm_timeout_secs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmTimeout {
+    #[default]
+    Discard,
+    Type,
+}
+
+/// Where whisper's initial prompt comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSource {
+    /// No prompt.
+    None,
+    /// `initial_prompt`.
+    Fixed,
+    /// The clipboard's text when recording starts (its last
+    /// `CLIPBOARD_PROMPT_WORDS` words), e.g. the paragraph being continued.
+    /// Falls back to `initial_prompt` if the clipboard holds no text.
+    Clipboard,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Model in use; `set_active_model` sets it from `models`.
+    pub model_path: String,
+    /// Named model files to switch between (name → path), e.g. a small
+    /// model for quick notes and a large one for accuracy.
+    pub models: BTreeMap<String, String>,
+    /// Model file per language code, e.g. a Polish fine-tune for `"pl"`.
+    /// Languages not listed use `model_path`.
+    pub model_paths: HashMap<String, String>,
+    pub language: String,
+    pub mode: TranscribeMode,
+    /// Pause between sentences in `TranscribeMode::Typewriter`.
+    pub sentence_delay_ms: u32,
+    /// Delay between keystrokes when typing directly; raise for apps that drop characters.
+    pub type_delay_ms: u32,
+    /// Type long text in chunks of this many characters (0 = in one go).
+    pub type_chunk_chars: usize,
+    /// Type lines shorter than this many characters instead of pasting
+    /// them (0 = always paste).
+    pub type_below_chars: usize,
+    /// Retries for a typing or paste command that failed.
+    pub typing_retries: u32,
+    /// Drop trailing spaces and newlines before typing, so a substitution
+    /// at the very end can't press Enter and send a chat message early.
+    pub trim_output: bool,
+    /// Type a space after each transcription, to separate phrases dictated
+    /// in a row.
+    pub append_space: bool,
+    /// Cut final transcriptions longer than this many characters before
+    /// they're typed, against whisper looping on noise (0 = no limit).
+    pub max_output_chars: usize,
+    /// Start a dictation with a space when it continues the last one: into
+    /// the same window, within `continuation_idle_secs`.
+    pub continuation_space: bool,
+    pub continuation_idle_secs: u32,
+    /// Put in front of each dictation's final text, e.g. `"[{time}] "`, with
+    /// `{time}`, `{date}` and `{lang}` filled in (see
+    /// `postprocess::expand_prefix`). `None` or empty = no prefix.
+    pub prefix_template: Option<String>,
+    /// Vocabulary hint for whisper (names, jargon); empty = none.
+    pub initial_prompt: String,
+    pub prompt_source: PromptSource,
+    /// Paste method per window class (WM_CLASS); unlisted apps use
+    /// `default_paste_method`.
+    pub paste_overrides: HashMap<String, typing::PasteMethod>,
+    /// X11 only: how to paste into apps without an override. Middle click
+    /// goes through the PRIMARY selection, for setups that block Ctrl+V.
+    pub default_paste_method: typing::PasteMethod,
+    /// X11 only: key for a line break per window class (WM_CLASS), e.g.
+    /// Shift+Enter for chat apps that send on Enter; unlisted apps use
+    /// Enter.
+    pub newline_overrides: HashMap<String, typing::Keystroke>,
+    /// Streaming commits text only up to the first word whisper gave a
+    /// probability below this (0 = commit whatever two ticks agree on).
+    pub commit_confidence: f32,
+    pub commit_granularity: CommitGranularity,
+    /// Time between commits with `CommitGranularity::Interval`.
+    pub commit_interval_ms: u32,
+    /// Let the final pass backspace over streamed text it disagrees with.
+    pub backspace_corrections: bool,
+    /// Remember the window focused when recording starts and type into it,
+    /// even if focus moves meanwhile (X11 only).
+    pub lock_target_window: bool,
+    /// What to do when the window to type into is the app's own.
+    pub own_window: OwnWindowPolicy,
+    /// Hold the final text back until `confirm_type` or `reject_type`,
+    /// announced by a `transcript-pending` event; streaming then types
+    /// nothing as it goes. Only for the output modes that type.
+    pub confirm_before_type: bool,
+    /// How long the text waits for an answer, and what happens after.
+    pub confirm_timeout_secs: u32,
+    pub confirm_timeout_action: ConfirmTimeout,
+    /// How many recent transcriptions to keep in memory.
+    pub history_max_entries: usize,
+    /// Also append each transcription to `history.jsonl` in the data dir.
+    pub history_to_file: bool,
+    /// Directory of daily Markdown notes (`YYYY-MM-DD.md`; a leading `~` is
+    /// the home directory) each dictation's final text is appended to,
+    /// whatever the output mode. `None` or empty = off.
+    pub append_to_markdown: Option<PathBuf>,
+    /// Heading above each note entry, with the placeholders of
+    /// `prefix_template`; empty = no heading.
+    pub markdown_heading: String,
+    /// Fixed gain applied before transcription (1.0 = as captured),
+    /// clipped at full scale. Runs before `auto_gain`.
+    pub input_gain: f32,
+    /// Boost quiet recordings to a -3 dBFS peak before transcription.
+    pub auto_gain: bool,
+    /// Mute audio quieter than this fraction of the recording's speech
+    /// before transcription, to drop background voices (0 = off; see
+    /// `audio::gate_quiet`).
+    pub loudness_gate: f32,
+    /// Warn with `clipping-detected` when more than this percentage of a
+    /// recording's samples were at full scale (0 = never).
+    pub clipping_warning_percent: f32,
+    /// Filter out rumble below ~80 Hz while recording.
+    pub highpass: bool,
+    /// Play the input on the default output while recording, to hear the
+    /// mic is picking you up. Meant for headphones: through speakers the
+    /// mic records its own playback.
+    pub monitor_input: bool,
+    /// Volume of `monitor_input` (0-1).
+    pub monitor_volume: f32,
+    /// Resampler for devices not running at 16kHz: linear (cheap, aliases)
+    /// or windowed sinc (clean, ~50x the CPU).
+    pub resample_quality: audio::ResampleQuality,
+    /// Suppress background noise (RNNoise) before transcription.
+    pub denoise: bool,
+    /// Silence added at both ends of every buffer whisper decodes, streaming
+    /// and final, so words at the edges aren't clipped; 0 = none. Up to
+    /// `MAX_PAD_MS`.
+    pub pad_ms: u32,
+    /// Beep when recording starts and stops.
+    pub feedback_sound: bool,
+    /// Show a small always-on-top window with the status and live text
+    /// while recording and transcribing.
+    pub overlay: bool,
+    pub window_behavior: WindowBehavior,
+    /// Also show recording outside the app's windows: the Scroll Lock LED
+    /// or a desktop notification (Linux).
+    pub recording_indicator: indicator::IndicatorKind,
+    /// Device to record from (see `list_input_devices`); empty = the
+    /// system's default input. A loopback device transcribes what's playing.
+    pub input_device: String,
+    /// Downmix all input channels, or record just one of them.
+    pub input_channel: audio::InputChannel,
+    /// Keep the microphone open while idle and start each recording with
+    /// this much audio from before it was started (0 = off), so the first
+    /// word isn't clipped. Capped at 5000.
+    pub preroll_ms: u32,
+    /// Spool audio to disk while recording so a crash doesn't lose it.
+    pub crash_recovery: bool,
+    /// Quitting mid-recording runs the final pass and delivers the text
+    /// (otherwise the recording is discarded).
+    pub finish_on_quit: bool,
+    pub output_mode: OutputMode,
+    /// Drop segments whisper thinks are probably not speech (1.0 = off).
+    pub no_speech_threshold: f32,
+    /// Stock phrases whisper hallucinates on silence; matching segments are dropped.
+    pub hallucination_blocklist: Vec<String>,
+    /// Fix spacing around punctuation and capitalize sentence starts.
+    pub post_process: bool,
+    /// Write spelled-out numbers as digits ("twenty three" → 23; en/pl).
+    pub format_numbers: bool,
+    /// Align words with DTW for per-word timings in the JSON export. Costs
+    /// extra compute and only works with the stock whisper models.
+    pub word_timestamps: bool,
+    /// GPU to load models on, by index, on machines with more than one;
+    /// `None` = whisper.cpp's default. One that can't be used falls back to
+    /// the default with a `gpu-device-unavailable` event.
+    pub gpu_device: Option<i32>,
+    /// Spoken phrase → replacement, applied to the final text (see
+    /// `postprocess::substitute`). A `\n` in a replacement presses Enter.
+    pub substitutions: Vec<(String, String)>,
+    /// Spoken phrase → key press or text, applied as text is typed (see
+    /// `parse_actions`), e.g. `["tab", {"key": "tab"}]` or
+    /// `["bullet", {"text": "- "}]`.
+    pub control_phrases: Vec<(String, typing::TypeAction)>,
+    /// Spoken phrase → language, e.g. `["in English", "en"]`: a recording
+    /// that opens with the phrase is transcribed in that language, without
+    /// the phrase (see `detect_language_prefix`).
+    pub language_prefixes: Vec<(String, String)>,
+    /// Decoding strategy for the final pass (and streaming, unless overridden).
+    pub sampling: transcribe::Sampling,
+    /// Strategy for streaming ticks; `None` = same as `sampling`. Greedy
+    /// keeps ticks fast while the final pass can afford beam search.
+    pub streaming_sampling: Option<transcribe::Sampling>,
+    /// Temperature fallback for segments that decode badly.
+    pub fallback: transcribe::Fallback,
+    /// Transcribe into one segment rather than one per phrase, so short
+    /// commands don't get split at pauses. Subtitle cues then cover 30s
+    /// each, and the streaming window can only move in whole segments.
+    pub single_segment: bool,
+    /// Let each streaming tick after a recording's first see the previous
+    /// tick's text as whisper context, for more coherent hypotheses. An
+    /// error in one tick can then carry over into the next ones.
+    pub use_context: bool,
+    /// Replaces `sampling`, `streaming_sampling` and `fallback` (and sets
+    /// the streaming interval) unless it's `Custom`.
+    pub quality_preset: transcribe::QualityPreset,
+    /// Finish the recording after this much trailing silence (0 = only on
+    /// toggle). Around 1500 works well for dictating single sentences.
+    pub auto_stop_silence_ms: u32,
+    /// A start pressed while transcribing begins a recording once that's
+    /// done, if within this long of the press (0 = such presses are
+    /// ignored).
+    pub queued_toggle_timeout_ms: u32,
+    /// Skip the final pass when the VAD heard less speech than this, so
+    /// brief noises don't get typed (0 = always transcribe).
+    pub min_speech_ms: u32,
+    /// Recordings shorter than this are discarded, and streaming ticks wait
+    /// until there's this much audio. At least 1000.
+    pub min_audio_ms: u32,
+    /// The first streaming tick of a recording comes early, as soon as the
+    /// VAD has heard this much speech and `min_audio_ms` have passed,
+    /// rather than a whole streaming interval in (0 = always wait).
+    pub onset_speech_ms: u32,
+    /// When speech resumes after a pause of at least this long within a
+    /// streaming recording, the next commit waits for two ticks agreeing
+    /// on the resumed speech, rather than comparing against the hypothesis
+    /// from before the pause (0 = never).
+    pub pause_reset_ms: u32,
+    /// A streaming tick that takes longer than the streaming interval
+    /// delays the next one by a full interval, instead of the next starting
+    /// right away.
+    pub adaptive_ticks: bool,
+    /// Streaming ticks transcribe at most roughly this much recent audio
+    /// (0 = the whole recording); the final pass uses all of it, unless
+    /// `final_pass` says otherwise.
+    pub stream_window_secs: u32,
+    /// A moved streaming window starts this long before the committed text
+    /// it follows, so a word it would cut is heard whole; the repeated
+    /// words are skipped (0 = no overlap).
+    pub stream_overlap_ms: u32,
+    /// Whether stopping transcribes the whole recording again or builds on
+    /// the streaming text, trading quality for a shorter wait.
+    pub final_pass: FinalPass,
+    /// The final pass transcribes recordings longer than this in chunks of
+    /// this many seconds, typing each as it's done (0 = in one go).
+    pub final_chunk_secs: u32,
+    /// CPU threads for whisper; `None` = one per core, up to 8. The main
+    /// speed control without a GPU.
+    pub n_threads: Option<usize>,
+    /// Run the model once on silence after loading it, so the first
+    /// dictation doesn't pay for GPU kernel setup.
+    pub warm_on_load: bool,
+    /// Unload the model after this many minutes without a recording or
+    /// transcription (0 = keep it loaded); it's reloaded on the next use.
+    pub unload_after_idle_mins: u32,
+    pub backend: Backend,
+    /// Transcriptions URL for `Backend::Api`, e.g. OpenAI's or a
+    /// self-hosted whisper server's.
+    pub api_url: String,
+    /// Sent as `Authorization: Bearer <key>`; empty = no header.
+    pub api_key: String,
+    /// Model name the endpoint expects.
+    pub api_model: String,
+    /// Serve the local HTTP API (see `remote`) on 127.0.0.1.
+    pub http_enabled: bool,
+    pub http_port: u16,
+    /// Secret clients must send as `Authorization: Bearer <token>`.
+    pub http_token: String,
+    /// Headless mode: write committed and final text as JSON lines to
+    /// stdout (`-`) or to a Unix socket at this path, instead of typing it
+    /// (empty = off; see `pipe`).
+    pub pipe_output: String,
+    /// Maximum gap between the key presses of a double/triple press.
+    pub double_press_window_ms: u32,
+    /// Gestures and what they do; double Alt toggles recording by default.
+    pub hotkeys: Vec<hotkey::Binding>,
+    /// Shorthand for binding triple Alt (on top of `hotkeys`).
+    pub triple_press_action: TriplePressAction,
+    /// Report what the gesture detector sees and decides, as `hotkey-debug`
+    /// events and in the log. Off by default: it logs every gesture key.
+    pub hotkey_debug: hotkey::HotkeyDebug,
+    /// Language `HotkeyAction::SwitchLanguage` swaps in.
+    pub secondary_language: String,
+    /// Languages offered in the tray submenu.
+    pub tray_languages: Vec<String>,
+    /// Languages `cycle_language` steps through, in this order, wrapping
+    /// around; empty = `tray_languages`.
+    pub cycle_languages: Vec<String>,
+    /// Language of the messages the backend writes: error summaries and the
+    /// tray tooltip.
+    pub ui_language: i18n::UiLanguage,
+    /// Log filter, e.g. `"info"` or `"info,voice_to_text=debug"`.
+    pub log_level: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            model_path: transcribe::default_model_path()
+                .to_string_lossy()
+                .to_string(),
+            models: BTreeMap::new(),
+            model_paths: HashMap::new(),
+            language: "pl".to_string(),
+            mode: TranscribeMode::Streaming,
+            sentence_delay_ms: 300,
+            type_delay_ms: 0,
+            type_chunk_chars: 0,
+            type_below_chars: 0,
+            typing_retries: 1,
+            trim_output: true,
+            append_space: false,
+            max_output_chars: 5000,
+            continuation_space: false,
+            continuation_idle_secs: 30,
+            prefix_template: None,
+            initial_prompt: String::new(),
+            prompt_source: PromptSource::Fixed,
+            paste_overrides: typing::default_paste_overrides(),
+            default_paste_method: typing::PasteMethod::CtrlV,
+            newline_overrides: typing::default_newline_overrides(),
+            commit_confidence: 0.0,
+            commit_granularity: CommitGranularity::Word,
+            commit_interval_ms: 3000,
+            backspace_corrections: false,
+            lock_target_window: false,
+            own_window: OwnWindowPolicy::Scratchpad,
+            confirm_before_type: false,
+            confirm_timeout_secs: 30,
+            confirm_timeout_action: ConfirmTimeout::Discard,
+            history_max_entries: 100,
+            history_to_file: false,
+            append_to_markdown: None,
+            markdown_heading: "## {time}".to_string(),
+            input_gain: 1.0,
+            auto_gain: false,
+            loudness_gate: 0.0,
+            clipping_warning_percent: 1.0,
+            highpass: false,
+            monitor_input: false,
+            monitor_volume: 0.5,
+            resample_quality: audio::ResampleQuality::Fast,
+            denoise: false,
+            pad_ms: 0,
+            feedback_sound: false,
+            overlay: false,
+            window_behavior: WindowBehavior::Manual,
+            recording_indicator: indicator::IndicatorKind::Off,
+            input_device: String::new(),
+            input_channel: audio::InputChannel::Mono,
+            preroll_ms: 0,
+            crash_recovery: true,
+            finish_on_quit: true,
+            output_mode: OutputMode::Type,
+            no_speech_threshold: 0.6,
+            hallucination_blocklist: transcribe::default_blocklist(),
+            post_process: false,
+            format_numbers: false,
+            word_timestamps: false,
+            gpu_device: None,
+            substitutions: postprocess::default_substitutions(),
+            control_phrases: Vec::new(),
+            language_prefixes: Vec::new(),
+            sampling: transcribe::Sampling::default(),
+            streaming_sampling: None,
+            fallback: transcribe::Fallback::default(),
+            single_segment: false,
+            use_context: false,
+            quality_preset: transcribe::QualityPreset::default(),
+            auto_stop_silence_ms: 0,
+            queued_toggle_timeout_ms: 5000,
+            min_speech_ms: 300,
+            min_audio_ms: MIN_AUDIO_MS,
+            onset_speech_ms: 400,
+            pause_reset_ms: 2000,
+            adaptive_ticks: true,
+            stream_window_secs: 30,
+            stream_overlap_ms: 1000,
+            final_pass: FinalPass::FullRetranscribe,
+            final_chunk_secs: 30,
+            n_threads: None,
+            warm_on_load: false,
+            unload_after_idle_mins: 0,
+            backend: Backend::Local,
+            api_url: "https://api.openai.com/v1/audio/transcriptions".to_string(),
+            api_key: String::new(),
+            api_model: "whisper-1".to_string(),
+            http_enabled: false,
+            http_port: 8765,
+            http_token: String::new(),
+            pipe_output: String::new(),
+            double_press_window_ms: 400,
+            hotkeys: hotkey::default_bindings(),
+            triple_press_action: TriplePressAction::Disabled,
+            hotkey_debug: hotkey::HotkeyDebug::Off,
+            secondary_language: "en".to_string(),
+            tray_languages: ["pl", "en", "de"].map(String::from).to_vec(),
+            cycle_languages: Vec::new(),
+            log_level: "info".to_string(),
+            ui_language: i18n::UiLanguage::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// The model for the current language (see `model_paths`).
+    fn active_model_path(&self) -> &str {
+        self.model_paths
+            .get(&self.language)
+            .unwrap_or(&self.model_path)
+    }
+
+    /// These settings with the API key and HTTP token blanked, for a file
+    /// that may be shared.
+    fn without_secrets(&self) -> Settings {
+        Settings {
+            api_key: String::new(),
+            http_token: String::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Take `current`'s API key and HTTP token where these settings leave
+    /// them blank, as an exported file does.
+    fn keep_secrets(&mut self, current: &Settings) {
+        if self.api_key.is_empty() {
+            self.api_key = current.api_key.clone();
+        }
+        if self.http_token.is_empty() {
+            self.http_token = current.http_token.clone();
+        }
+    }
+}
+
+/// What `get_diagnostics` reports.
+#[derive(Serialize)]
+struct Diagnostics {
+    status: AppStatus,
+    /// Resident memory of the whole process; `None` if the OS doesn't say.
+    rss_bytes: Option<u64>,
+    /// Samples in the current (or last) recording's buffer, at the
+    /// device's rate, and the bytes allocated for it.
+    buffer_samples: usize,
+    buffer_bytes: usize,
+    /// Models in memory, the active one last.
+    models: Vec<LoadedModel>,
+}
+
+#[derive(Serialize)]
+struct LoadedModel {
+    path: PathBuf,
+    /// Size of the model file: about what its weights take in RAM or VRAM.
+    file_bytes: Option<u64>,
+}
+
+/// Payload of the `error` event.
+#[derive(Clone, Serialize)]
+struct ErrorEvent {
+    /// `AppError::code`, for tailored guidance.
+    code: &'static str,
+    /// What went wrong, in `Settings::ui_language`.
+    summary: &'static str,
+    /// The underlying detail.
+    message: String,
+}
+
+/// Everything a freshly loaded window needs to draw itself (see
+/// `sync_state`).
+#[derive(Clone, Serialize)]
+struct StateSnapshot {
+    status: AppStatus,
+    recording: bool,
+    settings: Settings,
+    enabled: bool,
+    /// A model is in memory; `false` until the first one loads and after
+    /// an idle unload.
+    model_loaded: bool,
+    /// A model is being loaded; recordings started meanwhile wait for it.
+    model_loading: bool,
+    /// The last `error` event since the current recording started.
+    last_error: Option<ErrorEvent>,
+    /// Final text waiting for confirmation, as in `transcript-pending`.
+    pending_transcript: Option<String>,
+}
+
+/// Payload of the `transcript` event: everything committed so far this
+/// session, or the final text once the recording is done.
+#[derive(Clone, Serialize)]
+struct TranscriptEvent<'a> {
+    text: &'a str,
+    is_final: bool,
+}
+
+/// Payload of the `model-switched` event, sent once a different model is
+/// loaded (or taken from the cache) and in use.
+#[derive(Clone, Serialize)]
+struct ModelSwitchedEvent<'a> {
+    /// Entry of `Settings::models` with this path, if any.
+    name: Option<&'a str>,
+    path: &'a str,
+}
+
+/// Payload of the `model-loaded` event, sent once the configured model is
+/// ready after `preload_model` or the load at startup.
+#[derive(Clone, Serialize)]
+struct ModelLoadedEvent<'a> {
+    path: &'a str,
+    /// It was already loaded, so nothing was read from disk.
+    cached: bool,
+}
+
+/// Payload of the `model-load-failed` event.
+#[derive(Clone, Serialize)]
+struct ModelLoadFailedEvent<'a> {
+    path: &'a str,
+    error: String,
+}
+
+/// Payload of the `toggle-queued` event: what the start waits for.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QueuedReason {
+    Transcribing,
+    Loading,
+}
+
+/// Payload of the `falling-behind` event: a streaming tick took longer
+/// than the interval between ticks.
+#[derive(Clone, Copy, Serialize)]
+struct FallingBehindEvent {
+    tick_ms: u64,
+    interval_ms: u64,
+}
+
+/// Payload of the `own-window-focused` event.
+#[derive(Clone, Serialize)]
+struct OwnWindowEvent<'a> {
+    policy: OwnWindowPolicy,
+    /// The text that wasn't typed; empty for backspaces.
+    text: &'a str,
+}
+
+/// Payload of the `transcript-pending` event.
+#[derive(Clone, Serialize)]
+struct PendingTranscriptEvent<'a> {
+    text: &'a str,
+    /// How long it waits for `confirm_type` or `reject_type`.
+    timeout_secs: u32,
+}
+
+/// Payload of the `too-short` event, sent when a recording is discarded
+/// instead of transcribed.
+#[derive(Clone, Copy, Serialize)]
+struct TooShortEvent {
+    reason: TooShortReason,
+    /// The recording's length or the speech in it, in ms.
+    duration_ms: u64,
+    /// The setting it fell short of, in ms.
+    minimum_ms: u64,
+}
+
+/// Payload of the `clipping-detected` event.
+#[derive(Clone, Copy, Serialize)]
+struct ClippingEvent {
+    /// Share of the recording's samples at full scale.
+    percent: f32,
+    /// `Settings::clipping_warning_percent`.
+    threshold_percent: f32,
+}
+
+/// Payload of the `runaway-output` event.
+#[derive(Clone, Serialize)]
+struct RunawayOutputEvent {
+    chars: usize,
+    /// `Settings::max_output_chars`.
+    max_chars: usize,
+    /// Whether the text was cut to `max_chars`.
+    truncated: bool,
+    /// A phrase repeated back to back, and how many times (see
+    /// `postprocess::repeated_phrase`).
+    repeated_phrase: Option<String>,
+    repeats: usize,
+}
+
+impl TooShortEvent {
+    /// For `record_and_transcribe`, which returns it as its error.
+    fn message(&self) -> String {
+        match self.reason {
+            TooShortReason::Length => format!("Recording too short: {} ms", self.duration_ms),
+            TooShortReason::Speech => format!("No speech heard, only {} ms", self.duration_ms),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TooShortReason {
+    /// Shorter than `min_audio_ms`.
+    Length,
+    /// Less speech than `min_speech_ms`.
+    Speech,
+}
+
+/// Payload of the `model-language-unsupported` event, sent when an
+/// English-only model is active with another language set.
+#[derive(Clone, Serialize)]
+struct ModelLanguageEvent<'a> {
+    language: &'a str,
+    model_type: &'a str,
+}
+
+/// Payload of the `partial-transcript` event, sent every streaming tick:
+/// the committed text (as in `transcript`) plus whisper's current guess at
+/// what follows, which may still change.
+#[derive(Clone, Serialize)]
+struct PartialTranscriptEvent<'a> {
+    committed: &'a str,
+    tentative: &'a str,
+}
+
+/// Payload of the `caption-update` event, for live captions: what
+/// `partial-transcript` has, plus the streaming window's segments marked
+/// the same way. Sent at most every `PARTIAL_MIN_INTERVAL` and only when
+/// the text changed, then once more with the final text.
+#[derive(Clone, Serialize)]
+struct CaptionEvent<'a> {
+    /// Never changes once sent; later updates only add to it.
+    committed: &'a str,
+    /// The rest of the current hypothesis, which may still change.
+    tentative: &'a str,
+    segments: Vec<CaptionSegment>,
+    /// The final pass's text: the last update for the recording.
+    is_final: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct CaptionSegment {
+    /// As whisper heard it, before post-processing.
+    text: String,
+    /// From the start of the recording.
+    start_ms: u64,
+    end_ms: u64,
+    /// All of its text is committed.
+    stable: bool,
+}
+
+/// `segments` of a window starting at sample `offset`, the first `stable`
+/// of them committed.
+fn caption_segments(
+    segments: &[transcribe::Segment],
+    offset: usize,
+    stable: usize,
+) -> Vec<CaptionSegment> {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| CaptionSegment {
+            text: segment.text.trim().to_string(),
+            start_ms: samples_to_ms(offset + segment.start),
+            end_ms: samples_to_ms(offset + segment.end),
+            stable: i < stable,
+        })
+        .collect()
+}
+
+enum WorkerCmd {
+    Toggle,
+    /// Like `Toggle`, but a recording it starts is translated into English.
+    ToggleTranslate,
+    /// A start sent while transcribing (see `send_toggle`), applied once
+    /// idle again unless it's older than `queued_toggle_timeout_ms`.
+    Queued {
+        cmd: Box<WorkerCmd>,
+        at: Instant,
+    },
+    /// Like `Toggle`, but only when idle (HTTP API).
+    Start,
+    /// Like `Toggle`, but only while recording (HTTP API).
+    Stop,
+    /// Discard the current recording without a final pass.
+    Cancel,
+    /// The input stream died mid-recording (sent from the audio thread).
+    AudioFailed(String),
+    /// Transcribe the recording a crash left behind (see `recover_recording`).
+    Recover {
+        reply: mpsc::Sender<Result<Option<String>, String>>,
+    },
+    /// Transcribe an audio file (see `transcribe_file`).
+    TranscribeFile {
+        path: PathBuf,
+        deliver: bool,
+        reply: mpsc::Sender<Result<String, String>>,
+    },
+    /// Record and transcribe without typing (see `record_and_transcribe`).
+    RecordOnce {
+        max: Duration,
+        reply: mpsc::Sender<Result<String, String>>,
+    },
+    /// Load the configured model unless it's loaded (see `preload_model`).
+    /// Queued at most once; `AppState::preload` says whether to read it
+    /// from disk again anyway.
+    Preload,
+    /// Time the loaded model (see `benchmark_model`).
+    Benchmark {
+        runs: u32,
+        reply: mpsc::Sender<Result<benchmark::BenchmarkReport, String>>,
+    },
+    /// Play the last recording (see `play_last_recording`); replies with
+    /// its length in seconds.
+    PlayLast {
+        reply: mpsc::Sender<Result<f32, String>>,
+    },
+    UpdateSettings(Arc<Settings>),
+    /// Paused or resumed; the pre-roll capture stops or starts to match.
+    EnabledChanged,
+    /// Finish or discard the recording in progress (`finish_on_quit`),
+    /// release the microphone and models, reply and exit.
+    Shutdown {
+        reply: mpsc::Sender<()>,
+    },
+}
+
+/// Body of every HTTP API response.
+#[derive(Serialize)]
+struct HttpStatus {
+    status: AppStatus,
+}
+
+/// The current settings behind an `Arc` that changes swap out, so readers
+/// such as the worker's streaming ticks take a snapshot without cloning the
+/// struct or holding a lock while they use it.
+struct SharedSettings(Mutex<Arc<Settings>>);
+
+impl SharedSettings {
+    fn new(settings: Settings) -> Self {
+        Self(Mutex::new(Arc::new(settings)))
+    }
+
+    /// The settings as of now; later changes don't affect the snapshot.
+    fn get(&self) -> Arc<Settings> {
+        self.0.lock().clone()
+    }
+
+    fn set(&self, settings: Settings) {
+        *self.0.lock() = Arc::new(settings);
+    }
+
+    /// Apply `change` to a copy of the current settings and publish it.
+    /// Returns the new settings.
+    fn update(&self, change: impl FnOnce(&mut Settings)) -> Settings {
+        let mut current = self.0.lock();
+        let mut settings = Settings::clone(&current);
+        change(&mut settings);
+        *current = Arc::new(settings.clone());
+        settings
+    }
+}
+
+/// `preload_model` requests for the worker. One made while another is
+/// queued joins it, so the model is never loaded twice over.
+#[derive(Default)]
+struct PreloadQueue {
+    queued: AtomicBool,
+    /// The queued preload reads the model from disk even if it's loaded.
+    reload: AtomicBool,
+}
+
+impl PreloadQueue {
+    /// Ask for a preload. True if none was queued yet, in which case a
+    /// `WorkerCmd::Preload` needs sending.
+    fn request(&self, reload: bool) -> bool {
+        if reload {
+            self.reload.store(true, Ordering::Relaxed);
+        }
+        !self.queued.swap(true, Ordering::Relaxed)
+    }
+
+    /// Take the queued preload, as the worker starts on it: whether to
+    /// reload. Requests from now on queue another.
+    fn take(&self) -> bool {
+        self.queued.store(false, Ordering::Relaxed);
+        self.reload.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Final text held back for confirmation, and where the answer goes.
+type PendingConfirm = Mutex<Option<(String, mpsc::Sender<bool>)>>;
+
+pub struct AppState {
+    status: Mutex<AppStatus>,
+    settings: SharedSettings,
+    cmd_tx: Mutex<mpsc::Sender<WorkerCmd>>,
+    history: history::History,
+    http: Mutex<Option<remote::HttpServer>>,
+    pipe: Mutex<Option<pipe::Pipe>>,
+    hotkey: Arc<Mutex<hotkey::HotkeyConfig>>,
+    /// `false` while paused: nothing starts a recording (see `set_enabled`).
+    /// Kept outside `Settings` because it is persisted on its own.
+    enabled: Mutex<bool>,
+    /// Segments of the last final pass, for `export_transcription`.
+    last_cues: Mutex<Vec<subtitles::Cue>>,
+    /// Text of the last final pass, for `retype_last`.
+    last_text: Mutex<Option<String>>,
+    /// The open dictation session, if any (see `begin_session`).
+    session: Mutex<Option<session::Session>>,
+    /// Final text held back until `confirm_type` or `reject_type` (see
+    /// `Settings::confirm_before_type`), and where they send the answer.
+    pending_confirm: PendingConfirm,
+    /// The settings window was shown for the current recording, to be hidden
+    /// once it's over (see `WindowBehavior::ShowWhileRecording`).
+    window_auto_shown: AtomicBool,
+    /// The tray's "Copy last" item, whose label previews `last_text`; it is
+    /// replaced whenever the tray menu is rebuilt.
+    copy_item: Mutex<Option<MenuItem<tauri::Wry>>>,
+    /// What the current or last dictation typed, for `undo_last_type`;
+    /// emptied by an undo. Only changed by typing jobs, so it's up to date
+    /// for each job that runs.
+    last_typed: Mutex<String>,
+    /// Where all typing and clipboard writes run, in order.
+    typing: typing::TypingQueue,
+    /// LED or notification shown while recording (see
+    /// `Settings::recording_indicator`).
+    indicator: indicator::Indicator,
+    /// Window the current recording types into (see
+    /// `Settings::lock_target_window`).
+    target_window: Mutex<Option<String>>,
+    continuation: Mutex<Continuation>,
+    /// Prompt read from the clipboard for the current recording (see
+    /// `PromptSource::Clipboard`).
+    clipboard_prompt: Mutex<Option<String>>,
+    /// The current recording is translated into English (started with
+    /// `HotkeyAction::ToggleTranslate`).
+    translate: Mutex<bool>,
+    /// Language the current recording switched to by a spoken prefix (see
+    /// `Settings::language_prefixes`).
+    spoken_language: Mutex<Option<String>>,
+    /// The current recording's start was checked for such a prefix.
+    prefix_checked: AtomicBool,
+    /// Stops the final pass in progress (see `cancel`). Cleared whenever a
+    /// transcription starts.
+    abort: Arc<AtomicBool>,
+    /// A toggle is queued behind the current transcription (see
+    /// `send_toggle`).
+    toggle_queued: AtomicBool,
+    /// Models the worker holds, the active one last, for `sync_state` and
+    /// `get_diagnostics`. Updated by the worker between commands.
+    loaded_models: Mutex<Vec<PathBuf>>,
+    /// What whisper reports about the active model, for `model_info`.
+    model_info: Mutex<Option<transcribe::ModelInfo>>,
+    /// Set while the worker loads a model (see `load_model`).
+    model_loading: AtomicBool,
+    preload: PreloadQueue,
+    /// Why the global key listener stopped for good, for `health_check`.
+    hotkey_error: Mutex<Option<String>>,
+    /// Buffer of the current or last recording, for `get_diagnostics`.
+    recording_buffer: Mutex<Option<audio::BufferGauge>>,
+    /// Last error reported to the UI, for `sync_state`; cleared when a
+    /// recording starts.
+    last_error: Mutex<Option<ErrorEvent>>,
+    /// `None` if no logger could be installed.
+    logging: Option<logging::Logging>,
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+fn get_status(state: tauri::State<'_, AppState>) -> AppStatus {
+    *state.status.lock()
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<'_, AppState>) -> Settings {
+    Settings::clone(&state.settings.get())
+}
+
+/// Memory figures for bug reports about the app growing: the process's
+/// RSS, the recording buffer and the loaded models. Read-only, and only
+/// takes short locks.
+#[tauri::command]
+fn get_diagnostics(state: tauri::State<'_, AppState>) -> Diagnostics {
+    let (buffer_samples, buffer_bytes) = state
+        .recording_buffer
+        .lock()
+        .as_ref()
+        .map_or((0, 0), audio::BufferGauge::usage);
+    let paths = state.loaded_models.lock().clone();
+    let models = paths
+        .into_iter()
+        .map(|path| LoadedModel {
+            file_bytes: std::fs::metadata(&path).ok().map(|m| m.len()),
+            path,
+        })
+        .collect();
+    Diagnostics {
+        status: *state.status.lock(),
+        rss_bytes: diagnostics::resident_memory(),
+        buffer_samples,
+        buffer_bytes,
+        models,
+    }
+}
+
+/// Everything the app needs to work, each checked on its own with the
+/// probes the features use: the input device, the model file, whisper (or
+/// the API), the typing tools and the hotkey listener. A failing check
+/// doesn't stop the others.
+#[tauri::command]
+fn health_check(state: tauri::State<'_, AppState>) -> diagnostics::HealthReport {
+    let settings = state.settings.get();
+    let mut items = vec![diagnostics::audio_item(&audio::input_info(
+        &settings.input_device,
+    ))];
+    match settings.backend {
+        Backend::Local => {
+            let model = transcribe::inspect_model(Path::new(settings.active_model_path()));
+            items.push(diagnostics::model_item(&model));
+            let loading = state.model_loading.load(Ordering::Relaxed);
+            let info = state.model_info.lock().clone();
+            items.push(diagnostics::transcription_item(info.as_ref(), loading));
+        }
+        Backend::Api => items.extend(diagnostics::api_items(&settings.api_url)),
+    }
+    items.push(diagnostics::typing_item(&typing::recheck_dependencies()));
+    let hotkey_error = state.hotkey_error.lock().clone();
+    items.push(diagnostics::hotkey_item(hotkey_error.as_deref()));
+    diagnostics::HealthReport::new(items)
+}
+
+/// Status, settings and runtime facts in one call, so a window opened or
+/// reloaded mid-recording doesn't depend on events it missed. Each lock is
+/// taken and released on its own, so this can't deadlock with the worker.
+#[tauri::command]
+fn sync_state(state: tauri::State<'_, AppState>) -> StateSnapshot {
+    let status = *state.status.lock();
+    StateSnapshot {
+        status,
+        recording: status == AppStatus::Recording,
+        settings: Settings::clone(&state.settings.get()),
+        enabled: *state.enabled.lock(),
+        model_loaded: !state.loaded_models.lock().is_empty(),
+        model_loading: state.model_loading.load(Ordering::Relaxed),
+        last_error: state.last_error.lock().clone(),
+        pending_transcript: state
+            .pending_confirm
+            .lock()
+            .as_ref()
+            .map(|(text, _)| text.clone()),
+    }
+}
+
+/// The checks `update_settings` makes before applying `settings`.
+fn validate_settings(settings: &Settings) -> Result<(), String> {
+    for sampling in std::iter::once(&settings.sampling).chain(&settings.streaming_sampling) {
+        sampling.validate().map_err(|e| e.to_string())?;
+    }
+    settings.fallback.validate().map_err(|e| e.to_string())?;
+    audio::validate_gain(settings.input_gain).map_err(|e| e.to_string())?;
+    audio::validate_gate(settings.loudness_gate).map_err(|e| e.to_string())?;
+    if !(0.0..=1.0).contains(&settings.monitor_volume) {
+        return Err(format!(
+            "Monitor volume must be between 0 and 1, got {}",
+            settings.monitor_volume
+        ));
+    }
+    audio::validate_clipping_threshold(settings.clipping_warning_percent)
+        .map_err(|e| e.to_string())?;
+    if settings.backend == Backend::Api && settings.api_url.trim().is_empty() {
+        return Err("No API URL set".to_string());
+    }
+    if let Some(index) = settings.gpu_device.filter(|&index| index < 0) {
+        return Err(format!("Invalid GPU device {index}: numbered from 0"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_settings(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    mut settings: Settings,
+) -> Result<(), String> {
+    normalize_languages(&app, &mut settings);
+    validate_settings(&settings)?;
+    let log_level = logging::parse_level(&settings.log_level).map_err(|e| e.to_string())?;
+    if settings.log_level != state.settings.get().log_level {
+        if let Some(logging) = &state.logging {
+            logging.set_level(log_level);
+        }
+    }
+    *state.hotkey.lock() = hotkey_config(&settings);
+    state.settings.set(settings);
+    let _ = state
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::UpdateSettings(state.settings.get()));
+    apply_http_settings(&app);
+    apply_pipe_settings(&app);
+    refresh_tray_menu(&app);
+    update_overlay(&app, *state.status.lock());
+    Ok(())
+}
+
+/// Bring the language settings to codes whisper takes (see
+/// `transcribe::normalize_language`). One it doesn't know becomes `auto`,
+/// with a `language-unknown` event, instead of reaching whisper; unknown
+/// tray languages are left out.
+fn normalize_languages(app: &AppHandle, settings: &mut Settings) {
+    for language in [&mut settings.language, &mut settings.secondary_language] {
+        match transcribe::normalize_language(language) {
+            Some(code) => *language = code.to_string(),
+            None => {
+                log::warn!("Unknown language {language:?}, using auto-detection");
+                let _ = app.emit("language-unknown", language.as_str());
+                *language = "auto".to_string();
+            }
+        }
+    }
+    settings.tray_languages = normalize_language_list(&settings.tray_languages, "tray");
+    settings.cycle_languages = normalize_language_list(&settings.cycle_languages, "cycle");
+}
+
+/// `languages` as whisper's codes, without duplicates or unknown ones.
+fn normalize_language_list(languages: &[String], what: &str) -> Vec<String> {
+    let mut list = Vec::new();
+    for language in languages {
+        match transcribe::normalize_language(language) {
+            Some(code) if !list.iter().any(|known| known == code) => list.push(code.to_string()),
+            Some(_) => {}
+            None => log::warn!("Unknown {what} language {language:?} left out"),
+        }
+    }
+    list
+}
+
+/// The languages whisper can transcribe, for the language picker.
+#[tauri::command]
+fn list_languages() -> Vec<transcribe::Language> {
+    transcribe::languages()
+}
+
+/// Put every setting back to its default and apply it like
+/// `update_settings`, e.g. to recover from a configuration that broke
+/// recording. The worker then loads the default model, even if the old
+/// path didn't load. Returns the new settings.
+#[tauri::command]
+fn reset_settings(app: AppHandle, state: tauri::State<'_, AppState>) -> Result<Settings, String> {
+    let settings = Settings::default();
+    update_settings(app.clone(), state, settings.clone())?;
+    log::info!("Settings reset to defaults");
+    let _ = app.emit("settings-reset", &settings);
+    Ok(settings)
+}
+
+/// Write the current settings to `path` as JSON, to take them to another
+/// machine or share them. The API key and HTTP token are left out.
+#[tauri::command]
+fn export_settings(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let settings = state.settings.get().without_secrets();
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {path}: {e}"))?;
+    log::info!("Settings exported to {path}");
+    Ok(())
+}
+
+/// Apply settings written by `export_settings` like `update_settings`.
+/// Settings the file doesn't have get their defaults. A file that isn't
+/// settings JSON, or settings that don't pass `update_settings`, leave the
+/// current ones alone. A blank API key or HTTP token keeps the current one.
+/// Returns the settings applied.
+#[tauri::command]
+fn import_settings(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<Settings, String> {
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let mut settings: Settings =
+        serde_json::from_str(&json).map_err(|e| format!("{path} isn't a settings file: {e}"))?;
+    settings.keep_secrets(&state.settings.get());
+    update_settings(app.clone(), state, settings)?;
+    let settings = Settings::clone(&app.state::<AppState>().settings.get());
+    log::info!("Settings imported from {path}");
+    let _ = app.emit("settings-imported", &settings);
+    Ok(settings)
+}
+
+#[tauri::command]
+fn check_model(state: tauri::State<'_, AppState>) -> bool {
+    let path = PathBuf::from(state.settings.get().active_model_path());
+    path.exists()
+}
+
+/// Check the model file at `path` (default: the active model) from its
+/// header, without loading it: missing, present but unusable, or its type.
+#[tauri::command]
+fn validate_model(
+    state: tauri::State<'_, AppState>,
+    path: Option<String>,
+) -> transcribe::ModelCheck {
+    let path = path.unwrap_or_else(|| state.settings.get().active_model_path().to_string());
+    transcribe::inspect_model(Path::new(&path))
+}
+
+/// What whisper reported about the active model when loading it, or
+/// `None` if no model is loaded (yet).
+#[tauri::command]
+fn model_info(state: tauri::State<'_, AppState>) -> Option<transcribe::ModelInfo> {
+    state.model_info.lock().clone()
+}
+
+#[tauri::command]
+fn toggle_recording(app: AppHandle) {
+    send_toggle(&app, WorkerCmd::Toggle);
+}
+
+/// Discard the recording, or stop the transcription in progress.
+#[tauri::command]
+fn cancel_recording(state: tauri::State<'_, AppState>) {
+    cancel(&state);
+}
+
+/// Write the last final transcription to `path` as SRT or WebVTT subtitles
+/// (or JSON, with word timings if enabled), timed from the start of its
+/// recording.
+#[tauri::command]
+fn export_transcription(
+    state: tauri::State<'_, AppState>,
+    format: subtitles::SubtitleFormat,
+    path: String,
+) -> Result<(), String> {
+    let cues = state.last_cues.lock().clone();
+    if cues.is_empty() {
+        return Err("No transcription to export yet".to_string());
+    }
+    std::fs::write(&path, subtitles::format(&cues, format)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_enabled(state: tauri::State<'_, AppState>) -> bool {
+    *state.enabled.lock()
+}
+
+/// Pause (`false`) or resume the app. While paused, the hotkey, the tray and
+/// the HTTP API can't start a recording; one already running can still be
+/// stopped. The state survives restarts.
+#[tauri::command]
+fn set_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+    apply_enabled(&app, enabled).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn is_autostart_enabled(app: AppHandle) -> Result<bool, String> {
+    app.autolaunch().is_enabled().map_err(|e| e.to_string())
+}
+
+/// Register the app to start (minimized to the tray) on login. Returns the
+/// registration as the OS reports it afterwards.
+#[tauri::command]
+fn enable_autostart(app: AppHandle) -> Result<bool, String> {
+    let autolaunch = app.autolaunch();
+    autolaunch.enable().map_err(|e| e.to_string())?;
+    log::info!("Autostart enabled");
+    autolaunch.is_enabled().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn disable_autostart(app: AppHandle) -> Result<bool, String> {
+    let autolaunch = app.autolaunch();
+    autolaunch.disable().map_err(|e| e.to_string())?;
+    log::info!("Autostart disabled");
+    autolaunch.is_enabled().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_show_on_launch() -> bool {
+    marker_path(SHOW_ON_LAUNCH_MARKER).exists()
+}
+
+/// Whether the settings window opens when the app starts. Launches with
+/// `--minimized` (as autostart does) always stay in the tray.
+#[tauri::command]
+fn set_show_on_launch(show: bool) -> Result<(), String> {
+    set_marker(SHOW_ON_LAUNCH_MARKER, show).map_err(|e| e.to_string())
+}
+
+/// The last `lines` (default 200) lines of the current log file, e.g. to
+/// paste into a bug report.
+#[tauri::command]
+fn get_recent_logs(lines: Option<usize>) -> Result<String, String> {
+    logging::recent_logs(lines.unwrap_or(200)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn open_log_dir() -> Result<(), String> {
+    logging::open_log_dir().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_history(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Vec<history::HistoryEntry> {
+    state.history.recent(limit.unwrap_or(usize::MAX))
+}
+
+#[tauri::command]
+fn clear_history(state: tauri::State<'_, AppState>) {
+    state.history.clear();
+}
+
+/// Type `sample` into whatever window is focused after a short countdown
+/// (emitted as `typing-test-countdown` events) and report each step.
+#[tauri::command]
+async fn test_typing(app: AppHandle, sample: String) -> Result<typing::TypingReport, String> {
+    let config = typing_config(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        for remaining in (1..=TYPING_TEST_COUNTDOWN).rev() {
+            let _ = app.emit("typing-test-countdown", remaining);
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        let _ = app.emit("typing-test-countdown", 0);
+        app.state::<AppState>()
+            .typing
+            .run(move || typing::test_typing(&sample, &config))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Start gathering the final text of the recordings that follow into one
+/// transcript, until `end_session`. An open session outlives restarts.
+#[tauri::command]
+fn begin_session(app: AppHandle) -> Result<(), String> {
+    let st = app.state::<AppState>();
+    let mut current = st.session.lock();
+    if current.is_some() {
+        return Err("A dictation session is already open".to_string());
+    }
+    let session = session::Session::default();
+    session.save().map_err(|e| format!("{e:#}"))?;
+    log::info!("Dictation session begun");
+    let _ = app.emit("session-changed", Some(&session));
+    *current = Some(session);
+    Ok(())
+}
+
+/// End the open dictation session and return it. With `path`, its text is
+/// written there first; the session stays open if that fails.
+#[tauri::command]
+fn end_session(app: AppHandle, path: Option<String>) -> Result<session::Session, String> {
+    let st = app.state::<AppState>();
+    let mut current = st.session.lock();
+    let Some(session) = current.take() else {
+        return Err("No dictation session is open".to_string());
+    };
+    if let Some(path) = path.filter(|path| !path.trim().is_empty()) {
+        if let Err(e) = std::fs::write(&path, &session.text) {
+            *current = Some(session);
+            return Err(format!("Failed to write {path}: {e}"));
+        }
+        log::info!("Dictation session written to {path}");
+    }
+    let recordings = session.recordings;
+    log::info!("Dictation session of {recordings} recordings ended");
+    let _ = app.emit("session-changed", None::<session::Session>);
+    Ok(session.close())
+}
+
+#[tauri::command]
+fn get_session(state: tauri::State<'_, AppState>) -> Option<session::Session> {
+    state.session.lock().clone()
+}
+
+/// Type the last final transcription again into the focused window, e.g.
+/// after it went to the wrong one.
+#[tauri::command]
+async fn retype_last(app: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || retype(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Put the last final transcription on the clipboard, to paste by hand.
+/// Nothing is pasted, and whatever the clipboard held is replaced.
+#[tauri::command]
+async fn copy_last_to_clipboard(app: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || copy_last(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Backspace over what the last dictation typed, streamed and final text
+/// alike. Only right while the cursor is still at the end of it, in the
+/// same field. A second undo does nothing.
+#[tauri::command]
+async fn undo_last_type(app: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || undo_typed(&app))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Time transcription with the loaded model: on the last recording if there
+/// is one, otherwise on the bundled speech sample. Progress is emitted as
+/// `benchmark-progress` events.
+#[tauri::command]
+async fn benchmark_model(
+    state: tauri::State<'_, AppState>,
+    runs: Option<u32>,
+) -> Result<benchmark::BenchmarkReport, String> {
+    let (reply, reply_rx) = mpsc::channel();
+    let runs = runs.unwrap_or(BENCHMARK_RUNS);
+    state
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::Benchmark { runs, reply })
+        .map_err(|_| "Worker is not running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || reply_rx.recv())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| "Worker is not running".to_string())?
+}
+
+/// Load the configured model on the worker in the background, e.g. at app
+/// start or after downloading it, instead of on the first recording.
+/// Returns at once; `model-loaded` or `model-load-failed` follows. With
+/// `reload`, a loaded model is read from disk again. Requests made while
+/// one is queued join it, so the model is never loaded twice over.
+#[tauri::command]
+fn preload_model(state: tauri::State<'_, AppState>, reload: Option<bool>) -> Result<(), String> {
+    if state.settings.get().backend == Backend::Api {
+        return Err("Transcribing through the API; there's no model to load".to_string());
+    }
+    if !state.preload.request(reload.unwrap_or(false)) {
+        return Ok(());
+    }
+    state
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::Preload)
+        .map_err(|_| "Worker is not running".to_string())
+}
+
+/// Play the last recording on the default output device, as whisper got it
+/// (after gain and noise suppression), to check what the microphone
+/// picked up. Returns once playback started, with its length in seconds.
+#[tauri::command]
+async fn play_last_recording(state: tauri::State<'_, AppState>) -> Result<f32, String> {
+    let (reply, reply_rx) = mpsc::channel();
+    state
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::PlayLast { reply })
+        .map_err(|_| "Worker is not running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || reply_rx.recv())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| "Worker is not running".to_string())?
+}
+
+/// Transcribe a WAV file (any sample rate and channel count) with the final
+/// pass settings. With `deliver`, the text is also typed or copied per the
+/// output mode. Progress comes as `transcribe-progress` events.
+#[tauri::command]
+async fn transcribe_file(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    deliver: Option<bool>,
+) -> Result<String, String> {
+    let (reply, reply_rx) = mpsc::channel();
+    let cmd = WorkerCmd::TranscribeFile {
+        path: PathBuf::from(path),
+        deliver: deliver.unwrap_or(false),
+        reply,
+    };
+    state
+        .cmd_tx
+        .lock()
+        .send(cmd)
+        .map_err(|_| "Worker is not running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || reply_rx.recv())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| "Worker is not running".to_string())?
+}
+
+/// Record for up to `max_secs` seconds (at most 300), or until a pause
+/// after some speech, and return the transcription instead of typing it,
+/// for scripts. It goes to history like any other. Sent while a final
+/// pass runs, it waits for it; while recording or paused, it fails. A stop
+/// (hotkey, tray) ends the recording early, a cancel discards it.
+#[tauri::command]
+async fn record_and_transcribe(
+    state: tauri::State<'_, AppState>,
+    max_secs: u32,
+) -> Result<String, String> {
+    let max = one_shot_limit(max_secs)?;
+    let (reply, reply_rx) = mpsc::channel();
+    state
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::RecordOnce { max, reply })
+        .map_err(|_| "Worker is not running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || reply_rx.recv())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| "Worker is not running".to_string())?
+}
+
+/// `record_and_transcribe`'s `max_secs`, if it's within `MAX_ONE_SHOT`.
+fn one_shot_limit(max_secs: u32) -> Result<Duration, String> {
+    let max = Duration::from_secs(max_secs.into());
+    if max.is_zero() || max > MAX_ONE_SHOT {
+        let longest = MAX_ONE_SHOT.as_secs();
+        return Err(format!("max_secs must be 1 to {longest}, not {max_secs}"));
+    }
+    Ok(max)
+}
+
+/// Switch to one of the named models (see `Settings::models`).
+#[tauri::command]
+fn set_active_model(app: AppHandle, name: String) -> Result<(), String> {
+    select_model(&app, &name)
+}
+
+/// The `ggml-*.bin` files in the default model directory, for picking one
+/// without typing its path.
+#[tauri::command]
+fn list_available_models() -> Result<Vec<transcribe::AvailableModel>, String> {
+    transcribe::discover_models(&transcribe::default_model_dir()).map_err(|e| format!("{e:#}"))
+}
+
+/// Switch to the model file at `path`, e.g. one of `list_available_models`.
+#[tauri::command]
+fn set_model_path(app: AppHandle, path: String) -> Result<(), String> {
+    if !Path::new(&path).is_file() {
+        return Err(format!("No model file at {path}"));
+    }
+    let st = app.state::<AppState>();
+    log::info!("Model set to {path}");
+    let settings = st.settings.update(|settings| settings.model_path = path);
+    broadcast_settings(&app, settings);
+    Ok(())
+}
+
+/// Switch between local whisper and the transcription API.
+#[tauri::command]
+fn set_backend(app: AppHandle, backend: Backend) -> Result<(), String> {
+    let st = app.state::<AppState>();
+    if backend == Backend::Api && st.settings.get().api_url.trim().is_empty() {
+        return Err("No API URL set".to_string());
+    }
+    let settings = st.settings.update(|settings| settings.backend = backend);
+    log::info!("Backend set to {backend:?}");
+    broadcast_settings(&app, settings);
+    Ok(())
+}
+
+/// Type the final text held back for confirmation.
+#[tauri::command]
+fn confirm_type(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    answer_confirmation(&state, true)
+}
+
+/// Discard the final text held back for confirmation.
+#[tauri::command]
+fn reject_type(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    answer_confirmation(&state, false)
+}
+
+fn answer_confirmation(st: &AppState, confirmed: bool) -> Result<(), String> {
+    if answer_pending(&st.pending_confirm, confirmed) {
+        Ok(())
+    } else {
+        Err("No transcription is waiting for confirmation".to_string())
+    }
+}
+
+/// Settle the held-back text in `pending`, if there is any; returns
+/// whether there was.
+fn answer_pending(pending: &PendingConfirm, confirmed: bool) -> bool {
+    match pending.lock().take() {
+        Some((_, answer)) => answer.send(confirmed).is_ok(),
+        None => false,
+    }
+}
+
+/// Switch to the next of `Settings::cycle_languages` and return it, with a
+/// `language-cycled` event.
+#[tauri::command]
+fn cycle_language(app: AppHandle) -> Result<String, String> {
+    cycle_languages(&app)
+}
+
+/// Seconds of audio left behind by a crash mid-recording, if any.
+#[tauri::command]
+fn get_orphaned_recording() -> Option<f32> {
+    recovery::orphan_duration()
+}
+
+/// Transcribe the recording a crash left behind and delete it. Returns the
+/// text (also added to history), or `None` if there was nothing to recover.
+#[tauri::command]
+async fn recover_recording(state: tauri::State<'_, AppState>) -> Result<Option<String>, String> {
+    let (reply, reply_rx) = mpsc::channel();
+    state
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::Recover { reply })
+        .map_err(|_| "Worker is not running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || reply_rx.recv())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|_| "Worker is not running".to_string())?
+}
+
+#[tauri::command]
+fn discard_recording() {
+    recovery::discard_orphan();
+}
+
+/// The input device in use and its capture format, so it can be checked
+/// before recording.
+#[tauri::command]
+fn get_audio_info(state: tauri::State<'_, AppState>) -> Result<audio::DeviceInfo, String> {
+    let device = state.settings.get().input_device.clone();
+    audio::input_info(&device).map_err(|e| e.to_string())
+}
+
+/// Devices `Settings::input_device` can name, including loopback ones.
+#[tauri::command]
+fn list_input_devices() -> Result<Vec<audio::InputDevice>, String> {
+    audio::input_devices().map_err(|e| e.to_string())
+}
+
+/// Probe again for the external tools typing needs, e.g. after the user
+/// installed them. Returns (and emits as `missing-dependency`) what's absent.
+#[tauri::command]
+fn check_dependencies(app: AppHandle) -> Vec<String> {
+    let missing = typing::recheck_dependencies();
+    if !missing.is_empty() {
+        let _ = app.emit("missing-dependency", &missing);
+    }
+    missing
+}
+
+// --- Hotkey ---
+
+/// Shortest/longest double-press window accepted from the settings.
+const PRESS_WINDOW_RANGE: (u64, u64) = (150, 1500);
+
+fn hotkey_config(settings: &Settings) -> hotkey::HotkeyConfig {
+    let (min, max) = PRESS_WINDOW_RANGE;
+    let mut bindings = settings.hotkeys.clone();
+    let triple = match settings.triple_press_action {
+        TriplePressAction::Disabled => None,
+        TriplePressAction::Cancel => Some(hotkey::HotkeyAction::Cancel),
+        TriplePressAction::SwitchLanguage => Some(hotkey::HotkeyAction::SwitchLanguage),
+    };
+    bindings.extend(triple.map(|action| hotkey::Binding {
+        key: hotkey::HotkeyKey::Alt,
+        presses: 3,
+        held: Vec::new(),
+        action,
+    }));
+    hotkey::HotkeyConfig {
+        window: Duration::from_millis(u64::from(settings.double_press_window_ms).clamp(min, max)),
+        bindings,
+        debug: settings.hotkey_debug,
+    }
+}
+
+fn set_hotkey_error(app: &AppHandle, error: Option<String>) {
+    *app.state::<AppState>().hotkey_error.lock() = error;
+}
+
+fn on_hotkey(app: &AppHandle, action: hotkey::HotkeyAction) {
+    let st = app.state::<AppState>();
+    let cmd = match action {
+        // The worker ignores these itself while paused.
+        hotkey::HotkeyAction::Toggle => WorkerCmd::Toggle,
+        hotkey::HotkeyAction::ToggleTranslate => WorkerCmd::ToggleTranslate,
+        _ if !*st.enabled.lock() => return,
+        hotkey::HotkeyAction::Cancel => return cancel(&st),
+        hotkey::HotkeyAction::SwitchLanguage => {
+            let settings = st.settings.update(|settings| {
+                std::mem::swap(&mut settings.language, &mut settings.secondary_language);
+            });
+            log::info!("Language switched to {}", settings.language);
+            broadcast_settings(app, settings);
+            return;
+        }
+        hotkey::HotkeyAction::CycleLanguage => {
+            if let Err(e) = cycle_languages(app) {
+                log::warn!("{e}");
+            }
+            return;
+        }
+        hotkey::HotkeyAction::Undo => {
+            // Typing blocks; keep it off the listener thread.
+            let app = app.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = undo_typed(&app) {
+                    log::warn!("Undo failed: {e}");
+                }
+            });
+            return;
+        }
+    };
+    send_toggle(app, cmd);
+}
+
+/// Send a start or stop to the worker. While it's transcribing it only
+/// reads commands afterwards, so a start is sent as `WorkerCmd::Queued`
+/// then: it begins a recording once the transcription is done, instead of
+/// being taken for a double-fire. Only the first start per transcription
+/// is queued.
+fn send_toggle(app: &AppHandle, cmd: WorkerCmd) {
+    let st = app.state::<AppState>();
+    let status = *st.status.lock();
+    let starts = !matches!(cmd, WorkerCmd::Stop);
+    if starts && status == AppStatus::Idle && st.model_loading.load(Ordering::Relaxed) {
+        // Read once the model is in; no cooldown applies after a load.
+        log::info!("Toggle waits for the model to load");
+        let _ = app.emit("toggle-queued", QueuedReason::Loading);
+        let _ = st.cmd_tx.lock().send(cmd);
+        return;
+    }
+    let transcribing = status == AppStatus::Transcribing;
+    let timeout = st.settings.get().queued_toggle_timeout_ms;
+    let cmd = if starts && transcribing && timeout > 0 {
+        if st.toggle_queued.swap(true, Ordering::Relaxed) {
+            log::info!("Ignoring toggle: one is already queued");
+            return;
+        }
+        log::info!("Queueing toggle until the transcription is done");
+        let _ = app.emit("toggle-queued", QueuedReason::Transcribing);
+        WorkerCmd::Queued {
+            cmd: Box::new(cmd),
+            at: Instant::now(),
+        }
+    } else {
+        cmd
+    };
+    let _ = st.cmd_tx.lock().send(cmd);
+}
+
+/// Cancel whatever the worker is doing. A recording is discarded by the
+/// worker itself, but while transcribing it's blocked in whisper and only
+/// reads commands afterwards, so whisper is stopped through the abort flag.
+fn cancel(st: &AppState) {
+    answer_pending(&st.pending_confirm, false);
+    if *st.status.lock() == AppStatus::Transcribing {
+        log::info!("Aborting the transcription");
+        st.abort.store(true, Ordering::Relaxed);
+    }
+    let _ = st.cmd_tx.lock().send(WorkerCmd::Cancel);
+}
+
+// --- Persisted preferences ---
+
+/// Marker file for an on/off preference that must survive restarts (the
+/// settings themselves aren't saved): its presence means "on".
+fn marker_path(name: &str) -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("voice-to-text")
+        .join(name)
+}
+
+fn set_marker(name: &str, present: bool) -> std::io::Result<()> {
+    let marker = marker_path(name);
+    if present {
+        if let Some(dir) = marker.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&marker, b"")
+    } else {
+        match std::fs::remove_file(&marker) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Present while the app is paused.
+const PAUSED_MARKER: &str = "paused";
+/// Present if the settings window should open when the app starts.
+const SHOW_ON_LAUNCH_MARKER: &str = "show-window";
+
+fn load_enabled() -> bool {
+    !marker_path(PAUSED_MARKER).exists()
+}
+
+fn apply_enabled(app: &AppHandle, enabled: bool) -> std::io::Result<()> {
+    let st = app.state::<AppState>();
+    *st.enabled.lock() = enabled;
+    log::info!("{}", if enabled { "Resumed" } else { "Paused" });
+    let _ = st.cmd_tx.lock().send(WorkerCmd::EnabledChanged);
+    let _ = app.emit("enabled-changed", enabled);
+    refresh_tray_menu(app);
+    set_marker(PAUSED_MARKER, !enabled)
+}
+
+// --- HTTP API ---
+
+/// Start, restart or stop the HTTP API to match the current settings.
+fn apply_http_settings(app: &AppHandle) {
+    let st = app.state::<AppState>();
+    let (enabled, port, token) = {
+        let s = st.settings.get();
+        (s.http_enabled, s.http_port, s.http_token.clone())
+    };
+    let mut http = st.http.lock();
+
+    if !enabled {
+        *http = None;
+        return;
+    }
+    if let Some(server) = http.as_ref() {
+        if server.matches(port, &token) {
+            return;
+        }
+    }
+    // Release the old port before binding it again.
+    *http = None;
+
+    let handler_app = app.clone();
+    match remote::HttpServer::start(port, &token, move |action| {
+        http_action(&handler_app, action)
+    }) {
+        Ok(server) => *http = Some(server),
+        Err(e) => log::error!("HTTP API not started: {e}"),
+    }
+}
+
+fn http_action(app: &AppHandle, action: remote::Action) -> HttpStatus {
+    let st = app.state::<AppState>();
+    let cmd = match action {
+        remote::Action::Status => None,
+        remote::Action::Start => Some(WorkerCmd::Start),
+        remote::Action::Stop => Some(WorkerCmd::Stop),
+        remote::Action::Toggle => Some(WorkerCmd::Toggle),
+    };
+    if let Some(cmd) = cmd {
+        send_toggle(app, cmd);
+    }
+    // The worker applies the command asynchronously; this is the state it
+    // was received in.
+    let status = *st.status.lock();
+    HttpStatus { status }
+}
+
+// --- Output pipe ---
+
+/// Open, reopen or close the output pipe to match `Settings::pipe_output`.
+fn apply_pipe_settings(app: &AppHandle) {
+    let st = app.state::<AppState>();
+    let target = pipe::PipeTarget::parse(&st.settings.get().pipe_output);
+    let mut pipe = st.pipe.lock();
+    if pipe.as_ref().map(|pipe| pipe.target()) == target.as_ref() {
+        return;
+    }
+    // Close the old socket before binding the path again.
+    *pipe = None;
+    if let Some(target) = target {
+        match pipe::Pipe::open(target) {
+            Ok(opened) => *pipe = Some(opened),
+            Err(e) => log::error!("Output pipe not opened: {e:#}"),
+        }
+    }
+}
+
+/// Whether text goes to the output pipe rather than being typed. Also when
+/// the pipe failed to open, so a mistyped path doesn't type after all.
+fn piping(app: &AppHandle) -> bool {
+    !app.state::<AppState>()
+        .settings
+        .get()
+        .pipe_output
+        .trim()
+        .is_empty()
+}
+
+fn send_to_pipe(app: &AppHandle, message: pipe::Message) {
+    if let Some(pipe) = app.state::<AppState>().pipe.lock().as_ref() {
+        pipe.send(&message);
+    }
+}
+
+// --- Streaming worker ---
+
+fn set_status(app: &AppHandle, status: AppStatus) {
+    let st = app.state::<AppState>();
+    let previous = std::mem::replace(&mut *st.status.lock(), status);
+    match status {
+        AppStatus::Transcribing => st.abort.store(false, Ordering::Relaxed),
+        AppStatus::Recording => *st.last_error.lock() = None,
+        _ => {}
+    }
+    let settings = st.settings.get();
+    if settings.feedback_sound {
+        // The stop beep marks the end of capture, before any final pass.
+        match (previous, status) {
+            (AppStatus::Recording, AppStatus::Recording) => {}
+            (_, AppStatus::Recording) => feedback::play(feedback::Beep::Start),
+            (AppStatus::Recording, _) => feedback::play(feedback::Beep::Stop),
+            _ => {}
+        }
+    }
+    match (previous, status) {
+        (AppStatus::Recording, AppStatus::Recording) => {}
+        (_, AppStatus::Recording) => {
+            let message = i18n::status_text(status, false, settings.ui_language);
+            st.indicator.show(settings.recording_indicator, message);
+        }
+        (AppStatus::Recording, _) => st.indicator.hide(),
+        _ => {}
+    }
+    let _ = app.emit("status-changed", status);
+    update_tray(app, status);
+    update_overlay(app, status);
+    update_main_window(app, previous, status);
+}
+
+fn emit_error(app: &AppHandle, err: AppError) {
+    let st = app.state::<AppState>();
+    let language = st.settings.get().ui_language;
+    let event = ErrorEvent {
+        code: err.code(),
+        summary: i18n::error_summary(&err, language),
+        message: err.message(),
+    };
+    *st.last_error.lock() = Some(event.clone());
+    let _ = app.emit("error", event);
+}
+
+/// Decoding options for a streaming tick (`is_final == false`) or the final pass.
+fn transcribe_config(app: &AppHandle, is_final: bool) -> transcribe::TranscribeConfig {
+    let st = app.state::<AppState>();
+    let settings = st.settings.get();
+    let (sampling, fallback) = match settings.quality_preset.params() {
+        Some(preset) if is_final => (preset.sampling, preset.fallback),
+        Some(preset) => (preset.streaming_sampling, preset.fallback),
+        None => match settings.streaming_sampling {
+            Some(streaming) if !is_final => (streaming, settings.fallback),
+            _ => (settings.sampling, settings.fallback),
+        },
+    };
+    let translate = *st.translate.lock();
+    let language = st.spoken_language.lock().clone();
+    transcribe::TranscribeConfig {
+        language: language.unwrap_or_else(|| settings.language.clone()),
+        initial_prompt: session_prompt(&st, &settings),
+        no_speech_threshold: settings.no_speech_threshold,
+        blocklist: settings.hallucination_blocklist.clone(),
+        sampling,
+        fallback,
+        single_segment: settings.single_segment,
+        translate,
+        // Streaming ticks are short; only a final pass is worth aborting.
+        abort: is_final.then(|| st.abort.clone()),
+        n_threads: transcribe::thread_count(settings.n_threads),
+        // The worker turns it on for the ticks that may use it.
+        keep_context: false,
+        pad: settings.pad_ms.min(MAX_PAD_MS) as usize * audio::TARGET_SAMPLE_RATE as usize / 1000,
+    }
+}
+
+/// Whether a streaming tick decodes with the previous tick's text as
+/// context: with `Settings::use_context`, once a tick of the recording
+/// (`has_context`) was transcribed.
+fn tick_keeps_context(settings: &Settings, has_context: bool) -> bool {
+    settings.use_context && has_context
+}
+
+/// Time between streaming ticks.
+fn stream_interval(app: &AppHandle) -> Duration {
+    let preset = app.state::<AppState>().settings.get().quality_preset;
+    preset
+        .params()
+        .map_or(STREAM_INTERVAL, |params| params.stream_interval)
+}
+
+/// When the streaming tick after one that started at `started` and took
+/// `took` is due. On time, ticks are `interval` apart. After one that
+/// overran the interval, the next would be due at once, transcribing a
+/// longer buffer and falling further behind; with `adaptive` it waits a
+/// full interval after the slow one finished, skipping the ticks it overran.
+fn next_tick_at(started: Instant, took: Duration, interval: Duration, adaptive: bool) -> Instant {
+    if adaptive && took > interval {
+        started + took + interval
+    } else {
+        started + interval
+    }
+}
+
+/// When the next streaming tick is due after one that started at
+/// `started`, per `Settings::adaptive_ticks`. The first tick of a
+/// recording that overruns `interval` sends `falling-behind`.
+fn pace_ticks(
+    app: &AppHandle,
+    started: Instant,
+    interval: Duration,
+    fell_behind: &mut bool,
+) -> Instant {
+    let took = started.elapsed();
+    if took > interval && !*fell_behind {
+        *fell_behind = true;
+        log::warn!("Streaming tick took {took:.2?}, longer than the {interval:?} interval");
+        let event = FallingBehindEvent {
+            tick_ms: took.as_millis() as u64,
+            interval_ms: interval.as_millis() as u64,
+        };
+        let _ = app.emit("falling-behind", event);
+    }
+    let adaptive = app.state::<AppState>().settings.get().adaptive_ticks;
+    next_tick_at(started, took, interval, adaptive)
+}
+
+/// The initial prompt, per `Settings::prompt_source`.
+fn session_prompt(st: &AppState, settings: &Settings) -> String {
+    match settings.prompt_source {
+        PromptSource::None => String::new(),
+        PromptSource::Clipboard => st
+            .clipboard_prompt
+            .lock()
+            .clone()
+            .unwrap_or_else(|| settings.initial_prompt.clone()),
+        PromptSource::Fixed => settings.initial_prompt.clone(),
+    }
+}
+
+/// Read the prompt for a new recording off the clipboard, if that's the
+/// prompt source.
+fn capture_clipboard_prompt(app: &AppHandle) {
+    let st = app.state::<AppState>();
+    let source = st.settings.get().prompt_source;
+    let prompt = (source == PromptSource::Clipboard)
+        .then(typing::clipboard_text)
+        .flatten()
+        .map(|text| streaming::last_words(&text, CLIPBOARD_PROMPT_WORDS).to_string());
+    if source == PromptSource::Clipboard && prompt.is_none() {
+        log::info!("No text on the clipboard; using the fixed prompt");
+    }
+    *st.clipboard_prompt.lock() = prompt;
+}
+
+fn get_mode(app: &AppHandle) -> TranscribeMode {
+    app.state::<AppState>().settings.get().mode
+}
+
+/// Trailing silence that ends a recording, or `None` if auto-stop is off.
+fn auto_stop_silence(app: &AppHandle) -> Option<Duration> {
+    let ms = app.state::<AppState>().settings.get().auto_stop_silence_ms;
+    (ms > 0).then(|| Duration::from_millis(ms.into()).max(AUTO_STOP_MIN_SILENCE))
+}
+
+fn unload_after_idle(app: &AppHandle) -> Option<Duration> {
+    let mins = app
+        .state::<AppState>()
+        .settings
+        .get()
+        .unload_after_idle_mins;
+    (mins > 0).then(|| Duration::from_secs(u64::from(mins) * 60))
+}
+
+/// Length limit of the streaming window in 16kHz samples, or `None` to
+/// transcribe the whole recording on every tick.
+fn stream_window(app: &AppHandle) -> Option<usize> {
+    let secs = app.state::<AppState>().settings.get().stream_window_secs;
+    (secs > 0).then(|| secs as usize * audio::TARGET_SAMPLE_RATE as usize)
+}
+
+/// How far a moved streaming window reaches back (`stream_overlap_ms`), in
+/// 16kHz samples; at most half the window.
+fn stream_overlap(app: &AppHandle) -> usize {
+    let ms = app.state::<AppState>().settings.get().stream_overlap_ms;
+    let samples = ms as usize * audio::TARGET_SAMPLE_RATE as usize / 1000;
+    samples.min(stream_window(app).unwrap_or(0) / 2)
+}
+
+/// Chunk length of the final pass in 16kHz samples, or `None` to
+/// transcribe the whole recording at once.
+fn final_chunk(app: &AppHandle) -> Option<usize> {
+    let secs = app.state::<AppState>().settings.get().final_chunk_secs;
+    (secs > 0).then(|| secs.max(MIN_CHUNK_SECS) as usize * audio::TARGET_SAMPLE_RATE as usize)
+}
+
+/// Where each chunk of a `len`-sample recording starts; every chunk but the
+/// last is `chunk` samples long and overlaps the next by `CHUNK_OVERLAP`.
+fn chunk_starts(len: usize, chunk: Option<usize>) -> Vec<usize> {
+    let Some(chunk) = chunk else {
+        return vec![0];
+    };
+    let mut start = 0;
+    let mut starts = vec![start];
+    while start + chunk < len {
+        start += chunk - CHUNK_OVERLAP;
+        starts.push(start);
+    }
+    starts
+}
+
+/// `Settings::output_mode`, or `PreviewOnly` while text goes to the output
+/// pipe instead.
+fn get_output_mode(app: &AppHandle) -> OutputMode {
+    if piping(app) {
+        return OutputMode::PreviewOnly;
+    }
+    app.state::<AppState>().settings.get().output_mode
+}
+
+/// Whether the final text waits for confirmation before it's typed.
+fn holds_for_confirmation(app: &AppHandle) -> bool {
+    let settings = app.state::<AppState>().settings.get();
+    settings.confirm_before_type && get_output_mode(app).inserts()
+}
+
+/// Whether text is typed as soon as it's committed, before the final text
+/// is in.
+fn types_as_committed(app: &AppHandle) -> bool {
+    get_output_mode(app).inserts() && !holds_for_confirmation(app)
+}
+
+/// Offer `text` for confirmation with a `transcript-pending` event and
+/// wait for the answer, or the timeout. Returns whether to type it; a
+/// `transcript-confirmed` event tells the UI either way.
+fn confirm_delivery(app: &AppHandle, text: &str) -> bool {
+    let st = app.state::<AppState>();
+    let (timeout_secs, on_timeout) = {
+        let settings = st.settings.get();
+        (
+            settings.confirm_timeout_secs.max(1),
+            settings.confirm_timeout_action,
+        )
+    };
+    let (answer, answered) = mpsc::channel();
+    *st.pending_confirm.lock() = Some((text.to_string(), answer));
+    // The answer comes from the settings window.
+    match app.get_webview_window("main") {
+        Some(window) => {
+            let _ = window.show();
+        }
+        None => toggle_window(app),
+    }
+    let _ = app.emit(
+        "transcript-pending",
+        PendingTranscriptEvent { text, timeout_secs },
+    );
+    log::info!("Final text waits for confirmation");
+    let timeout = Duration::from_secs(timeout_secs.into());
+    let confirmed = await_answer(&st.pending_confirm, &answered, timeout, on_timeout);
+    let _ = app.emit("transcript-confirmed", confirmed);
+    confirmed
+}
+
+/// The answer to the text held back in `pending`, or what `on_timeout`
+/// says if none comes within `timeout`; the text is then no longer held.
+fn await_answer(
+    pending: &PendingConfirm,
+    answered: &mpsc::Receiver<bool>,
+    timeout: Duration,
+    on_timeout: ConfirmTimeout,
+) -> bool {
+    match answered.recv_timeout(timeout) {
+        Ok(confirmed) => confirmed,
+        Err(_) => {
+            pending.lock().take();
+            log::info!("No confirmation within {timeout:?}: {on_timeout:?}");
+            on_timeout == ConfirmTimeout::Type
+        }
+    }
+}
+
+fn typing_config(app: &AppHandle) -> typing::TypingConfig {
+    let st = app.state::<AppState>();
+    let settings = st.settings.get();
+    typing::TypingConfig {
+        delay_ms: settings.type_delay_ms,
+        chunk_chars: settings.type_chunk_chars,
+        paste_overrides: settings.paste_overrides.clone(),
+        default_paste: settings.default_paste_method,
+        newline_overrides: settings.newline_overrides.clone(),
+        type_below_chars: settings.type_below_chars,
+        retries: settings.typing_retries,
+    }
+}
+
+/// Conditioning applied to every buffer before whisper sees it, identically
+/// for streaming snapshots and the final pass.
+fn prepare_audio(app: &AppHandle, audio: &mut [f32]) {
+    let settings = app.state::<AppState>().settings.get();
+    audio::apply_gain(audio, settings.input_gain);
+    // Denoise first, so neither the gate nor the gain is set by a noise
+    // peak.
+    if settings.denoise {
+        denoise::denoise(audio);
+    }
+    audio::gate_quiet(audio, settings.loudness_gate);
+    if settings.auto_gain {
+        audio::normalize_peak(audio);
+    }
+}
+
+/// Text clean-up shared by streaming hypotheses and the final pass.
+fn postprocess_text(app: &AppHandle, text: String, language: &str) -> String {
+    let (tidy, format_numbers) = {
+        let settings = app.state::<AppState>().settings.get();
+        (settings.post_process, settings.format_numbers)
+    };
+    let text = if format_numbers {
+        numbers::format_numbers(&text, language)
+    } else {
+        text
+    };
+    if tidy {
+        postprocess::tidy(&text, language)
+    } else {
+        text
+    }
+}
+
+/// Whether the current recording's start still has to be checked for one
+/// of `Settings::language_prefixes`.
+fn needs_prefix_check(app: &AppHandle) -> bool {
+    let st = app.state::<AppState>();
+    !st.prefix_checked.load(Ordering::Relaxed) && !st.settings.get().language_prefixes.is_empty()
+}
+
+/// The first phase of a spoken language switch: transcribe the start of
+/// the recording with the language detected and the phrases as prompt,
+/// and if it opens with one of them, transcribe the rest of the recording
+/// in its language. Only the current recording's language changes.
+fn detect_language_prefix(
+    app: &AppHandle,
+    transcriber: &dyn transcribe::Transcribe,
+    audio: &[f32],
+) {
+    let st = app.state::<AppState>();
+    st.prefix_checked.store(true, Ordering::Relaxed);
+    let prefixes = st.settings.get().language_prefixes.clone();
+    let audio = &audio[..audio.len().min(LANGUAGE_PREFIX_SAMPLES)];
+    let mut config = transcribe_config(app, false);
+    config.language = "auto".to_string();
+    config.translate = false;
+    config.initial_prompt = prefixes
+        .iter()
+        .map(|(phrase, _)| phrase.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let segments = match transcriber.transcribe_segments(audio, &config, None) {
+        Ok(segments) => segments,
+        Err(e) => {
+            log::warn!("Language prefix check failed: {e}");
+            return;
+        }
+    };
+    let text = transcribe::join_segments(&segments);
+    if let Some((language, _)) = postprocess::split_language_prefix(&text, &prefixes) {
+        log::info!("Switching this recording to {language:?} as asked by {text:?}");
+        *st.spoken_language.lock() = Some(language.to_string());
+        let _ = app.emit("language-override", language);
+    }
+}
+
+/// `text` without the phrase that switched the recording's language.
+/// Transcribed in the new language, the phrase may come out different
+/// from the configured one; it's left in then.
+fn strip_language_prefix(app: &AppHandle, text: String) -> String {
+    let st = app.state::<AppState>();
+    if st.spoken_language.lock().is_none() {
+        return text;
+    }
+    let prefixes = st.settings.get().language_prefixes.clone();
+    match postprocess::split_language_prefix(&text, &prefixes) {
+        Some((_, rest)) => rest.to_string(),
+        None => text,
+    }
+}
+
+fn record_history(app: &AppHandle, text: &str, language: &str, summary: history::SessionSummary) {
+    if text.is_empty() {
+        return;
+    }
+    let st = app.state::<AppState>();
+    let (max_entries, persist) = {
+        let settings = st.settings.get();
+        (settings.history_max_entries, settings.history_to_file)
+    };
+    let entry = history::HistoryEntry::new(text.to_string(), language.to_string(), summary);
+    st.history.record(entry, max_entries, persist);
+}
+
+/// Append `text` to today's note (see `Settings::append_to_markdown`). It's
+/// written by the typing queue, after and never alongside the typing of
+/// earlier dictations, so back-to-back sessions land in order.
+fn append_note(app: &AppHandle, text: &str, language: &str) {
+    let settings = app.state::<AppState>().settings.get();
+    let dir = match &settings.append_to_markdown {
+        Some(dir) if !dir.as_os_str().is_empty() && !text.is_empty() => dir.clone(),
+        _ => return,
+    };
+    let heading = settings.markdown_heading.clone();
+    let (text, language) = (text.to_string(), language.to_string());
+    let now = chrono::Local::now().naive_local();
+    enqueue_typing(app, move |_| {
+        match notes::append(&dir, &heading, &text, now, &language) {
+            Ok(path) => log::info!("Appended to {}", path.display()),
+            Err(e) => log::error!("Markdown note: {e:#}"),
+        }
+    });
+}
+
+/// With `lock_target_window` (or confirmation), remember the focused
+/// window for this recording's text.
+fn capture_target_window(app: &AppHandle) {
+    let st = app.state::<AppState>();
+    // Answering a confirmation focuses the settings window, and showing it
+    // may; the text still goes where the dictation started.
+    let settings = st.settings.get();
+    let lock = settings.lock_target_window
+        || settings.window_behavior == WindowBehavior::ShowWhileRecording
+        || holds_for_confirmation(app);
+    let target = lock.then(typing::active_window).flatten();
+    if lock && target.is_none() {
+        log::warn!("Can't tell which window is focused; typing wherever focus is");
+    }
+    *st.target_window.lock() = target;
+}
+
+/// Where and when dictations were typed, to tell whether the next one
+/// continues the last (see `Settings::continuation_space`).
+#[derive(Default)]
+struct Continuation {
+    /// When the last session that typed something ended, and its window.
+    last: Option<(Instant, Option<String>)>,
+    /// Window focused when the current session started.
+    window: Option<String>,
+    /// The current session continues the last one and hasn't typed yet.
+    pending_space: bool,
+    /// Whether the current session typed anything.
+    typed: bool,
+}
+
+/// Whether a dictation starting at `now` in `window` continues the one
+/// that ended in `last`: less than `idle` later, in the same window. When
+/// either window is unknown (always on Windows and macOS) only the time
+/// counts.
+fn continues(
+    last: Option<&(Instant, Option<String>)>,
+    now: Instant,
+    window: Option<&str>,
+    idle: Duration,
+) -> bool {
+    let Some((ended, last_window)) = last else {
+        return false;
+    };
+    let same_window = match (last_window.as_deref(), window) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    };
+    now.saturating_duration_since(*ended) < idle && same_window
+}
+
+/// At the start of a recording: decide whether its text gets a leading
+/// space. Never for the first dictation, nor with `append_space`, which
+/// already separates them.
+fn begin_continuation(app: &AppHandle) {
+    let st = app.state::<AppState>();
+    let (enabled, idle) = {
+        let settings = st.settings.get();
+        let enabled = settings.continuation_space && !settings.append_space;
+        (enabled, settings.continuation_idle_secs)
+    };
+    let target = st.target_window.lock().clone();
+    let window = enabled
+        .then(|| target.or_else(typing::active_window))
+        .flatten();
+    let mut continuation = st.continuation.lock();
+    continuation.pending_space = enabled
+        && continues(
+            continuation.last.as_ref(),
+            Instant::now(),
+            window.as_deref(),
+            Duration::from_secs(idle.into()),
+        );
+    continuation.window = window;
+    continuation.typed = false;
+}
+
+/// At the end of a recording: remember it if it typed anything.
+fn end_continuation(app: &AppHandle) {
+    let st = app.state::<AppState>();
+    let mut continuation = st.continuation.lock();
+    if std::mem::take(&mut continuation.typed) {
+        continuation.last = Some((Instant::now(), continuation.window.take()));
+    }
+    continuation.pending_space = false;
+}
+
+/// The recording's target window as of now, for a typing job to bring back
+/// (see `focus_target`).
+fn target_window(app: &AppHandle) -> Option<String> {
+    app.state::<AppState>().target_window.lock().clone()
+}
+
+/// Bring back the window a typing job was queued for, from a typing job.
+/// If it was closed, forget it and type into whatever has focus now.
+fn focus_target(app: &AppHandle, target: Option<&str>) {
+    let Some(id) = target else {
+        return;
+    };
+    if let Err(e) = typing::focus_window(id) {
+        log::warn!("{e}; typing into the focused window instead");
+        let st = app.state::<AppState>();
+        let mut current = st.target_window.lock();
+        if current.as_deref() == Some(id) {
+            *current = None;
+        }
+    }
+}
+
+/// Whether one of the app's own windows has focus, so keystrokes would land
+/// in its inputs. The overlay never takes focus.
+fn own_window_focused(app: &AppHandle) -> bool {
+    any_focused(
+        app.webview_windows()
+            .values()
+            .map(|window| window.is_focused()),
+    )
+}
+
+/// Whether any window reports focus; one that can't say doesn't count.
+fn any_focused<E>(focus: impl IntoIterator<Item = Result<bool, E>>) -> bool {
+    focus.into_iter().any(|focused| focused.unwrap_or(false))
+}
+
+/// Whether `policy` lets text be typed, asking `own_focused` whether the
+/// app's own window has focus only if it matters.
+fn own_window_allows(policy: OwnWindowPolicy, own_focused: impl FnOnce() -> bool) -> bool {
+    policy == OwnWindowPolicy::Type || !own_focused()
+}
+
+/// From a typing job: whether to go ahead and type `text` (or backspace,
+/// for empty `text`). Not while the app's own window has focus, unless
+/// `Settings::own_window` says to; the text then goes to the scratchpad or
+/// nowhere, with an `own-window-focused` event.
+fn typing_allowed(app: &AppHandle, text: &str) -> bool {
+    let policy = app.state::<AppState>().settings.get().own_window;
+    if own_window_allows(policy, || own_window_focused(app)) {
+        return true;
+    }
+    log::warn!("The app's own window has focus; not typing ({policy:?})");
+    let _ = app.emit("own-window-focused", OwnWindowEvent { policy, text });
+    false
+}
+
+/// See `retype_last`.
+fn retype(app: &AppHandle) -> Result<(), String> {
+    let text = app.state::<AppState>().last_text.lock().clone();
+    let text = text.ok_or("No transcription to retype yet")?;
+    log::info!("Retyping the last transcription");
+    let actions = typing_actions(app, &output_text(app, &text, true));
+    let config = typing_config(app);
+    run_typing(app, move |app| {
+        let typed = typing::typed_text(&actions);
+        if !typing_allowed(app, &typed) {
+            return Ok(());
+        }
+        typing::type_text(&actions, &config)?;
+        *app.state::<AppState>().last_typed.lock() = typed;
+        Ok(())
+    })
+}
+
+/// See `copy_last_to_clipboard`.
+fn copy_last(app: &AppHandle) -> Result<(), String> {
+    let st = app.state::<AppState>();
+    let last = st.last_text.lock().clone();
+    let text = copied_text(last.as_deref(), &st.settings.get())?;
+    log::info!("Copying the last transcription to the clipboard");
+    run_typing(app, move |_| typing::set_clipboard(&text))
+}
+
+/// What `copy_last` puts on the clipboard for the `last` transcription.
+fn copied_text(last: Option<&str>, settings: &Settings) -> Result<String, String> {
+    let last = last.ok_or("No transcription to copy yet")?;
+    Ok(finished_text(settings, last, false))
+}
+
+/// See `undo_last_type`. Runs as a typing job, so it also undoes text that
+/// was still queued when it was asked for.
+fn undo_typed(app: &AppHandle) -> Result<(), String> {
+    run_typing(app, |app| {
+        if !typing_allowed(app, "") {
+            anyhow::bail!("Not undoing: the app's own window has focus");
+        }
+        let st = app.state::<AppState>();
+        let count = typing::backspaces_for(&st.last_typed.lock());
+        if count == 0 {
+            anyhow::bail!("Nothing to undo");
+        }
+        log::info!("Undoing {count} typed characters");
+        // Keep the text if backspacing fails, so the undo can be retried.
+        typing::backspace(count)?;
+        st.last_typed.lock().clear();
+        Ok(())
+    })
+}
+
+/// Queue `job` behind the typing already waiting (see
+/// `typing::TypingQueue`) and return at once.
+fn enqueue_typing(app: &AppHandle, job: impl FnOnce(&AppHandle) + Send + 'static) {
+    let job_app = app.clone();
+    let st = app.state::<AppState>();
+    st.typing.enqueue(move || job(&job_app));
+}
+
+/// Like `enqueue_typing`, but wait for `job` and return its outcome.
+fn run_typing(
+    app: &AppHandle,
+    job: impl FnOnce(&AppHandle) -> anyhow::Result<()> + Send + 'static,
+) -> Result<(), String> {
+    let job_app = app.clone();
+    let st = app.state::<AppState>();
+    match st.typing.run(move || job(&job_app)) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Type the part of the final text that streaming hasn't already typed.
+fn type_final(app: &AppHandle, typed: &str, text: &str) {
+    // Backspaces are counted in characters of the text, which control
+    // phrases don't type as such.
+    let backspace = {
+        let settings = app.state::<AppState>().settings.get();
+        settings.backspace_corrections && settings.control_phrases.is_empty()
+    };
+    let edit = streaming::reconcile_final(typed, text, backspace);
+    if edit.backspaces > 0 {
+        log::info!("Final pass: correcting {} characters", edit.backspaces);
+        let count = edit.backspaces;
+        let target = target_window(app);
+        enqueue_typing(app, move |app| {
+            focus_target(app, target.as_deref());
+            if !typing_allowed(app, "") {
+                return;
+            }
+            if let Err(e) = typing::backspace(count) {
+                log::error!("Backspace failed: {e}");
+                emit_error(app, AppError::Typing(e.to_string()));
+            } else {
+                let st = app.state::<AppState>();
+                let mut last_typed = st.last_typed.lock();
+                for _ in 0..count {
+                    last_typed.pop();
+                }
+            }
+        });
+    }
+    if edit.insert.is_empty() {
+        return;
+    }
+    if get_mode(app) != TranscribeMode::Typewriter {
+        insert(app, &edit.insert);
+        return;
+    }
+    let (delay, language) = {
+        let settings = app.state::<AppState>().settings.get();
+        let delay = Duration::from_millis(settings.sentence_delay_ms.into());
+        (delay, settings.language.clone())
+    };
+    for (i, sentence) in streaming::split_sentences(&edit.insert, &language)
+        .into_iter()
+        .enumerate()
+    {
+        if i > 0 {
+            // Queued like the typing itself, so the pause falls between the
+            // sentences rather than while they're being prepared.
+            enqueue_typing(app, move |_| std::thread::sleep(delay));
+        }
+        insert(app, sentence);
+    }
+}
+
+/// Committed text from before the streaming window followed by the window's
+/// transcription, so hypotheses always cover the whole session.
+fn join_window(base: &str, window: &str) -> String {
+    match (base.is_empty(), window.is_empty()) {
+        (true, _) => window.to_string(),
+        (false, true) => base.to_string(),
+        (false, false) => format!("{base} {window}"),
+    }
+}
+
+/// Where to move the streaming window so it holds at most `stream_window`
+/// samples.
+///
+/// The window may only move past segments whose text is already committed,
+/// so the committed text before it (`typed[..base_len]`) stays exact and
+/// typed offsets stay valid. Returns how many samples to advance and the new
+/// base length, or `None` if the window fits or no committed boundary is
+/// late enough yet, in which case it keeps growing until one is.
+///
+/// The new window starts `stream_overlap` before the end of the last
+/// segment it moves past, while the base length still covers all of that
+/// segment's text. So the next window's transcription starts with some
+/// typed words again, which the tick skips (`streaming::repeated_words`);
+/// `repeated` is how many it skipped in this window's.
+fn slide_window(
+    app: &AppHandle,
+    typed: &str,
+    base_len: usize,
+    segments: &[transcribe::Segment],
+    repeated: usize,
+    window_len: usize,
+) -> Option<(usize, usize)> {
+    let limit = stream_window(app)?;
+    if window_len <= limit {
+        return None;
+    }
+    let first = segments
+        .iter()
+        .position(|segment| window_len - segment.end <= limit)?;
+    let len = committed_through(app, typed, base_len, &segments[..=first], repeated)?;
+    let advance = segments[first].end.saturating_sub(stream_overlap(app));
+    (advance > 0).then_some((advance, len))
+}
+
+/// The length of `typed` that holds the committed base (`typed[..base_len]`)
+/// and the text of `segments`, the first of the window's, less the
+/// `repeated` words they start with (see `slide_window`). `None` unless
+/// all of that is committed.
+fn committed_through(
+    app: &AppHandle,
+    typed: &str,
+    base_len: usize,
+    segments: &[transcribe::Segment],
+    repeated: usize,
+) -> Option<usize> {
+    let language = app.state::<AppState>().settings.get().language.clone();
+    let head = transcribe::join_segments(segments);
+    let head = head[streaming::words_prefix_len(&head, repeated)..].trim_start();
+    let prefix = postprocess_text(app, join_window(&typed[..base_len], head), &language);
+    let words: Vec<&str> = prefix.split_whitespace().collect();
+    let typed_words: Vec<&str> = typed.split_whitespace().collect();
+    let committed = typed_words.get(..words.len()) == Some(&words[..]);
+    committed.then(|| streaming::words_prefix_len(typed, words.len()))
+}
+
+/// Where the committed text ends in the window: the index of the last of
+/// its `segments` whose text is all committed, and the length of `typed` up
+/// to there. For `FinalPass::ExtendOnly`, which transcribes only what
+/// follows, and the stable segments of `caption-update`.
+fn committed_boundary(
+    app: &AppHandle,
+    typed: &str,
+    base_len: usize,
+    segments: &[transcribe::Segment],
+    repeated: usize,
+) -> Option<(usize, usize)> {
+    (0..segments.len()).rev().find_map(|last| {
+        let len = committed_through(app, typed, base_len, &segments[..=last], repeated)?;
+        Some((last, len))
+    })
+}
+
+/// How much of the hypothesis `curr_text` (committed `base` plus the
+/// window's `window_text`, post-processed) streaming may commit, going by
+/// whisper's confidence in each word (see `Settings::commit_confidence`),
+/// in bytes. `segments` hold `repeated` words more than `window_text`, at
+/// the start (see `slide_window`).
+fn confident_prefix_len(
+    app: &AppHandle,
+    base: &str,
+    window_text: &str,
+    segments: &[transcribe::Segment],
+    repeated: usize,
+    curr_text: &str,
+) -> usize {
+    let (threshold, language) = {
+        let settings = app.state::<AppState>().settings.get();
+        (settings.commit_confidence, settings.language.clone())
+    };
+    if threshold <= 0.0 {
+        return curr_text.len();
+    }
+    let confidence: Vec<f32> = segments
+        .iter()
+        .flat_map(|segment| segment.confidence.iter().copied())
+        .skip(repeated)
+        .collect();
+    let words = streaming::confident_words(&confidence, threshold);
+    if words == confidence.len() {
+        return curr_text.len();
+    }
+    // Post-processing can merge or split words ("twenty three" → "23"), so
+    // count them again after it.
+    let head = &window_text[..streaming::words_prefix_len(window_text, words)];
+    let prefix = postprocess_text(app, join_window(base, head), &language);
+    streaming::words_prefix_len(curr_text, prefix.split_whitespace().count())
+}
+
+/// How much of `curr_text` a streaming tick may commit under
+/// `Settings::commit_granularity`, in bytes, given the `confident` limit
+/// and the previous hypothesis `prev_text`. `last_commit` is when text was
+/// last committed this session.
+fn commit_limit(
+    app: &AppHandle,
+    prev_text: &str,
+    curr_text: &str,
+    confident: usize,
+    last_commit: Instant,
+) -> usize {
+    let (granularity, interval, language) = {
+        let settings = app.state::<AppState>().settings.get();
+        let interval = Duration::from_millis(u64::from(settings.commit_interval_ms));
+        let language = settings.language.clone();
+        (settings.commit_granularity, interval, language)
+    };
+    match granularity {
+        CommitGranularity::Word => confident,
+        CommitGranularity::Sentence => {
+            let stable = streaming::stable_prefix_len(prev_text, curr_text).min(confident);
+            streaming::sentence_prefix_len(&curr_text[..stable], &language)
+        }
+        CommitGranularity::Interval if last_commit.elapsed() >= interval => confident,
+        CommitGranularity::Interval => 0,
+    }
+}
+
+/// `text` as typing actions, with each control phrase (see
+/// `Settings::control_phrases`) replaced by its action. Like a newline
+/// substitution, a phrase swallows the punctuation whisper tends to put
+/// after it, and a key also the spaces before it. The earliest phrase wins,
+/// the longest if several start at the same place.
+fn parse_actions(text: &str, phrases: &[(String, typing::TypeAction)]) -> Vec<typing::TypeAction> {
+    let mut actions = Vec::new();
+    let mut rest = text;
+    loop {
+        let next = phrases
+            .iter()
+            .filter_map(|(phrase, action)| {
+                postprocess::find_phrase(rest, phrase).map(|range| (range, action))
+            })
+            .min_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+        let Some((range, action)) = next else {
+            break;
+        };
+        let before = &rest[..range.start];
+        match action {
+            typing::TypeAction::Text(text) => {
+                actions.extend(typing::text_actions(before));
+                actions.extend(typing::text_actions(text));
+            }
+            typing::TypeAction::Key(_) => {
+                actions.extend(typing::text_actions(before.trim_end_matches(' ')));
+                actions.push(action.clone());
+            }
+        }
+        rest = rest[range.end..].trim_start_matches([' ', ',', '.', ';', ':']);
+    }
+    actions.extend(typing::text_actions(rest));
+    actions
+}
+
+fn typing_actions(app: &AppHandle, text: &str) -> Vec<typing::TypeAction> {
+    let settings = app.state::<AppState>().settings.get();
+    parse_actions(text, &settings.control_phrases)
+}
+
+/// Insert `text` at the cursor as the output mode asks for.
+fn insert(app: &AppHandle, text: &str) {
+    let text = {
+        let st = app.state::<AppState>();
+        let mut continuation = st.continuation.lock();
+        continuation.typed = true;
+        let space = std::mem::take(&mut continuation.pending_space);
+        if space && !text.starts_with(char::is_whitespace) {
+            format!(" {text}")
+        } else {
+            text.to_string()
+        }
+    };
+    let actions = typing_actions(app, &text);
+    let config = typing_config(app);
+    let paste = get_output_mode(app) == OutputMode::TypeAndClipboard;
+    let target = target_window(app);
+    enqueue_typing(app, move |app| {
+        focus_target(app, target.as_deref());
+        if !typing_allowed(app, &typing::typed_text(&actions)) {
+            return;
+        }
+        let result = if paste {
+            typing::paste_text(&actions, &config)
+        } else {
+            typing::type_text(&actions, &config)
+        };
+        match result {
+            Ok(()) => {
+                let typed = typing::typed_text(&actions);
+                app.state::<AppState>().last_typed.lock().push_str(&typed);
+            }
+            Err(e) => {
+                log::error!("Typing failed: {e}");
+                emit_error(app, AppError::Typing(e.to_string()));
+            }
+        }
+    });
+}
+
+fn set_clipboard(app: &AppHandle, text: &str) {
+    let text = text.to_string();
+    enqueue_typing(app, move |app| {
+        if let Err(e) = typing::set_clipboard(&text) {
+            log::error!("Setting clipboard failed: {e}");
+            emit_error(app, AppError::Typing(e.to_string()));
+        }
+    });
+}
+
+/// Transcribe a whole recording with all post-processing applied. Also
+/// returns whisper's segments, unprocessed. Progress is emitted as
+/// `transcribe-progress` events (percent).
+///
+/// Long recordings are transcribed in overlapping chunks (see
+/// `final_chunk`); `on_chunk` gets the text so far after each chunk but the
+/// last.
+fn final_text(
+    app: &AppHandle,
+    transcriber: &dyn transcribe::Transcribe,
+    audio: &[f32],
+    config: &transcribe::TranscribeConfig,
+    mut on_chunk: impl FnMut(&str),
+) -> anyhow::Result<(String, Vec<transcribe::Segment>)> {
+    let chunk = final_chunk(app);
+    let starts = chunk_starts(audio.len(), chunk);
+    let mut raw = String::new();
+    let mut segments = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = chunk.map_or(audio.len(), |chunk| (start + chunk).min(audio.len()));
+        // Only emits: takes none of the locks the worker may hold meanwhile.
+        let progress_app = app.clone();
+        let (done, count) = (i as i32, starts.len() as i32);
+        let on_progress: transcribe::ProgressFn = Box::new(move |percent| {
+            let overall = (done * 100 + percent) / count;
+            let _ = progress_app.emit("transcribe-progress", overall);
+        });
+        let chunk_segments =
+            transcriber.transcribe_segments(&audio[start..end], config, Some(on_progress))?;
+        raw = streaming::stitch(
+            &raw,
+            &transcribe::join_segments(&chunk_segments),
+            STITCH_MAX_WORDS,
+        );
+        // Segments that lie entirely in the overlap were already kept from
+        // the previous chunk.
+        let overlap_end = if i == 0 { 0 } else { start + CHUNK_OVERLAP };
+        segments.extend(
+            chunk_segments
+                .into_iter()
+                .map(|segment| shift_segment(segment, start))
+                .filter(|segment| segment.end > overlap_end),
+        );
+        if i + 1 < starts.len() {
+            on_chunk(&finish_text(app, raw.clone(), config));
+        }
+    }
+    Ok((finish_text(app, raw, config), segments))
+}
+
+/// `segment`, with its positions moved `offset` samples later.
+fn shift_segment(mut segment: transcribe::Segment, offset: usize) -> transcribe::Segment {
+    segment.start += offset;
+    segment.end += offset;
+    for word in &mut segment.words {
+        word.start += offset;
+        word.end += offset;
+    }
+    segment
+}
+
+/// Post-processing of the final pass's raw text.
+fn finish_text(app: &AppHandle, raw: String, config: &transcribe::TranscribeConfig) -> String {
+    let text = postprocess_text(app, strip_language_prefix(app, raw), &config.language);
+    final_substitutions(app, &text)
+}
+
+/// `Settings::substitutions` applied to final text. Only there, never on
+/// streaming ticks, so half-heard phrases can't match.
+fn final_substitutions(app: &AppHandle, text: &str) -> String {
+    let rules = app.state::<AppState>().settings.get().substitutions.clone();
+    postprocess::substitute(text, &rules)
+}
+
+/// What streaming made of a recording, for the final pass to build on
+/// (see `Settings::final_pass`).
+struct Streamed<'a> {
+    /// The committed text.
+    typed: &'a str,
+    /// The last tick's text of the whole recording, committed or not.
+    hypothesis: &'a str,
+    /// Where the committed text stops covering the audio (16kHz samples)
+    /// and the length of `typed` up to there (see `committed_boundary`).
+    committed_to: (usize, usize),
+}
+
+/// The final text and segments of a recording, transcribed as
+/// `Settings::final_pass` asks. `on_chunk` is as for `final_text`.
+fn final_pass_text(
+    app: &AppHandle,
+    transcriber: &dyn transcribe::Transcribe,
+    audio: &[f32],
+    streamed: &Streamed,
+    config: &transcribe::TranscribeConfig,
+    mut on_chunk: impl FnMut(&str),
+) -> anyhow::Result<(String, Vec<transcribe::Segment>)> {
+    let (start, len) = streamed.committed_to;
+    match app.state::<AppState>().settings.get().final_pass {
+        // With nothing streamed, there's nothing to trust.
+        FinalPass::None if !streamed.hypothesis.is_empty() => {
+            log::info!("Final pass skipped; keeping the streaming text");
+            return Ok((final_substitutions(app, streamed.hypothesis), Vec::new()));
+        }
+        FinalPass::ExtendOnly if len > 0 => {}
+        _ => return final_text(app, transcriber, audio, config, on_chunk),
+    }
+    let tail = &audio[start.min(audio.len())..];
+    if tail.len() < min_audio_samples(app) {
+        // Too short for whisper alone; the last tick heard it in context.
+        log::info!("Final pass skipped; the streaming text covers almost all");
+        return Ok((final_substitutions(app, streamed.hypothesis), Vec::new()));
+    }
+    log::info!("Final pass over the last {} ms", samples_to_ms(tail.len()));
+    let base = &streamed.typed[..len];
+    let mut config = config.clone();
+    // Let whisper continue the sentence, as streaming ticks do.
+    let context = streaming::last_words(base, WINDOW_CONTEXT_WORDS);
+    config.initial_prompt = join_window(&config.initial_prompt, context);
+    let (text, segments) = final_text(app, transcriber, tail, &config, |text| {
+        on_chunk(&postprocess::join_burst(base, text))
+    })?;
+    Ok(extend_committed(base, start, &text, segments))
+}
+
+/// The `ExtendOnly` result: the committed `base`, which covers the audio up
+/// to sample `start`, continued with the `text` and `segments` of the rest,
+/// the segments moved to their place in the whole recording.
+fn extend_committed(
+    base: &str,
+    start: usize,
+    text: &str,
+    segments: Vec<transcribe::Segment>,
+) -> (String, Vec<transcribe::Segment>) {
+    let segments = segments
+        .into_iter()
+        .map(|segment| shift_segment(segment, start))
+        .collect();
+    (postprocess::join_burst(base, text), segments)
+}
+
+/// `text` as it should be inserted, trimmed and followed by a space as the
+/// settings ask. The space only goes after whole transcriptions being typed
+/// (`typing`), not onto the clipboard or partial text.
+fn output_text(app: &AppHandle, text: &str, typing: bool) -> String {
+    finished_text(&app.state::<AppState>().settings.get(), text, typing)
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum AppStatus {
-    Idle,
-    Recording,
-    Transcribing,
+/// `output_text` with `settings`.
+fn finished_text(settings: &Settings, text: &str, typing: bool) -> String {
+    let append_space = typing && settings.append_space;
+    postprocess::finish_output(text, settings.trim_output, append_space)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum TranscribeMode {
-    Streaming,
-    Batch,
+/// Hand the final text over as the output mode asks, given what streaming
+/// already typed.
+fn deliver_final(app: &AppHandle, typed: &str, text: &str) {
+    let typing = output_text(app, text, true);
+    let clipboard = output_text(app, text, false);
+    match get_output_mode(app) {
+        OutputMode::Type => type_final(app, typed, &typing),
+        OutputMode::TypeAndClipboard => {
+            type_final(app, typed, &typing);
+            set_clipboard(app, &clipboard);
+        }
+        OutputMode::ClipboardOnly => set_clipboard(app, &clipboard),
+        OutputMode::PreviewOnly => {}
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Settings {
-    pub model_path: String,
-    pub language: String,
-    pub mode: TranscribeMode,
+/// `text` behind the expanded `prefix_template`. Skipped if streaming
+/// already typed part of the dictation, since the prefix would have to go
+/// in front of it.
+fn with_prefix(app: &AppHandle, typed: &str, text: &str, language: &str) -> String {
+    let settings = app.state::<AppState>().settings.get();
+    match settings.prefix_template.as_deref() {
+        Some(template) if !template.is_empty() && typed.is_empty() && !text.is_empty() => {
+            let now = chrono::Local::now().naive_local();
+            let prefix = postprocess::expand_prefix(template, now, language);
+            format!("{prefix}{text}")
+        }
+        _ => text.to_string(),
+    }
 }
 
-impl Default for Settings {
-    fn default() -> Self {
-        Self {
-            model_path: transcribe::default_model_path()
-                .to_string_lossy()
-                .to_string(),
-            language: "pl".to_string(),
-            mode: TranscribeMode::Streaming,
+/// Transcribe the whole recording and deliver whatever streaming hasn't already.
+fn final_pass(
+    app: &AppHandle,
+    transcriber: Option<&dyn transcribe::Transcribe>,
+    audio: &[f32],
+    streamed: &Streamed,
+) {
+    // `ensure_model` has already reported why there's no model.
+    let Some(t) = transcriber else {
+        return;
+    };
+
+    if needs_prefix_check(app) {
+        // Too short for streaming to have checked, or not streamed.
+        detect_language_prefix(app, t, audio);
+    }
+    let config = transcribe_config(app, true);
+    // Chunks of a long recording are typed as they're done, as far as they
+    // extend what's typed already; the rest waits for the final text.
+    let mut typed = streamed.typed.to_string();
+    let types = types_as_committed(app);
+    let pipes = piping(app);
+    let on_chunk = |text: &str| {
+        let _ = app.emit(
+            "transcript",
+            TranscriptEvent {
+                text,
+                is_final: false,
+            },
+        );
+        let text = output_text(app, text, false);
+        match text.strip_prefix(typed.as_str()) {
+            Some(rest) if types && !rest.is_empty() => {
+                insert(app, rest);
+                typed = text.clone();
+            }
+            Some(rest) if pipes && !rest.is_empty() => {
+                send_to_pipe(app, pipe::Message::Chunk { text: rest });
+                typed = text.clone();
+            }
+            _ => {}
+        }
+    };
+    let started = Instant::now();
+    match final_pass_text(app, t, audio, streamed, &config, on_chunk) {
+        Ok((text, segments)) => {
+            log::info!("Final transcription: {text}");
+            let text = limit_output(app, text);
+            let summary = session_summary(t, &text, &segments, audio.len(), started);
+            let caption = caption_segments(&segments, 0, segments.len());
+            let cues = segments
+                .into_iter()
+                .map(|segment| subtitles::Cue {
+                    start: segment.start,
+                    end: segment.end,
+                    text: postprocess_text(app, segment.text, &config.language),
+                    words: segment.words,
+                })
+                .collect();
+            *app.state::<AppState>().last_cues.lock() = cues;
+            if !text.is_empty() {
+                set_last_text(app, &text);
+                add_to_session(app, &text);
+            }
+            record_history(app, &text, &config.language, summary.clone());
+            append_note(app, &text, &config.language);
+            let _ = app.emit(
+                "transcript",
+                TranscriptEvent {
+                    text: &text,
+                    is_final: true,
+                },
+            );
+            let _ = app.emit(
+                "caption-update",
+                CaptionEvent {
+                    committed: &text,
+                    tentative: "",
+                    segments: caption,
+                    is_final: true,
+                },
+            );
+            let message = pipe::Message::Final {
+                text: &text,
+                language: &config.language,
+            };
+            send_to_pipe(app, message);
+            let held = holds_for_confirmation(app);
+            if held {
+                // Streaming only committed it; none of it was typed.
+                typed.clear();
+            }
+            let delivered = with_prefix(app, &typed, &text, &config.language);
+            if !held || delivered.is_empty() || confirm_delivery(app, &delivered) {
+                deliver_final(app, &typed, &delivered);
+            }
+            let _ = app.emit("session-summary", summary);
+        }
+        Err(e) if e.is::<transcribe::Aborted>() => {
+            log::info!("Final transcription aborted");
+            let _ = app.emit("transcription-aborted", ());
+        }
+        Err(e) => {
+            log::error!("Final transcription failed: {e}");
+            emit_error(app, AppError::Transcription(e.to_string()));
         }
     }
 }
 
-enum WorkerCmd {
-    Toggle,
-    UpdateSettings(Settings),
+/// `text` cut to `Settings::max_output_chars`. Warns with `runaway-output`
+/// if it was longer, or if whisper got stuck repeating a phrase.
+fn limit_output(app: &AppHandle, text: String) -> String {
+    let max = app.state::<AppState>().settings.get().max_output_chars;
+    let chars = text.chars().count();
+    let truncated = max > 0 && chars > max;
+    let repetition = postprocess::repeated_phrase(&text);
+    if !truncated && repetition.is_none() {
+        return text;
+    }
+    if let Some((phrase, repeats)) = &repetition {
+        log::warn!("Transcription repeats \"{phrase}\" {repeats} times; whisper may be looping");
+    }
+    if truncated {
+        log::warn!("Transcription cut from {chars} to {max} characters");
+    }
+    let (repeated_phrase, repeats) = repetition.unzip();
+    let event = RunawayOutputEvent {
+        chars,
+        max_chars: max,
+        truncated,
+        repeated_phrase,
+        repeats: repeats.unwrap_or(0),
+    };
+    let _ = app.emit("runaway-output", event);
+    if truncated {
+        postprocess::truncate_chars(&text, max).to_string()
+    } else {
+        text
+    }
 }
 
-pub struct AppState {
-    status: Mutex<AppStatus>,
-    settings: Mutex<Settings>,
-    cmd_tx: Mutex<mpsc::Sender<WorkerCmd>>,
+/// Figures for `session-summary` and history about a final pass begun at
+/// `started`.
+fn session_summary(
+    transcriber: &dyn transcribe::Transcribe,
+    text: &str,
+    segments: &[transcribe::Segment],
+    samples: usize,
+    started: Instant,
+) -> history::SessionSummary {
+    let confidence = segments.iter().flat_map(|s| s.confidence.iter().copied());
+    history::SessionSummary::new(
+        text,
+        samples,
+        confidence,
+        started.elapsed(),
+        transcriber.gpu_requested(),
+    )
 }
 
-// --- Tauri commands ---
+/// Shortest recording worth transcribing (`min_audio_ms`), in 16kHz
+/// samples.
+fn min_audio_samples(app: &AppHandle) -> usize {
+    let ms = app.state::<AppState>().settings.get().min_audio_ms;
+    ms.max(MIN_AUDIO_MS) as usize * audio::TARGET_SAMPLE_RATE as usize / 1000
+}
 
-#[tauri::command]
-fn get_status(state: tauri::State<'_, AppState>) -> AppStatus {
-    *state.status.lock()
+/// Whether a recording begun at `started` is ready for an early first
+/// streaming tick (see `Settings::onset_speech_ms`).
+fn speech_onset(app: &AppHandle, rec: &audio::AudioRecorder, started: Instant) -> bool {
+    let settings = app.state::<AppState>().settings.get();
+    // Pre-roll only adds audio, so the time since the start is a floor.
+    onset_reached(&settings, started.elapsed(), rec.voice_activity().speech)
 }
 
-#[tauri::command]
-fn get_settings(state: tauri::State<'_, AppState>) -> Settings {
-    state.settings.lock().clone()
+/// `speech_onset` for a recording `recorded` long that holds `speech`.
+fn onset_reached(settings: &Settings, recorded: Duration, speech: Duration) -> bool {
+    if settings.onset_speech_ms == 0 {
+        return false;
+    }
+    let min_audio = Duration::from_millis(settings.min_audio_ms.max(MIN_AUDIO_MS).into());
+    let onset = Duration::from_millis(settings.onset_speech_ms.max(MIN_ONSET_SPEECH_MS).into());
+    recorded >= min_audio && speech >= onset
 }
 
-#[tauri::command]
-fn update_settings(state: tauri::State<'_, AppState>, settings: Settings) {
-    *state.settings.lock() = settings.clone();
-    let _ = state.cmd_tx.lock().send(WorkerCmd::UpdateSettings(settings));
+/// Whether the streaming window `audio`, at `window_start` in the
+/// recording, has speech resuming after a `Settings::pause_reset_ms`
+/// pause since the last tick.
+fn resumed_after_pause(
+    app: &AppHandle,
+    pauses: &mut audio::PauseDetector,
+    audio: &[f32],
+    window_start: usize,
+) -> bool {
+    let ms = app.state::<AppState>().settings.get().pause_reset_ms;
+    let gap = Duration::from_millis(u64::from(ms));
+    // Scanned even when off, so turning it on doesn't find an old pause.
+    pauses.resumed_after(audio, window_start, gap) && ms > 0
 }
 
-#[tauri::command]
-fn check_model(state: tauri::State<'_, AppState>) -> bool {
-    let path = PathBuf::from(&state.settings.lock().model_path);
-    path.exists()
+/// Warn if too much of the recording `audio` (as captured, before any
+/// gain) was clipped, per `Settings::clipping_warning_percent`.
+fn check_clipping(app: &AppHandle, audio: &[f32]) {
+    let threshold = app
+        .state::<AppState>()
+        .settings
+        .get()
+        .clipping_warning_percent;
+    if threshold <= 0.0 {
+        return;
+    }
+    let percent = audio::clipped_fraction(audio) * 100.0;
+    if percent > threshold {
+        log::warn!("{percent:.1}% of the recording clipped; the input level is too high");
+        let event = ClippingEvent {
+            percent,
+            threshold_percent: threshold,
+        };
+        let _ = app.emit("clipping-detected", event);
+    }
 }
 
-#[tauri::command]
-fn toggle_recording(state: tauri::State<'_, AppState>) {
-    let _ = state.cmd_tx.lock().send(WorkerCmd::Toggle);
+fn samples_to_ms(samples: usize) -> u64 {
+    samples as u64 * 1000 / audio::TARGET_SAMPLE_RATE as u64
 }
 
-// --- Streaming worker ---
+/// Why a recording of `len` samples holding `speech` is discarded: shorter
+/// than `min_samples`, or less speech than `min_speech_ms`.
+fn too_short(
+    len: usize,
+    speech: Duration,
+    min_samples: usize,
+    min_speech_ms: u32,
+) -> Option<TooShortEvent> {
+    if len < min_samples {
+        log::info!("Discarding recording of {len} samples");
+        Some(TooShortEvent {
+            reason: TooShortReason::Length,
+            duration_ms: samples_to_ms(len),
+            minimum_ms: samples_to_ms(min_samples),
+        })
+    } else if speech < Duration::from_millis(min_speech_ms.into()) {
+        // A cough or an "um": whisper would only make something up.
+        log::info!("Discarding recording with only {speech:?} of speech");
+        Some(TooShortEvent {
+            reason: TooShortReason::Speech,
+            duration_ms: speech.as_millis() as u64,
+            minimum_ms: min_speech_ms.into(),
+        })
+    } else {
+        None
+    }
+}
+
+/// Stop `rec` and run the final pass over everything it captured. Returns
+/// the prepared audio.
+fn finish_recording(
+    app: &AppHandle,
+    transcriber: Option<&dyn transcribe::Transcribe>,
+    rec: &mut audio::AudioRecorder,
+    streamed: &Streamed,
+) -> Vec<f32> {
+    let mut audio = rec.snapshot();
+    let speech = rec.voice_activity().speech;
+    rec.stop();
+    check_clipping(app, &audio);
+    prepare_audio(app, &mut audio);
+
+    let min_speech = app.state::<AppState>().settings.get().min_speech_ms;
+    let min_samples = min_audio_samples(app);
+    match too_short(audio.len(), speech, min_samples, min_speech) {
+        Some(event) => {
+            let _ = app.emit("too-short", event);
+        }
+        None => final_pass(app, transcriber, &audio, streamed),
+    }
+    end_continuation(app);
+    // Later deliveries (e.g. `transcribe_file`) go to the focused window,
+    // with the fixed prompt and without translation.
+    app.state::<AppState>().target_window.lock().take();
+    app.state::<AppState>().clipboard_prompt.lock().take();
+    *app.state::<AppState>().translate.lock() = false;
+    app.state::<AppState>().spoken_language.lock().take();
+    audio
+}
+
+/// Final pass over audio that didn't come from a live session (a recovered
+/// recording or a file). The text goes to history and is returned.
+fn transcribe_offline(
+    app: &AppHandle,
+    transcriber: Option<&dyn transcribe::Transcribe>,
+    mut audio: Vec<f32>,
+) -> Result<String, String> {
+    let Some(t) = transcriber else {
+        return Err(AppError::ModelNotLoaded.message());
+    };
+    prepare_audio(app, &mut audio);
+    let config = transcribe_config(app, true);
+    let started = Instant::now();
+    let (text, segments) = final_text(app, t, &audio, &config, |_| {}).map_err(|e| {
+        if e.is::<transcribe::Aborted>() {
+            let _ = app.emit("transcription-aborted", ());
+        }
+        e.to_string()
+    })?;
+    let summary = session_summary(t, &text, &segments, audio.len(), started);
+    record_history(app, &text, &config.language, summary);
+    Ok(text)
+}
+
+/// Transcribe the orphaned recording, if any, and delete it once that worked.
+fn recover(
+    app: &AppHandle,
+    transcriber: Option<&dyn transcribe::Transcribe>,
+) -> Result<Option<String>, String> {
+    let Some((raw, sample_rate)) = recovery::read_orphan().map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let quality = app.state::<AppState>().settings.get().resample_quality;
+    let audio = audio::resample(&raw, sample_rate, audio::TARGET_SAMPLE_RATE, quality);
+    let text = transcribe_offline(app, transcriber, audio)?;
+    log::info!("Recovered transcription: {text}");
+    recovery::discard_orphan();
+    Ok(Some(text))
+}
+
+/// Transcribe a WAV file, optionally delivering the text like a recording's
+/// (typed or put on the clipboard, per the output mode).
+fn run_file_transcription(
+    app: &AppHandle,
+    transcriber: Option<&dyn transcribe::Transcribe>,
+    path: &Path,
+    deliver: bool,
+) -> Result<String, String> {
+    let quality = app.state::<AppState>().settings.get().resample_quality;
+    let audio = audio::load_wav(path, quality).map_err(|e| format!("{}: {e:#}", path.display()))?;
+    let text = transcribe_offline(app, transcriber, audio)?;
+    log::info!("Transcribed {}: {text}", path.display());
+    if deliver {
+        app.state::<AppState>().last_typed.lock().clear();
+        deliver_final(app, "", &text);
+    }
+    Ok(text)
+}
+
+fn run_benchmark(
+    app: &AppHandle,
+    transcriber: Option<&dyn transcribe::Transcribe>,
+    last_audio: &[f32],
+    runs: u32,
+) -> Result<benchmark::BenchmarkReport, String> {
+    let Some(t) = transcriber else {
+        return Err(AppError::ModelNotLoaded.message());
+    };
+    let clip;
+    let (audio, source) = if last_audio.len() >= min_audio_samples(app) {
+        (last_audio, benchmark::ClipSource::LastRecording)
+    } else {
+        let (samples, source) = match speech_sample(app) {
+            Ok(samples) => (samples, benchmark::ClipSource::SpeechSample),
+            Err(e) => {
+                log::warn!("Benchmarking a synthetic clip: {e:#}");
+                (
+                    benchmark::synthetic_clip(),
+                    benchmark::ClipSource::Synthetic,
+                )
+            }
+        };
+        clip = samples;
+        (&clip[..], source)
+    };
+    let config = transcribe_config(app, true);
+    let interval = stream_interval(app);
+    let report = benchmark::run(t, audio, source, &config, runs, interval, |p| {
+        let _ = app.emit("benchmark-progress", p);
+    });
+    report.map_err(|e| e.to_string())
+}
+
+/// `benchmark::SPEECH_SAMPLE`, decoded from the app's resources.
+fn speech_sample(app: &AppHandle) -> anyhow::Result<Vec<f32>> {
+    let path = app.path().resolve(
+        benchmark::SPEECH_SAMPLE,
+        tauri::path::BaseDirectory::Resource,
+    )?;
+    let quality = app.state::<AppState>().settings.get().resample_quality;
+    audio::load_wav(&path, quality)
+}
+
+/// What the pre-roll capture should record, or `None` if it shouldn't run
+/// (off, or paused: nothing listens then).
+fn preroll_config(app: &AppHandle) -> Option<audio::PreRollConfig> {
+    let st = app.state::<AppState>();
+    if !*st.enabled.lock() {
+        return None;
+    }
+    let settings = st.settings.get();
+    let ms = settings.preroll_ms.min(MAX_PREROLL_MS);
+    (ms > 0).then(|| audio::PreRollConfig {
+        device: settings.input_device.clone(),
+        channel: settings.input_channel,
+        highpass: settings.highpass,
+        length: Duration::from_millis(u64::from(ms)),
+    })
+}
+
+/// Start, restart or stop the pre-roll capture to match the settings.
+/// `failed` remembers a configuration that couldn't be opened, so it isn't
+/// retried (and logged) on every command until the settings change.
+fn update_preroll(
+    app: &AppHandle,
+    preroll: &mut Option<audio::PreRoll>,
+    failed: &mut Option<audio::PreRollConfig>,
+) {
+    let wanted = preroll_config(app);
+    if preroll.as_ref().map(audio::PreRoll::config) == wanted.as_ref() {
+        return;
+    }
+    // Release the device before opening it again.
+    *preroll = None;
+    let Some(wanted) = wanted else {
+        return;
+    };
+    if failed.as_ref() == Some(&wanted) {
+        return;
+    }
+    match audio::PreRoll::start(wanted.clone()) {
+        Ok(capture) => {
+            *preroll = Some(capture);
+            *failed = None;
+        }
+        Err(e) => {
+            log::warn!("Pre-roll capture failed: {e}");
+            *failed = Some(wanted);
+        }
+    }
+}
 
-/// Find byte length of the common prefix between two strings.
-fn stable_prefix_len(a: &str, b: &str) -> usize {
-    let mut len = 0;
-    for (ca, cb) in a.chars().zip(b.chars()) {
-        if ca != cb {
+fn capture_options(app: &AppHandle) -> audio::CaptureOptions {
+    let settings = app.state::<AppState>().settings.get();
+    audio::CaptureOptions {
+        highpass: settings.highpass,
+        spool: settings.crash_recovery,
+        channel: settings.input_channel,
+        resample: settings.resample_quality,
+        monitor: settings.monitor_input.then_some(settings.monitor_volume),
+    }
+}
+
+/// The recording of `record_and_transcribe`, made on the worker thread:
+/// until `max` has passed, a pause follows some speech, or a stop comes
+/// in. Commands it doesn't act on go to `deferred` for the worker. Returns
+/// the audio, unless it's too short or has no speech to transcribe.
+fn record_once(
+    app: &AppHandle,
+    rec: &mut audio::AudioRecorder,
+    max: Duration,
+    rx: &mpsc::Receiver<WorkerCmd>,
+    deferred: &mut VecDeque<WorkerCmd>,
+) -> Result<Vec<f32>, String> {
+    rec.start(capture_options(app), audio_error_sender(app))
+        .map_err(|e| AppError::AudioStream(e.to_string()).message())?;
+    set_status(app, AppStatus::Recording);
+    log::info!("Recording for up to {max:?} to return the text");
+    let silence = auto_stop_silence(app).unwrap_or(ONE_SHOT_SILENCE);
+    let started = Instant::now();
+    while started.elapsed() < max {
+        let activity = rec.voice_activity();
+        if activity.speech >= AUTO_STOP_MIN_SPEECH && activity.trailing_silence >= silence {
             break;
         }
-        len += ca.len_utf8();
+        match rx.recv_timeout(SILENCE_POLL) {
+            Ok(WorkerCmd::Toggle | WorkerCmd::ToggleTranslate | WorkerCmd::Stop) => break,
+            Ok(WorkerCmd::Cancel) => {
+                rec.stop();
+                return Err("Recording cancelled".to_string());
+            }
+            Ok(WorkerCmd::AudioFailed(message)) => {
+                log::error!("Audio stream failed while recording: {message}");
+                if !reopen_stream(app, rec) {
+                    // Whatever was captured before the stream died is kept.
+                    break;
+                }
+            }
+            Ok(cmd @ WorkerCmd::Shutdown { .. }) => {
+                rec.stop();
+                deferred.push_back(cmd);
+                return Err("Quitting".to_string());
+            }
+            Ok(cmd) => deferred.push_back(cmd),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    let speech = rec.voice_activity().speech;
+    let audio = rec.stop();
+    let min_speech = app.state::<AppState>().settings.get().min_speech_ms;
+    match too_short(audio.len(), speech, min_audio_samples(app), min_speech) {
+        Some(event) => Err(event.message()),
+        None => Ok(audio),
     }
-    len
 }
 
-fn set_status(app: &AppHandle, status: AppStatus) {
-    *app.state::<AppState>().status.lock() = status;
-    let _ = app.emit("status-changed", status);
+/// An `on_error` for `AudioRecorder::start` that tells the worker.
+fn audio_error_sender(app: &AppHandle) -> impl FnOnce(String) + Clone + Send + 'static {
+    let failed_tx = app.state::<AppState>().cmd_tx.lock().clone();
+    move |message| {
+        let _ = failed_tx.send(WorkerCmd::AudioFailed(message));
+    }
+}
+
+/// Carry on recording with `rec` after its stream failed, e.g. because the
+/// default input switched to a headset (see `AudioRecorder::reopen`), and
+/// tell the UI with `input-switched`. Returns whether it could.
+fn reopen_stream(app: &AppHandle, rec: &mut audio::AudioRecorder) -> bool {
+    match rec.reopen(audio_error_sender(app)) {
+        Ok(info) => {
+            let _ = app.emit("input-switched", info);
+            true
+        }
+        Err(e) => {
+            log::warn!("Recording can't go on: {e:#}");
+            false
+        }
+    }
 }
 
-fn get_language(app: &AppHandle) -> String {
-    app.state::<AppState>().settings.lock().language.clone()
+/// A recorder for `device`: the stopped one kept from the last recording
+/// if it's for the same device and that device is still current, or else a
+/// new one. The second value is whether it's the kept one.
+fn take_recorder(
+    kept: &mut Option<audio::AudioRecorder>,
+    device: &str,
+) -> anyhow::Result<(audio::AudioRecorder, bool)> {
+    match kept.take() {
+        Some(rec) if rec.device_name() == device && rec.is_current() => Ok((rec, true)),
+        _ => Ok((audio::AudioRecorder::new(device)?, false)),
+    }
 }
 
-fn get_mode(app: &AppHandle) -> TranscribeMode {
-    app.state::<AppState>().settings.lock().mode
+/// `load()` with `loading` set and `announce`d as `true` meanwhile, then
+/// `false` again, whether it succeeded or not.
+fn while_loading<T>(
+    loading: &AtomicBool,
+    mut announce: impl FnMut(bool),
+    load: impl FnOnce() -> T,
+) -> T {
+    loading.store(true, Ordering::Relaxed);
+    announce(true);
+    let result = load();
+    loading.store(false, Ordering::Relaxed);
+    announce(false);
+    result
+}
+
+/// `ModelCache::activate`, announcing a load from disk (not a cache hit)
+/// with `model-loading` events and `AppState::model_loading`, since it can
+/// take seconds. Commands sent meanwhile wait in the queue.
+fn load_model(
+    app: &AppHandle,
+    models: &mut transcribe::ModelCache,
+    path: &Path,
+) -> anyhow::Result<()> {
+    if models.paths().any(|loaded| loaded == path) {
+        return models.activate(path);
+    }
+    let st = app.state::<AppState>();
+    let announce = |loading: bool| {
+        let _ = app.emit("model-loading", loading);
+    };
+    let result = while_loading(&st.model_loading, announce, || models.activate(path));
+    let requested = st.settings.get().gpu_device;
+    let loaded = models.active().map(|t| t.info());
+    if let (Some(index), Some(info)) = (requested, loaded) {
+        if result.is_ok() && info.gpu_requested && info.gpu_device.is_none() {
+            let _ = app.emit("gpu-device-unavailable", index);
+        }
+    }
+    result
+}
+
+/// The API backend for `Settings::backend`, or `None` for local whisper.
+fn api_backend(settings: &Settings) -> Option<Box<dyn transcribe::Transcribe>> {
+    match settings.backend {
+        Backend::Local => None,
+        Backend::Api => Some(Box::new(whisper_api::ApiTranscriber::new(
+            &settings.api_url,
+            &settings.api_key,
+            &settings.api_model,
+        ))),
+    }
+}
+
+/// The backend to transcribe with as things stand: the API if selected,
+/// else the active model.
+fn active_backend<'a>(
+    models: &'a transcribe::ModelCache,
+    api: Option<&'a dyn transcribe::Transcribe>,
+) -> Option<&'a dyn transcribe::Transcribe> {
+    api.or_else(|| models.active().map(|t| t as &dyn transcribe::Transcribe))
+}
+
+/// Tell the UI how loading the model at `path` went, with `model-loaded`
+/// or `model-load-failed`.
+fn announce_load(app: &AppHandle, path: &Path, cached: bool, result: &anyhow::Result<()>) {
+    let path = &path.display().to_string();
+    let _ = match result {
+        Ok(()) => app.emit("model-loaded", ModelLoadedEvent { path, cached }),
+        Err(e) => app.emit(
+            "model-load-failed",
+            ModelLoadFailedEvent {
+                path,
+                error: format!("{e:#}"),
+            },
+        ),
+    };
+}
+
+/// The backend to transcribe with: `api` if set, otherwise the active
+/// model, loading the configured one first if none is loaded yet (e.g. it
+/// was still downloading at startup). Reports an error event when there's
+/// still no model.
+fn ensure_model<'a>(
+    app: &AppHandle,
+    models: &'a mut transcribe::ModelCache,
+    api: Option<&'a dyn transcribe::Transcribe>,
+) -> Option<&'a dyn transcribe::Transcribe> {
+    if api.is_some() {
+        return api;
+    }
+    if models.active().is_none() {
+        let path = PathBuf::from(app.state::<AppState>().settings.get().active_model_path());
+        if !path.exists() {
+            log::warn!("Model not found: {}", path.display());
+            emit_error(app, AppError::ModelNotFound(path.display().to_string()));
+            return None;
+        }
+        match load_model(app, models, &path) {
+            Ok(()) => {
+                log::info!("Whisper model loaded on demand");
+                warm_up(app, models);
+            }
+            Err(e) => {
+                log::error!("Failed to load model: {e}");
+                emit_error(app, AppError::ModelLoad(e.to_string()));
+                return None;
+            }
+        }
+    }
+    active_backend(models, None)
+}
+
+/// Prime the active model with a silent run if `Settings::warm_on_load` is
+/// on. Runs on the worker, so commands queue up meanwhile; the output is
+/// thrown away.
+fn warm_up(app: &AppHandle, models: &transcribe::ModelCache) {
+    if !app.state::<AppState>().settings.get().warm_on_load {
+        return;
+    }
+    let Some(transcriber) = models.active() else {
+        return;
+    };
+    match transcriber.warm_up(&transcribe_config(app, false)) {
+        Ok(Some(elapsed)) => log::info!("Model warmed up in {elapsed:.2?}"),
+        Ok(None) => {}
+        Err(e) => log::warn!("Model warm-up failed: {e}"),
+    }
+}
+
+/// Warn (once per model and language) when the settings ask an English-only
+/// model for another language, which it would silently transcribe as
+/// English.
+fn check_model_language(
+    app: &AppHandle,
+    transcriber: Option<&transcribe::Transcriber>,
+    warned: &mut Option<(PathBuf, String)>,
+) {
+    let Some(info) = transcriber.map(transcribe::Transcriber::info) else {
+        return;
+    };
+    let language = app.state::<AppState>().settings.get().language.clone();
+    if !info.lacks_language(&language) {
+        return;
+    }
+    let key = (info.path.clone(), language);
+    if warned.as_ref() == Some(&key) {
+        return;
+    }
+    log::warn!(
+        "Model {} is English-only; language {:?} isn't available",
+        info.path.display(),
+        key.1
+    );
+    let _ = app.emit(
+        "model-language-unsupported",
+        ModelLanguageEvent {
+            language: &key.1,
+            model_type: &info.model_type,
+        },
+    );
+    *warned = Some(key);
 }
 
 fn run_worker(rx: mpsc::Receiver<WorkerCmd>, app: AppHandle) {
-    let model_path = {
+    let (model_path, word_timestamps, gpu_device, mut api) = {
         let st = app.state::<AppState>();
-        let path = st.settings.lock().model_path.clone();
-        PathBuf::from(path)
+        let settings = st.settings.get();
+        let path = PathBuf::from(settings.active_model_path());
+        let api = api_backend(&settings);
+        (path, settings.word_timestamps, settings.gpu_device, api)
     };
 
-    let mut transcriber = if model_path.exists() {
-        match transcribe::Transcriber::new(&model_path) {
-            Ok(t) => {
+    let mut models = transcribe::ModelCache::new(MODEL_CACHE_SIZE);
+    models.set_word_timestamps(word_timestamps);
+    models.set_gpu_device(gpu_device);
+    if api.is_some() {
+        log::info!("Transcribing through the API; no model loaded");
+    } else if model_path.exists() {
+        let result = load_model(&app, &mut models, &model_path);
+        match &result {
+            Ok(()) => {
                 log::info!("Whisper model loaded");
-                Some(t)
+                warm_up(&app, &models);
             }
             Err(e) => {
                 log::error!("Failed to load model: {e}");
-                None
+                emit_error(&app, AppError::ModelLoad(e.to_string()));
             }
         }
+        announce_load(&app, &model_path, false, &result);
     } else {
         log::warn!("Model not found: {}", model_path.display());
-        None
-    };
+        let result = Err(anyhow::anyhow!("Model not found"));
+        announce_load(&app, &model_path, false, &result);
+    }
 
     let mut recorder: Option<audio::AudioRecorder> = None;
+    // The last recording's recorder, stopped, kept so the next recording
+    // doesn't have to look up and probe the device again. Dropped when its
+    // stream failed.
+    let mut kept_recorder: Option<audio::AudioRecorder> = None;
+    // Last finished recording, reused by `benchmark_model`.
+    let mut last_audio: Vec<f32> = Vec::new();
     let mut prev_text = String::new();
-    let mut typed_len: usize = 0;
+    // Exactly what streaming has committed (and, in Type mode, typed) this
+    // session, for final-pass reconciliation.
+    let mut typed = String::new();
+    // Start of the streaming window in the recording (16kHz samples) and
+    // the length of the committed text that precedes it in `typed`.
+    let mut window_start = 0;
+    let mut base_len = 0;
+    // See `Streamed::committed_to`.
+    let mut committed_to = (0, 0);
+    // Reused by every streaming tick, so long sessions don't allocate a
+    // window-sized buffer every STREAM_INTERVAL.
+    let mut tick_audio: Vec<f32> = Vec::new();
+    let mut last_transition = Instant::now() - TOGGLE_COOLDOWN;
+    let mut next_tick = Instant::now();
+    let mut last_partial = Instant::now() - PARTIAL_MIN_INTERVAL;
+    // What the last `caption-update` held, so an unchanged one isn't sent.
+    let mut last_caption = (String::new(), String::new());
+    // When streaming last committed text (see `commit_limit`).
+    let mut last_commit = Instant::now();
+    // Pauses found in this recording (see `Settings::pause_reset_ms`).
+    let mut pauses = audio::PauseDetector::default();
+    // A tick of this recording was slower than the tick interval.
+    let mut fell_behind = false;
+    // This recording's first streaming tick hasn't run yet.
+    let mut before_first_tick = false;
+    // A tick of this recording has been transcribed, so the next can use
+    // its text as context (see `Settings::use_context`).
+    let mut has_context = false;
+    // Runs while idle; a starting recording takes it over.
+    let mut preroll: Option<audio::PreRoll> = None;
+    let mut preroll_failed = None;
+    // Model and language last warned about in `check_model_language`.
+    let mut language_warned = None;
+    // Commands that came in during `record_once`, handled before new ones.
+    let mut deferred = VecDeque::new();
 
     loop {
+        if recorder.is_none() {
+            update_preroll(&app, &mut preroll, &mut preroll_failed);
+        }
+        {
+            let st = app.state::<AppState>();
+            let mut loaded = st.loaded_models.lock();
+            if !loaded.iter().map(PathBuf::as_path).eq(models.paths()) {
+                *loaded = models.paths().map(Path::to_path_buf).collect();
+                *st.model_info.lock() = models.active().map(|t| t.info().clone());
+            }
+        }
+        check_model_language(&app, models.active(), &mut language_warned);
         let is_recording = recorder.is_some();
         let is_streaming = is_recording && get_mode(&app) == TranscribeMode::Streaming;
+        let auto_stop = is_recording.then(|| auto_stop_silence(&app)).flatten();
+        let unload_at = if is_recording || models.active().is_none() {
+            None
+        } else {
+            unload_after_idle(&app).map(|idle| last_transition + idle)
+        };
 
-        // Idle/Batch: block on recv(). Streaming: timeout for transcription ticks.
-        let cmd_result = if is_streaming {
-            rx.recv_timeout(STREAM_INTERVAL)
+        // Idle/Batch: block on recv(). Streaming: timeout for transcription
+        // ticks. Auto-stop: also wake up regularly to check for silence.
+        // Idle with an unload timeout: wake up when it's due.
+        let cmd_result = if let Some(cmd) = deferred.pop_front() {
+            Ok(cmd)
+        } else if is_streaming || auto_stop.is_some() {
+            let until_tick = if is_streaming {
+                next_tick.saturating_duration_since(Instant::now())
+            } else {
+                Duration::MAX
+            };
+            let polls = auto_stop.is_some() || (is_streaming && before_first_tick);
+            let wait = if polls {
+                until_tick.min(SILENCE_POLL)
+            } else {
+                until_tick
+            };
+            rx.recv_timeout(wait)
+        } else if let Some(at) = unload_at {
+            rx.recv_timeout(at.saturating_duration_since(Instant::now()))
         } else {
             rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
         };
 
+        // Endpointing: enough trailing silence after some speech finishes the
+        // session exactly like a toggle would.
+        let cmd_result = match (cmd_result, recorder.as_ref(), auto_stop) {
+            (Err(RecvTimeoutError::Timeout), Some(rec), Some(silence)) => {
+                let activity = rec.voice_activity();
+                if activity.speech >= AUTO_STOP_MIN_SPEECH && activity.trailing_silence >= silence {
+                    log::info!(
+                        "Auto-stopping after {:?} of silence",
+                        activity.trailing_silence
+                    );
+                    Ok(WorkerCmd::Toggle)
+                } else {
+                    Err(RecvTimeoutError::Timeout)
+                }
+            }
+            (other, _, _) => other,
+        };
+
+        // A start queued while transcribing skips the cooldown, unless it
+        // went stale.
+        let mut queued = false;
+        let cmd_result = match cmd_result {
+            Ok(WorkerCmd::Queued { cmd, at }) => {
+                let st = app.state::<AppState>();
+                st.toggle_queued.store(false, Ordering::Relaxed);
+                let timeout = st.settings.get().queued_toggle_timeout_ms;
+                if at.elapsed() > Duration::from_millis(timeout.into()) {
+                    log::info!("Dropping a toggle queued {:?} ago", at.elapsed());
+                    continue;
+                }
+                queued = true;
+                Ok(*cmd)
+            }
+            other => other,
+        };
+
         match cmd_result {
-            Ok(WorkerCmd::Toggle) => {
+            Ok(
+                cmd @ (WorkerCmd::Toggle
+                | WorkerCmd::ToggleTranslate
+                | WorkerCmd::Start
+                | WorkerCmd::Stop),
+            ) => {
                 let status = *app.state::<AppState>().status.lock();
+                let translate = matches!(cmd, WorkerCmd::ToggleTranslate);
+
+                let applies = match cmd {
+                    WorkerCmd::Start => status == AppStatus::Idle,
+                    WorkerCmd::Stop => status == AppStatus::Recording,
+                    _ => true,
+                };
+                if !applies {
+                    continue;
+                }
+                if status == AppStatus::Idle && !*app.state::<AppState>().enabled.lock() {
+                    log::info!("Ignoring toggle while paused");
+                    continue;
+                }
+
+                if !queued && last_transition.elapsed() < TOGGLE_COOLDOWN {
+                    log::info!("Ignoring toggle within cooldown ({status:?})");
+                    continue;
+                }
 
                 match status {
                     AppStatus::Idle => {
                         // Start recording + streaming
-                        match audio::AudioRecorder::new() {
-                            Ok(mut rec) => {
-                                if let Err(e) = rec.start() {
+                        let toggled = Instant::now();
+                        let device = app.state::<AppState>().settings.get().input_device.clone();
+                        match take_recorder(&mut kept_recorder, &device) {
+                            Ok((mut rec, kept)) => {
+                                let options = capture_options(&app);
+                                let on_error = audio_error_sender(&app);
+                                let mut started = rec.start(options, on_error.clone());
+                                if started.is_err() && kept {
+                                    // Gone or reconfigured since the last recording.
+                                    log::warn!("Kept recorder failed; reopening the device");
+                                    started =
+                                        audio::AudioRecorder::new(&device).and_then(|fresh| {
+                                            rec = fresh;
+                                            rec.start(options, on_error.clone())
+                                        });
+                                }
+                                if started.is_err() && preroll.is_some() {
+                                    // Some devices (raw ALSA) can't be opened twice.
+                                    log::warn!("Retrying without the pre-roll capture");
+                                    preroll = None;
+                                    started = rec.start(options, on_error);
+                                }
+                                if let Err(e) = started {
                                     log::error!("Recording start failed: {e}");
-                                    let _ = app.emit("error", e.to_string());
+                                    emit_error(&app, AppError::AudioStream(e.to_string()));
                                     continue;
                                 }
+                                log::info!(
+                                    "Capture started {:?} after the toggle ({} recorder)",
+                                    toggled.elapsed(),
+                                    if kept { "kept" } else { "new" }
+                                );
+                                if let Some(preroll) = preroll.take() {
+                                    rec.prepend(preroll);
+                                }
+                                capture_target_window(&app);
+                                begin_continuation(&app);
+                                app.state::<AppState>().last_typed.lock().clear();
+                                capture_clipboard_prompt(&app);
+                                *app.state::<AppState>().translate.lock() = translate;
+                                app.state::<AppState>().spoken_language.lock().take();
+                                app.state::<AppState>()
+                                    .prefix_checked
+                                    .store(false, Ordering::Relaxed);
+                                *app.state::<AppState>().recording_buffer.lock() =
+                                    Some(rec.gauge());
                                 recorder = Some(rec);
                                 prev_text.clear();
-                                typed_len = 0;
+                                typed.clear();
+                                window_start = 0;
+                                base_len = 0;
+                                committed_to = (0, 0);
+                                last_caption = Default::default();
+                                pauses = Default::default();
                                 set_status(&app, AppStatus::Recording);
+                                last_transition = Instant::now();
+                                last_commit = last_transition;
+                                next_tick = last_transition + stream_interval(&app);
+                                fell_behind = false;
+                                before_first_tick = true;
+                                has_context = false;
                                 log::info!("Streaming started");
+
+                                // Unloaded while idle (or never loaded): load it now,
+                                // while already capturing, so no speech is lost. A stop
+                                // toggle meanwhile waits in the queue.
+                                if active_backend(&models, api.as_deref()).is_none() {
+                                    ensure_model(&app, &mut models, api.as_deref());
+                                }
                             }
                             Err(e) => {
                                 log::error!("Recorder init failed: {e}");
-                                let _ = app.emit("error", e.to_string());
+                                emit_error(&app, AppError::AudioDevice(e.to_string()));
                             }
                         }
                     }
@@ -188,70 +4112,358 @@ fn run_worker(rx: mpsc::Receiver<WorkerCmd>, app: AppHandle) {
                         // Stop — final transcription pass
                         set_status(&app, AppStatus::Transcribing);
 
-                        if let Some(ref mut rec) = recorder {
-                            let audio = rec.snapshot();
-                            rec.stop();
-
-                            if audio.len() >= MIN_AUDIO_SAMPLES {
-                                let language = get_language(&app);
-                                if let Some(ref t) = transcriber {
-                                    match t.transcribe(&audio, &language) {
-                                        Ok(text) => {
-                                            log::info!("Final transcription: {text}");
-                                            if text.len() > typed_len {
-                                                let remaining = &text[typed_len..];
-                                                if !remaining.is_empty() {
-                                                    let _ = typing::type_text(remaining);
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            log::error!("Final transcription failed: {e}");
-                                            let _ = app.emit("error", e.to_string());
-                                        }
-                                    }
-                                }
-                            }
+                        if let Some(mut rec) = recorder.take() {
+                            let transcriber = ensure_model(&app, &mut models, api.as_deref());
+                            let streamed = Streamed {
+                                typed: &typed,
+                                hypothesis: &prev_text,
+                                committed_to,
+                            };
+                            last_audio = finish_recording(&app, transcriber, &mut rec, &streamed);
+                            kept_recorder = Some(rec);
                         }
 
-                        recorder = None;
                         prev_text.clear();
-                        typed_len = 0;
+                        typed.clear();
+                        window_start = 0;
+                        base_len = 0;
+                        committed_to = (0, 0);
                         set_status(&app, AppStatus::Idle);
+                        last_transition = Instant::now();
                         log::info!("Streaming stopped");
                     }
-                    AppStatus::Transcribing => {}
+                    AppStatus::Transcribing => {
+                        log::info!("Ignoring toggle while transcribing");
+                    }
+                }
+            }
+
+            Ok(WorkerCmd::AudioFailed(message)) => {
+                // A late report from a session that already ended
+                let Some(mut rec) = recorder.take() else {
+                    continue;
+                };
+                log::error!("Audio stream failed while recording: {message}");
+                if reopen_stream(&app, &mut rec) {
+                    recorder = Some(rec);
+                    continue;
+                }
+                let _ = app.emit("audio-error", &message);
+                emit_error(&app, AppError::AudioStream(message));
+
+                // Keep whatever was captured before the stream died
+                set_status(&app, AppStatus::Transcribing);
+                let transcriber = ensure_model(&app, &mut models, api.as_deref());
+                let streamed = Streamed {
+                    typed: &typed,
+                    hypothesis: &prev_text,
+                    committed_to,
+                };
+                last_audio = finish_recording(&app, transcriber, &mut rec, &streamed);
+                prev_text.clear();
+                typed.clear();
+                window_start = 0;
+                base_len = 0;
+                committed_to = (0, 0);
+                set_status(&app, AppStatus::Idle);
+                last_transition = Instant::now();
+            }
+
+            Ok(WorkerCmd::Recover { reply }) => {
+                if recorder.is_some() {
+                    let _ = reply.send(Err("Stop recording before recovering".to_string()));
+                    continue;
+                }
+                set_status(&app, AppStatus::Transcribing);
+                let result = recover(&app, ensure_model(&app, &mut models, api.as_deref()));
+                set_status(&app, AppStatus::Idle);
+                last_transition = Instant::now();
+                let _ = reply.send(result);
+            }
+
+            Ok(WorkerCmd::TranscribeFile {
+                path,
+                deliver,
+                reply,
+            }) => {
+                if recorder.is_some() {
+                    let _ = reply.send(Err("Stop recording first".to_string()));
+                    continue;
+                }
+                set_status(&app, AppStatus::Transcribing);
+                let transcriber = ensure_model(&app, &mut models, api.as_deref());
+                let result = run_file_transcription(&app, transcriber, &path, deliver);
+                set_status(&app, AppStatus::Idle);
+                last_transition = Instant::now();
+                let _ = reply.send(result);
+            }
+
+            Ok(WorkerCmd::RecordOnce { max, reply }) => {
+                if recorder.is_some() {
+                    let _ = reply.send(Err("Already recording".to_string()));
+                    continue;
+                }
+                if !*app.state::<AppState>().enabled.lock() {
+                    let _ = reply.send(Err("Paused".to_string()));
+                    continue;
+                }
+                // Nothing from before the command is wanted, and some
+                // devices can't be opened twice; it restarts afterwards.
+                preroll = None;
+                let device = app.state::<AppState>().settings.get().input_device.clone();
+                let captured = match take_recorder(&mut kept_recorder, &device) {
+                    Ok((mut rec, _)) => {
+                        let captured = record_once(&app, &mut rec, max, &rx, &mut deferred);
+                        kept_recorder = Some(rec);
+                        captured
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+                let result = captured.and_then(|audio| {
+                    set_status(&app, AppStatus::Transcribing);
+                    let transcriber = ensure_model(&app, &mut models, api.as_deref());
+                    transcribe_offline(&app, transcriber, audio)
+                });
+                set_status(&app, AppStatus::Idle);
+                last_transition = Instant::now();
+                let _ = reply.send(result);
+            }
+
+            Ok(WorkerCmd::PlayLast { reply }) => {
+                let result = if last_audio.is_empty() {
+                    Err("No recording to play yet".to_string())
+                } else {
+                    // Plays on its own thread and stream; the worker and a
+                    // recording carry on.
+                    feedback::play_recording(last_audio.clone())
+                        .map(|length| length.as_secs_f32())
+                        .map_err(|e| e.to_string())
+                };
+                let _ = reply.send(result);
+            }
+
+            Ok(WorkerCmd::Preload) => {
+                let st = app.state::<AppState>();
+                let reload = st.preload.take();
+                if api.is_some() {
+                    continue;
+                }
+                let path = PathBuf::from(st.settings.get().active_model_path());
+                if !path.exists() {
+                    log::warn!("Model not found: {}", path.display());
+                    announce_load(&app, &path, false, &Err(anyhow::anyhow!("Model not found")));
+                    continue;
+                }
+                if reload {
+                    models.unload(&path);
+                }
+                let cached = models.paths().any(|loaded| loaded == path);
+                let result = load_model(&app, &mut models, &path);
+                match &result {
+                    Ok(()) if !cached => {
+                        log::info!("Whisper model preloaded");
+                        warm_up(&app, &models);
+                    }
+                    Ok(()) => {}
+                    Err(e) => log::error!("Failed to preload model: {e}"),
+                }
+                announce_load(&app, &path, cached, &result);
+            }
+
+            Ok(WorkerCmd::Benchmark { runs, reply }) => {
+                if recorder.is_some() {
+                    let _ = reply.send(Err("Stop recording before benchmarking".to_string()));
+                    continue;
+                }
+                set_status(&app, AppStatus::Transcribing);
+                let transcriber = ensure_model(&app, &mut models, api.as_deref());
+                let result = run_benchmark(&app, transcriber, &last_audio, runs);
+                set_status(&app, AppStatus::Idle);
+                last_transition = Instant::now();
+                let _ = reply.send(result);
+            }
+
+            Ok(WorkerCmd::Cancel) => {
+                if let Some(mut rec) = recorder.take() {
+                    rec.stop();
+                    kept_recorder = Some(rec);
+                    app.state::<AppState>().target_window.lock().take();
+                    app.state::<AppState>().clipboard_prompt.lock().take();
+                    *app.state::<AppState>().translate.lock() = false;
+                    prev_text.clear();
+                    typed.clear();
+                    window_start = 0;
+                    base_len = 0;
+                    committed_to = (0, 0);
+                    set_status(&app, AppStatus::Idle);
+                    last_transition = Instant::now();
+                    log::info!("Recording cancelled");
                 }
             }
 
             Err(RecvTimeoutError::Timeout) => {
+                if unload_at.is_some_and(|at| Instant::now() >= at) {
+                    log::info!("Unloading the model after being idle");
+                    models.clear();
+                    continue;
+                }
+                if is_streaming && before_first_tick {
+                    let heard = recorder
+                        .as_ref()
+                        .is_some_and(|rec| speech_onset(&app, rec, last_transition));
+                    if heard && Instant::now() < next_tick {
+                        log::info!("Speech heard; first streaming tick early");
+                        next_tick = Instant::now();
+                    }
+                }
+                // Only a silence poll, or not streaming at all
+                if !is_streaming || Instant::now() < next_tick {
+                    continue;
+                }
+                before_first_tick = false;
+                let tick_started = Instant::now();
+                let interval = stream_interval(&app);
+                next_tick = tick_started + interval;
+
                 // Streaming transcription tick
-                let audio = match recorder.as_ref() {
-                    Some(rec) => rec.snapshot(),
-                    None => continue,
+                let Some(rec) = recorder.as_ref() else {
+                    continue;
                 };
+                rec.snapshot_since_into(window_start, &mut tick_audio);
+                let audio = &mut tick_audio;
 
-                if audio.len() < MIN_AUDIO_SAMPLES {
+                if audio.len() < min_audio_samples(&app) {
                     continue;
                 }
+                if resumed_after_pause(&app, &mut pauses, audio, window_start) {
+                    // `typed` stays; only the comparison starts afresh.
+                    log::info!("Speech resumed after a pause; streaming baseline reset");
+                    prev_text.clear();
+                }
+                prepare_audio(&app, audio);
+                if needs_prefix_check(&app) {
+                    // Nothing is typed before the language is settled.
+                    match active_backend(&models, api.as_deref()) {
+                        Some(t) if audio.len() >= LANGUAGE_PREFIX_SAMPLES => {
+                            detect_language_prefix(&app, t, audio)
+                        }
+                        _ => continue,
+                    }
+                }
 
-                let language = get_language(&app);
+                let mut config = transcribe_config(&app, false);
+                config.keep_context =
+                    tick_keeps_context(&app.state::<AppState>().settings.get(), has_context);
+                let base = typed[..base_len].to_string();
+                if !base.is_empty() {
+                    // Let whisper continue the sentence rather than start afresh.
+                    let context = streaming::last_words(&base, WINDOW_CONTEXT_WORDS);
+                    config.initial_prompt = join_window(&config.initial_prompt, context);
+                }
 
-                if let Some(ref t) = transcriber {
-                    match t.transcribe(&audio, &language) {
-                        Ok(curr_text) => {
+                if let Some(t) = active_backend(&models, api.as_deref()) {
+                    let result = t.transcribe_segments(audio, &config, None);
+                    next_tick = pace_ticks(&app, tick_started, interval, &mut fell_behind);
+                    has_context = result.is_ok();
+                    match result {
+                        Ok(segments) => {
+                            let window_text = transcribe::join_segments(&segments);
+                            // A moved window starts with audio of typed words.
+                            let repeated = if window_start > 0 && stream_overlap(&app) > 0 {
+                                streaming::repeated_words(&base, &window_text, STITCH_MAX_WORDS)
+                            } else {
+                                0
+                            };
+                            let skip = streaming::words_prefix_len(&window_text, repeated);
+                            let window_text = window_text[skip..].trim_start();
+                            let curr_text = join_window(&base, window_text);
+                            let curr_text = strip_language_prefix(&app, curr_text);
+                            let curr_text = postprocess_text(&app, curr_text, &config.language);
                             // Only type text confirmed by two consecutive transcriptions
-                            let stable = stable_prefix_len(&prev_text, &curr_text);
-
-                            if stable > typed_len {
-                                let new_text = &curr_text[typed_len..stable];
-                                if !new_text.is_empty() {
-                                    log::info!("Streaming chunk: {new_text:?}");
-                                    let _ = typing::type_text(new_text);
-                                    typed_len = stable;
+                            let confident = confident_prefix_len(
+                                &app,
+                                &base,
+                                window_text,
+                                &segments,
+                                repeated,
+                                &curr_text,
+                            );
+                            let limit =
+                                commit_limit(&app, &prev_text, &curr_text, confident, last_commit);
+                            if let Some(new_text) =
+                                streaming::confirmed_suffix(&typed, &prev_text, &curr_text, limit)
+                            {
+                                log::info!("Streaming chunk: {new_text:?}");
+                                last_commit = Instant::now();
+                                // Clipboard-only keeps the clipboard for the final
+                                // text, as confirmation does the cursor.
+                                if types_as_committed(&app) {
+                                    insert(&app, new_text);
+                                }
+                                send_to_pipe(&app, pipe::Message::Chunk { text: new_text });
+                                typed.push_str(new_text);
+                                let _ = app.emit(
+                                    "transcript",
+                                    TranscriptEvent {
+                                        text: &typed,
+                                        is_final: false,
+                                    },
+                                );
+                            }
+
+                            let boundary =
+                                committed_boundary(&app, &typed, base_len, &segments, repeated);
+                            if last_partial.elapsed() >= PARTIAL_MIN_INTERVAL {
+                                // Whatever the hypothesis has beyond the committed words
+                                let tentative =
+                                    streaming::reconcile_final(&typed, &curr_text, false).insert;
+                                let _ = app.emit(
+                                    "partial-transcript",
+                                    PartialTranscriptEvent {
+                                        committed: &typed,
+                                        tentative: &tentative,
+                                    },
+                                );
+                                last_partial = Instant::now();
+                                if last_caption.0 != typed || last_caption.1 != tentative {
+                                    let stable = boundary.map_or(0, |(last, _)| last + 1);
+                                    let _ = app.emit(
+                                        "caption-update",
+                                        CaptionEvent {
+                                            committed: &typed,
+                                            tentative: &tentative,
+                                            segments: caption_segments(
+                                                &segments,
+                                                window_start,
+                                                stable,
+                                            ),
+                                            is_final: false,
+                                        },
+                                    );
+                                    last_caption = (typed.clone(), tentative);
                                 }
                             }
 
+                            let slide = slide_window(
+                                &app,
+                                &typed,
+                                base_len,
+                                &segments,
+                                repeated,
+                                audio.len(),
+                            );
+                            let final_pass = app.state::<AppState>().settings.get().final_pass;
+                            if let Some((last, len)) =
+                                boundary.filter(|_| final_pass == FinalPass::ExtendOnly)
+                            {
+                                committed_to = (window_start + segments[last].end, len);
+                            }
+                            if let Some((advance, len)) = slide {
+                                window_start += advance;
+                                base_len = len;
+                                log::debug!("Streaming window moved to sample {window_start}");
+                            }
+
                             prev_text = curr_text;
                         }
                         Err(e) => {
@@ -259,21 +4471,94 @@ fn run_worker(rx: mpsc::Receiver<WorkerCmd>, app: AppHandle) {
                         }
                     }
                 } else {
-                    let _ = app.emit("error", "Model not loaded".to_string());
+                    emit_error(&app, AppError::ModelNotLoaded);
                 }
             }
 
             Ok(WorkerCmd::UpdateSettings(settings)) => {
-                let new_path = PathBuf::from(&settings.model_path);
+                api = api_backend(&settings);
+                if api.is_some() {
+                    // Frees the memory; switching back loads it again.
+                    models.clear();
+                    continue;
+                }
+                // Everything else is read per call; only a new model (also
+                // after a language change, see `model_paths`, or one loaded
+                // with different word timestamps or on another GPU) needs
+                // work.
+                let new_path = PathBuf::from(settings.active_model_path());
+                let reload = models.set_word_timestamps(settings.word_timestamps)
+                    | models.set_gpu_device(settings.gpu_device);
+                if !reload && models.active_path() == Some(new_path.as_path()) {
+                    continue;
+                }
                 if new_path.exists() {
-                    match transcribe::Transcriber::new(&new_path) {
-                        Ok(t) => {
-                            transcriber = Some(t);
-                            log::info!("Model reloaded from {}", new_path.display());
+                    match load_model(&app, &mut models, &new_path) {
+                        Ok(()) => {
+                            log::info!("Switched to model {}", new_path.display());
+                            warm_up(&app, &models);
+                            let name = settings
+                                .models
+                                .iter()
+                                .find(|(_, path)| **path == settings.active_model_path())
+                                .map(|(name, _)| name.as_str());
+                            let _ = app.emit(
+                                "model-switched",
+                                ModelSwitchedEvent {
+                                    name,
+                                    path: settings.active_model_path(),
+                                },
+                            );
+                        }
+                        Err(e) => {
+                            log::error!("Model reload failed: {e}");
+                            emit_error(&app, AppError::ModelLoad(e.to_string()));
                         }
-                        Err(e) => log::error!("Model reload failed: {e}"),
+                    }
+                } else {
+                    // Don't keep transcribing with a model the settings no
+                    // longer point at; the next stop retries the load.
+                    log::warn!("Model not found: {}", new_path.display());
+                    models.clear();
+                    let path = settings.active_model_path().to_string();
+                    emit_error(&app, AppError::ModelNotFound(path));
+                }
+            }
+
+            // Handled at the top of the loop.
+            Ok(WorkerCmd::EnabledChanged) => {}
+
+            // Unwrapped above; `send_toggle` never queues one inside another.
+            Ok(WorkerCmd::Queued { .. }) => {}
+
+            Ok(WorkerCmd::Shutdown { reply }) => {
+                if let Some(mut rec) = recorder.take() {
+                    let finish = app.state::<AppState>().settings.get().finish_on_quit;
+                    if finish {
+                        log::info!("Quitting: finishing the recording");
+                        set_status(&app, AppStatus::Transcribing);
+                        let transcriber = ensure_model(&app, &mut models, api.as_deref());
+                        let streamed = Streamed {
+                            typed: &typed,
+                            hypothesis: &prev_text,
+                            committed_to,
+                        };
+                        finish_recording(&app, transcriber, &mut rec, &streamed);
+                    } else {
+                        log::info!("Quitting: discarding the recording");
+                        rec.stop();
                     }
                 }
+                preroll.take(); // releases the microphone
+                models.clear();
+                // A discarded recording never left `Recording`.
+                app.state::<AppState>()
+                    .indicator
+                    .hide_and_wait(INDICATOR_TIMEOUT);
+                // Let the text of the last recording finish typing.
+                let _ = app.state::<AppState>().typing.run(|| ());
+                let _ = reply.send(());
+                break;
             }
 
             Err(RecvTimeoutError::Disconnected) => break,
@@ -281,6 +4566,23 @@ fn run_worker(rx: mpsc::Receiver<WorkerCmd>, app: AppHandle) {
     }
 }
 
+/// Shut the worker down, then exit. Runs on its own thread: the worker
+/// still updates the tray while finishing, which needs the main thread.
+fn quit(app: &AppHandle) {
+    answer_pending(&app.state::<AppState>().pending_confirm, false);
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let (reply, done) = mpsc::channel();
+        let cmd = WorkerCmd::Shutdown { reply };
+        if app.state::<AppState>().cmd_tx.lock().send(cmd).is_ok() {
+            if let Err(RecvTimeoutError::Timeout) = done.recv_timeout(SHUTDOWN_TIMEOUT) {
+                log::warn!("Worker didn't stop within {SHUTDOWN_TIMEOUT:?}; quitting anyway");
+            }
+        }
+        app.exit(0);
+    });
+}
+
 // --- System tray ---
 
 fn toggle_window(app: &AppHandle) {
@@ -315,21 +4617,369 @@ fn toggle_window(app: &AppHandle) {
     }
 }
 
-fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+/// Show or hide the settings window on a status change, per
+/// `Settings::window_behavior`. Runs on whichever thread changed the
+/// status, mostly the worker: Tauri's window methods may be called from any
+/// thread, since they're handed to the main thread's event loop. A window
+/// that doesn't exist yet is only built for `ShowWhileRecording`.
+fn update_main_window(app: &AppHandle, previous: AppStatus, status: AppStatus) {
+    let st = app.state::<AppState>();
+    let window = app.get_webview_window("main");
+    if status == AppStatus::Idle && st.window_auto_shown.swap(false, Ordering::Relaxed) {
+        if let Some(w) = window {
+            let _ = w.hide();
+        }
+        return;
+    }
+    let starts = previous == AppStatus::Idle && status == AppStatus::Recording;
+    match (st.settings.get().window_behavior, window) {
+        (WindowBehavior::ShowWhileRecording, Some(w))
+            if starts && !w.is_visible().unwrap_or(false) =>
+        {
+            let _ = w.show();
+            st.window_auto_shown.store(true, Ordering::Relaxed);
+        }
+        (WindowBehavior::ShowWhileRecording, None) if starts => {
+            toggle_window(app);
+            st.window_auto_shown.store(true, Ordering::Relaxed);
+        }
+        (WindowBehavior::AlwaysHidden, Some(w)) if status == AppStatus::Recording => {
+            let _ = w.hide();
+        }
+        _ => {}
+    }
+}
+
+// --- Overlay ---
+
+const OVERLAY_LABEL: &str = "overlay";
+/// Logical size of the overlay window.
+const OVERLAY_SIZE: (f64, f64) = (420.0, 80.0);
+/// Gap between the overlay and the bottom edge of the screen.
+const OVERLAY_MARGIN: f64 = 60.0;
+
+/// Show the overlay while recording or transcribing (if enabled) and hide
+/// it otherwise. Like the tray, it's updated from whichever thread changed
+/// the status; Tauri hands the window calls to the main thread.
+fn update_overlay(app: &AppHandle, status: AppStatus) {
+    let enabled = app.state::<AppState>().settings.get().overlay;
+    let visible = enabled && status != AppStatus::Idle;
+    let result = match app.get_webview_window(OVERLAY_LABEL) {
+        Some(w) if visible => w.show(),
+        Some(w) => w.hide(),
+        None if visible => create_overlay(app),
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        log::error!("Overlay update failed: {e}");
+    }
+}
+
+/// Borderless, click-through window at the bottom center of the primary
+/// screen. It never takes focus, so typing still goes to the right window.
+fn create_overlay(app: &AppHandle) -> tauri::Result<()> {
+    use tauri::WebviewWindowBuilder;
+
+    let (width, height) = OVERLAY_SIZE;
+    let url = tauri::WebviewUrl::App("overlay.html".into());
+    let window = WebviewWindowBuilder::new(app, OVERLAY_LABEL, url)
+        .title("Voice to Text")
+        .inner_size(width, height)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .resizable(false)
+        .focused(false)
+        .build()?;
+    window.set_ignore_cursor_events(true)?;
+    if let Some(monitor) = window.primary_monitor()? {
+        let scale = monitor.scale_factor();
+        let size = monitor.size().to_logical::<f64>(scale);
+        let origin = monitor.position().to_logical::<f64>(scale);
+        let x = origin.x + (size.width - width) / 2.0;
+        let y = origin.y + size.height - height - OVERLAY_MARGIN;
+        window.set_position(tauri::LogicalPosition::new(x, y))?;
+    }
+    log::info!("Overlay window created");
+    Ok(())
+}
+
+const TRAY_ID: &str = "main";
+
+fn tray_icon_bytes(status: AppStatus, paused: bool) -> &'static [u8] {
+    match status {
+        AppStatus::Idle if paused => include_bytes!("../icons/icon-paused.png"),
+        AppStatus::Idle => include_bytes!("../icons/icon.png"),
+        AppStatus::Recording => include_bytes!("../icons/icon-recording.png"),
+        AppStatus::Transcribing => include_bytes!("../icons/icon-transcribing.png"),
+    }
+}
+
+fn tray_tooltip(status: AppStatus, paused: bool, settings: &Settings) -> String {
+    let state = i18n::status_text(status, paused, settings.ui_language);
+    format!("Voice to Text [{}] — {state}", settings.language)
+}
+
+/// Menu-event id prefixes for the language and model submenus (`lang:en`).
+const LANG_ITEM_PREFIX: &str = "lang:";
+const MODEL_ITEM_PREFIX: &str = "model:";
+
+/// Tray menu with the language submenu built from `Settings::tray_languages`,
+/// the active language checked.
+fn tray_menu(app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let (languages, language, models, model) = {
+        let st = app.state::<AppState>();
+        let s = st.settings.get();
+        let model = s
+            .models
+            .iter()
+            .find(|(_, path)| **path == s.model_path)
+            .map(|(name, _)| name.clone())
+            .unwrap_or_default();
+        let models: Vec<String> = s.models.keys().cloned().collect();
+        (s.tray_languages.clone(), s.language.clone(), models, model)
+    };
+
+    let languages = choice_submenu(app, "Language", LANG_ITEM_PREFIX, &languages, &language)?;
+    let models = (!models.is_empty())
+        .then(|| choice_submenu(app, "Model", MODEL_ITEM_PREFIX, &models, &model))
+        .transpose()?;
+
+    let paused = !*app.state::<AppState>().enabled.lock();
+    let pause = CheckMenuItemBuilder::with_id("pause", "Paused")
+        .checked(paused)
+        .build(app)?;
+    let retype = MenuItemBuilder::with_id("retype", "Retype last").build(app)?;
+    let copy_label = copy_item_label(app.state::<AppState>().last_text.lock().as_deref());
+    let copy = MenuItemBuilder::with_id("copy_last", copy_label).build(app)?;
+    *app.state::<AppState>().copy_item.lock() = Some(copy.clone());
     let show = MenuItemBuilder::with_id("show", "Settings").build(app)?;
     let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
-    let menu = MenuBuilder::new(app).items(&[&show, &quit]).build()?;
+    let mut menu = MenuBuilder::new(app).item(&languages);
+    if let Some(models) = &models {
+        menu = menu.item(models);
+    }
+    menu.items(&[&pause, &retype, &copy, &show, &quit]).build()
+}
+
+/// Longest preview of the last transcription in the tray menu, in characters.
+const COPY_PREVIEW_CHARS: usize = 40;
+
+/// "Copy last" with a one-line preview of `text`, cut with an ellipsis
+/// when it's long.
+fn copy_item_label(text: Option<&str>) -> String {
+    let Some(text) = text else {
+        return "Copy last".to_string();
+    };
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut preview: String = line.chars().take(COPY_PREVIEW_CHARS).collect();
+    if preview.len() < line.len() {
+        preview = format!("{}…", preview.trim_end());
+    }
+    // A single `&` marks a mnemonic in menu labels.
+    format!("Copy \"{}\"", preview.replace('&', "&&"))
+}
+
+/// Add the final text of a recording to the open dictation session.
+fn add_to_session(app: &AppHandle, text: &str) {
+    let st = app.state::<AppState>();
+    let mut session = st.session.lock();
+    if let Some(session) = session.as_mut() {
+        session.append(text);
+        let _ = app.emit("session-changed", Some(&*session));
+    }
+}
+
+/// Remember `text` as the last transcription and show it in the tray.
+/// `set_text` is forwarded to the main thread, so this works from the
+/// worker.
+fn set_last_text(app: &AppHandle, text: &str) {
+    let st = app.state::<AppState>();
+    *st.last_text.lock() = Some(text.to_string());
+    let copy_item = st.copy_item.lock();
+    if let Some(item) = &*copy_item {
+        if let Err(e) = item.set_text(copy_item_label(Some(text))) {
+            log::warn!("Failed to update the tray's copy item: {e}");
+        }
+    }
+}
+
+/// Submenu of check items with ids `<prefix><option>`, `active` checked.
+fn choice_submenu(
+    app: &AppHandle,
+    title: &str,
+    prefix: &str,
+    options: &[String],
+    active: &str,
+) -> tauri::Result<Submenu<tauri::Wry>> {
+    let items = options
+        .iter()
+        .map(|option| {
+            CheckMenuItemBuilder::with_id(format!("{prefix}{option}"), option)
+                .checked(*option == active)
+                .build(app)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let mut submenu = SubmenuBuilder::new(app, title);
+    for item in &items {
+        submenu = submenu.item(item);
+    }
+    submenu.build()
+}
+
+/// The language after `current` in `list`, wrapping around; the first one
+/// if `current` isn't in it.
+fn next_language<'a>(list: &'a [String], current: &str) -> Option<&'a str> {
+    let next = match list.iter().position(|language| language == current) {
+        Some(i) => (i + 1) % list.len(),
+        None => 0,
+    };
+    list.get(next).map(String::as_str)
+}
+
+/// See `cycle_language`. The tray's tooltip shows the new language too.
+fn cycle_languages(app: &AppHandle) -> Result<String, String> {
+    let st = app.state::<AppState>();
+    let (list, current) = {
+        let settings = st.settings.get();
+        let list = if settings.cycle_languages.is_empty() {
+            settings.tray_languages.clone()
+        } else {
+            settings.cycle_languages.clone()
+        };
+        (list, settings.language.clone())
+    };
+    let next = next_language(&list, &current)
+        .ok_or("No languages to cycle through")?
+        .to_string();
+    let settings = st.settings.update(|settings| {
+        settings.language = next.clone();
+    });
+    log::info!("Language cycled to {next}");
+    broadcast_settings(app, settings);
+    let _ = app.emit("language-cycled", &next);
+    Ok(next)
+}
+
+/// Switch the dictation language from the tray.
+fn set_language(app: &AppHandle, language: &str) {
+    let settings = app.state::<AppState>().settings.update(|settings| {
+        settings.language = language.to_string();
+    });
+    log::info!("Language set to {language} from tray");
+    broadcast_settings(app, settings);
+}
+
+/// Switch to the model named `name` in `Settings::models`. The worker loads
+/// it (or takes it from its cache) and emits `model-switched`.
+fn select_model(app: &AppHandle, name: &str) -> Result<(), String> {
+    let st = app.state::<AppState>();
+    let path = st
+        .settings
+        .get()
+        .models
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown model: {name}"))?;
+    let settings = st.settings.update(|settings| settings.model_path = path);
+    log::info!("Model set to {name}");
+    broadcast_settings(app, settings);
+    Ok(())
+}
+
+/// Hand settings changed outside the settings window to the worker, the UI
+/// and the tray.
+fn broadcast_settings(app: &AppHandle, settings: Settings) {
+    let _ = app
+        .state::<AppState>()
+        .cmd_tx
+        .lock()
+        .send(WorkerCmd::UpdateSettings(Arc::new(settings.clone())));
+    let _ = app.emit("settings-changed", settings);
+    refresh_tray_menu(app);
+}
 
-    let icon = Image::from_bytes(include_bytes!("../icons/icon.png"))?;
+/// Rebuild the tray menu and tooltip after the language or model (list)
+/// changed.
+fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match tray_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::error!("Failed to rebuild tray menu: {e}"),
+    }
+    update_tray(app, *app.state::<AppState>().status.lock());
+}
+
+/// Reflect `status` in the tray icon and tooltip. Called from the worker
+/// thread; Tauri forwards tray updates to the main (event loop) thread
+/// internally, so this is safe off the main thread.
+fn update_tray(app: &AppHandle, status: AppStatus) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let paused = !*app.state::<AppState>().enabled.lock();
+    match Image::from_bytes(tray_icon_bytes(status, paused)) {
+        Ok(icon) => {
+            let _ = tray.set_icon(Some(icon));
+        }
+        Err(e) => log::error!("Failed to decode tray icon: {e}"),
+    }
+    let tooltip = tray_tooltip(status, paused, &app.state::<AppState>().settings.get());
+    let _ = tray.set_tooltip(Some(tooltip));
+}
+
+fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let menu = tray_menu(app)?;
+    let paused = !*app.state::<AppState>().enabled.lock();
+    let icon = Image::from_bytes(tray_icon_bytes(AppStatus::Idle, paused))?;
+    let tooltip = {
+        let settings = app.state::<AppState>().settings.get();
+        tray_tooltip(AppStatus::Idle, paused, &settings)
+    };
 
-    TrayIconBuilder::new()
+    TrayIconBuilder::with_id(TRAY_ID)
         .icon(icon)
         .menu(&menu)
-        .tooltip("Voice to Text — Double-press Alt")
+        .tooltip(tooltip)
         .on_menu_event(|app, event| match event.id().as_ref() {
             "show" => toggle_window(app),
-            "quit" => app.exit(0),
-            _ => {}
+            "pause" => {
+                let enabled = *app.state::<AppState>().enabled.lock();
+                if let Err(e) = apply_enabled(app, !enabled) {
+                    log::error!("Failed to save paused state: {e}");
+                }
+            }
+            "retype" => {
+                // Typing blocks; keep it off the event loop.
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = retype(&app) {
+                        log::error!("Retype failed: {e}");
+                    }
+                });
+            }
+            "copy_last" => {
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = copy_last(&app) {
+                        log::error!("Copy failed: {e}");
+                    }
+                });
+            }
+            "quit" => quit(app),
+            id => {
+                if let Some(lang) = id.strip_prefix(LANG_ITEM_PREFIX) {
+                    set_language(app, lang);
+                } else if let Some(name) = id.strip_prefix(MODEL_ITEM_PREFIX) {
+                    if let Err(e) = select_model(app, name) {
+                        log::error!("{e}");
+                    }
+                }
+            }
         })
         .build(app)?;
 
@@ -338,19 +4988,60 @@ fn setup_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
 
 // --- App entry ---
 
+/// The defaults, but for `--pipe=<target>` (see `Settings::pipe_output`),
+/// which a headless setup can't leave to the settings window.
+fn initial_settings() -> Settings {
+    let pipe = std::env::args().find_map(|arg| arg.strip_prefix("--pipe=").map(String::from));
+    Settings {
+        pipe_output: pipe.unwrap_or_default(),
+        ..Settings::default()
+    }
+}
+
 pub fn run() {
     // Work around GBM/DRM permission errors with NVIDIA + WebKitGTK
     std::env::set_var("WEBKIT_DISABLE_DMABUF_RENDERER", "1");
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let logging = logging::Logging::init(&Settings::default().log_level);
 
     let (cmd_tx, cmd_rx) = mpsc::channel();
 
     let app_state = AppState {
         status: Mutex::new(AppStatus::Idle),
-        settings: Mutex::new(Settings::default()),
+        settings: SharedSettings::new(initial_settings()),
         cmd_tx: Mutex::new(cmd_tx.clone()),
+        history: history::History::new(),
+        http: Mutex::new(None),
+        pipe: Mutex::new(None),
+        hotkey: Arc::new(Mutex::new(hotkey_config(&Settings::default()))),
+        enabled: Mutex::new(load_enabled()),
+        last_cues: Mutex::new(Vec::new()),
+        last_text: Mutex::new(None),
+        session: Mutex::new(session::Session::restore()),
+        pending_confirm: Mutex::new(None),
+        window_auto_shown: AtomicBool::new(false),
+        copy_item: Mutex::new(None),
+        last_typed: Mutex::new(String::new()),
+        typing: typing::TypingQueue::new(),
+        indicator: indicator::Indicator::new(),
+        target_window: Mutex::new(None),
+        continuation: Mutex::new(Continuation::default()),
+        clipboard_prompt: Mutex::new(None),
+        translate: Mutex::new(false),
+        spoken_language: Mutex::new(None),
+        prefix_checked: AtomicBool::new(false),
+        abort: Arc::new(AtomicBool::new(false)),
+        toggle_queued: AtomicBool::new(false),
+        loaded_models: Mutex::new(Vec::new()),
+        model_info: Mutex::new(None),
+        model_loading: AtomicBool::new(false),
+        preload: PreloadQueue::default(),
+        hotkey_error: Mutex::new(None),
+        recording_buffer: Mutex::new(None),
+        last_error: Mutex::new(None),
+        logging,
     };
+    let hotkey_shared = app_state.hotkey.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_autostart::init(
@@ -361,22 +5052,117 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             get_status,
             get_settings,
+            sync_state,
+            get_diagnostics,
+            health_check,
             update_settings,
+            reset_settings,
+            export_settings,
+            import_settings,
+            list_languages,
             check_model,
+            validate_model,
+            model_info,
             toggle_recording,
+            cancel_recording,
+            get_history,
+            clear_history,
+            export_transcription,
+            test_typing,
+            retype_last,
+            copy_last_to_clipboard,
+            undo_last_type,
+            check_dependencies,
+            get_audio_info,
+            list_input_devices,
+            get_recent_logs,
+            open_log_dir,
+            get_enabled,
+            set_enabled,
+            is_autostart_enabled,
+            enable_autostart,
+            disable_autostart,
+            get_show_on_launch,
+            set_show_on_launch,
+            benchmark_model,
+            preload_model,
+            play_last_recording,
+            transcribe_file,
+            record_and_transcribe,
+            set_active_model,
+            list_available_models,
+            set_model_path,
+            begin_session,
+            end_session,
+            get_session,
+            set_backend,
+            cycle_language,
+            confirm_type,
+            reject_type,
+            get_orphaned_recording,
+            recover_recording,
+            discard_recording,
         ])
         .setup(move |app| {
             setup_tray(app.handle())?;
+            apply_http_settings(app.handle());
+            apply_pipe_settings(app.handle());
+
+            // Before the worker starts, so no new recording can reuse the spool.
+            if let Some(secs) = recovery::adopt_orphan() {
+                log::warn!("{secs:.1}s of audio from a crashed session can be recovered");
+                let _ = app.emit("orphaned-recording", secs);
+            }
+            if let Some(session) = &*app.state::<AppState>().session.lock() {
+                log::info!(
+                    "Carrying on the dictation session of {} recordings",
+                    session.recordings
+                );
+            }
+
+            let missing = typing::missing_dependencies();
+            if !missing.is_empty() {
+                log::warn!("Missing tools for typing: {}", missing.join(", "));
+                let _ = app.emit("missing-dependency", &missing);
+            }
+
+            let minimized = std::env::args().any(|arg| arg == "--minimized");
+            if get_show_on_launch() && !minimized {
+                toggle_window(app.handle());
+            }
+
+            let signal_app = app.handle().clone();
+            if let Err(e) = ctrlc::set_handler(move || quit(&signal_app)) {
+                log::warn!("No SIGINT/SIGTERM handler: {e}");
+            }
 
             let worker_handle = app.handle().clone();
             std::thread::spawn(move || run_worker(cmd_rx, worker_handle));
 
-            let hotkey_tx = cmd_tx.clone();
+            let hotkey_app = app.handle().clone();
             let (htx, hrx) = mpsc::channel();
-            hotkey::start_listener(htx);
+            hotkey::start_listener(htx, hotkey_shared);
             std::thread::spawn(move || {
-                while let Ok(hotkey::HotkeyEvent::DoubleAlt) = hrx.recv() {
-                    let _ = hotkey_tx.send(WorkerCmd::Toggle);
+                while let Ok(event) = hrx.recv() {
+                    match event {
+                        hotkey::HotkeyEvent::Action(action) => on_hotkey(&hotkey_app, action),
+                        hotkey::HotkeyEvent::Debug(step) => {
+                            let _ = hotkey_app.emit("hotkey-debug", step);
+                        }
+                        hotkey::HotkeyEvent::PermissionRequired => {
+                            let error = "Accessibility permission required".to_string();
+                            set_hotkey_error(&hotkey_app, Some(error));
+                            let _ = hotkey_app.emit("permission-required", "accessibility");
+                        }
+                        hotkey::HotkeyEvent::Restarted(failures) => {
+                            set_hotkey_error(&hotkey_app, None);
+                            let _ = hotkey_app.emit("hotkey-restarted", failures);
+                        }
+                        hotkey::HotkeyEvent::Failed(error) => {
+                            set_hotkey_error(&hotkey_app, Some(error.clone()));
+                            let _ = hotkey_app.emit("hotkey-failed", error);
+                        }
+                    }
                 }
             });
 
@@ -385,3 +5171,541 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("Failed to run application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, start: usize, end: usize) -> transcribe::Segment {
+        transcribe::Segment {
+            text: text.to_string(),
+            start,
+            end,
+            words: vec![transcribe::Word {
+                text: text.to_string(),
+                start,
+                end,
+            }],
+            confidence: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn exported_settings_import_unchanged() {
+        let settings = Settings {
+            language: "en".to_string(),
+            monitor_volume: 0.25,
+            ..Settings::default()
+        };
+        let json = serde_json::to_string_pretty(&settings).unwrap();
+        let imported: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&imported).unwrap(),
+            serde_json::to_value(&settings).unwrap()
+        );
+        assert_eq!(validate_settings(&imported), Ok(()));
+    }
+
+    #[test]
+    fn exports_leave_the_secrets_out() {
+        let settings = Settings {
+            api_key: "sk-secret".to_string(),
+            http_token: "token".to_string(),
+            language: "en".to_string(),
+            ..Settings::default()
+        };
+        let exported = settings.without_secrets();
+        assert_eq!(exported.api_key, "");
+        assert_eq!(exported.http_token, "");
+        assert_eq!(exported.language, "en");
+    }
+
+    #[test]
+    fn imports_keep_the_current_secrets_when_blank() {
+        let current = Settings {
+            api_key: "sk-current".to_string(),
+            http_token: "current".to_string(),
+            ..Settings::default()
+        };
+        let mut blank = current.without_secrets();
+        blank.keep_secrets(&current);
+        assert_eq!(blank.api_key, "sk-current");
+        assert_eq!(blank.http_token, "current");
+
+        let mut given = Settings {
+            api_key: "sk-new".to_string(),
+            ..Settings::default()
+        };
+        given.keep_secrets(&current);
+        assert_eq!(given.api_key, "sk-new");
+        assert_eq!(given.http_token, "current");
+    }
+
+    #[test]
+    fn settings_missing_from_the_file_get_their_defaults() {
+        let imported: Settings = serde_json::from_str(r#"{"language": "en"}"#).unwrap();
+        assert_eq!(imported.language, "en");
+        assert_eq!(imported.monitor_volume, Settings::default().monitor_volume);
+    }
+
+    #[test]
+    fn garbage_is_not_a_settings_file() {
+        for json in ["", "not json", "42", r#"{"language": 5}"#] {
+            assert!(serde_json::from_str::<Settings>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn out_of_range_settings_are_rejected() {
+        let invalid = [
+            Settings {
+                monitor_volume: 2.0,
+                ..Settings::default()
+            },
+            Settings {
+                gpu_device: Some(-1),
+                ..Settings::default()
+            },
+            Settings {
+                backend: Backend::Api,
+                api_url: " ".to_string(),
+                ..Settings::default()
+            },
+        ];
+        for settings in invalid {
+            assert!(validate_settings(&settings).is_err());
+        }
+    }
+
+    #[test]
+    fn extend_only_moves_the_tail_after_the_committed_audio() {
+        let tail = vec![segment(" and home.", 0, 8_000)];
+        let (text, segments) = extend_committed("We went to the park", 48_000, " and home.", tail);
+        assert_eq!(text, "We went to the park and home.");
+        assert_eq!((segments[0].start, segments[0].end), (48_000, 56_000));
+        let word = &segments[0].words[0];
+        assert_eq!((word.start, word.end), (48_000, 56_000));
+    }
+
+    #[test]
+    fn extend_only_keeps_the_committed_text() {
+        let typed = "We went to the park.";
+        let (text, _) = extend_committed(typed, 48_000, " then home", Vec::new());
+        assert_eq!(text, "We went to the park. Then home");
+        // Only the tail is typed; nothing committed is taken back.
+        let edit = streaming::reconcile_final(typed, &text, true);
+        assert_eq!(edit.backspaces, 0);
+        assert_eq!(edit.insert, " Then home");
+    }
+
+    #[test]
+    fn caption_segments_are_placed_and_marked() {
+        let segments = [
+            segment(" Hello.", 0, 16_000),
+            segment(" World", 16_000, 24_000),
+        ];
+        let captions = caption_segments(&segments, 32_000, 1);
+        let spans: Vec<_> = captions
+            .iter()
+            .map(|c| (c.text.as_str(), c.start_ms, c.end_ms, c.stable))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("Hello.", 2_000, 3_000, true),
+                ("World", 3_000, 3_500, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn preload_requests_join_the_queued_one() {
+        let preload = PreloadQueue::default();
+        assert!(preload.request(false));
+        assert!(!preload.request(false));
+        assert!(!preload.take());
+        // Taken: the next request queues a load of its own.
+        assert!(preload.request(false));
+    }
+
+    #[test]
+    fn a_joined_reload_request_reloads() {
+        let preload = PreloadQueue::default();
+        assert!(preload.request(false));
+        assert!(!preload.request(true));
+        assert!(preload.take());
+        assert!(preload.request(false));
+        assert!(!preload.take());
+    }
+
+    #[test]
+    fn load_events_carry_the_outcome() {
+        let loaded = ModelLoadedEvent {
+            path: "/models/ggml-base.bin",
+            cached: true,
+        };
+        assert_eq!(
+            serde_json::to_value(loaded).unwrap(),
+            serde_json::json!({"path": "/models/ggml-base.bin", "cached": true})
+        );
+        let failed = ModelLoadFailedEvent {
+            path: "/models/ggml-base.bin",
+            error: "Model not found".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(failed).unwrap(),
+            serde_json::json!({"path": "/models/ggml-base.bin", "error": "Model not found"})
+        );
+    }
+
+    fn control_phrases(
+        phrases: &[(&str, typing::TypeAction)],
+    ) -> Vec<(String, typing::TypeAction)> {
+        phrases
+            .iter()
+            .map(|(phrase, action)| (phrase.to_string(), action.clone()))
+            .collect()
+    }
+
+    fn text(text: &str) -> typing::TypeAction {
+        typing::TypeAction::Text(text.to_string())
+    }
+
+    const ENTER: typing::TypeAction = typing::TypeAction::Key(typing::Keystroke::Enter);
+
+    #[test]
+    fn control_phrases_become_actions() {
+        let phrases = control_phrases(&[("press enter", ENTER), ("smiley face", text(":)"))]);
+        assert_eq!(
+            parse_actions("Hello there press enter. How are you?", &phrases),
+            [text("Hello there"), ENTER, text("How are you?")]
+        );
+        assert_eq!(
+            parse_actions("Nice smiley face, thanks", &phrases),
+            [text("Nice "), text(":)"), text("thanks")]
+        );
+        assert_eq!(
+            parse_actions("Smiley face press enter", &phrases),
+            [text(":)"), ENTER]
+        );
+    }
+
+    #[test]
+    fn the_longest_phrase_at_a_place_wins() {
+        let phrases = control_phrases(&[("new", text("New")), ("new line", ENTER)]);
+        assert_eq!(
+            parse_actions("new line here", &phrases),
+            [ENTER, text("here")]
+        );
+    }
+
+    #[test]
+    fn text_without_phrases_only_splits_newlines() {
+        assert_eq!(
+            parse_actions("one\ntwo", &[]),
+            [
+                text("one"),
+                typing::TypeAction::Key(typing::Keystroke::Newline),
+                text("two")
+            ]
+        );
+        assert_eq!(parse_actions("", &control_phrases(&[("go", ENTER)])), []);
+    }
+
+    const IDLE: Duration = Duration::from_secs(10);
+
+    fn ended(window: Option<&str>) -> (Instant, Option<String>) {
+        (Instant::now(), window.map(str::to_string))
+    }
+
+    #[test]
+    fn dictation_continues_in_the_same_window_within_idle() {
+        let last = ended(Some("42"));
+        let soon = last.0 + Duration::from_secs(3);
+        assert!(continues(Some(&last), soon, Some("42"), IDLE));
+        assert!(!continues(Some(&last), soon, Some("7"), IDLE));
+    }
+
+    #[test]
+    fn dictation_after_idle_starts_afresh() {
+        let last = ended(Some("42"));
+        assert!(!continues(Some(&last), last.0 + IDLE, Some("42"), IDLE));
+        assert!(!continues(None, Instant::now(), Some("42"), IDLE));
+    }
+
+    #[test]
+    fn unknown_windows_only_count_the_time() {
+        let soon = |last: &(Instant, Option<String>)| last.0 + Duration::from_secs(3);
+        let unknown = ended(None);
+        assert!(continues(Some(&unknown), soon(&unknown), Some("42"), IDLE));
+        let known = ended(Some("42"));
+        assert!(continues(Some(&known), soon(&known), None, IDLE));
+        assert!(!continues(Some(&known), known.0 + IDLE, None, IDLE));
+    }
+
+    fn languages(list: &[&str]) -> Vec<String> {
+        list.iter().map(|language| language.to_string()).collect()
+    }
+
+    #[test]
+    fn next_language_advances_and_wraps() {
+        let list = languages(&["pl", "en", "de"]);
+        assert_eq!(next_language(&list, "pl"), Some("en"));
+        assert_eq!(next_language(&list, "en"), Some("de"));
+        assert_eq!(next_language(&list, "de"), Some("pl"));
+    }
+
+    #[test]
+    fn next_language_starts_over_from_an_unlisted_one() {
+        assert_eq!(next_language(&languages(&["pl", "en"]), "fr"), Some("pl"));
+        assert_eq!(next_language(&languages(&["pl"]), "pl"), Some("pl"));
+        assert_eq!(next_language(&[], "pl"), None);
+    }
+
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn ticks_on_time_stay_an_interval_apart() {
+        let started = Instant::now();
+        let took = Duration::from_millis(200);
+        assert_eq!(
+            next_tick_at(started, took, INTERVAL, false),
+            started + INTERVAL
+        );
+        assert_eq!(
+            next_tick_at(started, took, INTERVAL, true),
+            started + INTERVAL
+        );
+    }
+
+    #[test]
+    fn adaptive_ticks_skip_the_ones_a_slow_tick_overran() {
+        let started = Instant::now();
+        let took = Duration::from_millis(1200);
+        assert_eq!(
+            next_tick_at(started, took, INTERVAL, true),
+            started + took + INTERVAL
+        );
+        // Without it the next tick is already due when the slow one ends.
+        assert_eq!(
+            next_tick_at(started, took, INTERVAL, false),
+            started + INTERVAL
+        );
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn onset_opens_once_speech_is_heard() {
+        let settings = Settings::default();
+        assert!(onset_reached(&settings, ms(1200), ms(400)));
+        assert!(!onset_reached(&settings, ms(1200), ms(390)));
+        // Not before the minimum audio, however much was speech.
+        assert!(!onset_reached(&settings, ms(900), ms(900)));
+    }
+
+    #[test]
+    fn onset_stays_closed_on_silence() {
+        let settings = Settings::default();
+        assert!(!onset_reached(&settings, ms(5000), Duration::ZERO));
+        let off = Settings {
+            onset_speech_ms: 0,
+            ..Settings::default()
+        };
+        assert!(!onset_reached(&off, ms(5000), ms(5000)));
+    }
+
+    #[test]
+    fn onset_needs_more_than_a_click() {
+        let settings = Settings {
+            onset_speech_ms: 10,
+            ..Settings::default()
+        };
+        assert!(!onset_reached(&settings, ms(1200), ms(60)));
+        assert!(onset_reached(
+            &settings,
+            ms(1200),
+            ms(MIN_ONSET_SPEECH_MS.into())
+        ));
+    }
+
+    #[test]
+    fn loading_is_announced_around_a_load() {
+        for outcome in [Ok(()), Err("Model file not found")] {
+            let loading = AtomicBool::new(false);
+            let mut announced = Vec::new();
+            let result = while_loading(
+                &loading,
+                |state| announced.push(state),
+                || {
+                    assert!(loading.load(Ordering::Relaxed));
+                    outcome
+                },
+            );
+            assert_eq!(result, outcome);
+            assert!(!loading.load(Ordering::Relaxed));
+            assert_eq!(announced, [true, false]);
+        }
+    }
+
+    #[test]
+    fn short_recordings_are_discarded() {
+        let event = too_short(8_000, ms(500), 16_000, 300).unwrap();
+        assert!(matches!(event.reason, TooShortReason::Length));
+        assert_eq!((event.duration_ms, event.minimum_ms), (500, 1000));
+    }
+
+    #[test]
+    fn recordings_without_enough_speech_are_discarded() {
+        let event = too_short(32_000, ms(200), 16_000, 300).unwrap();
+        assert!(matches!(event.reason, TooShortReason::Speech));
+        assert_eq!((event.duration_ms, event.minimum_ms), (200, 300));
+        assert!(too_short(32_000, ms(300), 16_000, 300).is_none());
+        assert!(too_short(16_000, Duration::ZERO, 16_000, 0).is_none());
+    }
+
+    #[test]
+    fn greedy_best_of_comes_from_the_settings_window() {
+        let json = r#"{"sampling": {"strategy": "greedy", "best_of": 1}}"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            settings.sampling,
+            transcribe::Sampling::Greedy { best_of: 1 }
+        );
+        assert_eq!(validate_settings(&settings), Ok(()));
+
+        let zero = transcribe::Sampling::Greedy { best_of: 0 };
+        let invalid = [
+            Settings {
+                sampling: zero,
+                ..Settings::default()
+            },
+            Settings {
+                streaming_sampling: Some(zero),
+                ..Settings::default()
+            },
+        ];
+        for settings in invalid {
+            assert!(validate_settings(&settings).is_err());
+        }
+    }
+
+    #[test]
+    fn copying_needs_a_transcription() {
+        let settings = Settings::default();
+        assert_eq!(
+            copied_text(None, &settings),
+            Err("No transcription to copy yet".to_string())
+        );
+    }
+
+    #[test]
+    fn the_copy_is_trimmed_without_a_trailing_space() {
+        let settings = Settings {
+            trim_output: true,
+            append_space: true,
+            ..Settings::default()
+        };
+        let copied = copied_text(Some("Hello there.\n"), &settings);
+        assert_eq!(copied.as_deref(), Ok("Hello there."));
+        let untrimmed = Settings {
+            trim_output: false,
+            ..settings
+        };
+        let copied = copied_text(Some("Hello there.\n"), &untrimmed);
+        assert_eq!(copied.as_deref(), Ok("Hello there.\n"));
+    }
+
+    #[test]
+    fn a_focused_window_of_the_apps_counts() {
+        assert!(any_focused([Ok::<_, ()>(false), Ok(true)]));
+        assert!(!any_focused([Ok(false), Err(())]));
+        assert!(!any_focused(Vec::<Result<bool, ()>>::new()));
+    }
+
+    #[test]
+    fn only_the_type_policy_types_into_the_apps_window() {
+        for policy in [OwnWindowPolicy::Refuse, OwnWindowPolicy::Scratchpad] {
+            assert!(!own_window_allows(policy, || true));
+            assert!(own_window_allows(policy, || false));
+        }
+        // Without asking the window system.
+        assert!(own_window_allows(OwnWindowPolicy::Type, || unreachable!()));
+    }
+
+    #[test]
+    fn ticks_keep_context_only_when_asked_and_after_the_first() {
+        let on = Settings {
+            use_context: true,
+            ..Settings::default()
+        };
+        assert!(tick_keeps_context(&on, true));
+        assert!(!tick_keeps_context(&on, false));
+        assert!(!tick_keeps_context(&Settings::default(), true));
+    }
+
+    /// `text` held back as `confirm_delivery` does, and where its answer
+    /// arrives.
+    fn held(text: &str) -> (PendingConfirm, mpsc::Receiver<bool>) {
+        let (answer, answered) = mpsc::channel();
+        (Mutex::new(Some((text.to_string(), answer))), answered)
+    }
+
+    const ANSWER_TIMEOUT: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn confirmed_text_is_typed() {
+        let (pending, answered) = held("Hello.");
+        assert!(answer_pending(&pending, true));
+        assert!(pending.lock().is_none());
+        let typed = await_answer(&pending, &answered, ANSWER_TIMEOUT, ConfirmTimeout::Discard);
+        assert!(typed);
+    }
+
+    #[test]
+    fn rejected_text_is_dropped() {
+        let (pending, answered) = held("Hello.");
+        assert!(answer_pending(&pending, false));
+        let typed = await_answer(&pending, &answered, ANSWER_TIMEOUT, ConfirmTimeout::Type);
+        assert!(!typed);
+        // Nothing is left to answer.
+        assert!(!answer_pending(&pending, true));
+    }
+
+    #[test]
+    fn unanswered_text_goes_as_the_timeout_says() {
+        for (on_timeout, typed) in [
+            (ConfirmTimeout::Discard, false),
+            (ConfirmTimeout::Type, true),
+        ] {
+            let (pending, answered) = held("Hello.");
+            assert_eq!(
+                await_answer(&pending, &answered, ANSWER_TIMEOUT, on_timeout),
+                typed
+            );
+            assert!(pending.lock().is_none());
+        }
+    }
+
+    #[test]
+    fn one_shot_recordings_are_bounded() {
+        assert_eq!(one_shot_limit(1), Ok(Duration::from_secs(1)));
+        assert_eq!(one_shot_limit(300), Ok(MAX_ONE_SHOT));
+        for max_secs in [0, 301] {
+            assert_eq!(
+                one_shot_limit(max_secs),
+                Err(format!("max_secs must be 1 to 300, not {max_secs}"))
+            );
+        }
+    }
+
+    #[test]
+    fn one_shot_recordings_without_speech_fail_with_the_reason() {
+        let short = too_short(8_000, ms(500), 16_000, 300).unwrap();
+        assert_eq!(short.message(), "Recording too short: 500 ms");
+        let quiet = too_short(32_000, ms(120), 16_000, 300).unwrap();
+        assert_eq!(quiet.message(), "No speech heard, only 120 ms");
+    }
+}
@@ -0,0 +1,99 @@
+//! Logging to stderr and to rotating files in the data dir, so runs started
+//! by autostart leave something to attach to a bug report.
+
+use anyhow::{Context, Result};
+use flexi_logger::{
+    Age, Cleanup, Criterion, Duplicate, FileSpec, LogSpecification, Logger, LoggerHandle, Naming,
+};
+use std::path::PathBuf;
+
+/// A log file is rotated daily, or earlier once it reaches this size.
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+/// Rotated files kept besides the current one.
+const KEEP_FILES: usize = 7;
+
+pub fn log_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("voice-to-text")
+        .join("logs")
+}
+
+/// Keeps the logger running; dropping it flushes and closes the file.
+pub struct Logging {
+    handle: LoggerHandle,
+}
+
+impl Logging {
+    /// Start logging at `level` (a filter like `"info"` or
+    /// `"info,voice_to_text=debug"`); `RUST_LOG` takes precedence if set.
+    /// Falls back to stderr only if the log dir can't be used. Returns
+    /// `None` if no logger could be installed at all.
+    pub fn init(level: &str) -> Option<Self> {
+        let file_logger = Logger::try_with_env_or_str(level).and_then(|logger| {
+            logger
+                .log_to_file(FileSpec::default().directory(log_dir()))
+                .format_for_files(flexi_logger::detailed_format)
+                .duplicate_to_stderr(Duplicate::All)
+                .rotate(
+                    Criterion::AgeOrSize(Age::Day, MAX_FILE_BYTES),
+                    Naming::Timestamps,
+                    Cleanup::KeepLogFiles(KEEP_FILES),
+                )
+                .start()
+        });
+        let handle = match file_logger {
+            Ok(handle) => handle,
+            Err(file_err) => {
+                let handle = Logger::try_with_env_or_str(level)
+                    .and_then(|logger| logger.log_to_stderr().start())
+                    .ok()?;
+                log::warn!("Logging to stderr only: {file_err}");
+                handle
+            }
+        };
+        Some(Self { handle })
+    }
+
+    /// Switch to a new level filter (see `parse_level`), e.g. after the
+    /// settings changed.
+    pub fn set_level(&self, level: LogSpecification) {
+        self.handle.set_new_spec(level);
+    }
+}
+
+/// Validate a level filter such as `"debug"` or `"warn,voice_to_text=info"`.
+pub fn parse_level(level: &str) -> Result<LogSpecification> {
+    LogSpecification::parse(level).with_context(|| format!("Invalid log level {level:?}"))
+}
+
+/// The last `lines` lines of the most recently written log file.
+pub fn recent_logs(lines: usize) -> Result<String> {
+    let newest = std::fs::read_dir(log_dir())
+        .context("No log files yet")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .context("No log files yet")?;
+    let content = std::fs::read_to_string(newest.path())?;
+    let all: Vec<&str> = content.lines().collect();
+    Ok(all[all.len().saturating_sub(lines)..].join("\n"))
+}
+
+/// Show the log directory in the system's file manager.
+pub fn open_log_dir() -> Result<()> {
+    let dir = log_dir();
+    std::fs::create_dir_all(&dir)?;
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(&dir)
+        .spawn()
+        .with_context(|| format!("Failed to run {opener}"))?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Daily Markdown notes: each dictation appended to `<dir>/YYYY-MM-DD.md`
+//! under a heading, for note-taking alongside (or instead of) typing.
+
+use crate::postprocess;
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `dir` with a leading `~` standing for the home directory, as in
+/// `~/notes`.
+fn expand_home(dir: &Path) -> PathBuf {
+    match (dir.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => dir.to_path_buf(),
+    }
+}
+
+/// The note file for the day of `now`.
+pub fn note_path(dir: &Path, now: NaiveDateTime) -> PathBuf {
+    expand_home(dir).join(format!("{}.md", now.format("%Y-%m-%d")))
+}
+
+/// The Markdown appended for one dictation: `heading` (expanded like
+/// `Settings::prefix_template`) on a line of its own unless it's empty,
+/// then `text`. Set off from what the file already holds by a blank line.
+fn entry(heading: &str, text: &str, now: NaiveDateTime, language: &str, first: bool) -> String {
+    let mut entry = String::new();
+    if !first {
+        entry.push('\n');
+    }
+    let heading = postprocess::expand_prefix(heading, now, language);
+    if !heading.trim().is_empty() {
+        entry.push_str(heading.trim_end());
+        entry.push_str("\n\n");
+    }
+    entry.push_str(text.trim());
+    entry.push('\n');
+    entry
+}
+
+/// Append `text` to the day's note in `dir`, creating both as needed.
+/// The entry goes out in one write to a file opened for appending, so it
+/// never interleaves with another writer's. Returns the file written.
+pub fn append(
+    dir: &Path,
+    heading: &str,
+    text: &str,
+    now: NaiveDateTime,
+    language: &str,
+) -> Result<PathBuf> {
+    let path = note_path(dir, now);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let first = file.metadata()?.len() == 0;
+    file.write_all(entry(heading, text, now, language, first).as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    /// A fresh directory under the system's temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("vtt-notes-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn each_day_has_its_own_note() {
+        let dir = Path::new("/notes");
+        assert_eq!(
+            note_path(dir, at(1, 9, 0)),
+            Path::new("/notes/2024-06-01.md")
+        );
+        assert_eq!(
+            note_path(dir, at(2, 0, 5)),
+            Path::new("/notes/2024-06-02.md")
+        );
+    }
+
+    #[test]
+    fn entries_are_set_off_from_what_comes_before() {
+        let now = at(1, 14, 32);
+        assert_eq!(
+            entry("## {time}", " Hello. ", now, "en", true),
+            "## 14:32\n\nHello.\n"
+        );
+        assert_eq!(
+            entry("## {time}", "Hello.", now, "en", false),
+            "\n## 14:32\n\nHello.\n"
+        );
+        assert_eq!(entry("  ", "Hello.", now, "en", false), "\nHello.\n");
+    }
+
+    #[test]
+    fn appends_accumulate_in_the_days_note() {
+        let dir = TempDir::new("append");
+        // Created along with its parent directories.
+        let notes = dir.0.join("daily");
+        let first = append(&notes, "## {time}", "First.", at(1, 9, 0), "en").unwrap();
+        let second = append(&notes, "## {time} ({lang})", "Drugie.", at(1, 9, 30), "pl").unwrap();
+        let next_day = append(&notes, "", "Third.", at(2, 8, 0), "en").unwrap();
+
+        assert_eq!(first, notes.join("2024-06-01.md"));
+        assert_eq!(second, first);
+        assert_eq!(
+            std::fs::read_to_string(&first).unwrap(),
+            "## 09:00\n\nFirst.\n\n## 09:30 (pl)\n\nDrugie.\n"
+        );
+        assert_eq!(std::fs::read_to_string(next_day).unwrap(), "Third.\n");
+    }
+}
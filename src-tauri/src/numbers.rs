@@ -0,0 +1,393 @@
+//! Inverse text normalization: spelled-out numbers become digits.
+//!
+//! Covers 0–9999 in English and Polish (nominative forms only), plus
+//! decimals read digit by digit ("three point five" → `3.5`, "trzy
+//! przecinek pięć" → `3,5`). Other languages pass through unchanged.
+
+/// Grammatical role of a number word, which decides what may follow it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// 0–9
+    Unit,
+    /// 10–19
+    Teen,
+    /// 20, 30, … 90
+    Tens,
+    /// Polish 100–900 words ("sto", "dwieście", …), which add their value.
+    Hundreds,
+    /// English "hundred", which multiplies the unit before it.
+    Hundred,
+    /// Multiplies everything before it by 1000.
+    Thousand,
+}
+
+struct Lexicon {
+    words: &'static [(&'static str, Kind, u32)],
+    /// Word allowed between a hundred/thousand and the rest ("and").
+    conjunction: Option<&'static str>,
+    decimal_word: &'static str,
+    decimal_separator: char,
+    /// Multipliers that make a number on their own (Polish "tysiąc");
+    /// all others need a count before them ("one thousand", "dwa tysiące").
+    bare_multipliers: &'static [&'static str],
+}
+
+const EN: Lexicon = Lexicon {
+    words: &[
+        ("zero", Kind::Unit, 0),
+        ("one", Kind::Unit, 1),
+        ("two", Kind::Unit, 2),
+        ("three", Kind::Unit, 3),
+        ("four", Kind::Unit, 4),
+        ("five", Kind::Unit, 5),
+        ("six", Kind::Unit, 6),
+        ("seven", Kind::Unit, 7),
+        ("eight", Kind::Unit, 8),
+        ("nine", Kind::Unit, 9),
+        ("ten", Kind::Teen, 10),
+        ("eleven", Kind::Teen, 11),
+        ("twelve", Kind::Teen, 12),
+        ("thirteen", Kind::Teen, 13),
+        ("fourteen", Kind::Teen, 14),
+        ("fifteen", Kind::Teen, 15),
+        ("sixteen", Kind::Teen, 16),
+        ("seventeen", Kind::Teen, 17),
+        ("eighteen", Kind::Teen, 18),
+        ("nineteen", Kind::Teen, 19),
+        ("twenty", Kind::Tens, 20),
+        ("thirty", Kind::Tens, 30),
+        ("forty", Kind::Tens, 40),
+        ("fifty", Kind::Tens, 50),
+        ("sixty", Kind::Tens, 60),
+        ("seventy", Kind::Tens, 70),
+        ("eighty", Kind::Tens, 80),
+        ("ninety", Kind::Tens, 90),
+        ("hundred", Kind::Hundred, 100),
+        ("thousand", Kind::Thousand, 1000),
+    ],
+    conjunction: Some("and"),
+    decimal_word: "point",
+    decimal_separator: '.',
+    bare_multipliers: &[],
+};
+
+const PL: Lexicon = Lexicon {
+    words: &[
+        ("zero", Kind::Unit, 0),
+        ("jeden", Kind::Unit, 1),
+        ("jedna", Kind::Unit, 1),
+        ("jedno", Kind::Unit, 1),
+        ("dwa", Kind::Unit, 2),
+        ("dwie", Kind::Unit, 2),
+        ("trzy", Kind::Unit, 3),
+        ("cztery", Kind::Unit, 4),
+        ("pięć", Kind::Unit, 5),
+        ("sześć", Kind::Unit, 6),
+        ("siedem", Kind::Unit, 7),
+        ("osiem", Kind::Unit, 8),
+        ("dziewięć", Kind::Unit, 9),
+        ("dziesięć", Kind::Teen, 10),
+        ("jedenaście", Kind::Teen, 11),
+        ("dwanaście", Kind::Teen, 12),
+        ("trzynaście", Kind::Teen, 13),
+        ("czternaście", Kind::Teen, 14),
+        ("piętnaście", Kind::Teen, 15),
+        ("szesnaście", Kind::Teen, 16),
+        ("siedemnaście", Kind::Teen, 17),
+        ("osiemnaście", Kind::Teen, 18),
+        ("dziewiętnaście", Kind::Teen, 19),
+        ("dwadzieścia", Kind::Tens, 20),
+        ("trzydzieści", Kind::Tens, 30),
+        ("czterdzieści", Kind::Tens, 40),
+        ("pięćdziesiąt", Kind::Tens, 50),
+        ("sześćdziesiąt", Kind::Tens, 60),
+        ("siedemdziesiąt", Kind::Tens, 70),
+        ("osiemdziesiąt", Kind::Tens, 80),
+        ("dziewięćdziesiąt", Kind::Tens, 90),
+        ("sto", Kind::Hundreds, 100),
+        ("dwieście", Kind::Hundreds, 200),
+        ("trzysta", Kind::Hundreds, 300),
+        ("czterysta", Kind::Hundreds, 400),
+        ("pięćset", Kind::Hundreds, 500),
+        ("sześćset", Kind::Hundreds, 600),
+        ("siedemset", Kind::Hundreds, 700),
+        ("osiemset", Kind::Hundreds, 800),
+        ("dziewięćset", Kind::Hundreds, 900),
+        ("tysiąc", Kind::Thousand, 1000),
+        ("tysiące", Kind::Thousand, 1000),
+        ("tysięcy", Kind::Thousand, 1000),
+    ],
+    conjunction: None,
+    decimal_word: "przecinek",
+    decimal_separator: ',',
+    bare_multipliers: &["tysiąc"],
+};
+
+fn lexicon(language: &str) -> Option<&'static Lexicon> {
+    if language.starts_with("en") {
+        Some(&EN)
+    } else if language.starts_with("pl") {
+        Some(&PL)
+    } else {
+        None
+    }
+}
+
+impl Lexicon {
+    fn lookup(&self, word: &str) -> Option<(Kind, u32)> {
+        self.words
+            .iter()
+            .find(|(w, _, _)| *w == word)
+            .map(|&(_, kind, value)| (kind, value))
+    }
+}
+
+/// A number being assembled word by word.
+#[derive(Debug, Clone, Copy, Default)]
+struct Number {
+    /// Thousands already multiplied out.
+    total: u32,
+    /// Value below the last "thousand".
+    current: u32,
+    last: Option<Kind>,
+    words: usize,
+}
+
+impl Number {
+    /// Add the next word, or return `false` if it can't continue this number
+    /// (e.g. "two three" is two numbers, not five).
+    /// `bare` says whether a multiplier may start a number.
+    fn push(&mut self, kind: Kind, value: u32, bare: bool) -> bool {
+        use Kind::*;
+        let last = self.last;
+        let ok = match kind {
+            Unit => matches!(last, None | Some(Tens | Hundred | Hundreds | Thousand)),
+            Teen | Tens => matches!(last, None | Some(Hundred | Hundreds | Thousand)),
+            Hundreds => matches!(last, None | Some(Thousand)),
+            Hundred => match last {
+                None => bare,
+                Some(Unit) => self.current < 10,
+                _ => false,
+            },
+            Thousand => {
+                self.total == 0
+                    && self.current < 10
+                    && match last {
+                        None => bare,
+                        Some(Thousand) => false,
+                        Some(_) => true,
+                    }
+            }
+        };
+        if !ok {
+            return false;
+        }
+
+        match kind {
+            Hundred => self.current = self.current.max(1) * value,
+            Thousand => {
+                self.total = self.current.max(1) * value;
+                self.current = 0;
+            }
+            _ => self.current += value,
+        }
+        if self.total + self.current > 9999 {
+            return false;
+        }
+        self.last = Some(kind);
+        self.words += 1;
+        true
+    }
+
+    fn value(&self) -> u32 {
+        self.total + self.current
+    }
+}
+
+/// A whitespace-separated token split into leading punctuation, the
+/// lowercased word and trailing punctuation.
+struct Token<'a> {
+    lead: &'a str,
+    word: String,
+    trail: &'a str,
+}
+
+fn split_token(token: &str) -> Token<'_> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '-';
+    let start = token.find(is_word_char).unwrap_or(token.len());
+    let end = token.rfind(is_word_char).map_or(start, |i| {
+        i + token[i..].chars().next().map_or(0, char::len_utf8)
+    });
+    Token {
+        lead: &token[..start],
+        word: token[start..end].to_lowercase(),
+        trail: &token[end..],
+    }
+}
+
+/// Replace spelled-out numbers in `text` with digits, using the rules of
+/// `language`.
+///
+/// Lone words below ten ("one of them", "dwa razy") stay spelled out, as
+/// most style guides prefer; anything longer or larger is converted.
+pub fn format_numbers(text: &str, language: &str) -> String {
+    let Some(lex) = lexicon(language) else {
+        return text.to_string();
+    };
+    let tokens: Vec<&str> = text.split(' ').collect();
+    let mut out: Vec<String> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        match parse_number(&tokens[i..], lex) {
+            Some((formatted, consumed)) => {
+                out.push(formatted);
+                i += consumed;
+            }
+            None => {
+                out.push(tokens[i].to_string());
+                i += 1;
+            }
+        }
+    }
+    out.join(" ")
+}
+
+/// Parse the longest number starting at `tokens[0]`. Returns the formatted
+/// replacement and how many tokens it covers.
+fn parse_number(tokens: &[&str], lex: &Lexicon) -> Option<(String, usize)> {
+    let first = split_token(tokens.first()?);
+    let lead = first.lead;
+    let mut number = Number::default();
+    let mut consumed = 0;
+    let mut trail = "";
+
+    while let Some(token) = tokens.get(consumed) {
+        let token = split_token(token);
+        // Punctuation inside the run ends the number.
+        if consumed > 0 && (!token.lead.is_empty() || !trail.is_empty()) {
+            break;
+        }
+
+        if lex.conjunction == Some(token.word.as_str())
+            && token.trail.is_empty()
+            && matches!(number.last, Some(Kind::Hundred | Kind::Thousand))
+        {
+            let continues = tokens.get(consumed + 1).is_some_and(|next| {
+                let next = split_token(next);
+                // "and twenty-three" continues too, so look at the first half.
+                let head = next.word.split('-').next().unwrap_or_default();
+                next.lead.is_empty()
+                    && lex.lookup(head).is_some_and(|(kind, _)| {
+                        matches!(kind, Kind::Unit | Kind::Teen | Kind::Tens)
+                    })
+            });
+            if continues {
+                consumed += 1;
+                continue;
+            }
+            break;
+        }
+
+        let mut candidate = number;
+        let all_numeric = !token.word.is_empty()
+            && token.word.split('-').all(|part| {
+                lex.lookup(part).is_some_and(|(kind, value)| {
+                    candidate.push(kind, value, lex.bare_multipliers.contains(&part))
+                })
+            });
+        if !all_numeric {
+            break;
+        }
+        number = candidate;
+        trail = token.trail;
+        consumed += 1;
+    }
+
+    if number.words == 0 {
+        return None;
+    }
+
+    let mut formatted = format!("{lead}{}", number.value());
+    if trail.is_empty() {
+        if let Some((digits, used, digits_trail)) = parse_decimals(&tokens[consumed..], lex) {
+            formatted.push(lex.decimal_separator);
+            formatted.push_str(&digits);
+            consumed += used;
+            trail = digits_trail;
+        }
+    }
+
+    let is_decimal = formatted.contains(lex.decimal_separator);
+    if number.words == 1 && number.value() < 10 && !is_decimal {
+        return None;
+    }
+    formatted.push_str(trail);
+    Some((formatted, consumed))
+}
+
+/// "point five two" → `("52", 3, trailing punctuation)`.
+fn parse_decimals<'a>(tokens: &[&'a str], lex: &Lexicon) -> Option<(String, usize, &'a str)> {
+    let marker = split_token(tokens.first()?);
+    if marker.word != lex.decimal_word || !marker.lead.is_empty() || !marker.trail.is_empty() {
+        return None;
+    }
+
+    let mut digits = String::new();
+    let mut trail = "";
+    for token in &tokens[1..] {
+        let token = split_token(token);
+        let digit = match lex.lookup(&token.word) {
+            Some((Kind::Unit, value)) if token.lead.is_empty() => value,
+            _ => break,
+        };
+        digits.push(char::from_digit(digit, 10)?);
+        trail = token.trail;
+        if !trail.is_empty() {
+            break;
+        }
+    }
+    if digits.is_empty() {
+        return None;
+    }
+    let used = digits.len() + 1;
+    Some((digits, used, trail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polish_compounds() {
+        assert_eq!(
+            format_numbers("mam dwadzieścia trzy lata", "pl"),
+            "mam 23 lata"
+        );
+        assert_eq!(format_numbers("dwa tysiące osób", "pl"), "2000 osób");
+        assert_eq!(format_numbers("tysiąc dwieście pięć", "pl"), "1205");
+    }
+
+    #[test]
+    fn polish_decimals() {
+        assert_eq!(format_numbers("trzy przecinek pięć", "pl"), "3,5");
+    }
+
+    #[test]
+    fn english_compounds() {
+        assert_eq!(format_numbers("one hundred and five", "en"), "105");
+        assert_eq!(format_numbers("twenty-three apples.", "en"), "23 apples.");
+        assert_eq!(format_numbers("three point one four", "en"), "3.14");
+    }
+
+    #[test]
+    fn single_units_stay_spelled_out() {
+        assert_eq!(format_numbers("one of them", "en"), "one of them");
+        assert_eq!(format_numbers("dwa razy", "pl"), "dwa razy");
+        assert_eq!(format_numbers("two three", "en"), "two three");
+    }
+
+    #[test]
+    fn other_languages_pass_through() {
+        assert_eq!(format_numbers("zwei und zwanzig", "de"), "zwei und zwanzig");
+    }
+}
@@ -0,0 +1,326 @@
+//! Headless output: committed and final text written as newline-delimited
+//! JSON to stdout or to the clients of a Unix domain socket, for feeding
+//! another program instead of typing.
+//!
+//! ```text
+//! {"type":"chunk","text":"Hello"}
+//! {"type":"chunk","text":" world."}
+//! {"type":"final","text":"Hello world.","language":"en"}
+//! ```
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// Where `Settings::pipe_output` sends text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipeTarget {
+    Stdout,
+    /// A socket the app listens on; every connected client gets every line.
+    Socket(PathBuf),
+}
+
+impl PipeTarget {
+    /// `-` for stdout, anything else a socket path; `None` for empty.
+    pub fn parse(target: &str) -> Option<Self> {
+        match target.trim() {
+            "" => None,
+            "-" => Some(Self::Stdout),
+            path => Some(Self::Socket(PathBuf::from(path))),
+        }
+    }
+}
+
+/// One line of output.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message<'a> {
+    /// Text streaming committed; each chunk follows on from the last.
+    Chunk { text: &'a str },
+    /// The whole dictation, after the final pass. It may differ from the
+    /// chunks joined, which were only a first take.
+    Final { text: &'a str, language: &'a str },
+}
+
+/// Where the lines end up.
+pub trait Sink: Send {
+    /// Write `line` (without its newline). An error is logged, and the
+    /// sink is kept for the next line.
+    fn write_line(&mut self, line: &str) -> std::io::Result<()>;
+}
+
+struct Stdout;
+
+impl Sink for Stdout {
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{line}")?;
+        stdout.flush()
+    }
+}
+
+/// Writes lines on a thread of its own, so a slow reader never holds up
+/// the worker.
+pub struct Pipe {
+    target: PipeTarget,
+    tx: Mutex<mpsc::Sender<String>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Pipe {
+    pub fn open(target: PipeTarget) -> Result<Self> {
+        let sink: Box<dyn Sink> = match &target {
+            PipeTarget::Stdout => Box::new(Stdout),
+            PipeTarget::Socket(path) => Box::new(socket::Listener::bind(path)?),
+        };
+        Ok(Self::with_sink(target, sink))
+    }
+
+    /// A pipe writing to `sink`, described as `target`.
+    pub fn with_sink(target: PipeTarget, mut sink: Box<dyn Sink>) -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        let thread = std::thread::spawn(move || {
+            // Ends once the pipe is dropped, dropping the sink with it.
+            for line in rx {
+                if let Err(e) = sink.write_line(&line) {
+                    log::warn!("Failed to write to the output pipe: {e}");
+                }
+            }
+        });
+        Self {
+            target,
+            tx: Mutex::new(tx),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn target(&self) -> &PipeTarget {
+        &self.target
+    }
+
+    pub fn send(&self, message: &Message) {
+        match serde_json::to_string(message) {
+            Ok(line) => {
+                let _ = self.tx.lock().send(line);
+            }
+            Err(e) => log::error!("Failed to encode pipe output: {e}"),
+        }
+    }
+}
+
+impl Drop for Pipe {
+    /// Waits for the lines still queued and the sink to close, so a pipe
+    /// opened next on the same socket path doesn't race this one.
+    fn drop(&mut self) {
+        drop(std::mem::replace(&mut *self.tx.lock(), mpsc::channel().0));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use super::Sink;
+    use anyhow::{Context, Result};
+    use parking_lot::Mutex;
+    use std::io::Write;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    /// How long a client may keep a line waiting before it's dropped.
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// The socket at `path` and the clients connected to it.
+    pub struct Listener {
+        path: PathBuf,
+        clients: Arc<Mutex<Vec<UnixStream>>>,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Listener {
+        /// Listen at `path`, replacing a socket a previous run left behind.
+        pub fn bind(path: &Path) -> Result<Self> {
+            if path.exists() && UnixStream::connect(path).is_err() {
+                let _ = std::fs::remove_file(path);
+            }
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Failed to listen on {}", path.display()))?;
+            let clients = Arc::new(Mutex::new(Vec::new()));
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = {
+                let clients = clients.clone();
+                let stop = stop.clone();
+                std::thread::spawn(move || {
+                    for stream in listener.incoming() {
+                        if stop.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        match stream {
+                            Ok(stream) => {
+                                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                                log::info!("Output pipe client connected");
+                                clients.lock().push(stream);
+                            }
+                            Err(e) => log::warn!("Output pipe client not accepted: {e}"),
+                        }
+                    }
+                })
+            };
+            log::info!("Output pipe listening on {}", path.display());
+            Ok(Self {
+                path: path.to_path_buf(),
+                clients,
+                stop,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl Sink for Listener {
+        /// Clients that hung up, or stopped reading, are dropped; the rest
+        /// still get the line. With no clients the line is lost.
+        fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+            self.clients.lock().retain_mut(|client| {
+                match writeln!(client, "{line}").and_then(|()| client.flush()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log::info!("Output pipe client gone: {e}");
+                        false
+                    }
+                }
+            });
+            Ok(())
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            // Wake the accept loop so it sees `stop`.
+            let _ = UnixStream::connect(&self.path);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+            let _ = std::fs::remove_file(&self.path);
+            log::info!("Output pipe on {} closed", self.path.display());
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::{BufRead, BufReader};
+        use std::time::Instant;
+
+        /// Wait for the accept loop to take `n` clients.
+        fn wait_for_clients(listener: &Listener, n: usize) {
+            let started = Instant::now();
+            while listener.clients.lock().len() < n {
+                assert!(started.elapsed() < Duration::from_secs(5), "no client");
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        }
+
+        #[test]
+        fn a_client_hanging_up_leaves_the_others() {
+            let path = std::env::temp_dir().join(format!("vtt-pipe-{}.sock", std::process::id()));
+            let mut listener = Listener::bind(&path).unwrap();
+            let gone = UnixStream::connect(&path).unwrap();
+            let staying = UnixStream::connect(&path).unwrap();
+            wait_for_clients(&listener, 2);
+            drop(gone);
+
+            listener.write_line("one").unwrap();
+            listener.write_line("two").unwrap();
+            assert_eq!(listener.clients.lock().len(), 1);
+            let lines: Vec<String> = BufReader::new(staying)
+                .lines()
+                .take(2)
+                .collect::<std::io::Result<_>>()
+                .unwrap();
+            assert_eq!(lines, ["one", "two"]);
+
+            drop(listener);
+            assert!(!path.exists());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod socket {
+    use super::Sink;
+    use anyhow::Result;
+    use std::path::Path;
+
+    pub enum Listener {}
+
+    impl Listener {
+        pub fn bind(_path: &Path) -> Result<Self> {
+            anyhow::bail!("Unix sockets aren't available here; use - for stdout")
+        }
+    }
+
+    impl Sink for Listener {
+        fn write_line(&mut self, _line: &str) -> std::io::Result<()> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Keeps the lines written to it.
+    struct Lines(Arc<Mutex<Vec<String>>>);
+
+    impl Sink for Lines {
+        fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+            self.0.lock().push(line.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn messages_are_written_as_json_lines() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let pipe = Pipe::with_sink(PipeTarget::Stdout, Box::new(Lines(lines.clone())));
+        pipe.send(&Message::Chunk { text: "Hello" });
+        pipe.send(&Message::Chunk { text: " world." });
+        pipe.send(&Message::Final {
+            text: "Hello world.",
+            language: "en",
+        });
+        // Dropping the pipe waits for the queued lines.
+        drop(pipe);
+        assert_eq!(
+            *lines.lock(),
+            [
+                r#"{"type":"chunk","text":"Hello"}"#,
+                r#"{"type":"chunk","text":" world."}"#,
+                r#"{"type":"final","text":"Hello world.","language":"en"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_parse() {
+        assert_eq!(PipeTarget::parse("  "), None);
+        assert_eq!(PipeTarget::parse("-"), Some(PipeTarget::Stdout));
+        assert_eq!(
+            PipeTarget::parse(" /tmp/vtt.sock "),
+            Some(PipeTarget::Socket(PathBuf::from("/tmp/vtt.sock")))
+        );
+    }
+}
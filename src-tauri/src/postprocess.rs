@@ -0,0 +1,497 @@
+//! Text clean-up applied to transcriptions before they are typed.
+
+use chrono::NaiveDateTime;
+
+/// Longest phrase, in words, `repeated_phrase` looks for.
+const LOOP_MAX_WORDS: usize = 8;
+/// Back-to-back repeats that make a phrase a loop rather than emphasis.
+const LOOP_MIN_REPEATS: usize = 10;
+
+/// Collapse runs of whitespace, remove stray spaces before punctuation and
+/// capitalize the first letter of each sentence.
+///
+/// Capitalization goes through `char::to_uppercase`, which is a no-op for
+/// scripts without letter case, so it never mangles them. French keeps its
+/// space before `; : ! ?`.
+///
+/// The result only ever depends on preceding characters for capitalization,
+/// but space removal looks ahead, so streaming callers must compare processed
+/// hypotheses with each other rather than patch already-typed text.
+pub fn tidy(text: &str, language: &str) -> String {
+    let spaced_high_punct = language.starts_with("fr");
+    let mut out = String::with_capacity(text.len());
+    let mut capitalize = true;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !out.is_empty() && !out.ends_with(' ') {
+                out.push(' ');
+            }
+            continue;
+        }
+
+        let tight = matches!(c, ',' | '.' | '…' | ')' | ']')
+            || (!spaced_high_punct && matches!(c, ';' | ':' | '!' | '?'));
+        if tight && out.ends_with(' ') {
+            out.pop();
+        }
+
+        if capitalize && c.is_alphabetic() {
+            out.extend(c.to_uppercase());
+            capitalize = false;
+        } else {
+            out.push(c);
+            if c.is_alphanumeric() {
+                capitalize = false;
+            }
+        }
+
+        if matches!(c, '.' | '!' | '?' | '…') {
+            capitalize = true;
+        }
+    }
+
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// `template` with `{time}` (HH:MM), `{date}` (YYYY-MM-DD) and `{lang}`
+/// filled in, e.g. `"[{time}] "` gives `"[14:32] "`. Anything else,
+/// unknown placeholders included, is kept as is.
+pub fn expand_prefix(template: &str, now: NaiveDateTime, language: &str) -> String {
+    template
+        .replace("{time}", &now.format("%H:%M").to_string())
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{lang}", language)
+}
+
+/// Final touches on text about to be typed: with `trim`, trailing spaces
+/// and newlines are dropped (a trailing newline presses Enter, which sends
+/// the message in most chat apps); with `append_space`, a space follows, so
+/// phrases dictated one after another don't run together.
+pub fn finish_output(text: &str, trim: bool, append_space: bool) -> String {
+    let mut out = if trim { text.trim_end() } else { text }.to_string();
+    if append_space && !out.is_empty() && !out.ends_with(' ') {
+        out.push(' ');
+    }
+    out
+}
+
+/// `transcript` continued with the next recording's `burst`, as if both had
+/// been one dictation: a space between them (none after a newline or
+/// before punctuation), and `burst` capitalized as the sentence needs.
+/// Whisper capitalizes the start of every recording, so a burst that
+/// carries on a sentence gets its first word lowercased, unless that looks
+/// like it's meant (`I`, `NASA`).
+pub fn join_burst(transcript: &str, burst: &str) -> String {
+    let transcript = transcript.trim_end_matches(' ');
+    let burst = burst.trim_start();
+    if transcript.is_empty() || burst.is_empty() {
+        return format!("{transcript}{burst}");
+    }
+    let last = transcript
+        .trim_end_matches(['"', '\'', ')', '\u{201D}', '\u{00BB}'])
+        .chars()
+        .last();
+    let ends_sentence = matches!(last, Some('.' | '!' | '?' | '\u{2026}' | '\n') | None);
+    let burst = if ends_sentence {
+        capitalize_first(burst)
+    } else {
+        lowercase_first_word(burst)
+    };
+    let glued = transcript.ends_with('\n') || burst.starts_with([',', '.', ';', ':', '!', '?']);
+    let space = if glued { "" } else { " " };
+    format!("{transcript}{space}{burst}")
+}
+
+fn capitalize_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `text` with its first word lowercased if only its first letter is
+/// uppercase, so `The` changes but `I` and `NASA` don't.
+fn lowercase_first_word(text: &str) -> String {
+    let end = text.find(|c: char| !c.is_alphanumeric());
+    let mut chars = text[..end.unwrap_or(text.len())].chars();
+    let capitalized = chars.next().is_some_and(char::is_uppercase)
+        && !chars.as_str().is_empty()
+        && chars.all(char::is_lowercase);
+    if !capitalized {
+        return text.to_string();
+    }
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Built-in spoken commands. Newlines are turned into Enter keystrokes by the
+/// typing layer.
+pub fn default_substitutions() -> Vec<(String, String)> {
+    [
+        ("new paragraph", "\n\n"),
+        ("new line", "\n"),
+        ("nowy akapit", "\n\n"),
+        ("nowa linia", "\n"),
+        ("neuer Absatz", "\n\n"),
+        ("neue Zeile", "\n"),
+    ]
+    .into_iter()
+    .map(|(from, to)| (from.to_string(), to.to_string()))
+    .collect()
+}
+
+/// Apply `(phrase, replacement)` rules to `text`.
+///
+/// Phrases match whole words only, case-insensitively. Rules run in list
+/// order, each over the output of the previous one, and within a rule
+/// matches are found left to right without overlapping — so list longer
+/// phrases before shorter ones that they contain ("new paragraph" before
+/// "new"). Replacements are inserted verbatim and are not matched again by
+/// the same rule.
+///
+/// When a replacement is pure whitespace (e.g. a newline command), the
+/// spaces before it and the punctuation whisper tends to put after the
+/// spoken command are swallowed, so "Hello, new line, world" becomes
+/// "Hello,\nworld".
+pub fn substitute(text: &str, rules: &[(String, String)]) -> String {
+    let mut text = text.to_string();
+    for (phrase, replacement) in rules {
+        if phrase.trim().is_empty() {
+            continue;
+        }
+        text = substitute_one(&text, phrase, replacement);
+    }
+    text
+}
+
+fn substitute_one(text: &str, phrase: &str, replacement: &str) -> String {
+    let layout = replacement.chars().all(char::is_whitespace);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev: Option<char> = None;
+
+    while let Some(c) = rest.chars().next() {
+        let matched = if prev.is_some_and(char::is_alphanumeric) {
+            None
+        } else {
+            match_len(rest, phrase).filter(|&len| !starts_alphanumeric(&rest[len..]))
+        };
+        if let Some(len) = matched {
+            rest = &rest[len..];
+            if layout {
+                out.truncate(out.trim_end_matches(' ').len());
+                rest = rest.trim_start_matches([' ', ',', '.', ';', ':']);
+            }
+            out.push_str(replacement);
+            prev = replacement.chars().last();
+            continue;
+        }
+        out.push(c);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Byte range of the first match of `phrase` in `text`, matched like
+/// `substitute` does: whole words, ignoring case.
+pub fn find_phrase(text: &str, phrase: &str) -> Option<std::ops::Range<usize>> {
+    if phrase.trim().is_empty() {
+        return None;
+    }
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if !prev.is_some_and(char::is_alphanumeric) {
+            let len =
+                match_len(&text[i..], phrase).filter(|&len| !starts_alphanumeric(&text[i + len..]));
+            if let Some(len) = len {
+                return Some(i..i + len);
+            }
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+/// The language of the first `(phrase, language)` in `prefixes` that
+/// `text` opens with, and the rest of `text` after the phrase and the
+/// punctuation whisper puts after it. Phrases match like in `substitute`.
+pub fn split_language_prefix<'a>(
+    text: &'a str,
+    prefixes: &'a [(String, String)],
+) -> Option<(&'a str, &'a str)> {
+    // Only quotes or a dash may come first, not words.
+    let text = text.trim_start_matches(|c: char| !c.is_alphanumeric());
+    prefixes.iter().find_map(|(phrase, language)| {
+        let range = find_phrase(text, phrase.trim()).filter(|range| range.start == 0)?;
+        let rest = text[range.end..].trim_start_matches([' ', ',', '.', ';', ':', '!']);
+        Some((language.as_str(), rest))
+    })
+}
+
+/// A phrase of up to `LOOP_MAX_WORDS` words that `text` repeats at least
+/// `LOOP_MIN_REPEATS` times in a row, and how many times: whisper's way of
+/// getting stuck on noise ("Thank you. Thank you. ..."). Words compare
+/// ignoring case and the punctuation around them.
+pub fn repeated_phrase(text: &str) -> Option<(String, usize)> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+    for n in 1..=LOOP_MAX_WORDS {
+        let mut start = 0;
+        while start + n * LOOP_MIN_REPEATS <= words.len() {
+            let phrase = &words[start..start + n];
+            let repeats = words[start..]
+                .chunks_exact(n)
+                .take_while(|chunk| *chunk == phrase)
+                .count();
+            if repeats >= LOOP_MIN_REPEATS {
+                return Some((phrase.join(" "), repeats));
+            }
+            start += 1;
+        }
+    }
+    None
+}
+
+/// `text` cut to at most `max` characters, at the last space before the
+/// limit if there is one, so no word is typed half.
+pub fn truncate_chars(text: &str, max: usize) -> &str {
+    let Some((end, _)) = text.char_indices().nth(max) else {
+        return text;
+    };
+    let cut = text[..end]
+        .rfind(char::is_whitespace)
+        .filter(|&i| i > 0)
+        .unwrap_or(end);
+    text[..cut].trim_end()
+}
+
+fn starts_alphanumeric(s: &str) -> bool {
+    s.chars().next().is_some_and(char::is_alphanumeric)
+}
+
+/// Byte length of the prefix of `text` equal to `phrase`, ignoring case.
+fn match_len(text: &str, phrase: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for p in phrase.chars() {
+        let (_, c) = chars.next()?;
+        if !c.to_lowercase().eq(p.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tidy_collapses_spaces_and_tightens_punctuation() {
+        assert_eq!(
+            tidy("  hello   world ,  how are you ?  ", "en"),
+            "Hello world, how are you?"
+        );
+    }
+
+    #[test]
+    fn tidy_capitalizes_each_sentence() {
+        assert_eq!(
+            tidy("one. two! three? four… five", "en"),
+            "One. Two! Three? Four… Five"
+        );
+    }
+
+    #[test]
+    fn tidy_keeps_french_spacing_before_high_punctuation() {
+        assert_eq!(tidy("bonjour , ça va ?", "fr"), "Bonjour, ça va ?");
+    }
+
+    #[test]
+    fn tidy_leaves_uncased_scripts_alone() {
+        assert_eq!(tidy("你好。 世界", "zh"), "你好。 世界");
+    }
+
+    fn rules(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitute_matches_whole_words_ignoring_case() {
+        let rules = rules(&[("acme corp", "ACME Corp.")]);
+        assert_eq!(
+            substitute("call Acme Corp now", &rules),
+            "call ACME Corp. now"
+        );
+        assert_eq!(
+            substitute("megaacme corporate", &rules),
+            "megaacme corporate"
+        );
+    }
+
+    #[test]
+    fn substitute_swallows_spacing_around_layout_commands() {
+        let rules = default_substitutions();
+        assert_eq!(
+            substitute("Hello, new line, world", &rules),
+            "Hello,\nworld"
+        );
+        assert_eq!(
+            substitute("Intro. New paragraph. Body", &rules),
+            "Intro.\n\nBody"
+        );
+    }
+
+    #[test]
+    fn substitute_runs_rules_in_order() {
+        let longer_first = rules(&[("new paragraph", "¶"), ("new", "fresh")]);
+        assert_eq!(substitute("a new paragraph", &longer_first), "a ¶");
+        let shorter_first = rules(&[("new", "fresh"), ("new paragraph", "¶")]);
+        assert_eq!(
+            substitute("a new paragraph", &shorter_first),
+            "a fresh paragraph"
+        );
+    }
+
+    #[test]
+    fn truncate_cuts_at_the_last_space() {
+        assert_eq!(truncate_chars("hello brave new world", 13), "hello brave");
+        assert_eq!(truncate_chars("hello world", 11), "hello world");
+        assert_eq!(truncate_chars("hello world", 50), "hello world");
+        // Counted in characters: "żółw" is 4 of them but 7 bytes.
+        assert_eq!(truncate_chars("żółw żółw", 6), "żółw");
+    }
+
+    #[test]
+    fn truncate_cuts_a_single_long_word() {
+        assert_eq!(truncate_chars("abcdefgh", 5), "abcde");
+        assert_eq!(truncate_chars("abc", 0), "");
+    }
+
+    #[test]
+    fn repeated_phrase_finds_a_looped_phrase() {
+        let repeats = LOOP_MIN_REPEATS + 2;
+        let looped = format!("Okay. {}", "Thank you. ".repeat(repeats));
+        assert_eq!(
+            repeated_phrase(&looped),
+            Some(("thank you".to_string(), repeats))
+        );
+        let words = ["no"; LOOP_MIN_REPEATS].join(" ");
+        assert_eq!(
+            repeated_phrase(&words),
+            Some(("no".to_string(), LOOP_MIN_REPEATS))
+        );
+    }
+
+    #[test]
+    fn repeated_phrase_ignores_ordinary_repetition() {
+        assert_eq!(repeated_phrase("no, no, I said no"), None);
+        let words = ["no"; LOOP_MIN_REPEATS - 1].join(" ");
+        assert_eq!(repeated_phrase(&words), None);
+        assert_eq!(repeated_phrase(""), None);
+    }
+
+    #[test]
+    fn finish_output_trims_and_appends() {
+        let text = "Send it.  \n";
+        assert_eq!(finish_output(text, false, false), "Send it.  \n");
+        assert_eq!(finish_output(text, true, false), "Send it.");
+        assert_eq!(finish_output(text, true, true), "Send it. ");
+        assert_eq!(finish_output(text, false, true), "Send it.  \n ");
+    }
+
+    #[test]
+    fn finish_output_appends_one_space_at_most() {
+        assert_eq!(finish_output("Done ", false, true), "Done ");
+        assert_eq!(finish_output("", false, true), "");
+        assert_eq!(finish_output(" \n", true, true), "");
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 3, 7)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn prefix_placeholders_expand_from_the_clock() {
+        assert_eq!(
+            expand_prefix("[{date} {time}] ({lang}) ", at(9, 5), "pl"),
+            "[2026-03-07 09:05] (pl) "
+        );
+        assert_eq!(
+            expand_prefix("{time}{time}", at(23, 59), "en"),
+            "23:5923:59"
+        );
+    }
+
+    #[test]
+    fn prefix_keeps_other_text_as_is() {
+        assert_eq!(expand_prefix("", at(9, 5), "en"), "");
+        assert_eq!(
+            expand_prefix("{user} says: ", at(9, 5), "en"),
+            "{user} says: "
+        );
+    }
+
+    fn language_prefixes() -> Vec<(String, String)> {
+        rules(&[(" po polsku ", "pl"), ("in English", "en")])
+    }
+
+    #[test]
+    fn language_prefix_is_stripped() {
+        let prefixes = language_prefixes();
+        assert_eq!(
+            split_language_prefix("Po polsku, dzień dobry.", &prefixes),
+            Some(("pl", "dzień dobry."))
+        );
+        assert_eq!(
+            split_language_prefix("\"In English: hello there!\"", &prefixes),
+            Some(("en", "hello there!\""))
+        );
+        assert_eq!(
+            split_language_prefix("In English.", &prefixes),
+            Some(("en", ""))
+        );
+    }
+
+    #[test]
+    fn language_prefix_must_open_the_text() {
+        let prefixes = language_prefixes();
+        for text in [
+            "I said it in English.",
+            "In Englishman's words",
+            "Polsku po",
+            "",
+        ] {
+            assert_eq!(split_language_prefix(text, &prefixes), None, "{text:?}");
+        }
+        assert_eq!(split_language_prefix("In English", &[]), None);
+    }
+
+    #[test]
+    fn find_phrase_matches_whole_words_ignoring_case() {
+        assert_eq!(find_phrase("Press new line now", "new line"), Some(6..14));
+        assert_eq!(find_phrase("NEW LINE", "new line"), Some(0..8));
+        assert_eq!(find_phrase("Zażółć nowa linia", "nowa linia"), Some(11..21));
+        assert_eq!(find_phrase("renew lines", "new line"), None);
+        assert_eq!(find_phrase("a new liner", "new line"), None);
+        assert_eq!(find_phrase("anything", " "), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Crash recovery: while recording, captured audio is spooled to a WAV file
+//! on disk. A clean stop deletes it; if the app dies mid-recording, the
+//! next launch finds it and can transcribe it.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the spool is flushed to the OS and its header sizes patched.
+/// A crash loses at most this much audio.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const HEADER_LEN: u32 = 44;
+
+fn data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("voice-to-text")
+}
+
+/// The spool of the recording in progress.
+fn spool_path() -> PathBuf {
+    data_dir().join("recording.wav")
+}
+
+/// A spool left behind by a crash, moved aside so the next recording
+/// doesn't overwrite it.
+fn orphan_path() -> PathBuf {
+    data_dir().join("recovered.wav")
+}
+
+/// Incremental WAV writer for one recording. The audio callback only sends
+/// chunks over a channel; a background thread does the file I/O. Dropping
+/// the spool finishes the writer and deletes the file.
+pub struct Spool {
+    tx: Option<mpsc::Sender<Vec<f32>>>,
+    thread: Option<JoinHandle<()>>,
+    path: PathBuf,
+}
+
+impl Spool {
+    /// Start a new spool of mono f32 samples at `sample_rate`.
+    pub fn create(sample_rate: u32) -> Result<Self> {
+        let path = spool_path();
+        std::fs::create_dir_all(data_dir())?;
+        let mut file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        file.write_all(&header(sample_rate, 0))?;
+
+        let (tx, rx) = mpsc::channel();
+        let writer_path = path.clone();
+        let thread = std::thread::spawn(move || {
+            if let Err(e) = run_writer(rx, file) {
+                log::error!("Recording spool {}: {e}", writer_path.display());
+            }
+        });
+        Ok(Self {
+            tx: Some(tx),
+            thread: Some(thread),
+            path,
+        })
+    }
+
+    /// Sender for the audio callback. Never blocks.
+    pub fn sender(&self) -> Option<mpsc::Sender<Vec<f32>>> {
+        self.tx.clone()
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        // The writer exits once every sender (ours and the stream's) is gone.
+        self.tx.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Failed to remove {}: {e}", self.path.display());
+        }
+    }
+}
+
+fn run_writer(rx: mpsc::Receiver<Vec<f32>>, file: File) -> Result<()> {
+    let mut out = BufWriter::new(file);
+    let mut data_len: u32 = 0;
+    let mut last_flush = Instant::now();
+    while let Ok(chunk) = rx.recv() {
+        for sample in &chunk {
+            out.write_all(&sample.to_le_bytes())?;
+        }
+        data_len = data_len.saturating_add(chunk.len() as u32 * 4);
+        if last_flush.elapsed() >= FLUSH_INTERVAL {
+            patch_sizes(&mut out, data_len)?;
+            last_flush = Instant::now();
+        }
+    }
+    patch_sizes(&mut out, data_len)
+}
+
+/// Flush and fill in the RIFF and data chunk sizes, so the file is a valid
+/// WAV up to this point.
+fn patch_sizes(out: &mut BufWriter<File>, data_len: u32) -> Result<()> {
+    out.flush()?;
+    let file = out.get_mut();
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(HEADER_LEN - 8 + data_len).to_le_bytes())?;
+    file.seek(SeekFrom::Start(40))?;
+    file.write_all(&data_len.to_le_bytes())?;
+    file.seek(SeekFrom::End(0))?;
+    Ok(())
+}
+
+/// Canonical 44-byte header for mono 32-bit float PCM.
+fn header(sample_rate: u32, data_len: u32) -> Vec<u8> {
+    let mut h = Vec::with_capacity(HEADER_LEN as usize);
+    h.extend_from_slice(b"RIFF");
+    h.extend_from_slice(&(HEADER_LEN - 8 + data_len).to_le_bytes());
+    h.extend_from_slice(b"WAVEfmt ");
+    h.extend_from_slice(&16u32.to_le_bytes());
+    h.extend_from_slice(&3u16.to_le_bytes()); // IEEE float
+    h.extend_from_slice(&1u16.to_le_bytes()); // mono
+    h.extend_from_slice(&sample_rate.to_le_bytes());
+    h.extend_from_slice(&(sample_rate * 4).to_le_bytes()); // byte rate
+    h.extend_from_slice(&4u16.to_le_bytes()); // block align
+    h.extend_from_slice(&32u16.to_le_bytes()); // bits per sample
+    h.extend_from_slice(b"data");
+    h.extend_from_slice(&data_len.to_le_bytes());
+    h
+}
+
+/// Move a spool left over from a crashed session aside, and report how many
+/// seconds of orphaned audio are waiting (including one adopted earlier).
+pub fn adopt_orphan() -> Option<f32> {
+    let spool = spool_path();
+    if spool.exists() {
+        log::warn!("Found an unfinished recording from a previous session");
+        if let Err(e) = std::fs::rename(&spool, orphan_path()) {
+            log::error!("Failed to keep {}: {e}", spool.display());
+        }
+    }
+    orphan_duration()
+}
+
+/// Length in seconds of the orphaned recording waiting to be recovered.
+pub fn orphan_duration() -> Option<f32> {
+    let mut file = File::open(orphan_path()).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut header = [0u8; HEADER_LEN as usize];
+    file.read_exact(&mut header).ok()?;
+    let sample_rate = parse_sample_rate(&header)?;
+    let samples = len.saturating_sub(HEADER_LEN as u64) / 4;
+    Some(samples as f32 / sample_rate as f32)
+}
+
+fn parse_sample_rate(header: &[u8]) -> Option<u32> {
+    if header.len() < HEADER_LEN as usize || &header[..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+    let rate = u32::from_le_bytes(header[24..28].try_into().ok()?);
+    (rate > 0).then_some(rate)
+}
+
+/// The orphaned recording as `(samples, sample_rate)`, if there is one.
+/// The header's sizes are ignored, since a crash can leave them stale; all
+/// complete samples in the file are returned.
+pub fn read_orphan() -> Result<Option<(Vec<f32>, u32)>> {
+    let bytes = match std::fs::read(orphan_path()) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let sample_rate = parse_sample_rate(&bytes).context("Recovered recording is not a WAV file")?;
+    let samples = bytes[HEADER_LEN as usize..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    Ok(Some((samples, sample_rate)))
+}
+
+/// Delete the orphaned recording, e.g. once it was transcribed.
+pub fn discard_orphan() {
+    match std::fs::remove_file(orphan_path()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            log::error!("Failed to remove recovered recording: {e}");
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,121 @@
+//! Optional local HTTP API so external tools (a Stream Deck, a shell script)
+//! can drive recording without the global hotkey.
+//!
+//! The server only binds to 127.0.0.1 and every request must carry
+//! `Authorization: Bearer <token>`, so arbitrary local processes can't use it.
+//!
+//! | Request        | Effect                              |
+//! |----------------|-------------------------------------|
+//! | `GET /status`  | nothing                             |
+//! | `POST /start`  | start recording if idle             |
+//! | `POST /stop`   | stop recording (final pass) if busy |
+//! | `POST /toggle` | same as the hotkey                  |
+//!
+//! Every authorized request is answered with the current status as JSON.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// What a request asks the app to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Status,
+    Start,
+    Stop,
+    Toggle,
+}
+
+/// A running server; dropping it shuts the listener down.
+pub struct HttpServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+    port: u16,
+    token: String,
+}
+
+impl HttpServer {
+    /// Listen on `127.0.0.1:port` and answer every authorized request with
+    /// `handle(action)` serialized as JSON.
+    pub fn start<T, F>(port: u16, token: &str, handle: F) -> Result<Self>
+    where
+        T: Serialize,
+        F: Fn(Action) -> T + Send + 'static,
+    {
+        if token.trim().is_empty() {
+            anyhow::bail!("The HTTP API needs a non-empty token");
+        }
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|e| anyhow::anyhow!("Failed to listen on 127.0.0.1:{port}: {e}"))?;
+        let server = Arc::new(server);
+
+        let thread = {
+            let server = server.clone();
+            let expected = format!("Bearer {token}");
+            std::thread::spawn(move || {
+                // Ends once `unblock` is called from `drop`.
+                for request in server.incoming_requests() {
+                    let response = respond(&request, &expected, &handle);
+                    let _ = request.respond(response);
+                }
+            })
+        };
+
+        log::info!("HTTP API listening on 127.0.0.1:{port}");
+        Ok(Self {
+            server,
+            thread: Some(thread),
+            port,
+            token: token.to_string(),
+        })
+    }
+
+    /// Whether this server already runs with the given port and token.
+    pub fn matches(&self, port: u16, token: &str) -> bool {
+        self.port == port && self.token == token
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        log::info!("HTTP API on port {} stopped", self.port);
+    }
+}
+
+fn respond<T, F>(request: &Request, expected: &str, handle: &F) -> Response<Cursor<Vec<u8>>>
+where
+    T: Serialize,
+    F: Fn(Action) -> T,
+{
+    let authorized = request
+        .headers()
+        .iter()
+        .any(|h| h.field.equiv("Authorization") && h.value.as_str() == expected);
+    if !authorized {
+        return json(401, &serde_json::json!({ "error": "unauthorized" }));
+    }
+
+    let action = match (request.method(), request.url()) {
+        (Method::Get, "/status") => Action::Status,
+        (Method::Post, "/start") => Action::Start,
+        (Method::Post, "/stop") => Action::Stop,
+        (Method::Post, "/toggle") => Action::Toggle,
+        _ => return json(404, &serde_json::json!({ "error": "not found" })),
+    };
+    json(200, &handle(action))
+}
+
+fn json(code: u16, body: &impl Serialize) -> Response<Cursor<Vec<u8>>> {
+    let header =
+        Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    Response::from_data(serde_json::to_vec(body).unwrap_or_default())
+        .with_status_code(code)
+        .with_header(header)
+}
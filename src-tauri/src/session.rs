@@ -0,0 +1,127 @@
+//! Dictation sessions: the text of several recordings gathered into one
+//! transcript, for long documents dictated in bursts. The open session is
+//! kept in a file in the data dir as it grows, so quitting or a crash
+//! doesn't lose it; the next run carries on with it.
+
+use crate::postprocess;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Everything dictated so far, joined with `postprocess::join_burst`.
+    pub text: String,
+    /// Recordings that added to it.
+    pub recordings: usize,
+}
+
+fn session_path() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("voice-to-text")
+        .join("session.json")
+}
+
+impl Session {
+    /// The session a previous run left open. One whose file can't be read
+    /// is set aside next to it rather than deleted, and not carried on.
+    pub fn restore() -> Option<Self> {
+        let path = session_path();
+        let data = std::fs::read(&path).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                log::warn!("Dictation session in {} unreadable: {e}", path.display());
+                let _ = std::fs::rename(&path, path.with_extension("json.bad"));
+                None
+            }
+        }
+    }
+
+    /// Add the text of one recording and save the session.
+    pub fn append(&mut self, burst: &str) {
+        if !self.add(burst) {
+            return;
+        }
+        if let Err(e) = self.save() {
+            log::error!("Dictation session not saved: {e:#}");
+        }
+    }
+
+    /// `append` without saving. A blank `burst` isn't added; returns
+    /// whether it was.
+    fn add(&mut self, burst: &str) -> bool {
+        if burst.trim().is_empty() {
+            return false;
+        }
+        self.text = postprocess::join_burst(&self.text, burst);
+        self.recordings += 1;
+        true
+    }
+
+    /// Save the session, as it's begun and after every recording.
+    pub fn save(&self) -> Result<()> {
+        let path = session_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_vec(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// End the session: its file is deleted.
+    pub fn close(self) -> Self {
+        if let Err(e) = std::fs::remove_file(session_path()) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to delete the dictation session file: {e}");
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_of(bursts: &[&str]) -> Session {
+        let mut session = Session::default();
+        for burst in bursts {
+            session.add(burst);
+        }
+        session
+    }
+
+    #[test]
+    fn bursts_accumulate() {
+        let session = session_of(&["First point.", "Second point."]);
+        assert_eq!(session.text, "First point. Second point.");
+        assert_eq!(session.recordings, 2);
+    }
+
+    #[test]
+    fn blank_bursts_are_not_recordings() {
+        let session = session_of(&["Hello.", "  ", ""]);
+        assert_eq!(session.text, "Hello.");
+        assert_eq!(session.recordings, 1);
+    }
+
+    #[test]
+    fn a_burst_continuing_a_sentence_is_lowercased() {
+        let session = session_of(&["We met at", "The corner and", "I left early"]);
+        assert_eq!(session.text, "We met at the corner and I left early");
+    }
+
+    #[test]
+    fn a_burst_after_a_sentence_is_capitalized() {
+        let session = session_of(&["Done!", "next one", "okay"]);
+        assert_eq!(session.text, "Done! Next one okay");
+    }
+
+    #[test]
+    fn punctuation_and_newlines_get_no_space() {
+        let session = session_of(&["Wait", ", what?", "Line one.\n", "line two"]);
+        assert_eq!(session.text, "Wait, what? Line one.\nLine two");
+    }
+}
@@ -0,0 +1,594 @@
+//! Pure text bookkeeping for streaming commits, final-pass reconciliation
+//! and stitching chunked transcriptions back together.
+
+use std::cmp::Reverse;
+
+/// Find byte length of the common prefix between two strings.
+pub fn stable_prefix_len(a: &str, b: &str) -> usize {
+    let mut len = 0;
+    for (ca, cb) in a.chars().zip(b.chars()) {
+        if ca != cb {
+            break;
+        }
+        len += ca.len_utf8();
+    }
+    len
+}
+
+/// Text to type on a streaming tick: the part of `curr` confirmed by `prev`
+/// that extends what has already been typed, and lies within its first
+/// `confident` bytes (see `confident_words`). `None` when nothing new is
+/// confirmed or the hypothesis no longer starts with the typed text.
+pub fn confirmed_suffix<'a>(
+    typed: &str,
+    prev: &str,
+    curr: &'a str,
+    confident: usize,
+) -> Option<&'a str> {
+    let stable = stable_prefix_len(prev, curr).min(confident);
+    if stable <= typed.len() || !curr.starts_with(typed) {
+        return None;
+    }
+    Some(&curr[typed.len()..stable])
+}
+
+/// How many leading words have a `confidence` of at least `threshold`:
+/// two ticks agreeing on a word whisper itself doubts isn't enough to
+/// commit it.
+pub fn confident_words(confidence: &[f32], threshold: f32) -> usize {
+    confidence
+        .iter()
+        .position(|&p| p < threshold)
+        .unwrap_or(confidence.len())
+}
+
+/// Whether `c` ends a sentence in `language` (a whisper language code).
+fn ends_sentence(c: char, language: &str) -> bool {
+    match c {
+        '.' | '!' | '?' | '…' => true,
+        // CJK full-width marks
+        '。' | '！' | '？' => true,
+        // Devanagari and related scripts, Arabic question mark, Urdu full stop
+        '।' | '॥' | '؟' | '۔' => true,
+        // Armenian, Ethiopic, Burmese
+        '։' | '።' | '။' => true,
+        // Greek writes its question mark like a semicolon.
+        ';' => language == "el",
+        _ => false,
+    }
+}
+
+/// Abbreviations (lowercase, without their final dot) whose dot doesn't end
+/// a sentence, in the languages the app is mostly used with.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "st", "vs", "e.g", "i.e", "np", "tj", "tzn", "m.in", "ul",
+    "mgr", "inż", "wg", "nr",
+];
+
+/// Whether the dot after `before` belongs to an abbreviation or an initial
+/// ("J. Smith") rather than ending a sentence.
+fn is_abbreviation(before: &str) -> bool {
+    let word = before
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or("")
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    let mut chars = word.chars();
+    let initial = chars.next().is_some_and(char::is_alphabetic) && chars.next().is_none();
+    initial || ABBREVIATIONS.contains(&word.as_str())
+}
+
+/// Byte offsets just past each complete sentence of `text`, in order.
+///
+/// A sentence ends at one of `language`'s end marks, possibly followed by
+/// closing quotes or brackets, and then whitespace. Full-width CJK marks
+/// need no whitespace after them. A mark at the very end of `text` doesn't
+/// count yet: it may still turn out to be a decimal point or the start of
+/// an ellipsis. Neither does the dot of an abbreviation or an initial.
+fn sentence_ends(text: &str, language: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !ends_sentence(c, language) || (c == '.' && is_abbreviation(&text[..i])) {
+            continue;
+        }
+        let full_width = matches!(c, '。' | '！' | '？');
+        while let Some(&(_, next)) = chars.peek() {
+            if !matches!(next, '"' | '\'' | '”' | '’' | '»' | ')' | ']' | '」' | '』') {
+                break;
+            }
+            chars.next();
+        }
+        match chars.peek() {
+            Some(&(at, next)) if next.is_whitespace() || full_width => ends.push(at),
+            _ => {}
+        }
+    }
+    ends
+}
+
+/// Byte length of `text` up to the end of its last complete sentence (see
+/// `sentence_ends`), or 0 if none has ended yet.
+pub fn sentence_prefix_len(text: &str, language: &str) -> usize {
+    sentence_ends(text, language).last().copied().unwrap_or(0)
+}
+
+/// `text` cut into sentences (see `sentence_ends`), each keeping the
+/// whitespace after it, so the pieces add up to `text`. Whatever follows
+/// the last end mark is a piece of its own.
+pub fn split_sentences<'a>(text: &'a str, language: &str) -> Vec<&'a str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for end in sentence_ends(text, language) {
+        let next = text[end..]
+            .find(|c: char| !c.is_whitespace())
+            .map_or(text.len(), |n| end + n);
+        pieces.push(&text[start..next]);
+        start = next;
+    }
+    if start < text.len() {
+        pieces.push(&text[start..]);
+    }
+    pieces
+}
+
+/// Byte length of the first `n` words of `text` (all of it if it has fewer).
+pub fn words_prefix_len(text: &str, n: usize) -> usize {
+    after_nth_word(text, n).unwrap_or(text.len())
+}
+
+/// The last `n` words of `text`.
+pub fn last_words(text: &str, n: usize) -> &str {
+    let total = text.split_whitespace().count();
+    text[words_prefix_len(text, total.saturating_sub(n))..].trim()
+}
+
+/// Join the transcriptions of two overlapping chunks of audio: `next`
+/// starts with audio `prev` already ended with, so the words heard twice
+/// are kept only once.
+///
+/// The overlap is the longest run of `prev`'s last words (at most
+/// `max_words`) that `next` starts with, compared ignoring case and
+/// punctuation. Words cut in half at a chunk boundary tend to come out
+/// garbled, so the run may also leave out `prev`'s last word or `next`'s
+/// first one, if it's at least two words long; the garbled word is dropped
+/// along with the duplicates. If nothing matches, the texts are just
+/// concatenated: a repeated word is better than a lost one.
+pub fn stitch(prev: &str, next: &str, max_words: usize) -> String {
+    let prev_words: Vec<&str> = prev.split_whitespace().collect();
+    let next_words: Vec<&str> = next.split_whitespace().collect();
+    let skips = [(0, 0), (1, 0), (0, 1), (1, 1)];
+    let best = best_overlap(prev, next, max_words, &skips);
+
+    let (kept_prev, next_from) = match best {
+        Some((n, skip_prev, skip_next)) => (prev_words.len() - skip_prev, skip_next + n),
+        None => (prev_words.len(), 0),
+    };
+    prev_words[..kept_prev]
+        .iter()
+        .chain(&next_words[next_from..])
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// How many leading words of a streaming window's transcription `window`
+/// to skip. The window's audio reaches back before the end of the
+/// already-typed `base`, so `window` may begin with `base`'s last words
+/// again, possibly after a garbled word cut at the window start. Matches
+/// like `stitch` (at most `max_words`), except that `base` is never
+/// shortened. 0 if nothing repeats.
+pub fn repeated_words(base: &str, window: &str, max_words: usize) -> usize {
+    best_overlap(base, window, max_words, &[(0, 0), (0, 1)])
+        .map_or(0, |(n, _, skip_next)| skip_next + n)
+}
+
+/// The longest run of `prev`'s last words (at most `max_words`) that
+/// `next` starts with, as (words matched, trailing `prev` words skipped,
+/// leading `next` words skipped), trying each of `skips`. The longest
+/// match wins, then the one skipping fewer words. A run after skipped
+/// words must be at least two words long.
+fn best_overlap(
+    prev: &str,
+    next: &str,
+    max_words: usize,
+    skips: &[(usize, usize)],
+) -> Option<(usize, usize, usize)> {
+    let prev_keys: Vec<String> = prev.split_whitespace().map(word_key).collect();
+    let next_keys: Vec<String> = next.split_whitespace().map(word_key).collect();
+    skips
+        .iter()
+        .filter_map(|&(skip_prev, skip_next)| {
+            let min_len = if skip_prev + skip_next == 0 { 1 } else { 2 };
+            let prev_end = prev_keys.len().checked_sub(skip_prev)?;
+            let next_rest = next_keys.get(skip_next..)?;
+            let longest = max_words.min(prev_end).min(next_rest.len());
+            let n = (min_len..=longest).rev().find(|&n| {
+                let tail = &prev_keys[prev_end - n..prev_end];
+                tail == &next_rest[..n] && tail.iter().all(|key| !key.is_empty())
+            })?;
+            Some((n, skip_prev, skip_next))
+        })
+        .max_by_key(|&(n, skip_prev, skip_next)| (n, Reverse(skip_prev + skip_next)))
+}
+
+/// A word as compared by `stitch`: lowercase, letters and digits only.
+fn word_key(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Edit that brings already-typed text in line with the final transcription.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Reconciliation {
+    /// Characters to delete from the end of the typed text first.
+    pub backspaces: usize,
+    /// Text to type afterwards.
+    pub insert: String,
+}
+
+/// Work out what to type after the final pass, given the exact text that
+/// streaming already typed.
+///
+/// - If the final text extends the typed text, only the new suffix is typed.
+/// - If they diverge and `backspace` is allowed, the mismatched tail of the
+///   typed text is deleted and replaced with the final wording.
+/// - Otherwise the typed text is kept as-is and words are aligned by count:
+///   whatever the final text has beyond the number of words already typed is
+///   appended. A shorter final text adds nothing.
+pub fn reconcile_final(typed: &str, final_text: &str, backspace: bool) -> Reconciliation {
+    if let Some(rest) = final_text.strip_prefix(typed) {
+        return Reconciliation {
+            backspaces: 0,
+            insert: rest.to_string(),
+        };
+    }
+
+    if backspace {
+        let common = stable_prefix_len(typed, final_text);
+        return Reconciliation {
+            backspaces: typed[common..].chars().count(),
+            insert: final_text[common..].to_string(),
+        };
+    }
+
+    let typed_words = typed.split_whitespace().count();
+    let insert = after_nth_word(final_text, typed_words)
+        .map(|start| final_text[start..].to_string())
+        .unwrap_or_default();
+    Reconciliation {
+        backspaces: 0,
+        insert,
+    }
+}
+
+/// Byte offset just past the `n`-th whitespace-separated word of `text`
+/// (so the remainder keeps its leading separator), or `None` if nothing
+/// but whitespace follows it.
+fn after_nth_word(text: &str, n: usize) -> Option<usize> {
+    if n == 0 {
+        return (!text.trim().is_empty()).then_some(0);
+    }
+    let mut seen = 0;
+    let mut in_word = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if in_word {
+                seen += 1;
+                in_word = false;
+                if seen == n {
+                    return (!text[i..].trim().is_empty()).then_some(i);
+                }
+            }
+        } else {
+            in_word = true;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(backspaces: usize, insert: &str) -> Reconciliation {
+        Reconciliation {
+            backspaces,
+            insert: insert.to_string(),
+        }
+    }
+
+    #[test]
+    fn reconcile_shorter_final() {
+        let typed = "hello world again";
+        assert_eq!(reconcile_final(typed, "hello world", true), edit(6, ""));
+        assert_eq!(reconcile_final(typed, "hello world", false), edit(0, ""));
+    }
+
+    #[test]
+    fn reconcile_longer_final() {
+        let typed = "hello world";
+        let final_text = "hello world again";
+        assert_eq!(reconcile_final(typed, final_text, true), edit(0, " again"));
+        assert_eq!(reconcile_final(typed, final_text, false), edit(0, " again"));
+    }
+
+    #[test]
+    fn reconcile_diverging_final() {
+        let typed = "hello word";
+        let final_text = "hello world today";
+        assert_eq!(
+            reconcile_final(typed, final_text, true),
+            edit(1, "ld today")
+        );
+        assert_eq!(reconcile_final(typed, final_text, false), edit(0, " today"));
+    }
+
+    #[test]
+    fn reconcile_counts_backspaces_in_characters() {
+        let reconciled = reconcile_final("zażółć gęślą", "zażółć jaźń", true);
+        assert_eq!(reconciled, edit(5, "jaźń"));
+    }
+
+    #[test]
+    fn stitch_drops_the_repeated_words() {
+        assert_eq!(
+            stitch(
+                "we went to the park today",
+                "the park today and then home",
+                5
+            ),
+            "we went to the park today and then home"
+        );
+        // Compared ignoring case and punctuation; `prev`'s spelling is kept.
+        assert_eq!(
+            stitch("to the park.", "The park, and home", 5),
+            "to the park. and home"
+        );
+    }
+
+    #[test]
+    fn stitch_drops_a_garbled_boundary_word() {
+        assert_eq!(
+            stitch("we went to the par", "to the park and home", 5),
+            "we went to the park and home"
+        );
+        assert_eq!(
+            stitch("we went to the park", "ark the park and home", 5),
+            "we went to the park and home"
+        );
+    }
+
+    #[test]
+    fn stitch_concatenates_without_an_overlap() {
+        assert_eq!(
+            stitch("hello there", "general kenobi", 5),
+            "hello there general kenobi"
+        );
+        assert_eq!(stitch("", "general kenobi", 5), "general kenobi");
+        assert_eq!(stitch("hello there", "", 5), "hello there");
+    }
+
+    #[test]
+    fn stitch_overlap_is_capped_at_max_words() {
+        let (prev, next) = ("we went to the park", "the park and home");
+        assert_eq!(stitch(prev, next, 2), "we went to the park and home");
+        assert_eq!(
+            stitch(prev, next, 1),
+            "we went to the park the park and home"
+        );
+    }
+
+    #[test]
+    fn confident_words_stop_at_the_first_doubt() {
+        assert_eq!(confident_words(&[0.9, 0.8, 0.3, 0.9], 0.5), 2);
+        assert_eq!(confident_words(&[0.2, 0.9], 0.5), 0);
+        assert_eq!(confident_words(&[0.9, 0.5], 0.5), 2);
+        assert_eq!(confident_words(&[], 0.5), 0);
+    }
+
+    #[test]
+    fn confirmed_suffix_is_cut_at_the_confident_prefix() {
+        let prev = "we went to the park and";
+        let curr = "we went to the park today";
+        // Stable through "park"; confident through "went".
+        let confident = words_prefix_len(curr, confident_words(&[0.9, 0.9, 0.2, 0.9], 0.5));
+        assert_eq!(confirmed_suffix("", prev, curr, confident), Some("we went"));
+        assert_eq!(confirmed_suffix("we went", prev, curr, confident), None);
+        // Fully confident: the stable prefix decides.
+        let confident = words_prefix_len(curr, confident_words(&[0.9; 6], 0.5));
+        assert_eq!(
+            confirmed_suffix("we went", prev, curr, confident),
+            Some(" to the park ")
+        );
+    }
+
+    #[test]
+    fn confirmed_suffix_needs_the_typed_text() {
+        assert_eq!(
+            confirmed_suffix("we ran", "we went on", "we went on", 10),
+            None
+        );
+    }
+
+    #[test]
+    fn sentence_prefix_ends_after_the_last_complete_sentence() {
+        let text = "It works. Does it? Then go";
+        assert_eq!(
+            &text[..sentence_prefix_len(text, "en")],
+            "It works. Does it?"
+        );
+        assert_eq!(sentence_prefix_len("Still going", "en"), 0);
+        // Closing quotes stay with their sentence.
+        let quoted = "He said \"stop.\" Then";
+        assert_eq!(
+            &quoted[..sentence_prefix_len(quoted, "en")],
+            "He said \"stop.\""
+        );
+    }
+
+    #[test]
+    fn sentence_end_at_the_end_of_the_text_is_held_back() {
+        // The dot may still become "3.5" or "...".
+        assert_eq!(sentence_prefix_len("It costs 3.", "en"), 0);
+        assert_eq!(sentence_prefix_len("Done. Wait.", "en"), 5);
+    }
+
+    #[test]
+    fn abbreviations_and_initials_dont_end_sentences() {
+        assert_eq!(sentence_prefix_len("I met dr. Nowak today", "pl"), 0);
+        assert_eq!(sentence_prefix_len("Ask J. Smith now", "en"), 0);
+        assert_eq!(sentence_prefix_len("Np. tak. I dalej", "pl"), 8);
+    }
+
+    #[test]
+    fn sentence_marks_follow_the_language() {
+        assert_eq!(sentence_prefix_len("你好。再见", "zh"), "你好。".len());
+        assert_eq!(sentence_prefix_len("नमस्ते। आप", "hi"), "नमस्ते।".len());
+        // Greek's question mark; elsewhere `;` only separates clauses.
+        assert_eq!(
+            sentence_prefix_len("Τι κάνεις; Καλά", "el"),
+            "Τι κάνεις;".len()
+        );
+        assert_eq!(sentence_prefix_len("first; second", "en"), 0);
+    }
+
+    #[test]
+    fn split_sentences_keeps_every_byte() {
+        let text = "One. Two!  Three";
+        assert_eq!(split_sentences(text, "en"), ["One. ", "Two!  ", "Three"]);
+        assert_eq!(split_sentences(text, "en").concat(), text);
+    }
+
+    #[test]
+    fn words_prefix_counts_whole_words() {
+        let text = "  one two  three";
+        assert_eq!(&text[..words_prefix_len(text, 2)], "  one two");
+        assert_eq!(words_prefix_len(text, 0), 0);
+        assert_eq!(words_prefix_len(text, 5), text.len());
+        assert_eq!(last_words(text, 2), "two  three");
+    }
+
+    #[test]
+    fn repeated_words_skips_the_overlap() {
+        let base = "we went to the park";
+        let window = "the park and then home";
+        let n = repeated_words(base, window, 5);
+        assert_eq!(n, 2);
+        // What the tick appends to the typed text after skipping them.
+        assert_eq!(
+            window[words_prefix_len(window, n)..].trim_start(),
+            "and then home"
+        );
+    }
+
+    #[test]
+    fn repeated_words_skips_a_garbled_first_word() {
+        assert_eq!(
+            repeated_words("we went to the park", "ark the park and home", 5),
+            3
+        );
+    }
+
+    #[test]
+    fn repeated_words_never_shortens_the_base() {
+        // The base's garbled last word has been typed; only stitching the
+        // final pass may drop it.
+        assert_eq!(
+            repeated_words("we went to the par", "to the park and home", 5),
+            0
+        );
+    }
+
+    #[test]
+    fn repeated_words_without_an_overlap() {
+        assert_eq!(repeated_words("we went to the park", "and then home", 5), 0);
+        assert_eq!(repeated_words("", "and then home", 5), 0);
+        assert_eq!(repeated_words("the park", "the park and home", 1), 0);
+    }
+
+    #[test]
+    fn split_sentences_at_mixed_punctuation() {
+        assert_eq!(
+            split_sentences("Wait... What?! Yes… Go.", "en"),
+            ["Wait... ", "What?! ", "Yes… ", "Go."]
+        );
+        assert_eq!(
+            split_sentences("(Really?) \"Yes.\" No", "en"),
+            ["(Really?) ", "\"Yes.\" ", "No"]
+        );
+        assert_eq!(split_sentences("One.\nTwo.", "en"), ["One.\n", "Two."]);
+        assert_eq!(split_sentences("你好。再见。", "zh"), ["你好。", "再见。"]);
+        assert!(split_sentences("", "en").is_empty());
+    }
+
+    #[test]
+    fn split_sentences_past_abbreviations_and_numbers() {
+        assert_eq!(
+            split_sentences("Pan dr. Kowalski przyszedł. Usiadł.", "pl"),
+            ["Pan dr. Kowalski przyszedł. ", "Usiadł."]
+        );
+        assert_eq!(
+            split_sentences("J. R. R. Tolkien wrote it. Then", "en"),
+            ["J. R. R. Tolkien wrote it. ", "Then"]
+        );
+        assert_eq!(
+            split_sentences("It costs 3.5 zł. Cheap.", "pl"),
+            ["It costs 3.5 zł. ", "Cheap."]
+        );
+    }
+
+    /// A tick as the worker splits it for `caption-update`: the committed
+    /// text after the tick and the tentative rest of the hypothesis.
+    fn caption_tick(typed: &mut String, prev: &str, curr: &str) -> String {
+        if let Some(new_text) = confirmed_suffix(typed, prev, curr, curr.len()) {
+            typed.push_str(new_text);
+        }
+        reconcile_final(typed, curr, false).insert
+    }
+
+    #[test]
+    fn caption_split_follows_the_stable_prefix() {
+        let ticks = ["We went", "We went to the", "We went to the park."];
+        let expected = [
+            ("", "We went"),
+            ("We went", " to the"),
+            ("We went to the", " park."),
+        ];
+        let mut typed = String::new();
+        let mut prev = "";
+        for (curr, (committed, tentative)) in ticks.into_iter().zip(expected) {
+            let split = caption_tick(&mut typed, prev, curr);
+            assert_eq!((typed.as_str(), split.as_str()), (committed, tentative));
+            assert_eq!(format!("{typed}{split}"), curr);
+            prev = curr;
+        }
+    }
+
+    #[test]
+    fn caption_split_keeps_committed_words_when_the_hypothesis_changes() {
+        let mut typed = "We went to the".to_string();
+        let tentative = caption_tick(&mut typed, "We went to the park.", "We went to a park.");
+        assert_eq!(typed, "We went to the");
+        assert_eq!(tentative, " park.");
+    }
+
+    #[test]
+    fn a_reset_baseline_keeps_the_typed_text() {
+        let mut typed = "Before the pause.".to_string();
+        // The pause reset cleared the previous hypothesis: the first tick
+        // after it has nothing to agree with.
+        let prev = "";
+        let curr = "Before the pause. After it";
+        assert_eq!(confirmed_suffix(&typed, prev, curr, curr.len()), None);
+        // The next one confirms only resumed speech, after the typed text.
+        let next = "Before the pause. After it we";
+        let new_text = confirmed_suffix(&typed, curr, next, next.len()).unwrap();
+        assert_eq!(new_text, " After it");
+        typed.push_str(new_text);
+        assert_eq!(typed, "Before the pause. After it");
+    }
+}
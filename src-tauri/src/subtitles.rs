@@ -0,0 +1,192 @@
+//! SRT / WebVTT / JSON export of a transcription's segments.
+
+use crate::audio::TARGET_SAMPLE_RATE;
+use crate::transcribe::Word;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    /// Cues with their words' timings (see `Settings::word_timestamps`),
+    /// for captioning tools.
+    Json,
+}
+
+/// One subtitle: text shown from `start` to `end` (16kHz sample offsets
+/// from the start of the recording).
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// Per-word timings, if word timestamps were on. Their text is whisper's
+    /// raw output, before post-processing.
+    pub words: Vec<Word>,
+}
+
+/// Render `cues` as a subtitle file. Cues without text are skipped and SRT
+/// indices count only the cues written, starting at 1.
+///
+/// ```text
+/// 1
+/// 00:00:00,000 --> 00:00:02,500
+/// Hello there.
+/// ```
+pub fn format(cues: &[Cue], format: SubtitleFormat) -> String {
+    let (header, separator) = match format {
+        SubtitleFormat::Srt => ("", ','),
+        SubtitleFormat::Vtt => ("WEBVTT\n\n", '.'),
+        SubtitleFormat::Json => return json(cues),
+    };
+    let mut out = String::from(header);
+    let cues = cues.iter().filter(|cue| !cue.text.trim().is_empty());
+    for (i, cue) in cues.enumerate() {
+        if format == SubtitleFormat::Srt {
+            out.push_str(&format!("{}\n", i + 1));
+        }
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            timestamp(cue.start, separator),
+            timestamp(cue.end, separator),
+            cue.text.trim()
+        ));
+    }
+    out
+}
+
+/// `HH:MM:SS<sep>mmm`; SRT separates milliseconds with a comma, WebVTT with
+/// a dot.
+fn timestamp(samples: usize, separator: char) -> String {
+    let ms = samples as u64 * 1000 / TARGET_SAMPLE_RATE as u64;
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// A span in seconds, as written to JSON.
+#[derive(Serialize)]
+struct JsonSpan<'a> {
+    start: f64,
+    end: f64,
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<Vec<JsonSpan<'a>>>,
+}
+
+fn seconds(samples: usize) -> f64 {
+    // Millisecond precision, like the subtitle formats.
+    (samples as u64 * 1000 / TARGET_SAMPLE_RATE as u64) as f64 / 1000.0
+}
+
+/// `[{"start": 0.0, "end": 2.5, "text": "Hello there.", "words": [...]}]`,
+/// with `words` only present when there are word timings.
+fn json(cues: &[Cue]) -> String {
+    let spans: Vec<JsonSpan> = cues
+        .iter()
+        .filter(|cue| !cue.text.trim().is_empty())
+        .map(|cue| JsonSpan {
+            start: seconds(cue.start),
+            end: seconds(cue.end),
+            text: cue.text.trim(),
+            words: (!cue.words.is_empty()).then(|| {
+                cue.words
+                    .iter()
+                    .map(|word| JsonSpan {
+                        start: seconds(word.start),
+                        end: seconds(word.end),
+                        text: &word.text,
+                        words: None,
+                    })
+                    .collect()
+            }),
+        })
+        .collect();
+    serde_json::to_string_pretty(&spans).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cue from `start` to `end` seconds into the recording.
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        let samples = |secs: f64| (secs * TARGET_SAMPLE_RATE as f64) as usize;
+        Cue {
+            start: samples(start),
+            end: samples(end),
+            text: text.to_string(),
+            words: Vec::new(),
+        }
+    }
+
+    fn cues() -> Vec<Cue> {
+        vec![
+            cue(0.0, 2.5, " Hello there."),
+            cue(2.5, 4.0, "  "),
+            cue(61.5, 64.125, "A minute in."),
+            cue(3661.25, 3663.0, "An hour in."),
+        ]
+    }
+
+    #[test]
+    fn srt_numbers_the_written_cues() {
+        assert_eq!(
+            format(&cues(), SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:02,500\nHello there.\n\n\
+             2\n00:01:01,500 --> 00:01:04,125\nA minute in.\n\n\
+             3\n01:01:01,250 --> 01:01:03,000\nAn hour in.\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_has_a_header_and_dotted_milliseconds() {
+        assert_eq!(
+            format(&cues(), SubtitleFormat::Vtt),
+            "WEBVTT\n\n\
+             00:00:00.000 --> 00:00:02.500\nHello there.\n\n\
+             00:01:01.500 --> 00:01:04.125\nA minute in.\n\n\
+             01:01:01.250 --> 01:01:03.000\nAn hour in.\n\n"
+        );
+    }
+
+    #[test]
+    fn json_includes_word_timings_when_present() {
+        let mut with_words = cue(1.0, 2.0, "Hi you.");
+        with_words.words = vec![
+            Word {
+                text: " Hi".to_string(),
+                start: 16_000,
+                end: 24_000,
+            },
+            Word {
+                text: " you.".to_string(),
+                start: 24_000,
+                end: 32_000,
+            },
+        ];
+        let cues = [with_words, cue(2.0, 3.0, "Bye.")];
+        let json: serde_json::Value =
+            serde_json::from_str(&format(&cues, SubtitleFormat::Json)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "start": 1.0,
+                    "end": 2.0,
+                    "text": "Hi you.",
+                    "words": [
+                        {"start": 1.0, "end": 1.5, "text": " Hi"},
+                        {"start": 1.5, "end": 2.0, "text": " you."},
+                    ],
+                },
+                {"start": 2.0, "end": 3.0, "text": "Bye."},
+            ])
+        );
+    }
+}
@@ -1,61 +1,1142 @@
 use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use whisper_rs::{
+    DtwMode, DtwModelPreset, DtwParameters, FullParams, SamplingStrategy, WhisperContext,
+    WhisperContextParameters, WhisperState,
+};
+
+/// Progress callback for `Transcribe::transcribe_segments` (percent done).
+pub type ProgressFn = Box<dyn FnMut(i32)>;
+
+/// A speech-to-text backend: whisper.cpp in-process (`Transcriber`) or an
+/// HTTP endpoint (`whisper_api::ApiTranscriber`), per `Settings::backend`.
+pub trait Transcribe {
+    /// Transcribe `audio` (16kHz mono) into segments, with their positions
+    /// in the input. Dropped segments (no speech, blocklisted) stay in the
+    /// list with empty text so their boundaries can still be used.
+    ///
+    /// `on_progress` is called with the percentage done, if the backend
+    /// can tell.
+    fn transcribe_segments(
+        &self,
+        audio: &[f32],
+        config: &TranscribeConfig,
+        on_progress: Option<ProgressFn>,
+    ) -> Result<Vec<Segment>>;
+
+    fn transcribe(&self, audio: &[f32], config: &TranscribeConfig) -> Result<String> {
+        let segments = self.transcribe_segments(audio, config, None)?;
+        Ok(join_segments(&segments))
+    }
+
+    /// Whether decoding was set up to use the GPU.
+    fn gpu_requested(&self) -> bool {
+        false
+    }
+}
+
+/// The error of a transcription stopped through `TranscribeConfig::abort`.
+#[derive(Debug)]
+pub struct Aborted;
+
+impl std::fmt::Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Transcription aborted")
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// Length of the silence `Transcriber::warm_up` decodes: whisper.cpp skips
+/// anything under a second.
+const WARM_UP_SAMPLES: usize = 2 * crate::audio::TARGET_SAMPLE_RATE as usize;
+/// Set to `1` (or `true`/`yes`), models load without GPU offload, for
+/// machines whose GPU runtime makes whisper crash.
+const FORCE_CPU_ENV: &str = "VTT_FORCE_CPU";
 
 pub struct Transcriber {
-    ctx: WhisperContext,
+    /// Decoder state (KV caches, scratch buffers) reused by every call; it
+    /// holds its own reference to the loaded model. Allocating it costs
+    /// hundreds of MB for the large models, which used to happen on every
+    /// streaming tick. Reuse is safe because calls set `no_context` (unless
+    /// `TranscribeConfig::keep_context`), so whisper clears the previous
+    /// call's tokens before decoding and nothing leaks from one buffer into
+    /// the next.
+    state: Mutex<WhisperState>,
+    gpu: bool,
+    /// Whether DTW alignment is on, so tokens carry word-level timestamps.
+    dtw: bool,
+    /// Set once `warm_up` ran.
+    warm: AtomicBool,
+    info: ModelInfo,
 }
 
-impl Transcriber {
-    pub fn new(model_path: &Path) -> Result<Self> {
-        let mut params = WhisperContextParameters::default();
-        params.use_gpu(true);
-
-        let ctx = WhisperContext::new_with_params(
-            model_path
-                .to_str()
-                .context("Invalid model path encoding")?,
-            params,
-        )
-        .map_err(|e| anyhow::anyhow!("Failed to load whisper model: {e}"))?;
+/// Facts about a loaded model, read from whisper.cpp's context at load
+/// time (unlike `inspect_model`, which only reads the file header).
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub path: PathBuf,
+    /// whisper.cpp's size name: "tiny", "base", "small", "medium" or
+    /// "large".
+    pub model_type: String,
+    /// Whether it knows languages besides English (`.en` models don't).
+    pub multilingual: bool,
+    pub vocab_size: i32,
+    pub gpu_requested: bool,
+    /// The GPU it was loaded on; `None` = whisper.cpp's default, also when
+    /// the one asked for couldn't be used (see `Transcriber::new`).
+    pub gpu_device: Option<i32>,
+    /// Whether DTW alignment is on (see `Transcriber::new`).
+    pub word_timestamps: bool,
+}
+
+impl ModelInfo {
+    /// Whether `language` needs a multilingual model and this isn't one.
+    /// With "auto" an English-only model just transcribes English.
+    pub fn lacks_language(&self, language: &str) -> bool {
+        !self.multilingual && language != "en" && language != "auto"
+    }
+}
+
+/// Upper bound for `best_of` / `beam_size`; whisper.cpp runs at most this
+/// many decoders in parallel.
+const MAX_DECODERS: i32 = 8;
+/// Upper bound for a configured thread count.
+const MAX_THREADS: usize = 64;
+/// The automatic thread count stops here: whisper.cpp scales poorly past
+/// about 8 threads, and more mostly compete with the rest of the desktop.
+const MAX_AUTO_THREADS: usize = 8;
+
+/// CPU threads whisper decodes with: `configured` clamped to
+/// `1..=MAX_THREADS`, or for `None` the available cores, up to
+/// `MAX_AUTO_THREADS`.
+pub fn thread_count(configured: Option<usize>) -> usize {
+    match configured {
+        Some(n) => n.clamp(1, MAX_THREADS),
+        None => std::thread::available_parallelism()
+            .map_or(4, |n| n.get())
+            .min(MAX_AUTO_THREADS),
+    }
+}
+
+/// Decoding strategy: greedy is fast, beam search is more accurate but
+/// noticeably slower on long recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Sampling {
+    Greedy { best_of: i32 },
+    BeamSearch { beam_size: i32 },
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling::Greedy { best_of: 5 }
+    }
+}
+
+impl Sampling {
+    pub fn validate(&self) -> Result<()> {
+        let (name, value) = match *self {
+            Sampling::Greedy { best_of } => ("best_of", best_of),
+            Sampling::BeamSearch { beam_size } => ("beam_size", beam_size),
+        };
+        if !(1..=MAX_DECODERS).contains(&value) {
+            anyhow::bail!("{name} must be between 1 and {MAX_DECODERS}, got {value}");
+        }
+        Ok(())
+    }
+
+    fn strategy(self) -> SamplingStrategy {
+        match self {
+            Sampling::Greedy { best_of } => SamplingStrategy::Greedy { best_of },
+            Sampling::BeamSearch { beam_size } => SamplingStrategy::BeamSearch {
+                beam_size,
+                // whisper.cpp's default (no length penalty tweak)
+                patience: -1.0,
+            },
+        }
+    }
+}
+
+/// Temperature fallback: when a segment decodes badly (too repetitive or
+/// too unlikely), whisper retries it at a higher temperature. Defaults are
+/// whisper.cpp's.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fallback {
+    /// Starting temperature (0 = deterministic).
+    pub temperature: f32,
+    /// Added on each retry; 0 disables the fallback.
+    pub temperature_inc: f32,
+    /// Retry when the compression-ratio/entropy measure exceeds this
+    /// (catches repetition loops).
+    pub entropy_threshold: f32,
+    /// Retry when the average token log probability is below this.
+    pub logprob_threshold: f32,
+}
+
+impl Default for Fallback {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+        }
+    }
+}
+
+impl Fallback {
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("temperature", self.temperature),
+            ("temperature_inc", self.temperature_inc),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                anyhow::bail!("{name} must be between 0 and 1, got {value}");
+            }
+        }
+        if !self.entropy_threshold.is_finite() || self.entropy_threshold <= 0.0 {
+            anyhow::bail!(
+                "entropy_threshold must be positive, got {}",
+                self.entropy_threshold
+            );
+        }
+        if !self.logprob_threshold.is_finite() {
+            anyhow::bail!(
+                "logprob_threshold must be a number, got {}",
+                self.logprob_threshold
+            );
+        }
+        Ok(())
+    }
+
+    fn apply(&self, params: &mut FullParams) {
+        params.set_temperature(self.temperature);
+        params.set_temperature_inc(self.temperature_inc);
+        params.set_entropy_thold(self.entropy_threshold);
+        params.set_logprob_thold(self.logprob_threshold);
+    }
+}
+
+/// One dial for speed vs. accuracy. A preset stands in for `sampling`,
+/// `streaming_sampling`, `fallback` and the streaming interval; `Custom`
+/// uses those settings as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityPreset {
+    #[default]
+    Custom,
+    /// Greedy decoding without temperature retries, frequent ticks: for
+    /// slow machines or CPU-only whisper.
+    Fast,
+    /// Like the default settings: greedy with 5 candidates for the final
+    /// pass, a single candidate for ticks.
+    Balanced,
+    /// Beam search for the final pass, and fewer but better ticks.
+    Accurate,
+}
+
+/// What a `QualityPreset` sets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PresetParams {
+    pub sampling: Sampling,
+    pub streaming_sampling: Sampling,
+    pub fallback: Fallback,
+    pub stream_interval: Duration,
+}
+
+impl QualityPreset {
+    /// The preset's parameters, or `None` for `Custom`.
+    pub fn params(self) -> Option<PresetParams> {
+        let params = match self {
+            QualityPreset::Custom => return None,
+            QualityPreset::Fast => PresetParams {
+                sampling: Sampling::Greedy { best_of: 1 },
+                streaming_sampling: Sampling::Greedy { best_of: 1 },
+                fallback: Fallback {
+                    temperature_inc: 0.0,
+                    ..Fallback::default()
+                },
+                stream_interval: Duration::from_secs(2),
+            },
+            QualityPreset::Balanced => PresetParams {
+                sampling: Sampling::Greedy { best_of: 5 },
+                streaming_sampling: Sampling::Greedy { best_of: 1 },
+                fallback: Fallback::default(),
+                stream_interval: Duration::from_secs(3),
+            },
+            QualityPreset::Accurate => PresetParams {
+                sampling: Sampling::BeamSearch { beam_size: 5 },
+                streaming_sampling: Sampling::Greedy { best_of: 5 },
+                fallback: Fallback::default(),
+                stream_interval: Duration::from_secs(4),
+            },
+        };
+        Some(params)
+    }
+}
+
+/// A language whisper can transcribe.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Language {
+    /// What whisper takes, e.g. `"pl"`.
+    pub code: &'static str,
+    /// English name, e.g. `"Polish"`.
+    pub name: &'static str,
+}
+
+/// Every language whisper knows as `(code, name)`, in whisper.cpp's order
+/// (roughly by how much training data it had).
+const LANGUAGES: &[(&str, &str)] = &[
+    ("en", "English"),
+    ("zh", "Chinese"),
+    ("de", "German"),
+    ("es", "Spanish"),
+    ("ru", "Russian"),
+    ("ko", "Korean"),
+    ("fr", "French"),
+    ("ja", "Japanese"),
+    ("pt", "Portuguese"),
+    ("tr", "Turkish"),
+    ("pl", "Polish"),
+    ("ca", "Catalan"),
+    ("nl", "Dutch"),
+    ("ar", "Arabic"),
+    ("sv", "Swedish"),
+    ("it", "Italian"),
+    ("id", "Indonesian"),
+    ("hi", "Hindi"),
+    ("fi", "Finnish"),
+    ("vi", "Vietnamese"),
+    ("he", "Hebrew"),
+    ("uk", "Ukrainian"),
+    ("el", "Greek"),
+    ("ms", "Malay"),
+    ("cs", "Czech"),
+    ("ro", "Romanian"),
+    ("da", "Danish"),
+    ("hu", "Hungarian"),
+    ("ta", "Tamil"),
+    ("no", "Norwegian"),
+    ("th", "Thai"),
+    ("ur", "Urdu"),
+    ("hr", "Croatian"),
+    ("bg", "Bulgarian"),
+    ("lt", "Lithuanian"),
+    ("la", "Latin"),
+    ("mi", "Maori"),
+    ("ml", "Malayalam"),
+    ("cy", "Welsh"),
+    ("sk", "Slovak"),
+    ("te", "Telugu"),
+    ("fa", "Persian"),
+    ("lv", "Latvian"),
+    ("bn", "Bengali"),
+    ("sr", "Serbian"),
+    ("az", "Azerbaijani"),
+    ("sl", "Slovenian"),
+    ("kn", "Kannada"),
+    ("et", "Estonian"),
+    ("mk", "Macedonian"),
+    ("br", "Breton"),
+    ("eu", "Basque"),
+    ("is", "Icelandic"),
+    ("hy", "Armenian"),
+    ("ne", "Nepali"),
+    ("mn", "Mongolian"),
+    ("bs", "Bosnian"),
+    ("kk", "Kazakh"),
+    ("sq", "Albanian"),
+    ("sw", "Swahili"),
+    ("gl", "Galician"),
+    ("mr", "Marathi"),
+    ("pa", "Punjabi"),
+    ("si", "Sinhala"),
+    ("km", "Khmer"),
+    ("sn", "Shona"),
+    ("yo", "Yoruba"),
+    ("so", "Somali"),
+    ("af", "Afrikaans"),
+    ("oc", "Occitan"),
+    ("ka", "Georgian"),
+    ("be", "Belarusian"),
+    ("tg", "Tajik"),
+    ("sd", "Sindhi"),
+    ("gu", "Gujarati"),
+    ("am", "Amharic"),
+    ("yi", "Yiddish"),
+    ("lo", "Lao"),
+    ("uz", "Uzbek"),
+    ("fo", "Faroese"),
+    ("ht", "Haitian Creole"),
+    ("ps", "Pashto"),
+    ("tk", "Turkmen"),
+    ("nn", "Nynorsk"),
+    ("mt", "Maltese"),
+    ("sa", "Sanskrit"),
+    ("lb", "Luxembourgish"),
+    ("my", "Myanmar"),
+    ("bo", "Tibetan"),
+    ("tl", "Tagalog"),
+    ("mg", "Malagasy"),
+    ("as", "Assamese"),
+    ("tt", "Tatar"),
+    ("haw", "Hawaiian"),
+    ("ln", "Lingala"),
+    ("ha", "Hausa"),
+    ("ba", "Bashkir"),
+    ("jw", "Javanese"),
+    ("su", "Sundanese"),
+    ("yue", "Cantonese"),
+];
+
+/// Codes people use for a language that whisper knows under another.
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[("nb", "no"), ("iw", "he"), ("jv", "jw")];
+
+/// The whisper code for `input`: a code (`"pl"`), one with a region or
+/// encoding (`"en-US"`, `"pl_PL.UTF-8"`) or an English name
+/// (`"Polish"`), in any case. `"auto"` and an empty string mean
+/// detection. `None` if it's no language whisper knows.
+pub fn normalize_language(input: &str) -> Option<&'static str> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() || input == "auto" {
+        return Some("auto");
+    }
+    let code = input.split(['-', '_', '.']).next().unwrap_or_default();
+    let code = LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == code)
+        .map_or(code, |(_, code)| code);
+    LANGUAGES
+        .iter()
+        .find(|(lang, name)| *lang == code || name.to_lowercase() == input)
+        .map(|(code, _)| *code)
+}
+
+/// Every language whisper knows, for a language picker.
+pub fn languages() -> Vec<Language> {
+    LANGUAGES
+        .iter()
+        .map(|&(code, name)| Language { code, name })
+        .collect()
+}
+
+/// Per-call decoding options, derived from `Settings` by the worker.
+#[derive(Debug, Clone)]
+pub struct TranscribeConfig {
+    pub language: String,
+    /// Text fed to whisper as preceding context to bias it toward names and
+    /// jargon. Empty means no prompt.
+    pub initial_prompt: String,
+    /// Drop segments whose no-speech probability exceeds this (1.0 = keep all),
+    /// unless whisper is confident in their text anyway.
+    pub no_speech_threshold: f32,
+    /// Segments consisting solely of one of these phrases are dropped.
+    pub blocklist: Vec<String>,
+    pub sampling: Sampling,
+    pub fallback: Fallback,
+    /// Ask whisper for one segment per 30s window instead of one per
+    /// phrase.
+    pub single_segment: bool,
+    /// Translate into English instead of transcribing (multilingual models
+    /// only).
+    pub translate: bool,
+    /// whisper checks this between decoding steps and gives up once it's
+    /// set, failing the call with `Aborted`. `None` = runs to the end.
+    pub abort: Option<Arc<AtomicBool>>,
+    /// CPU threads to decode with (see `thread_count`).
+    pub n_threads: usize,
+    /// Give whisper the previous call's text as context (`no_context`
+    /// off). Only right for the calls after the first of a session: the
+    /// first one, with this off, clears what earlier calls left behind.
+    pub keep_context: bool,
+    /// Silence added at either end of the audio before decoding, in
+    /// samples (see `padded`). Segment positions still refer to the input.
+    pub pad: usize,
+}
+
+impl TranscribeConfig {
+    pub(crate) fn aborted(&self) -> bool {
+        self.abort
+            .as_ref()
+            .is_some_and(|abort| abort.load(Ordering::Relaxed))
+    }
+}
+
+/// `audio` with `config.pad` samples of silence at either end, without a
+/// copy if there's nothing to add.
+pub(crate) fn padded<'a>(audio: &'a [f32], config: &TranscribeConfig) -> Cow<'a, [f32]> {
+    if config.pad == 0 {
+        Cow::Borrowed(audio)
+    } else {
+        Cow::Owned(crate::audio::pad_silence(audio, config.pad))
+    }
+}
+
+/// Move `segments`, positioned in the buffer `padded` returned, back onto
+/// the `len` samples of the input. Whatever fell into the padding lands on
+/// the input's first or last sample.
+pub(crate) fn unpad(segments: &mut [Segment], pad: usize, len: usize) {
+    let shift = |at: usize| at.saturating_sub(pad).min(len);
+    for segment in segments {
+        segment.start = shift(segment.start);
+        segment.end = shift(segment.end);
+        for word in &mut segment.words {
+            word.start = shift(word.start);
+            word.end = shift(word.end);
+        }
+    }
+}
 
-        log::info!("Whisper model loaded from {}", model_path.display());
-        Ok(Self { ctx })
+/// Phrases whisper is known to hallucinate on silence or noise (mostly
+/// subtitle credits from its training data).
+pub fn default_blocklist() -> Vec<String> {
+    [
+        "Thank you.",
+        "Thanks for watching!",
+        "Subtitles by the Amara.org community",
+        "Dziękuję.",
+        "Dzięki za obejrzenie!",
+        "Napisy stworzone przez społeczność Amara.org",
+        "Napisy wykonane przez społeczność Amara.org",
+        "Untertitel der Amara.org-Community",
+        "Vielen Dank.",
+        "Дякую за перегляд!",
+        "Субтитры сделал DimaTorzok",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// whisper.cpp's alignment-head preset for the model at `path`, guessed
+/// from the standard ggml file names (`ggml-large-v3-turbo.bin`,
+/// `ggml-small.en-q5_1.bin`, …). Fine-tunes with other names aren't
+/// recognized.
+fn dtw_preset(path: &Path) -> Option<DtwModelPreset> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    // Longest names first, so "large-v3" doesn't match "large-v3-turbo".
+    let presets = [
+        ("large-v3-turbo", DtwModelPreset::LargeV3Turbo),
+        ("large-v3", DtwModelPreset::LargeV3),
+        ("large-v2", DtwModelPreset::LargeV2),
+        ("large-v1", DtwModelPreset::LargeV1),
+        ("medium.en", DtwModelPreset::MediumEn),
+        ("medium", DtwModelPreset::Medium),
+        ("small.en", DtwModelPreset::SmallEn),
+        ("small", DtwModelPreset::Small),
+        ("base.en", DtwModelPreset::BaseEn),
+        ("base", DtwModelPreset::Base),
+        ("tiny.en", DtwModelPreset::TinyEn),
+        ("tiny", DtwModelPreset::Tiny),
+    ];
+    presets
+        .into_iter()
+        .find(|(model, _)| name.contains(model))
+        .map(|(_, preset)| preset)
+}
+
+/// `ggml` as a little-endian u32: the first bytes of a whisper.cpp model.
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+/// The magic and the 11 i32 hyperparameters after it.
+const GGML_HEADER_LEN: usize = 48;
+
+/// What `inspect_model` found at a model path.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ModelCheck {
+    Missing,
+    /// There's a file, but whisper.cpp wouldn't load it.
+    Invalid {
+        reason: String,
+    },
+    Valid {
+        /// `tiny`, `base`, `small`, `medium` or `large`, from the encoder's
+        /// depth; `unknown` for unusual architectures.
+        model_type: &'static str,
+        vocab_size: u32,
+        /// English-only models have a smaller vocabulary.
+        multilingual: bool,
+    },
+}
+
+/// Check that `path` holds a whisper.cpp ggml model, from its header and
+/// size alone, so it's quick even for multi-GB models and needs no GPU.
+/// Catches truncated downloads, error pages saved as the model and other
+/// formats; a file corrupted past the header still only fails on load.
+pub fn inspect_model(path: &Path) -> ModelCheck {
+    let invalid = |reason: String| ModelCheck::Invalid { reason };
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return ModelCheck::Missing,
+        Err(e) => return invalid(e.to_string()),
+    };
+    let file_len = file.metadata().map_or(0, |m| m.len());
+    let mut header = [0u8; GGML_HEADER_LEN];
+    if file.read_exact(&mut header).is_err() {
+        return invalid(format!("Only {file_len} bytes, too short for a model"));
+    }
+    parse_model_header(&header, file_len).unwrap_or_else(|e| invalid(e.to_string()))
+}
+
+fn parse_model_header(header: &[u8; GGML_HEADER_LEN], file_len: u64) -> Result<ModelCheck> {
+    if &header[..4] == b"GGUF" {
+        anyhow::bail!("GGUF file; whisper.cpp needs a ggml .bin model");
     }
+    let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    if magic != GGML_MAGIC {
+        anyhow::bail!("Not a ggml model (unexpected header {:02x?})", &header[..4]);
+    }
+    let hparams: [i32; 11] = std::array::from_fn(|i| {
+        let at = 4 + 4 * i;
+        i32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    });
+    let [n_vocab, _, n_audio_state, _, n_audio_layer, _, n_text_state, _, n_text_layer, _, _] =
+        hparams;
+    let plausible = |value: i32, max: i32| (1..=max).contains(&value);
+    if !plausible(n_vocab, 1_000_000)
+        || !plausible(n_audio_state, 8192)
+        || !plausible(n_audio_layer, 128)
+        || !plausible(n_text_state, 8192)
+        || !plausible(n_text_layer, 128)
+    {
+        anyhow::bail!("Corrupt header (hyperparameters {hparams:?})");
+    }
+
+    // Parameters of the encoder (attention 4d² + MLP 8d² per layer),
+    // decoder (self and cross attention 8d² + MLP 8d²) and token
+    // embedding. Even 2-bit quantization needs a quarter byte each.
+    let (audio, text) = (n_audio_state as u64, n_text_state as u64);
+    let params = 12 * audio * audio * n_audio_layer as u64
+        + 16 * text * text * n_text_layer as u64
+        + n_vocab as u64 * text;
+    let min_len = params / 4;
+    if file_len < min_len {
+        anyhow::bail!("Truncated: {file_len} bytes, expected at least {min_len}");
+    }
+
+    let model_type = match n_audio_layer {
+        4 => "tiny",
+        6 => "base",
+        12 => "small",
+        24 => "medium",
+        32 => "large",
+        _ => "unknown",
+    };
+    Ok(ModelCheck::Valid {
+        model_type,
+        vocab_size: n_vocab as u32,
+        // whisper.cpp's own test.
+        multilingual: n_vocab >= 51865,
+    })
+}
 
-    pub fn transcribe(&self, audio: &[f32], language: &str) -> Result<String> {
-        let mut state = self
-            .ctx
+/// Whether to ask for GPU offload: yes, unless `VTT_FORCE_CPU` says no.
+fn gpu_allowed() -> bool {
+    let value = std::env::var(FORCE_CPU_ENV).ok();
+    let allowed = gpu_allowed_by(value.as_deref());
+    if !allowed {
+        log::info!(
+            "{FORCE_CPU_ENV}={}: loading the model without GPU offload",
+            value.unwrap_or_default()
+        );
+    }
+    allowed
+}
+
+/// `gpu_allowed` for `VTT_FORCE_CPU` set to `value` (`None` = unset).
+fn gpu_allowed_by(value: Option<&str>) -> bool {
+    let Some(value) = value else {
+        return true;
+    };
+    !matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+/// The GPU to load on: the `gpu_device` setting, unless offload is off.
+fn gpu_device_for(gpu_device: Option<i32>, gpu: bool) -> Option<i32> {
+    gpu_device.filter(|_| gpu)
+}
+
+/// Load with `load` on GPU `device`, or on the default one if that fails
+/// (e.g. there's no such device). Returns what was loaded and the device it
+/// ended up on.
+fn load_with_fallback<T, E: std::fmt::Display>(
+    device: Option<i32>,
+    mut load: impl FnMut(Option<i32>) -> std::result::Result<T, E>,
+) -> std::result::Result<(T, Option<i32>), E> {
+    let Some(index) = device else {
+        return load(None).map(|loaded| (loaded, None));
+    };
+    match load(Some(index)) {
+        Ok(loaded) => Ok((loaded, Some(index))),
+        Err(e) => {
+            log::warn!("Can't load the model on GPU {index} ({e}); using the default GPU");
+            load(None).map(|loaded| (loaded, None))
+        }
+    }
+}
+
+impl Transcriber {
+    /// Load the model at `model_path`. With `word_timestamps`, DTW
+    /// alignment is enabled if the model is one whisper.cpp has alignment
+    /// heads for; otherwise it loads without, and segments have no words.
+    ///
+    /// `gpu_device` picks the GPU by index, for machines with more than
+    /// one. If the model fails to load on it (no such device), it's loaded
+    /// on the default one instead, with a warning, and
+    /// `ModelInfo::gpu_device` says so.
+    pub fn new(model_path: &Path, word_timestamps: bool, gpu_device: Option<i32>) -> Result<Self> {
+        let gpu = gpu_allowed();
+        let preset = word_timestamps.then(|| dtw_preset(model_path)).flatten();
+        let dtw = preset.is_some();
+        if word_timestamps && !dtw {
+            log::warn!(
+                "No DTW alignment heads known for {}; word timestamps are off",
+                model_path.display()
+            );
+        }
+        // Not `Clone`, so built afresh for the fallback load.
+        let params = |device: Option<i32>| {
+            let mut params = WhisperContextParameters::default();
+            params.use_gpu(gpu);
+            if let Some(index) = device {
+                params.gpu_device(index);
+            }
+            if let Some(model_preset) = preset.clone() {
+                params.dtw_parameters(DtwParameters {
+                    mode: DtwMode::ModelPreset { model_preset },
+                    ..Default::default()
+                });
+            }
+            params
+        };
+
+        let path = model_path.to_str().context("Invalid model path encoding")?;
+        let (ctx, device) = load_with_fallback(gpu_device_for(gpu_device, gpu), |device| {
+            WhisperContext::new_with_params(path, params(device))
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to load whisper model: {e}"))?;
+
+        let state = ctx
             .create_state()
             .map_err(|e| anyhow::anyhow!("Failed to create whisper state: {e}"))?;
+        let info = ModelInfo {
+            path: model_path.to_path_buf(),
+            model_type: ctx
+                .model_type_readable()
+                .unwrap_or_else(|_| "unknown".to_string()),
+            multilingual: ctx.is_multilingual(),
+            vocab_size: ctx.n_vocab(),
+            gpu_requested: gpu,
+            gpu_device: device,
+            word_timestamps: dtw,
+        };
+
+        log::info!(
+            "Whisper model loaded from {} ({}, {}, {})",
+            model_path.display(),
+            info.model_type,
+            if info.multilingual {
+                "multilingual"
+            } else {
+                "English-only"
+            },
+            match device {
+                Some(index) => format!("GPU {index}"),
+                None if gpu => "default GPU".to_string(),
+                None => "CPU".to_string(),
+            }
+        );
+        Ok(Self {
+            state: Mutex::new(state),
+            gpu,
+            dtw,
+            warm: AtomicBool::new(false),
+            info,
+        })
+    }
+
+    pub fn info(&self) -> &ModelInfo {
+        &self.info
+    }
 
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 5 });
-        params.set_language(Some(language));
+    /// Decode a short stretch of silence and discard the result, so the
+    /// GPU kernels and decoder buffers are set up now rather than during
+    /// the first dictation. Only the first call on a model does anything;
+    /// returns how long it took, or `None` if it was already warm.
+    pub fn warm_up(&self, config: &TranscribeConfig) -> Result<Option<Duration>> {
+        if self.warm.swap(true, Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let started = Instant::now();
+        let silence = vec![0.0; WARM_UP_SAMPLES];
+        self.transcribe_segments(&silence, config, None)?;
+        Ok(Some(started.elapsed()))
+    }
+}
+
+impl Transcribe for Transcriber {
+    /// Whether the model was loaded with GPU offload requested. whisper.cpp
+    /// silently falls back to the CPU when no GPU backend is available.
+    fn gpu_requested(&self) -> bool {
+        self.gpu
+    }
+
+    /// whisper's segments, plus their words if word timestamps are on.
+    /// `on_progress` is called from the thread running the transcription,
+    /// while the decoder state is locked.
+    fn transcribe_segments(
+        &self,
+        audio: &[f32],
+        config: &TranscribeConfig,
+        on_progress: Option<ProgressFn>,
+    ) -> Result<Vec<Segment>> {
+        let started = Instant::now();
+        let input_len = audio.len();
+        let padded = padded(audio, config);
+        let audio = &*padded;
+        let mut state = self.state.lock();
+
+        let mut params = FullParams::new(config.sampling.strategy());
+        params.set_language(Some(&config.language));
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
-        params.set_no_context(true);
-        params.set_single_segment(false);
+        // Required for reusing `state`: drops the previous call's tokens,
+        // unless they're wanted as context.
+        params.set_no_context(!config.keep_context);
+        params.set_n_threads(config.n_threads as i32);
+        params.set_single_segment(config.single_segment);
+        params.set_translate(config.translate);
+        // whisper drops such segments itself; their probability isn't
+        // exposed per state to check afterwards.
+        params.set_no_speech_thold(config.no_speech_threshold);
+        config.fallback.apply(&mut params);
+        if let Some(on_progress) = on_progress {
+            params.set_progress_callback_safe(on_progress);
+        }
+        if let Some(abort) = config.abort.clone() {
+            let should_abort: Box<dyn FnMut() -> bool> =
+                Box::new(move || abort.load(Ordering::Relaxed));
+            params.set_abort_callback_safe(should_abort);
+        }
+        // `no_context` only stops whisper from carrying text over from a
+        // previous call; the initial prompt is still prepended to the decoder.
+        if let Some(prompt) = initial_prompt(config) {
+            params.set_initial_prompt(prompt);
+        }
 
-        state
-            .full(params, audio)
-            .map_err(|e| anyhow::anyhow!("Transcription failed: {e}"))?;
+        let result = state.full(params, audio);
+        // An aborted run may fail or return partial segments; either way
+        // they're not wanted.
+        if config.aborted() {
+            return Err(Aborted.into());
+        }
+        result.map_err(|e| anyhow::anyhow!("Transcription failed: {e}"))?;
 
         let n_segments = state
             .full_n_segments()
             .map_err(|e| anyhow::anyhow!("Failed to get segments: {e}"))?;
 
-        let mut text = String::new();
+        let mut segments = Vec::with_capacity(n_segments.max(0) as usize);
         for i in 0..n_segments {
-            if let Ok(segment) = state.full_get_segment_text(i) {
-                text.push_str(&segment);
+            let Ok(segment) = state.full_get_segment_text(i) else {
+                continue;
+            };
+            let to_samples = |t: i64| centis_to_samples(t, audio.len());
+            let start = to_samples(state.full_get_segment_t0(i).unwrap_or(0));
+            let end = to_samples(state.full_get_segment_t1(i).unwrap_or(0));
+            // Whisper has already dropped the segments it deemed silence.
+            let text = kept_text(&segment, 0.0, config);
+            let words = if self.dtw && !text.is_empty() {
+                segment_words(&state, i, end, audio.len())
+            } else {
+                Vec::new()
+            };
+            let confidence = if text.is_empty() {
+                Vec::new()
+            } else {
+                word_confidence(&state, i)
+            };
+            segments.push(Segment {
+                text,
+                start,
+                end,
+                words,
+                confidence,
+            });
+        }
+
+        unpad(&mut segments, config.pad, input_len);
+
+        log::debug!(
+            "Transcribed {:.1}s of audio in {:?}",
+            input_len as f32 / crate::audio::TARGET_SAMPLE_RATE as f32,
+            started.elapsed()
+        );
+        Ok(segments)
+    }
+}
+
+/// whisper timestamps are in centiseconds.
+fn centis_to_samples(t: i64, len: usize) -> usize {
+    (t.max(0) as usize * (crate::audio::TARGET_SAMPLE_RATE as usize / 100)).min(len)
+}
+
+/// Group segment `i`'s tokens into words using their DTW timestamps. A
+/// token starting with a space begins a new word; each word lasts until
+/// the next one starts (the last one until `segment_end`).
+fn segment_words(state: &WhisperState, i: i32, segment_end: usize, len: usize) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    for j in 0..state.full_n_tokens(i).unwrap_or(0) {
+        let (Ok(text), Ok(data)) = (
+            state.full_get_token_text(i, j),
+            state.full_get_token_data(i, j),
+        ) else {
+            continue;
+        };
+        // Special tokens: [_BEG_], [_TT_150], <|endoftext|>, …
+        if text.starts_with("[_") || text.starts_with("<|") || data.t_dtw < 0 {
+            continue;
+        }
+        let at = centis_to_samples(data.t_dtw, len);
+        match words.last_mut() {
+            Some(word) if !text.starts_with(' ') => word.text.push_str(&text),
+            _ => {
+                if let Some(word) = words.last_mut() {
+                    word.end = at.max(word.start);
+                }
+                words.push(Word {
+                    text: text.trim_start().to_string(),
+                    start: at,
+                    end: at,
+                });
             }
         }
+    }
+    if let Some(word) = words.last_mut() {
+        word.end = segment_end.max(word.start);
+    }
+    words.retain(|word| !strip_non_speech(&word.text).trim().is_empty());
+    words
+}
+
+/// Probability of each word of segment `i`: the lowest of its tokens'.
+/// Words are split like `segment_words` does, so they line up with the
+/// segment's text split at whitespace.
+fn word_confidence(state: &WhisperState, i: i32) -> Vec<f32> {
+    let mut words: Vec<(String, f32)> = Vec::new();
+    for j in 0..state.full_n_tokens(i).unwrap_or(0) {
+        let (Ok(text), Ok(p)) = (
+            state.full_get_token_text(i, j),
+            state.full_get_token_prob(i, j),
+        ) else {
+            continue;
+        };
+        if text.starts_with("[_") || text.starts_with("<|") {
+            continue;
+        }
+        match words.last_mut() {
+            Some((word, lowest)) if !text.starts_with(' ') => {
+                word.push_str(&text);
+                *lowest = lowest.min(p);
+            }
+            _ => words.push((text.trim_start().to_string(), p)),
+        }
+    }
+    words
+        .into_iter()
+        .filter(|(word, _)| !strip_non_speech(word).trim().is_empty())
+        .map(|(_, p)| p)
+        .collect()
+}
+
+/// Loaded models, least recently used first. Each one holds a context and
+/// decoder state worth hundreds of MB (GBs for the large models), so only
+/// `capacity` stay loaded; switching back to a cached one is instant.
+pub struct ModelCache {
+    models: Vec<(PathBuf, Transcriber)>,
+    capacity: usize,
+    /// Passed to `Transcriber::new` for every model loaded.
+    word_timestamps: bool,
+    gpu_device: Option<i32>,
+}
+
+impl ModelCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            models: Vec::new(),
+            capacity: capacity.max(1),
+            word_timestamps: false,
+            gpu_device: None,
+        }
+    }
+
+    /// Load models with or without word timestamps from now on. Returns
+    /// `true` if that changed, in which case every cached model (loaded the
+    /// other way) was unloaded and the active one needs loading again.
+    pub fn set_word_timestamps(&mut self, on: bool) -> bool {
+        if self.word_timestamps == on {
+            return false;
+        }
+        self.word_timestamps = on;
+        self.clear();
+        true
+    }
+
+    /// Load models on GPU `device` from now on; like `set_word_timestamps`,
+    /// a change unloads every cached model.
+    pub fn set_gpu_device(&mut self, device: Option<i32>) -> bool {
+        if self.gpu_device == device {
+            return false;
+        }
+        self.gpu_device = device;
+        self.clear();
+        true
+    }
+
+    /// Make the model at `path` the active one, loading it unless cached.
+    /// If loading fails, the previously active model stays active.
+    pub fn activate(&mut self, path: &Path) -> Result<()> {
+        if let Some(i) = self.models.iter().position(|(p, _)| p == path) {
+            let entry = self.models.remove(i);
+            self.models.push(entry);
+            return Ok(());
+        }
+        // Make room first so memory use stays bounded, but keep the active
+        // model until the new one has loaded, in case that fails.
+        while self.models.len() >= self.capacity && self.models.len() > 1 {
+            self.evict_oldest();
+        }
+        let transcriber = Transcriber::new(path, self.word_timestamps, self.gpu_device)?;
+        if self.models.len() >= self.capacity {
+            self.evict_oldest();
+        }
+        self.models.push((path.to_path_buf(), transcriber));
+        Ok(())
+    }
+
+    /// Unload every model, leaving none active.
+    pub fn clear(&mut self) {
+        while !self.models.is_empty() {
+            self.evict_oldest();
+        }
+    }
+
+    /// Unload the model at `path` if it's loaded, e.g. to read it from disk
+    /// again. If it was the active one, the one used before it takes over.
+    pub fn unload(&mut self, path: &Path) {
+        if let Some(i) = self.models.iter().position(|(p, _)| p == path) {
+            self.models.remove(i);
+            log::info!("Unloaded model {}", path.display());
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let (path, _) = self.models.remove(0);
+        log::info!("Unloaded model {}", path.display());
+    }
+
+    pub fn active(&self) -> Option<&Transcriber> {
+        self.models.last().map(|(_, t)| t)
+    }
+
+    pub fn active_path(&self) -> Option<&Path> {
+        self.models.last().map(|(p, _)| p.as_path())
+    }
 
-        Ok(text.trim().to_string())
+    /// Paths of the loaded models, the active one last.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.models.iter().map(|(p, _)| p.as_path())
     }
 }
 
+/// One whisper segment of a transcription.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub text: String,
+    /// Start and end of the segment, in samples from the start of the input.
+    pub start: usize,
+    pub end: usize,
+    /// Word-level timings; empty unless word timestamps are on.
+    pub words: Vec<Word>,
+    /// Probability of each whitespace-separated word of `text`, in order.
+    pub confidence: Vec<f32>,
+}
+
+/// One word of a segment, timed by DTW alignment (positions in samples).
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The segments' text as one string with normalized whitespace. Segments
+/// are concatenated as-is; whisper starts them with a space where the
+/// language uses one.
+pub fn join_segments(segments: &[Segment]) -> String {
+    let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The segment's text as kept: empty if it's probably not speech or
+/// blocklisted, otherwise without non-speech annotations.
+pub(crate) fn kept_text(segment: &str, no_speech: f32, config: &TranscribeConfig) -> String {
+    if no_speech > config.no_speech_threshold {
+        log::debug!("Dropping segment (no_speech={no_speech:.2}): {segment:?}");
+        String::new()
+    } else if is_blocklisted(segment, &config.blocklist) {
+        log::debug!("Dropping blocklisted segment: {segment:?}");
+        String::new()
+    } else {
+        strip_non_speech(segment)
+    }
+}
+
+/// The prompt to pass to whisper, if any.
+pub(crate) fn initial_prompt(config: &TranscribeConfig) -> Option<&str> {
+    let prompt = config.initial_prompt.trim();
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+/// Remove whisper's non-speech annotations such as `[BLANK_AUDIO]`,
+/// `[Music]` or `♪`.
+fn strip_non_speech(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    let mut depth = 0usize;
+    for c in segment.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            '♪' => {}
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Whether the segment is nothing but one of the blocklisted phrases,
+/// ignoring case, surrounding whitespace and punctuation.
+fn is_blocklisted(segment: &str, blocklist: &[String]) -> bool {
+    let normalize = |s: &str| {
+        s.trim_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+            .to_lowercase()
+    };
+    let segment = normalize(segment);
+    !segment.is_empty() && blocklist.iter().any(|p| normalize(p) == segment)
+}
+
 pub fn default_model_dir() -> PathBuf {
     dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -66,3 +1147,590 @@ pub fn default_model_dir() -> PathBuf {
 pub fn default_model_path() -> PathBuf {
     default_model_dir().join("ggml-large-v3-turbo.bin")
 }
+
+/// Model sizes as the standard file names spell them, longest first, so
+/// "large-v3" doesn't match "large-v3-turbo".
+const MODEL_SIZES: [&str; 9] = [
+    "large-v3-turbo",
+    "large-v3",
+    "large-v2",
+    "large-v1",
+    "large",
+    "medium",
+    "small",
+    "base",
+    "tiny",
+];
+
+/// A model file found by `discover_models`, described from its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AvailableModel {
+    pub path: PathBuf,
+    /// Between `ggml-` and `.bin`, e.g. `small.en-q5_1`.
+    pub name: String,
+    /// `tiny` to `large-v3-turbo`; `None` for names that don't say, like
+    /// most fine-tunes.
+    pub size: Option<&'static str>,
+    /// English-only models are named `<size>.en`.
+    pub multilingual: bool,
+    /// The quantization suffix, e.g. `q5_1`; `None` for full precision.
+    pub quantization: Option<String>,
+    pub bytes: u64,
+}
+
+/// The model at `path`, described from its `ggml-<name>.bin` file name, or
+/// `None` if it isn't named like one.
+fn describe_model(path: PathBuf, bytes: u64) -> Option<AvailableModel> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = file_name.strip_prefix("ggml-")?.strip_suffix(".bin")?;
+    if name.is_empty() {
+        return None;
+    }
+    let lower = name.to_lowercase();
+    let size = MODEL_SIZES.into_iter().find(|size| lower.contains(size));
+    let multilingual = !lower.contains(".en");
+    let quantization = lower
+        .rsplit_once('-')
+        .map(|(_, suffix)| suffix)
+        .filter(|suffix| {
+            let mut chars = suffix.chars();
+            chars.next() == Some('q') && chars.next().is_some_and(|c| c.is_ascii_digit())
+        })
+        .map(String::from);
+    Some(AvailableModel {
+        name: name.to_string(),
+        size,
+        multilingual,
+        quantization,
+        bytes,
+        path,
+    })
+}
+
+/// The `ggml-*.bin` files in `dir`, by name. A missing directory has none;
+/// other files and subdirectories are skipped.
+pub fn discover_models(dir: &Path) -> Result<Vec<AvailableModel>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    let mut models = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        models.extend(describe_model(entry.path(), metadata.len()));
+    }
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn config() -> TranscribeConfig {
+        TranscribeConfig {
+            language: "en".to_string(),
+            initial_prompt: String::new(),
+            no_speech_threshold: 1.0,
+            blocklist: Vec::new(),
+            sampling: Sampling::default(),
+            fallback: Fallback::default(),
+            single_segment: false,
+            translate: false,
+            abort: None,
+            n_threads: 1,
+            keep_context: false,
+            pad: 0,
+        }
+    }
+
+    /// The model for tests that need one: `VTT_TEST_MODEL`, or the default.
+    pub(crate) fn test_model() -> PathBuf {
+        std::env::var_os("VTT_TEST_MODEL").map_or_else(default_model_path, PathBuf::from)
+    }
+
+    #[test]
+    fn initial_prompt_is_trimmed() {
+        let config = TranscribeConfig {
+            initial_prompt: "  Kubernetes, Grafana\n".to_string(),
+            ..config()
+        };
+        assert_eq!(initial_prompt(&config), Some("Kubernetes, Grafana"));
+    }
+
+    #[test]
+    fn blank_initial_prompt_is_no_prompt() {
+        assert_eq!(initial_prompt(&config()), None);
+        let config = TranscribeConfig {
+            initial_prompt: " \t\n".to_string(),
+            ..config()
+        };
+        assert_eq!(initial_prompt(&config), None);
+    }
+
+    #[test]
+    fn fallback_defaults_are_valid() {
+        assert!(Fallback::default().validate().is_ok());
+        let greedy = Fallback {
+            temperature: 1.0,
+            temperature_inc: 0.0,
+            ..Fallback::default()
+        };
+        assert!(greedy.validate().is_ok());
+    }
+
+    #[test]
+    fn fallback_rejects_out_of_range_values() {
+        let invalid = [
+            Fallback {
+                temperature: 1.5,
+                ..Fallback::default()
+            },
+            Fallback {
+                temperature_inc: -0.2,
+                ..Fallback::default()
+            },
+            Fallback {
+                entropy_threshold: 0.0,
+                ..Fallback::default()
+            },
+            Fallback {
+                logprob_threshold: f32::NAN,
+                ..Fallback::default()
+            },
+        ];
+        for fallback in invalid {
+            assert!(fallback.validate().is_err(), "{fallback:?}");
+        }
+    }
+
+    #[test]
+    fn failed_load_leaves_no_model_active() {
+        let mut models = ModelCache::new(2);
+        assert!(models.activate(Path::new("/nonexistent/ggml.bin")).is_err());
+        assert!(models.active().is_none());
+        assert_eq!(models.paths().count(), 0);
+        models.clear();
+        assert!(models.active_path().is_none());
+    }
+
+    #[test]
+    fn load_options_report_changes() {
+        let mut models = ModelCache::new(2);
+        assert!(!models.set_word_timestamps(false));
+        assert!(models.set_word_timestamps(true));
+        assert!(!models.set_word_timestamps(true));
+        assert!(!models.set_gpu_device(None));
+        assert!(models.set_gpu_device(Some(1)));
+    }
+
+    #[test]
+    #[ignore = "needs a whisper model (VTT_TEST_MODEL)"]
+    fn models_load_clear_and_reload() {
+        let model = test_model();
+        let mut models = ModelCache::new(2);
+        models.activate(&model).unwrap();
+        assert_eq!(models.active_path(), Some(model.as_path()));
+        // A missing file keeps the loaded model in use.
+        assert!(models.activate(Path::new("/nonexistent/ggml.bin")).is_err());
+        assert_eq!(models.active_path(), Some(model.as_path()));
+        models.clear();
+        assert!(models.active().is_none());
+        models.activate(&model).unwrap();
+        assert_eq!(models.active_path(), Some(model.as_path()));
+    }
+
+    #[test]
+    fn sampling_decoders_are_bounded() {
+        for n in [1, MAX_DECODERS] {
+            assert!(Sampling::Greedy { best_of: n }.validate().is_ok());
+            assert!(Sampling::BeamSearch { beam_size: n }.validate().is_ok());
+        }
+        for n in [0, MAX_DECODERS + 1] {
+            assert!(Sampling::Greedy { best_of: n }.validate().is_err());
+            assert!(Sampling::BeamSearch { beam_size: n }.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn sampling_maps_to_whisper_strategies() {
+        assert!(matches!(
+            Sampling::Greedy { best_of: 3 }.strategy(),
+            SamplingStrategy::Greedy { best_of: 3 }
+        ));
+        assert!(matches!(
+            Sampling::BeamSearch { beam_size: 4 }.strategy(),
+            SamplingStrategy::BeamSearch { beam_size: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn custom_preset_keeps_the_settings() {
+        assert_eq!(QualityPreset::Custom.params(), None);
+    }
+
+    #[test]
+    fn presets_set_valid_params() {
+        for preset in [
+            QualityPreset::Fast,
+            QualityPreset::Balanced,
+            QualityPreset::Accurate,
+        ] {
+            let params = preset.params().unwrap();
+            assert!(params.sampling.validate().is_ok(), "{preset:?}");
+            assert!(params.streaming_sampling.validate().is_ok(), "{preset:?}");
+            assert!(params.fallback.validate().is_ok(), "{preset:?}");
+        }
+    }
+
+    #[test]
+    fn presets_trade_speed_for_accuracy() {
+        let fast = QualityPreset::Fast.params().unwrap();
+        assert_eq!(fast.sampling, Sampling::Greedy { best_of: 1 });
+        assert_eq!(fast.fallback.temperature_inc, 0.0);
+
+        let balanced = QualityPreset::Balanced.params().unwrap();
+        assert_eq!(balanced.sampling, Sampling::default());
+        assert_eq!(balanced.fallback, Fallback::default());
+
+        let accurate = QualityPreset::Accurate.params().unwrap();
+        assert_eq!(accurate.sampling, Sampling::BeamSearch { beam_size: 5 });
+        assert!(fast.stream_interval < balanced.stream_interval);
+        assert!(balanced.stream_interval < accurate.stream_interval);
+    }
+
+    fn abort_set() -> TranscribeConfig {
+        TranscribeConfig {
+            abort: Some(Arc::new(AtomicBool::new(true))),
+            ..config()
+        }
+    }
+
+    #[test]
+    fn aborted_follows_the_flag() {
+        assert!(!config().aborted());
+        let flag = Arc::new(AtomicBool::new(false));
+        let config = TranscribeConfig {
+            abort: Some(flag.clone()),
+            ..config()
+        };
+        assert!(!config.aborted());
+        flag.store(true, Ordering::Relaxed);
+        assert!(config.aborted());
+        assert!(abort_set().aborted());
+    }
+
+    #[test]
+    #[ignore = "needs a whisper model (VTT_TEST_MODEL)"]
+    fn abort_stops_whisper() {
+        let transcriber = Transcriber::new(&test_model(), false, None).unwrap();
+        let audio = vec![0.0; 30 * crate::audio::TARGET_SAMPLE_RATE as usize];
+        let err = transcriber.transcribe(&audio, &abort_set()).unwrap_err();
+        assert!(err.is::<Aborted>(), "{err:#}");
+    }
+
+    #[test]
+    fn unloading_a_model_that_isnt_loaded_does_nothing() {
+        let mut models = ModelCache::new(2);
+        models.unload(Path::new("/nonexistent/ggml.bin"));
+        assert!(models.active().is_none());
+    }
+
+    #[test]
+    #[ignore = "needs a whisper model (VTT_TEST_MODEL)"]
+    fn unloaded_models_load_again() {
+        let model = test_model();
+        let mut models = ModelCache::new(2);
+        models.activate(&model).unwrap();
+        models.unload(&model);
+        assert!(models.active().is_none());
+        models.activate(&model).unwrap();
+        assert_eq!(models.paths().collect::<Vec<_>>(), [model.as_path()]);
+    }
+
+    #[test]
+    fn force_cpu_values() {
+        assert!(gpu_allowed_by(None));
+        for value in ["1", "true", "YES", " yes\n"] {
+            assert!(!gpu_allowed_by(Some(value)), "{value:?}");
+        }
+        for value in ["", "0", "false", "no", "gpu"] {
+            assert!(gpu_allowed_by(Some(value)), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn forced_cpu_drops_the_gpu_device() {
+        let gpu = gpu_allowed_by(Some("1"));
+        assert!(!gpu);
+        assert_eq!(gpu_device_for(Some(1), gpu), None);
+        assert_eq!(gpu_device_for(Some(1), true), Some(1));
+        assert_eq!(gpu_device_for(None, true), None);
+    }
+
+    #[test]
+    fn load_falls_back_to_the_default_gpu() {
+        let mut tried = Vec::new();
+        let loaded = load_with_fallback(Some(3), |device| {
+            tried.push(device);
+            match device {
+                Some(_) => Err("no such device"),
+                None => Ok("model"),
+            }
+        });
+        assert_eq!(loaded, Ok(("model", None)));
+        assert_eq!(tried, [Some(3), None]);
+    }
+
+    #[test]
+    fn load_keeps_a_working_gpu() {
+        let mut tried = Vec::new();
+        let loaded = load_with_fallback(Some(1), |device| {
+            tried.push(device);
+            Ok::<_, &str>("model")
+        });
+        assert_eq!(loaded, Ok(("model", Some(1))));
+        assert_eq!(tried, [Some(1)]);
+    }
+
+    #[test]
+    fn load_without_a_device_doesnt_retry() {
+        let mut calls = 0;
+        let loaded = load_with_fallback(None, |_| {
+            calls += 1;
+            Err::<(), _>("corrupt model")
+        });
+        assert_eq!(loaded, Err("corrupt model"));
+        assert_eq!(calls, 1);
+    }
+
+    fn segment(start: usize, end: usize) -> Segment {
+        Segment {
+            text: "hello".to_string(),
+            start,
+            end,
+            words: vec![Word {
+                text: "hello".to_string(),
+                start,
+                end,
+            }],
+            confidence: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn unpad_moves_segments_back_onto_the_input() {
+        let mut segments = [segment(1000, 5000), segment(200, 9500)];
+        unpad(&mut segments, 800, 8000);
+        assert_eq!((segments[0].start, segments[0].end), (200, 4200));
+        assert_eq!(
+            (segments[0].words[0].start, segments[0].words[0].end),
+            (200, 4200)
+        );
+        // In the padding before and after the input.
+        assert_eq!((segments[1].start, segments[1].end), (0, 8000));
+        assert_eq!(
+            (segments[1].words[0].start, segments[1].words[0].end),
+            (0, 8000)
+        );
+    }
+
+    /// tiny.en's hyperparameters.
+    const TINY_EN: [i32; 11] = [51864, 1500, 384, 6, 4, 448, 384, 6, 4, 80, 1];
+    /// About what ggml-tiny.en.bin weighs.
+    const TINY_LEN: u64 = 75_000_000;
+
+    fn ggml_header(hparams: [i32; 11]) -> [u8; GGML_HEADER_LEN] {
+        let mut header = [0u8; GGML_HEADER_LEN];
+        header[..4].copy_from_slice(&GGML_MAGIC.to_le_bytes());
+        for (i, value) in hparams.iter().enumerate() {
+            header[4 + 4 * i..8 + 4 * i].copy_from_slice(&value.to_le_bytes());
+        }
+        header
+    }
+
+    fn rejection(header: &[u8; GGML_HEADER_LEN], file_len: u64) -> String {
+        parse_model_header(header, file_len)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn a_ggml_header_describes_the_model() {
+        let check = parse_model_header(&ggml_header(TINY_EN), TINY_LEN).unwrap();
+        assert!(matches!(
+            check,
+            ModelCheck::Valid {
+                model_type: "tiny",
+                vocab_size: 51864,
+                multilingual: false,
+            }
+        ));
+        let mut large = TINY_EN;
+        (large[0], large[4]) = (51866, 32);
+        (large[2], large[6]) = (1280, 1280);
+        let check = parse_model_header(&ggml_header(large), 3_100_000_000).unwrap();
+        assert!(matches!(
+            check,
+            ModelCheck::Valid {
+                model_type: "large",
+                multilingual: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        let mut gguf = ggml_header(TINY_EN);
+        gguf[..4].copy_from_slice(b"GGUF");
+        assert!(rejection(&gguf, TINY_LEN).starts_with("GGUF file"));
+
+        let mut html = [b' '; GGML_HEADER_LEN];
+        html[..5].copy_from_slice(b"<html");
+        assert!(rejection(&html, TINY_LEN).starts_with("Not a ggml model"));
+    }
+
+    #[test]
+    fn implausible_hyperparameters_are_rejected() {
+        for (at, value) in [(0, 0), (2, -384), (4, 1000), (8, 0)] {
+            let mut hparams = TINY_EN;
+            hparams[at] = value;
+            let reason = rejection(&ggml_header(hparams), TINY_LEN);
+            assert!(reason.starts_with("Corrupt header"), "{at}: {reason}");
+        }
+    }
+
+    #[test]
+    fn a_truncated_model_is_rejected() {
+        let reason = rejection(&ggml_header(TINY_EN), 1_000_000);
+        assert!(reason.starts_with("Truncated: 1000000 bytes"), "{reason}");
+    }
+
+    #[test]
+    fn inspect_model_reads_the_file() {
+        let path = std::env::temp_dir().join(format!("vtt-model-{}.bin", std::process::id()));
+        assert!(matches!(inspect_model(&path), ModelCheck::Missing));
+
+        std::fs::write(&path, b"Not Found").unwrap();
+        let check = inspect_model(&path);
+        std::fs::remove_file(&path).unwrap();
+        let ModelCheck::Invalid { reason } = check else {
+            panic!("{check:?}");
+        };
+        assert_eq!(reason, "Only 9 bytes, too short for a model");
+    }
+
+    fn described(file_name: &str) -> Option<AvailableModel> {
+        describe_model(Path::new("/models").join(file_name), 1)
+    }
+
+    #[test]
+    fn model_file_names_describe_the_model() {
+        let model = described("ggml-small.en-q5_1.bin").unwrap();
+        assert_eq!(model.name, "small.en-q5_1");
+        assert_eq!(model.path, Path::new("/models/ggml-small.en-q5_1.bin"));
+        assert_eq!(model.size, Some("small"));
+        assert!(!model.multilingual);
+        assert_eq!(model.quantization.as_deref(), Some("q5_1"));
+
+        let model = described("ggml-large-v3-turbo.bin").unwrap();
+        assert_eq!(model.size, Some("large-v3-turbo"));
+        assert!(model.multilingual);
+        assert_eq!(model.quantization, None);
+
+        let model = described("ggml-Whisper-NL-q8_0.bin").unwrap();
+        assert_eq!(model.size, None);
+        assert_eq!(model.quantization.as_deref(), Some("q8_0"));
+    }
+
+    #[test]
+    fn other_files_are_not_models() {
+        for file_name in [
+            "model.bin",
+            "ggml-.bin",
+            "ggml-tiny.gguf",
+            "ggml-tiny.bin.part",
+        ] {
+            assert_eq!(described(file_name), None, "{file_name}");
+        }
+    }
+
+    #[test]
+    fn discover_models_lists_model_files_by_name() {
+        let dir = std::env::temp_dir().join(format!("vtt-models-{}", std::process::id()));
+        assert_eq!(discover_models(&dir).unwrap(), []);
+
+        std::fs::create_dir_all(dir.join("ggml-folder.bin")).unwrap();
+        std::fs::write(dir.join("ggml-tiny.bin"), b"tiny").unwrap();
+        std::fs::write(dir.join("ggml-base.en.bin"), b"base").unwrap();
+        std::fs::write(dir.join("README.txt"), b"").unwrap();
+        let models = discover_models(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let models = models.unwrap();
+        let names: Vec<_> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["base.en", "tiny"]);
+        assert_eq!(models[1].bytes, 4);
+    }
+
+    #[test]
+    #[ignore = "needs a whisper model (VTT_TEST_MODEL)"]
+    fn single_segment_keeps_the_transcription_in_one_segment() {
+        let transcriber = Transcriber::new(&test_model(), false, None).unwrap();
+        let speech = crate::benchmark::tests::speech_sample();
+        let pause = vec![0.0; 2 * crate::audio::TARGET_SAMPLE_RATE as usize];
+        let audio = [&speech[..], &pause, &speech].concat();
+        let segments = |single_segment| {
+            let config = TranscribeConfig {
+                single_segment,
+                ..config()
+            };
+            transcriber
+                .transcribe_segments(&audio, &config, None)
+                .unwrap()
+        };
+        assert!(segments(false).len() > 1);
+        assert_eq!(segments(true).len(), 1);
+    }
+
+    #[test]
+    fn configured_thread_counts_are_clamped() {
+        assert_eq!(thread_count(Some(0)), 1);
+        assert_eq!(thread_count(Some(6)), 6);
+        assert_eq!(thread_count(Some(1000)), MAX_THREADS);
+    }
+
+    #[test]
+    fn the_automatic_thread_count_follows_the_cores() {
+        let cores = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(thread_count(None), cores.min(MAX_AUTO_THREADS));
+        assert!((1..=MAX_AUTO_THREADS).contains(&thread_count(None)));
+    }
+
+    fn model_info(multilingual: bool) -> ModelInfo {
+        ModelInfo {
+            path: PathBuf::from("ggml-base.bin"),
+            model_type: "base".to_string(),
+            multilingual,
+            vocab_size: if multilingual { 51865 } else { 51864 },
+            gpu_requested: false,
+            gpu_device: None,
+            word_timestamps: false,
+        }
+    }
+
+    #[test]
+    fn english_only_models_lack_other_languages() {
+        let english_only = model_info(false);
+        assert!(english_only.lacks_language("pl"));
+        assert!(!english_only.lacks_language("en"));
+        assert!(!english_only.lacks_language("auto"));
+        assert!(!model_info(true).lacks_language("pl"));
+    }
+}
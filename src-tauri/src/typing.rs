@@ -1,73 +1,585 @@
-use anyhow::{Context, Result};
-use std::process::Command;
-
-/// Type text into the currently focused input field using clipboard + xdotool.
-pub fn type_text(text: &str) -> Result<()> {
-    if text.is_empty() {
-        return Ok(());
-    }
-
-    // Debug: log which window has focus
-    let focus_before = Command::new("xdotool")
-        .args(["getactivewindow", "getwindowname"])
-        .output();
-    if let Ok(out) = &focus_before {
-        let name = String::from_utf8_lossy(&out.stdout);
-        log::info!("type_text: active window = {name:?}");
-    }
-
-    // Ensure Alt is released before anything (double-Alt might leave state)
-    let _ = Command::new("xdotool").args(["keyup", "Alt_L"]).status();
-    let _ = Command::new("xdotool").args(["keyup", "Alt_R"]).status();
-    let _ = Command::new("xdotool").args(["keyup", "super"]).status();
-    std::thread::sleep(std::time::Duration::from_millis(100));
-
-    // Set clipboard using xsel (more reliable in pipes than xclip)
-    let mut child = Command::new("xsel")
-        .args(["--clipboard", "--input"])
-        .stdin(std::process::Stdio::piped())
-        .spawn()
-        .or_else(|_| {
-            // Fallback to xclip
-            Command::new("xclip")
-                .args(["-selection", "clipboard", "-i"])
-                .stdin(std::process::Stdio::piped())
-                .spawn()
+//! Text insertion into the currently focused input field.
+//!
+//! Each platform has its own backend; all of them expose the same
+//! `insert_text`, `paste`, `press_key`, `backspace`, `set_clipboard`,
+//! `clipboard_text`, `active_window`, `active_window_class` and
+//! `activate_window` functions so the worker stays platform-independent.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Keystroke (or click) used to paste into a given application.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteMethod {
+    #[default]
+    CtrlV,
+    /// Terminals reserve Ctrl+V, so they paste with Ctrl+Shift+V.
+    CtrlShiftV,
+    /// X11 PRIMARY selection + middle mouse button, for apps that block
+    /// Ctrl+V or paste from PRIMARY anyway. Clicks wherever the mouse
+    /// pointer is, which may not be where the keyboard focus is.
+    MiddleClick,
+}
+
+/// A key pressed between pieces of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Keystroke {
+    Enter,
+    ShiftEnter,
+    Tab,
+    /// A line break: Enter, unless `TypingConfig::newline_overrides` names
+    /// another key for the focused app.
+    Newline,
+}
+
+/// One step of typing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeAction {
+    Text(String),
+    Key(Keystroke),
+}
+
+/// `text` as actions: each `\n` becomes a `Newline` keystroke and each `\t`
+/// a Tab, since many apps treat a pasted newline differently from a pressed
+/// Enter (or send the message on it).
+pub fn text_actions(text: &str) -> Vec<TypeAction> {
+    let mut actions = Vec::new();
+    for piece in text.split_inclusive(['\n', '\t']) {
+        let (body, key) = match piece.chars().last() {
+            Some('\n') => (&piece[..piece.len() - 1], Some(Keystroke::Newline)),
+            Some('\t') => (&piece[..piece.len() - 1], Some(Keystroke::Tab)),
+            _ => (piece, None),
+        };
+        if !body.is_empty() {
+            actions.push(TypeAction::Text(body.to_string()));
+        }
+        actions.extend(key.map(TypeAction::Key));
+    }
+    actions
+}
+
+/// What `actions` leave in a text field, with keys as `\n` and `\t`.
+pub fn typed_text(actions: &[TypeAction]) -> String {
+    actions
+        .iter()
+        .map(|action| match action {
+            TypeAction::Text(text) => text.as_str(),
+            TypeAction::Key(Keystroke::Tab) => "\t",
+            TypeAction::Key(_) => "\n",
         })
-        .context("Failed to spawn xsel/xclip")?;
+        .collect()
+}
 
-    {
-        use std::io::Write;
-        let mut stdin = child.stdin.take().context("No stdin")?;
-        stdin.write_all(text.as_bytes())?;
+/// Backspaces it takes to delete `text`: one per grapheme, since editors
+/// delete e.g. an emoji or a letter with a combining accent as a whole.
+pub fn backspaces_for(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Built-in paste methods keyed by lowercase WM_CLASS. Anything not listed
+/// here (or in the user's overrides) gets `TypingConfig::default_paste`.
+pub fn default_paste_overrides() -> HashMap<String, PasteMethod> {
+    [
+        "gnome-terminal",
+        "gnome-terminal-server",
+        "alacritty",
+        "kitty",
+        "xterm",
+        "konsole",
+        "terminator",
+        "tilix",
+        "xfce4-terminal",
+        "org.wezfurlong.wezterm",
+    ]
+    .into_iter()
+    .map(|class| (class.to_string(), PasteMethod::CtrlShiftV))
+    .collect()
+}
+
+/// Built-in line-break keys keyed by lowercase WM_CLASS: chat apps that
+/// send the message on Enter take Shift+Enter for a new line.
+pub fn default_newline_overrides() -> HashMap<String, Keystroke> {
+    ["slack", "discord", "telegramdesktop", "signal", "element"]
+        .into_iter()
+        .map(|class| (class.to_string(), Keystroke::ShiftEnter))
+        .collect()
+}
+
+/// The entry for a window class in an override table, matched
+/// case-insensitively.
+fn class_override<T: Copy>(class: &str, overrides: &HashMap<String, T>) -> Option<T> {
+    let class = class.to_lowercase();
+    overrides
+        .iter()
+        .find(|(k, _)| k.to_lowercase() == class)
+        .map(|(_, v)| *v)
+}
+
+/// Paste method for a window class, matched case-insensitively against the
+/// override table.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn paste_method_for(class: &str, config: &TypingConfig) -> PasteMethod {
+    class_override(class, &config.paste_overrides).unwrap_or(config.default_paste)
+}
+
+/// The key for a line break in the focused window (see
+/// `Keystroke::Newline`).
+fn newline_key(config: &TypingConfig) -> Keystroke {
+    if config.newline_overrides.is_empty() {
+        return Keystroke::Enter;
     }
-    child.wait()?;
+    backend::active_window_class()
+        .and_then(|class| class_override(&class, &config.newline_overrides))
+        .unwrap_or(Keystroke::Enter)
+}
+
+/// Press `key`, resolving `Newline` on first use and remembering it in
+/// `newline`, so a multi-line text looks up the window once.
+fn press(
+    key: Keystroke,
+    config: &TypingConfig,
+    newline: &mut Option<Keystroke>,
+) -> anyhow::Result<()> {
+    let key = match key {
+        Keystroke::Newline => *newline.get_or_insert_with(|| newline_key(config)),
+        key => key,
+    };
+    backend::press_key(key)
+}
 
-    // Verify clipboard was set
-    let verify = Command::new("xclip")
-        .args(["-selection", "clipboard", "-o"])
-        .output();
-    if let Ok(out) = &verify {
-        let content = String::from_utf8_lossy(&out.stdout);
-        log::info!("type_text: clipboard = {content:?}");
+/// Outcome of one step of a typing self-test (see `test_typing`).
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub step: &'static str,
+    pub ok: bool,
+    /// What was used on success, or an actionable error message.
+    pub detail: String,
+}
+
+impl StepResult {
+    fn new(step: &'static str, result: &anyhow::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self {
+                step,
+                ok: true,
+                detail: detail.clone(),
+            },
+            Err(e) => Self {
+                step,
+                ok: false,
+                detail: e.to_string(),
+            },
+        }
     }
+}
 
-    std::thread::sleep(std::time::Duration::from_millis(150));
+/// Result of `test_typing`: which backend ran and how each step went.
+#[derive(Debug, Clone, Serialize)]
+pub struct TypingReport {
+    pub backend: &'static str,
+    pub steps: Vec<StepResult>,
+}
 
-    // Paste via Ctrl+Shift+V (works in more terminals) then fallback to Ctrl+V
-    let active = Command::new("xdotool")
-        .args(["getactivewindow"])
-        .output()
-        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
-        .unwrap_or_default();
+type Job = Box<dyn FnOnce() + Send>;
 
-    log::info!("type_text: pasting to window id {active}");
+/// Runs typing jobs one at a time, in the order they were queued, on a
+/// thread of its own. Everything that types or sets the clipboard goes
+/// through it, so a streaming chunk and the final pass (or a retype) can't
+/// interleave their keystrokes and clipboard swaps, and the worker doesn't
+/// wait for xdotool.
+pub struct TypingQueue {
+    tx: Mutex<mpsc::Sender<Job>>,
+}
+
+impl TypingQueue {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+        std::thread::spawn(move || {
+            for job in rx {
+                // A panicking job must not take later ones down with it.
+                if std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)).is_err() {
+                    log::error!("Typing job panicked");
+                }
+            }
+        });
+        Self { tx: Mutex::new(tx) }
+    }
+
+    /// Queue `job` behind the ones already waiting and return at once.
+    pub fn enqueue(&self, job: impl FnOnce() + Send + 'static) {
+        if self.tx.lock().send(Box::new(job)).is_err() {
+            log::error!("Typing thread is gone; dropping a typing job");
+        }
+    }
+
+    /// Queue `job` and wait for its result. Must not be called from a job.
+    pub fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let (tx, rx) = mpsc::channel();
+        self.enqueue(move || {
+            let _ = tx.send(job());
+        });
+        rx.recv()
+            .map_err(|_| anyhow::anyhow!("Typing thread is gone"))
+    }
+}
+
+/// Knobs for how text is delivered to the focused window, derived from
+/// `Settings` by the worker for every call.
+#[derive(Debug, Clone, Default)]
+pub struct TypingConfig {
+    /// Delay between keystrokes for direct typing (xdotool `--delay`).
+    pub delay_ms: u32,
+    /// Split direct typing into chunks of this many characters (0 = off).
+    pub chunk_chars: usize,
+    /// Paste method per WM_CLASS (X11 only).
+    pub paste_overrides: HashMap<String, PasteMethod>,
+    /// Paste method for windows not in `paste_overrides` (X11 only).
+    pub default_paste: PasteMethod,
+    /// Key for a line break per WM_CLASS (X11 only); others get Enter.
+    pub newline_overrides: HashMap<String, Keystroke>,
+    /// Lines shorter than this many characters are typed instead of pasted
+    /// (0 = always paste).
+    pub type_below_chars: usize,
+    /// How often a failed keystroke command (typing a chunk, pasting) is
+    /// tried again before giving up.
+    pub retries: u32,
+}
+
+/// How a line of text gets into the focused window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertMethod {
+    /// Keystroke by keystroke: quick and reliable for a few words, and the
+    /// clipboard is left alone.
+    Type,
+    /// Through the clipboard and a paste keystroke: much faster for long
+    /// text.
+    Paste,
+}
+
+/// The method for inserting `line`, by its length (see
+/// `TypingConfig::type_below_chars`). Backends may still type when pasting
+/// isn't possible, but never do both.
+pub fn insert_method(line: &str, config: &TypingConfig) -> InsertMethod {
+    let short = line.chars().count() < config.type_below_chars;
+    if short {
+        InsertMethod::Type
+    } else {
+        InsertMethod::Paste
+    }
+}
+
+/// Pause between chunks so slow apps can drain their input queue.
+const CHUNK_PAUSE: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Split `text` into pieces of at most `chunk_chars` characters, never
+/// cutting a UTF-8 sequence. `chunk_chars == 0` yields the whole string.
+fn chunks(text: &str, chunk_chars: usize) -> Vec<&str> {
+    if chunk_chars == 0 {
+        return vec![text];
+    }
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (n, (i, _)) in text.char_indices().enumerate() {
+        if n > 0 && n % chunk_chars == 0 {
+            out.push(&text[start..i]);
+            start = i;
+        }
+    }
+    out.push(&text[start..]);
+    out
+}
+
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as backend;
 
-    // Try xdotool type first (most direct method)
-    let status = Command::new("xdotool")
-        .args(["type", "--clearmodifiers", "--delay", "0", text])
-        .status();
-    log::info!("type_text: xdotool type exit={status:?}");
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+mod desktop;
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use desktop as backend;
 
+/// Wait before trying a failed step again (see `TypingConfig::retries`).
+const RETRY_PAUSE: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Run `step`, trying it again up to `retries` times while it fails.
+/// Only for steps that fail before sending any input (a tool that fails to
+/// start or can't reach the display), so a retry can't insert text twice.
+fn with_retries<T>(
+    what: &str,
+    retries: u32,
+    mut step: impl FnMut() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match step() {
+            Err(e) if attempt < retries => {
+                attempt += 1;
+                log::warn!("{what} failed, retrying ({attempt}/{retries}): {e}");
+                std::thread::sleep(RETRY_PAUSE);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Give the target app a moment to see the new clipboard before pasting.
+const CLIPBOARD_SETTLE: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// Tools the backend needs that aren't on `PATH`, probed on first use and
+/// cached until `recheck_dependencies`.
+static MISSING: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// External tools missing for the current backend, e.g. `["xdotool"]` or
+/// `["xsel or xclip"]`. Empty when everything is available.
+pub fn missing_dependencies() -> Vec<String> {
+    MISSING
+        .lock()
+        .get_or_insert_with(probe_dependencies)
+        .clone()
+}
+
+/// Probe again (after the user installed something) and update the cache.
+pub fn recheck_dependencies() -> Vec<String> {
+    let missing = probe_dependencies();
+    *MISSING.lock() = Some(missing.clone());
+    missing
+}
+
+fn probe_dependencies() -> Vec<String> {
+    backend::REQUIRED_TOOLS
+        .iter()
+        .filter(|alternatives| !alternatives.iter().any(|bin| on_path(bin)))
+        .map(|alternatives| alternatives.join(" or "))
+        .collect()
+}
+
+/// `which`-style lookup without spawning anything.
+fn on_path(bin: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(bin).is_file()))
+}
+
+/// Fail early with a clear message instead of spawning doomed processes.
+fn ensure_dependencies() -> anyhow::Result<()> {
+    let missing = missing_dependencies();
+    if !missing.is_empty() {
+        anyhow::bail!("Missing required tools: {}", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// Opaque ID of the focused window, to bring it back with `focus_window`,
+/// or `None` if it can't be determined (always on Windows and macOS).
+pub fn active_window() -> Option<String> {
+    backend::active_window()
+}
+
+/// Focus the window `active_window` returned, e.g. before typing into it.
+/// Fails if it has been closed meanwhile.
+pub fn focus_window(id: &str) -> anyhow::Result<()> {
+    ensure_dependencies()?;
+    backend::activate_window(id)
+}
+
+/// Delete `count` characters before the cursor in the focused window.
+pub fn backspace(count: usize) -> anyhow::Result<()> {
+    ensure_dependencies()?;
+    backend::backspace(count)
+}
+
+/// The text on the clipboard, or `None` if it's empty, holds something
+/// else (an image, copied files) or can't be read.
+pub fn clipboard_text() -> Option<String> {
+    if !missing_dependencies().is_empty() {
+        return None;
+    }
+    backend::clipboard_text().filter(|text| !text.trim().is_empty())
+}
+
+/// Put `text` on the clipboard without pasting it anywhere.
+pub fn set_clipboard(text: &str) -> anyhow::Result<()> {
+    ensure_dependencies()?;
+    backend::set_clipboard(text)
+}
+
+/// Insert `sample` like `type_text` would, recording every step instead of
+/// stopping at the first failure being swallowed, so missing tools show up
+/// with an actionable message.
+pub fn test_typing(sample: &str, config: &TypingConfig) -> TypingReport {
+    TypingReport {
+        backend: backend::NAME,
+        steps: backend::diagnose(sample, config),
+    }
+}
+
+/// Deliver text through the clipboard: each piece of text is put on the
+/// clipboard and pasted, keys in between are pressed, so the clipboard ends
+/// up holding the last piece. Unlike `type_text` this never falls back to
+/// typing keystrokes.
+pub fn paste_text(actions: &[TypeAction], config: &TypingConfig) -> anyhow::Result<()> {
+    ensure_dependencies()?;
+    let mut newline = None;
+    for action in actions {
+        match action {
+            TypeAction::Text(text) => {
+                backend::set_clipboard(text)?;
+                std::thread::sleep(CLIPBOARD_SETTLE);
+                backend::paste(config)?;
+            }
+            TypeAction::Key(key) => press(*key, config, &mut newline)?,
+        }
+    }
+    Ok(())
+}
+
+/// Type into the focused field, piece by piece (see `text_actions`).
+pub fn type_text(actions: &[TypeAction], config: &TypingConfig) -> anyhow::Result<()> {
+    ensure_dependencies()?;
+    let mut newline = None;
+    for action in actions {
+        match action {
+            TypeAction::Text(text) => backend::insert_text(text, config)?,
+            TypeAction::Key(key) => press(*key, config, &mut newline)?,
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn typing_below(chars: usize) -> TypingConfig {
+        TypingConfig {
+            type_below_chars: chars,
+            ..TypingConfig::default()
+        }
+    }
+
+    #[test]
+    fn short_lines_are_typed() {
+        let config = typing_below(5);
+        assert_eq!(insert_method("abcd", &config), InsertMethod::Type);
+        assert_eq!(insert_method("abcde", &config), InsertMethod::Paste);
+        // Counted in characters, not bytes.
+        assert_eq!(insert_method("żółć", &config), InsertMethod::Type);
+    }
+
+    #[test]
+    fn zero_threshold_always_pastes() {
+        let config = typing_below(0);
+        assert_eq!(insert_method("", &config), InsertMethod::Paste);
+        assert_eq!(insert_method("a", &config), InsertMethod::Paste);
+    }
+
+    #[test]
+    fn chunks_split_by_characters() {
+        assert_eq!(chunks("abcdefg", 3), ["abc", "def", "g"]);
+        assert_eq!(chunks("zażółć", 4), ["zażó", "łć"]);
+        assert_eq!(chunks("abc", 3), ["abc"]);
+    }
+
+    #[test]
+    fn zero_chunk_chars_keeps_the_text_whole() {
+        assert_eq!(chunks("abcdefg", 0), ["abcdefg"]);
+    }
+
+    #[test]
+    fn backspaces_count_graphemes() {
+        assert_eq!(backspaces_for(""), 0);
+        assert_eq!(backspaces_for("hello"), 5);
+        assert_eq!(backspaces_for("zażółć"), 6);
+        // "é" as "e" plus a combining acute accent.
+        assert_eq!(backspaces_for("cafe\u{301}"), 4);
+        // A family emoji: four people joined by zero-width joiners.
+        assert_eq!(backspaces_for("hi 👨‍👩‍👧‍👦"), 4);
+        assert_eq!(backspaces_for("🇵🇱!"), 2);
+    }
+
+    #[test]
+    fn queued_jobs_run_one_at_a_time_in_order() {
+        let queue = TypingQueue::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for job in 0..3 {
+            let log = log.clone();
+            queue.enqueue(move || {
+                log.lock().push(("start", job));
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                log.lock().push(("end", job));
+            });
+        }
+        assert_eq!(queue.run(|| 42).unwrap(), 42);
+        assert_eq!(
+            *log.lock(),
+            [
+                ("start", 0),
+                ("end", 0),
+                ("start", 1),
+                ("end", 1),
+                ("start", 2),
+                ("end", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn a_panicking_job_doesnt_stop_the_queue() {
+        let queue = TypingQueue::new();
+        queue.enqueue(|| panic!("typing failed"));
+        assert_eq!(queue.run(|| "still running").unwrap(), "still running");
+    }
+
+    fn text(text: &str) -> TypeAction {
+        TypeAction::Text(text.to_string())
+    }
+
+    #[test]
+    fn newlines_and_tabs_become_keys() {
+        let actions = text_actions("one\ntwo\tthree\n");
+        assert_eq!(
+            actions,
+            [
+                text("one"),
+                TypeAction::Key(Keystroke::Newline),
+                text("two"),
+                TypeAction::Key(Keystroke::Tab),
+                text("three"),
+                TypeAction::Key(Keystroke::Newline),
+            ]
+        );
+        assert_eq!(typed_text(&actions), "one\ntwo\tthree\n");
+    }
+
+    #[test]
+    fn blank_lines_are_only_keys() {
+        assert_eq!(
+            text_actions("\n\n"),
+            [
+                TypeAction::Key(Keystroke::Newline),
+                TypeAction::Key(Keystroke::Newline)
+            ]
+        );
+        assert_eq!(text_actions(""), []);
+        assert_eq!(text_actions("no breaks"), [text("no breaks")]);
+    }
+
+    #[test]
+    fn newline_is_enter_without_overrides() {
+        let config = TypingConfig {
+            newline_overrides: HashMap::new(),
+            ..TypingConfig::default()
+        };
+        assert_eq!(newline_key(&config), Keystroke::Enter);
+    }
+}
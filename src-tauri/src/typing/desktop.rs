@@ -0,0 +1,224 @@
+use super::{
+    chunks, insert_method, with_retries, InsertMethod, Keystroke, StepResult, TypingConfig,
+    CHUNK_PAUSE,
+};
+use anyhow::{Context, Result};
+use enigo::{Direction, Enigo, Key, Keyboard};
+
+/// What the Windows and macOS flavours of this backend differ in.
+struct Platform {
+    /// How synthetic input reaches other apps, for error messages.
+    input: &'static str,
+    /// Held for the paste chord.
+    paste_modifier: Key,
+    /// The paste chord, for log messages.
+    paste_name: &'static str,
+    /// What the OS calls the clipboard.
+    clipboard: &'static str,
+}
+
+#[cfg(target_os = "windows")]
+pub const NAME: &str = "windows (clipboard + SendInput)";
+
+/// The paste keystroke goes out through `SendInput`, which works in nearly
+/// every Windows application including those that reject synthetic unicode
+/// input.
+#[cfg(target_os = "windows")]
+const PLATFORM: Platform = Platform {
+    input: "SendInput",
+    paste_modifier: Key::Control,
+    paste_name: "Ctrl+V",
+    clipboard: "clipboard",
+};
+
+#[cfg(target_os = "macos")]
+pub const NAME: &str = "macos (pasteboard + CGEvent)";
+
+/// Synthesized key events are delivered through `CGEventPost`, which macOS
+/// only allows once the app has been granted Accessibility permission
+/// (System Settings → Privacy & Security → Accessibility). Without it the
+/// keystroke is silently dropped, so the hotkey listener checks the same
+/// permission at startup and reports it (see `hotkey::start_listener`).
+#[cfg(target_os = "macos")]
+const PLATFORM: Platform = Platform {
+    input: "CGEvent input",
+    paste_modifier: Key::Meta,
+    paste_name: "Cmd+V",
+    clipboard: "pasteboard",
+};
+
+/// Everything goes through enigo/arboard, so no external tools are needed.
+pub const REQUIRED_TOOLS: &[&[&str]] = &[];
+
+/// Insert a single line of text into the focused input field: put it on the
+/// clipboard and synthesize the paste chord (Ctrl+V, or Cmd+V on macOS).
+/// Lines shorter than `TypingConfig::type_below_chars` are typed with
+/// unicode key events instead, `TypingConfig::chunk_chars` at a time and
+/// `TypingConfig::delay_ms` apart as on X11.
+pub fn insert_text(text: &str, config: &TypingConfig) -> Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    insert_reporting(text, config, None)
+}
+
+/// Run the same steps as `insert_text` and record the outcome of each.
+pub fn diagnose(text: &str, config: &TypingConfig) -> Vec<StepResult> {
+    let mut steps = Vec::new();
+    let _ = insert_reporting(text, config, Some(&mut steps));
+    steps
+}
+
+fn insert_reporting(
+    text: &str,
+    config: &TypingConfig,
+    mut report: Option<&mut Vec<StepResult>>,
+) -> Result<()> {
+    let mut step = |name: &'static str, result: &Result<String>| {
+        if let Some(steps) = report.as_deref_mut() {
+            steps.push(StepResult::new(name, result));
+        }
+    };
+
+    // Nothing has been sent before the input is set up, so only this step
+    // is safe to retry.
+    let mut enigo = match with_retries("Input setup", config.retries, new_enigo) {
+        Ok(enigo) => enigo,
+        Err(e) => {
+            let result: Result<String> = Err(e);
+            step("input", &result);
+            return result.map(|_| ());
+        }
+    };
+
+    // Ensure Alt is released before anything (double-Alt might leave state)
+    if let Err(e) = enigo.key(Key::Alt, Direction::Release) {
+        log::warn!("Releasing Alt failed: {e}");
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    if insert_method(text, config) == InsertMethod::Type {
+        let result = type_text(&mut enigo, text, config).map(|_| "unicode key events".to_string());
+        step("type", &result);
+        result?;
+        log::info!("insert_text: typed {} bytes", text.len());
+        return Ok(());
+    }
+
+    let result = set_clipboard(text).map(|_| "arboard".to_string());
+    step("clipboard_set", &result);
+    result?;
+    std::thread::sleep(std::time::Duration::from_millis(150));
+
+    let result = send_paste(&mut enigo).map(|_| PLATFORM.paste_name.to_string());
+    step("paste", &result);
+    result?;
+    log::info!(
+        "insert_text: pasted {} bytes via {}",
+        text.len(),
+        PLATFORM.paste_name
+    );
+    Ok(())
+}
+
+/// Type `text` with unicode key events, in chunks of
+/// `TypingConfig::chunk_chars` with a pause between them, and with
+/// `TypingConfig::delay_ms` between characters.
+fn type_text(enigo: &mut Enigo, text: &str, config: &TypingConfig) -> Result<()> {
+    let delay = std::time::Duration::from_millis(config.delay_ms.into());
+    for (i, piece) in chunks(text, config.chunk_chars).into_iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(CHUNK_PAUSE);
+        }
+        if delay.is_zero() {
+            enigo.text(piece).context("Failed to type text")?;
+            continue;
+        }
+        for (n, c) in piece.chars().enumerate() {
+            if n > 0 {
+                std::thread::sleep(delay);
+            }
+            enigo
+                .text(c.encode_utf8(&mut [0; 4]))
+                .context("Failed to type text")?;
+        }
+    }
+    Ok(())
+}
+
+fn new_enigo() -> Result<Enigo> {
+    Enigo::new(&enigo::Settings::default())
+        .with_context(|| format!("Failed to initialize {}", PLATFORM.input))
+}
+
+/// Send the paste chord to the focused window.
+pub fn paste(_config: &TypingConfig) -> Result<()> {
+    send_paste(&mut new_enigo()?)
+}
+
+fn send_paste(enigo: &mut Enigo) -> Result<()> {
+    chord(enigo, PLATFORM.paste_modifier, Key::Unicode('v'))
+        .with_context(|| format!("Failed to send {}", PLATFORM.paste_name))
+}
+
+/// Click `key` with `modifier` held. The modifier is released even if the
+/// click fails, so it isn't left stuck down.
+fn chord(enigo: &mut Enigo, modifier: Key, key: Key) -> enigo::InputResult<()> {
+    enigo.key(modifier, Direction::Press)?;
+    let clicked = enigo.key(key, Direction::Click);
+    let released = enigo.key(modifier, Direction::Release);
+    clicked.and(released)
+}
+
+/// Press `key` in the focused window.
+pub fn press_key(key: Keystroke) -> Result<()> {
+    let mut enigo = new_enigo()?;
+    let result = match key {
+        Keystroke::Enter | Keystroke::Newline => enigo.key(Key::Return, Direction::Click),
+        Keystroke::Tab => enigo.key(Key::Tab, Direction::Click),
+        Keystroke::ShiftEnter => chord(&mut enigo, Key::Shift, Key::Return),
+    };
+    result.with_context(|| format!("Failed to send {key:?}"))
+}
+
+/// Put `text` on the clipboard without pasting it anywhere.
+pub fn set_clipboard(text: &str) -> Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut c| c.set_text(text.to_string()))
+        .with_context(|| format!("Failed to set {}", PLATFORM.clipboard))
+}
+
+/// The clipboard's text; `None` for images, files or an empty clipboard.
+pub fn clipboard_text() -> Option<String> {
+    arboard::Clipboard::new()
+        .and_then(|mut c| c.get_text())
+        .ok()
+}
+
+/// Window targeting isn't implemented here; text always goes to the
+/// focused window.
+pub fn active_window() -> Option<String> {
+    None
+}
+
+pub fn active_window_class() -> Option<String> {
+    None
+}
+
+pub fn activate_window(_id: &str) -> Result<()> {
+    anyhow::bail!("Window targeting is not supported on this platform")
+}
+
+/// Delete `count` characters before the cursor in the focused window.
+pub fn backspace(count: usize) -> Result<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    let mut enigo = new_enigo()?;
+    for _ in 0..count {
+        enigo
+            .key(Key::Backspace, Direction::Click)
+            .context("Failed to send Backspace")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,466 @@
+use super::{
+    chunks, insert_method, paste_method_for, with_retries, InsertMethod, Keystroke, PasteMethod,
+    StepResult, TypingConfig, CHUNK_PAUSE,
+};
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+
+pub const NAME: &str = "x11 (xsel/xclip + xdotool)";
+
+/// Binaries this backend shells out to; any one of each group will do.
+pub const REQUIRED_TOOLS: &[&[&str]] = &[&["xdotool"], &["xsel", "xclip"]];
+
+/// How long `activate_window` waits for the window manager.
+const ACTIVATE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Wait after a middle-click paste before putting back the previous PRIMARY
+/// selection.
+const PRIMARY_RESTORE_DELAY: Duration = Duration::from_millis(300);
+
+/// X11 selection used to hand text over to the target app.
+#[derive(Debug, Clone, Copy)]
+enum Selection {
+    Clipboard,
+    Primary,
+}
+
+/// Insert a single line of text into the currently focused input field.
+///
+/// Short lines are typed with `xdotool type` (see `insert_method`); others
+/// are pasted with the method the active window's WM_CLASS calls for (see
+/// `TypingConfig::paste_overrides`). When the class can't be determined the
+/// text is typed directly instead — never both, so text is inserted exactly
+/// once.
+pub fn insert_text(text: &str, config: &TypingConfig) -> Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    insert_reporting(text, config, None)
+}
+
+/// Run the same steps as `insert_text`, additionally verifying the
+/// clipboard, and record the outcome of each step.
+pub fn diagnose(text: &str, config: &TypingConfig) -> Vec<StepResult> {
+    let mut steps = Vec::new();
+    let _ = insert_reporting(text, config, Some(&mut steps));
+    steps
+}
+
+fn insert_reporting(
+    text: &str,
+    config: &TypingConfig,
+    mut report: Option<&mut Vec<StepResult>>,
+) -> Result<()> {
+    let verify = report.is_some();
+    let mut step = |name: &'static str, result: &Result<String>| {
+        if let Some(steps) = report.as_deref_mut() {
+            steps.push(StepResult::new(name, result));
+        }
+    };
+
+    let class = active_window_class();
+    log::info!("insert_text: active window class = {class:?}");
+    step(
+        "active_window",
+        &class
+            .clone()
+            .context("xdotool couldn't read the active window class"),
+    );
+
+    // Ensure Alt is released before anything (double-Alt might leave state)
+    for key in ["Alt_L", "Alt_R", "super"] {
+        if let Err(e) = xdotool(["keyup", key]) {
+            log::warn!("Releasing {key} failed: {e}");
+        }
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let method = class
+        .as_deref()
+        .map(|c| paste_method_for(c, config))
+        .filter(|_| insert_method(text, config) == InsertMethod::Paste);
+    let Some(method) = method else {
+        let result = type_direct(text, config).map(|_| "xdotool type".to_string());
+        step("type", &result);
+        return result.map(|_| ());
+    };
+
+    let selection = match method {
+        PasteMethod::MiddleClick => Selection::Primary,
+        PasteMethod::CtrlV | PasteMethod::CtrlShiftV => Selection::Clipboard,
+    };
+
+    // Pasting would replace an image or copied files with our text; type
+    // instead so the user's clipboard survives.
+    if let Selection::Clipboard = selection {
+        let binary = binary_clipboard_target();
+        step(
+            "clipboard_check",
+            &Ok(binary
+                .clone()
+                .map_or("text or empty".to_string(), |t| format!("holds {t}"))),
+        );
+        if let Some(target) = binary {
+            log::warn!("Clipboard holds {target}; typing instead of pasting to keep it");
+            let result = type_direct(text, config).map(|_| "xdotool type".to_string());
+            step("type", &result);
+            return result.map(|_| ());
+        }
+    }
+    // PRIMARY changes whenever the user selects text, so keeping it is a
+    // courtesy: put back whatever was selected before, if it can be read.
+    let previous_primary = match selection {
+        Selection::Primary => read_selection(selection).ok(),
+        Selection::Clipboard => None,
+    };
+    let result = set_selection(text, selection).map(|tool| format!("{selection:?} via {tool}"));
+    step("clipboard_set", &result);
+    result?;
+
+    if verify {
+        let verified = read_selection(selection).and_then(|content| {
+            if content == text {
+                Ok("matches".to_string())
+            } else {
+                anyhow::bail!("Selection holds {content:?} instead")
+            }
+        });
+        step("clipboard_verify", &verified);
+    }
+
+    std::thread::sleep(std::time::Duration::from_millis(150));
+    let pasted = retry_start("Paste", config.retries, || {
+        spawn_xdotool(paste_args(method))
+    });
+    let result = pasted.map(|_| format!("{method:?}"));
+    step("paste", &result);
+    if let Some(previous) = previous_primary {
+        // The target app requests the selection after the click; don't pull
+        // it away before then.
+        std::thread::sleep(PRIMARY_RESTORE_DELAY);
+        if let Err(e) = set_selection(&previous, Selection::Primary) {
+            log::warn!("Restoring the PRIMARY selection failed: {e}");
+        }
+    }
+    result.map(|_| ())
+}
+
+/// Paste the clipboard into the focused window with the keystroke its class
+/// calls for. Middle click pastes PRIMARY rather than the clipboard, so
+/// those windows get Ctrl+V here.
+pub fn paste(config: &TypingConfig) -> Result<()> {
+    let method = active_window_class()
+        .map(|class| paste_method_for(&class, config))
+        .unwrap_or(PasteMethod::CtrlV);
+    match method {
+        PasteMethod::MiddleClick => send_paste(PasteMethod::CtrlV),
+        method => send_paste(method),
+    }
+}
+
+/// Press `key` in the focused window.
+pub fn press_key(key: Keystroke) -> Result<()> {
+    let name = match key {
+        Keystroke::Enter | Keystroke::Newline => "Return",
+        Keystroke::ShiftEnter => "shift+Return",
+        Keystroke::Tab => "Tab",
+    };
+    xdotool(["key", "--clearmodifiers", name])
+}
+
+/// Put `text` on the clipboard without pasting it anywhere.
+pub fn set_clipboard(text: &str) -> Result<()> {
+    set_selection(text, Selection::Clipboard).map(|_| ())
+}
+
+/// Delete `count` characters before the cursor in the focused window.
+pub fn backspace(count: usize) -> Result<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    xdotool([
+        "key",
+        "--clearmodifiers",
+        "--repeat",
+        &count.to_string(),
+        "BackSpace",
+    ])
+}
+
+/// Run xdotool, turning a missing binary or a non-zero exit into an error.
+fn xdotool<I, S>(args: I) -> Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    check_exit(spawn_xdotool(args)?)
+}
+
+/// Run xdotool to completion; an error only if it couldn't be started.
+fn spawn_xdotool<I, S>(args: I) -> Result<ExitStatus>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::new("xdotool")
+        .args(args)
+        .status()
+        .map_err(|e| spawn_error("xdotool", e))
+}
+
+fn check_exit(status: ExitStatus) -> Result<()> {
+    if !status.success() {
+        anyhow::bail!("xdotool exited with {status}");
+    }
+    Ok(())
+}
+
+fn spawn_error(bin: &str, e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        anyhow::anyhow!("{bin} is not installed (sudo apt install {bin})")
+    } else {
+        anyhow::anyhow!("Failed to run {bin}: {e}")
+    }
+}
+
+/// ID of the focused window, or `None` if xdotool can't tell.
+pub fn active_window() -> Option<String> {
+    let out = Command::new("xdotool")
+        .arg("getactivewindow")
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let id = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (!id.is_empty()).then_some(id)
+}
+
+/// Focus window `id` and wait until it's active. Fails if the window is
+/// gone, or if the window manager doesn't activate it within
+/// `ACTIVATE_TIMEOUT` (`--sync` would otherwise wait forever).
+pub fn activate_window(id: &str) -> Result<()> {
+    let mut child = Command::new("xdotool")
+        .args(["windowactivate", "--sync", id])
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| spawn_error("xdotool", e))?;
+    let started = std::time::Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                anyhow::bail!("Window {id} can't be activated (closed?)");
+            }
+            return Ok(());
+        }
+        if started.elapsed() >= ACTIVATE_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Window {id} wasn't activated in time");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// WM_CLASS of the focused window, or `None` if xdotool can't tell.
+pub fn active_window_class() -> Option<String> {
+    let out = Command::new("xdotool")
+        .args(["getactivewindow", "getwindowclassname"])
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let class = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (!class.is_empty()).then_some(class)
+}
+
+/// The clipboard's text, unless it holds an image or files.
+pub fn clipboard_text() -> Option<String> {
+    if binary_clipboard_target().is_some() {
+        return None;
+    }
+    read_selection(Selection::Clipboard).ok()
+}
+
+/// Clipboard targets that mean it holds something other than text.
+const BINARY_TARGETS: &[&str] = &["text/uri-list", "x-special/gnome-copied-files"];
+
+/// The first non-text target the clipboard offers (an `image/*` type or
+/// copied files), if any. Needs xclip, since xsel can't list targets;
+/// without it, or without a clipboard owner, this returns `None`.
+fn binary_clipboard_target() -> Option<String> {
+    let out = Command::new("xclip")
+        .args(["-selection", "clipboard", "-t", "TARGETS", "-o"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|target| target.starts_with("image/") || BINARY_TARGETS.contains(target))
+        .map(String::from)
+}
+
+/// Put `text` into the given selection using xsel, falling back to xclip.
+/// Returns the name of the tool that was used.
+fn set_selection(text: &str, selection: Selection) -> Result<&'static str> {
+    let (xsel_flag, xclip_name) = selection_args(selection);
+
+    // xsel is more reliable in pipes than xclip
+    let (tool, mut child) = Command::new("xsel")
+        .args([xsel_flag, "--input"])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map(|c| ("xsel", c))
+        .or_else(|_| {
+            Command::new("xclip")
+                .args(["-selection", xclip_name, "-i"])
+                .stdin(std::process::Stdio::piped())
+                .spawn()
+                .map(|c| ("xclip", c))
+        })
+        .map_err(|_| {
+            anyhow::anyhow!("Neither xsel nor xclip is installed (sudo apt install xclip)")
+        })?;
+
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().context("No stdin")?;
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{tool} exited with {status}");
+    }
+    Ok(tool)
+}
+
+/// Current contents of the given selection.
+fn read_selection(selection: Selection) -> Result<String> {
+    let (xsel_flag, xclip_name) = selection_args(selection);
+    let out = Command::new("xsel")
+        .args([xsel_flag, "--output"])
+        .output()
+        .or_else(|_| {
+            Command::new("xclip")
+                .args(["-selection", xclip_name, "-o"])
+                .output()
+        })
+        .map_err(|_| anyhow::anyhow!("Neither xsel nor xclip is installed"))?;
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+fn selection_args(selection: Selection) -> (&'static str, &'static str) {
+    match selection {
+        Selection::Clipboard => ("--clipboard", "clipboard"),
+        Selection::Primary => ("--primary", "primary"),
+    }
+}
+
+/// Send the paste gesture for `method` to the focused window.
+fn send_paste(method: PasteMethod) -> Result<()> {
+    xdotool(paste_args(method))
+}
+
+/// xdotool arguments for the paste gesture `method`.
+fn paste_args(method: PasteMethod) -> &'static [&'static str] {
+    match method {
+        PasteMethod::CtrlV => &["key", "--clearmodifiers", "ctrl+v"],
+        PasteMethod::CtrlShiftV => &["key", "--clearmodifiers", "ctrl+shift+v"],
+        PasteMethod::MiddleClick => &["click", "--clearmodifiers", "2"],
+    }
+}
+
+/// Type text keystroke by keystroke with `xdotool type`.
+fn type_direct(text: &str, config: &TypingConfig) -> Result<()> {
+    let pieces = chunks(text, config.chunk_chars);
+    for (i, piece) in pieces.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(CHUNK_PAUSE);
+        }
+        let args = type_args(piece, config.delay_ms);
+        retry_start("xdotool type", config.retries, || spawn_xdotool(&args))?;
+    }
+
+    Ok(())
+}
+
+/// Run xdotool through `spawn`, retrying only when it couldn't be started.
+/// Once running it may have typed part of the text or sent the paste chord
+/// before failing, and a retry would send that input twice.
+fn retry_start(what: &str, retries: u32, spawn: impl FnMut() -> Result<ExitStatus>) -> Result<()> {
+    let status = with_retries(what, retries, spawn)?;
+    check_exit(status)
+}
+
+/// Arguments for `xdotool type` with the given per-keystroke delay.
+fn type_args(text: &str, delay_ms: u32) -> Vec<String> {
+    vec![
+        "type".to_string(),
+        "--clearmodifiers".to_string(),
+        "--delay".to_string(),
+        delay_ms.to_string(),
+        text.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn exited(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[test]
+    fn type_args_pass_the_delay() {
+        assert_eq!(
+            type_args("hi there", 12),
+            ["type", "--clearmodifiers", "--delay", "12", "hi there"]
+        );
+    }
+
+    #[test]
+    fn retries_failures_to_start() {
+        let mut calls = 0;
+        let result = retry_start("xdotool type", 2, || {
+            calls += 1;
+            if calls < 3 {
+                anyhow::bail!("no display")
+            }
+            Ok(exited(0))
+        });
+        assert!(result.is_ok());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn gives_up_after_the_retries() {
+        let mut calls = 0;
+        let result = retry_start("xdotool type", 2, || {
+            calls += 1;
+            anyhow::bail!("no display")
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn does_not_retype_after_a_failed_exit() {
+        let mut calls = 0;
+        let result = retry_start("xdotool type", 2, || {
+            calls += 1;
+            Ok(exited(1))
+        });
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("exited"), "{err}");
+        assert_eq!(calls, 1);
+    }
+}
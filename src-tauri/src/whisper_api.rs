@@ -0,0 +1,335 @@
+//! Transcription through an OpenAI-compatible HTTP endpoint
+//! (`POST .../audio/transcriptions`) instead of whisper.cpp, for machines
+//! too slow to run a model locally.
+
+use crate::audio::TARGET_SAMPLE_RATE;
+use crate::transcribe::{self, Aborted, ProgressFn, Segment, Transcribe, TranscribeConfig};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::io::Cursor;
+use std::time::Duration;
+
+/// How long a request may take end to end; long recordings upload and
+/// decode in one go.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+const BOUNDARY: &str = "voice-to-text-7f3a9c21e4b8";
+
+pub struct ApiTranscriber {
+    agent: ureq::Agent,
+    endpoint: String,
+    api_key: String,
+    model: String,
+}
+
+impl ApiTranscriber {
+    /// A backend posting to `endpoint` (the full transcriptions URL), with
+    /// `api_key` as bearer token if it isn't empty.
+    pub fn new(endpoint: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            endpoint: endpoint.trim().to_string(),
+            api_key: api_key.trim().to_string(),
+            model: model.trim().to_string(),
+        }
+    }
+
+    /// Translation has an endpoint of its own next to transcriptions.
+    fn url(&self, config: &TranscribeConfig) -> String {
+        match self.endpoint.strip_suffix("/transcriptions") {
+            Some(base) if config.translate => format!("{base}/translations"),
+            _ => self.endpoint.clone(),
+        }
+    }
+}
+
+/// The `verbose_json` response; servers that only send `text` still parse.
+#[derive(Deserialize)]
+struct ApiResponse {
+    text: String,
+    #[serde(default)]
+    segments: Vec<ApiSegment>,
+}
+
+#[derive(Deserialize)]
+struct ApiSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    no_speech_prob: f32,
+}
+
+impl Transcribe for ApiTranscriber {
+    /// The server's segments, without words or confidence. Progress isn't
+    /// reported, and an abort only takes effect once the response is in.
+    fn transcribe_segments(
+        &self,
+        audio: &[f32],
+        config: &TranscribeConfig,
+        _on_progress: Option<ProgressFn>,
+    ) -> Result<Vec<Segment>> {
+        if config.aborted() {
+            return Err(Aborted.into());
+        }
+        let mut form = Form::default();
+        form.field("model", &self.model);
+        form.field("response_format", "verbose_json");
+        if config.language != "auto" && !config.translate {
+            form.field("language", &config.language);
+        }
+        if let Some(prompt) = transcribe::initial_prompt(config) {
+            form.field("prompt", prompt);
+        }
+        let padded = transcribe::padded(audio, config);
+        form.file("file", "audio.wav", "audio/wav", &encode_wav(&padded)?);
+
+        let mut request = self.agent.post(&self.url(config)).set(
+            "Content-Type",
+            &format!("multipart/form-data; boundary={BOUNDARY}"),
+        );
+        if !self.api_key.is_empty() {
+            request = request.set("Authorization", &format!("Bearer {}", self.api_key));
+        }
+        let response = match request.send_bytes(&form.finish()) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                anyhow::bail!("Transcription endpoint returned {code}: {}", body.trim());
+            }
+            Err(e) => return Err(e).context("Transcription endpoint unreachable"),
+        };
+        let response: ApiResponse = serde_json::from_reader(response.into_reader())
+            .context("Unexpected response from the transcription endpoint")?;
+        if config.aborted() {
+            return Err(Aborted.into());
+        }
+        let mut segments = segments(response, padded.len(), config);
+        transcribe::unpad(&mut segments, config.pad, audio.len());
+        Ok(segments)
+    }
+}
+
+fn segments(response: ApiResponse, len: usize, config: &TranscribeConfig) -> Vec<Segment> {
+    if response.segments.is_empty() {
+        return vec![Segment {
+            text: transcribe::kept_text(&response.text, 0.0, config),
+            start: 0,
+            end: len,
+            words: Vec::new(),
+            confidence: Vec::new(),
+        }];
+    }
+    let to_samples = |secs: f64| ((secs.max(0.0) * TARGET_SAMPLE_RATE as f64) as usize).min(len);
+    response
+        .segments
+        .into_iter()
+        .map(|segment| Segment {
+            text: transcribe::kept_text(&segment.text, segment.no_speech_prob, config),
+            start: to_samples(segment.start),
+            end: to_samples(segment.end),
+            words: Vec::new(),
+            confidence: Vec::new(),
+        })
+        .collect()
+}
+
+/// `audio` as a 16-bit mono WAV file, half the size of the f32 samples.
+fn encode_wav(audio: &[f32]) -> Result<Vec<u8>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: TARGET_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut wav = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut wav, spec)?;
+    for &sample in audio {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(wav.into_inner())
+}
+
+/// A `multipart/form-data` body delimited by `BOUNDARY`.
+#[derive(Default)]
+struct Form {
+    body: Vec<u8>,
+}
+
+impl Form {
+    fn field(&mut self, name: &str, value: &str) {
+        self.part(&format!("name=\"{name}\""), None, value.as_bytes());
+    }
+
+    fn file(&mut self, name: &str, filename: &str, content_type: &str, data: &[u8]) {
+        let disposition = format!("name=\"{name}\"; filename=\"{filename}\"");
+        self.part(&disposition, Some(content_type), data);
+    }
+
+    fn part(&mut self, disposition: &str, content_type: Option<&str>, data: &[u8]) {
+        let mut head = format!("--{BOUNDARY}\r\nContent-Disposition: form-data; {disposition}\r\n");
+        if let Some(content_type) = content_type {
+            head.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        head.push_str("\r\n");
+        self.body.extend_from_slice(head.as_bytes());
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\r\n");
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+        self.body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+
+    /// What the mock endpoint was sent.
+    struct Received {
+        url: String,
+        authorization: Option<String>,
+        content_type: String,
+        body: String,
+    }
+
+    /// A local endpoint answering one request with `status` and `body`;
+    /// its transcriptions URL, and what it received once joined.
+    fn serve_once(status: u16, body: &'static str) -> (String, JoinHandle<Received>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv(name))
+                    .map(|h| h.value.to_string())
+            };
+            let authorization = header("Authorization");
+            let content_type = header("Content-Type").unwrap_or_default();
+            let mut received = Vec::new();
+            request.as_reader().read_to_end(&mut received).unwrap();
+            let url = request.url().to_string();
+            let response = tiny_http::Response::from_string(body).with_status_code(status);
+            request.respond(response).unwrap();
+            Received {
+                url,
+                authorization,
+                content_type,
+                // The WAV data isn't UTF-8; the fields around it are.
+                body: String::from_utf8_lossy(&received).into_owned(),
+            }
+        });
+        (
+            format!("http://127.0.0.1:{port}/v1/audio/transcriptions"),
+            handle,
+        )
+    }
+
+    #[test]
+    fn transcription_posts_a_form_and_maps_the_segments() {
+        let (endpoint, server) = serve_once(
+            200,
+            r#"{"text": "Hello. Bye.", "segments": [
+                {"start": 0.0, "end": 0.5, "text": "Hello. [Music]", "no_speech_prob": 0.1},
+                {"start": 0.5, "end": 9.0, "text": "Bye.", "no_speech_prob": 0.9}
+            ]}"#,
+        );
+        let api = ApiTranscriber::new(&endpoint, " sk-test ", "whisper-1");
+        let config = TranscribeConfig {
+            language: "pl".to_string(),
+            initial_prompt: "Grafana".to_string(),
+            no_speech_threshold: 0.6,
+            ..transcribe::tests::config()
+        };
+        let segments = api
+            .transcribe_segments(&[0.0; 16_000], &config, None)
+            .unwrap();
+        let received = server.join().unwrap();
+
+        assert_eq!(received.url, "/v1/audio/transcriptions");
+        assert_eq!(received.authorization.as_deref(), Some("Bearer sk-test"));
+        assert_eq!(
+            received.content_type,
+            format!("multipart/form-data; boundary={BOUNDARY}")
+        );
+        for field in [
+            "name=\"model\"\r\n\r\nwhisper-1\r\n",
+            "name=\"response_format\"\r\n\r\nverbose_json\r\n",
+            "name=\"language\"\r\n\r\npl\r\n",
+            "name=\"prompt\"\r\n\r\nGrafana\r\n",
+            "name=\"file\"; filename=\"audio.wav\"\r\nContent-Type: audio/wav\r\n\r\nRIFF",
+        ] {
+            assert!(received.body.contains(field), "no {field:?} in the form");
+        }
+        assert!(received.body.ends_with(&format!("--{BOUNDARY}--\r\n")));
+
+        let spans: Vec<_> = segments.iter().map(|s| (s.start, s.end)).collect();
+        // Past the end of the audio is its end.
+        assert_eq!(spans, [(0, 8_000), (8_000, 16_000)]);
+        assert_eq!(segments[0].text.trim(), "Hello.");
+        // Over the no-speech threshold.
+        assert_eq!(segments[1].text, "");
+    }
+
+    #[test]
+    fn translation_goes_to_its_own_endpoint_without_a_language() {
+        let (endpoint, server) = serve_once(200, r#"{"text": "Good morning."}"#);
+        let api = ApiTranscriber::new(&endpoint, "", "whisper-1");
+        let config = TranscribeConfig {
+            language: "pl".to_string(),
+            translate: true,
+            ..transcribe::tests::config()
+        };
+        let segments = api
+            .transcribe_segments(&[0.0; 16_000], &config, None)
+            .unwrap();
+        let received = server.join().unwrap();
+
+        assert_eq!(received.url, "/v1/audio/translations");
+        assert_eq!(received.authorization, None);
+        assert!(!received.body.contains("name=\"language\""));
+        // Only `text`: one segment over all of it.
+        assert_eq!(segments.len(), 1);
+        assert_eq!((segments[0].start, segments[0].end), (0, 16_000));
+        assert_eq!(segments[0].text, "Good morning.");
+    }
+
+    #[test]
+    fn an_error_status_reports_the_body() {
+        let (endpoint, server) = serve_once(401, "Invalid API key\n");
+        let api = ApiTranscriber::new(&endpoint, "sk-wrong", "whisper-1");
+        let err = api
+            .transcribe(&[0.0; 16_000], &transcribe::tests::config())
+            .unwrap_err();
+        server.join().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Transcription endpoint returned 401: Invalid API key"
+        );
+    }
+
+    #[test]
+    fn abort_skips_the_request() {
+        // Nothing listens on the discard port: the call must not get there.
+        let api = ApiTranscriber::new(
+            "http://127.0.0.1:9/v1/audio/transcriptions",
+            "",
+            "whisper-1",
+        );
+        let config = TranscribeConfig {
+            abort: Some(Arc::new(AtomicBool::new(true))),
+            ..transcribe::tests::config()
+        };
+        let err = api.transcribe(&[0.0; 16_000], &config).unwrap_err();
+        assert!(err.is::<Aborted>(), "{err:#}");
+    }
+}
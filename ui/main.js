@@ -4,8 +4,92 @@ const { listen } = window.__TAURI__.event;
 const ring = document.getElementById("status-ring");
 const statusText = document.getElementById("status-text");
 const modelWarning = document.getElementById("model-warning");
-const langSelect = document.getElementById("lang-select");
-const modeSelect = document.getElementById("mode-select");
+const transcriptText = document.getElementById("transcript");
+const dependencyWarning = document.getElementById("dependency-warning");
+const recoveryNotice = document.getElementById("recovery-notice");
+
+// Settings form controls: element id → `Settings` field and value kind.
+const FIELDS = [
+  { id: "mode-select", key: "mode" },
+  { id: "sentence-delay", key: "sentence_delay_ms", kind: "int" },
+  { id: "output-select", key: "output_mode" },
+  { id: "lang-select", key: "language" },
+  { id: "type-delay", key: "type_delay_ms", kind: "int" },
+  { id: "type-below", key: "type_below_chars", kind: "int" },
+  { id: "paste-method", key: "default_paste_method" },
+  { id: "typing-retries", key: "typing_retries", kind: "int" },
+  { id: "trim-output", key: "trim_output", kind: "bool" },
+  { id: "append-space", key: "append_space", kind: "bool" },
+  { id: "max-output", key: "max_output_chars", kind: "int" },
+  { id: "continuation-space", key: "continuation_space", kind: "bool" },
+  { id: "continuation-idle", key: "continuation_idle_secs", kind: "int" },
+  { id: "prefix-template", key: "prefix_template" },
+  { id: "markdown-dir", key: "append_to_markdown" },
+  { id: "markdown-heading", key: "markdown_heading" },
+  { id: "auto-stop", key: "auto_stop_silence_ms", kind: "int" },
+  { id: "queued-toggle", key: "queued_toggle_timeout_ms", kind: "int" },
+  { id: "min-speech", key: "min_speech_ms", kind: "int" },
+  { id: "min-audio", key: "min_audio_ms", kind: "int" },
+  { id: "onset-speech", key: "onset_speech_ms", kind: "int" },
+  { id: "pause-reset", key: "pause_reset_ms", kind: "int" },
+  { id: "stream-window", key: "stream_window_secs", kind: "int" },
+  { id: "stream-overlap", key: "stream_overlap_ms", kind: "int" },
+  { id: "final-pass", key: "final_pass" },
+  { id: "adaptive-ticks", key: "adaptive_ticks", kind: "bool" },
+  { id: "final-chunk", key: "final_chunk_secs", kind: "int" },
+  { id: "n-threads", key: "n_threads", kind: "auto" },
+  { id: "gpu-device", key: "gpu_device", kind: "index" },
+  { id: "warm-on-load", key: "warm_on_load", kind: "bool" },
+  { id: "unload-idle", key: "unload_after_idle_mins", kind: "int" },
+  { id: "press-window", key: "double_press_window_ms", kind: "int" },
+  { id: "triple-select", key: "triple_press_action" },
+  { id: "hotkey-debug", key: "hotkey_debug" },
+  { id: "input-gain", key: "input_gain", kind: "float" },
+  { id: "loudness-gate", key: "loudness_gate", kind: "float" },
+  { id: "clipping-warning", key: "clipping_warning_percent", kind: "float" },
+  { id: "auto-gain", key: "auto_gain", kind: "bool" },
+  { id: "denoise", key: "denoise", kind: "bool" },
+  { id: "pad-ms", key: "pad_ms", kind: "int" },
+  { id: "lock-target", key: "lock_target_window", kind: "bool" },
+  { id: "own-window", key: "own_window" },
+  { id: "confirm-before-type", key: "confirm_before_type", kind: "bool" },
+  { id: "confirm-timeout", key: "confirm_timeout_secs", kind: "int" },
+  { id: "confirm-timeout-action", key: "confirm_timeout_action" },
+  { id: "finish-on-quit", key: "finish_on_quit", kind: "bool" },
+  { id: "highpass", key: "highpass", kind: "bool" },
+  { id: "monitor-input", key: "monitor_input", kind: "bool" },
+  { id: "monitor-volume", key: "monitor_volume", kind: "float" },
+  { id: "resample-quality", key: "resample_quality" },
+  { id: "input-device", key: "input_device" },
+  { id: "input-channel", key: "input_channel", kind: "channel" },
+  { id: "preroll", key: "preroll_ms", kind: "int" },
+  { id: "feedback-sound", key: "feedback_sound", kind: "bool" },
+  { id: "overlay", key: "overlay", kind: "bool" },
+  { id: "window-behavior", key: "window_behavior" },
+  { id: "recording-indicator", key: "recording_indicator" },
+  { id: "post-process", key: "post_process", kind: "bool" },
+  { id: "format-numbers", key: "format_numbers", kind: "bool" },
+  { id: "word-timestamps", key: "word_timestamps", kind: "bool" },
+  { id: "initial-prompt", key: "initial_prompt" },
+  { id: "prompt-source", key: "prompt_source" },
+  { id: "log-level", key: "log_level" },
+  { id: "ui-language", key: "ui_language" },
+  { id: "backend-select", key: "backend" },
+  { id: "api-url", key: "api_url" },
+  { id: "api-key", key: "api_key" },
+  { id: "api-model", key: "api_model" },
+  { id: "quality-select", key: "quality_preset" },
+  { id: "sampling-select", key: "sampling", kind: "sampling" },
+  { id: "single-segment", key: "single_segment", kind: "bool" },
+  { id: "use-context", key: "use_context", kind: "bool" },
+  { id: "commit-confidence", key: "commit_confidence", kind: "float" },
+  { id: "commit-granularity", key: "commit_granularity" },
+  { id: "commit-interval", key: "commit_interval_ms", kind: "int" },
+  { id: "http-enabled", key: "http_enabled", kind: "bool" },
+  { id: "http-port", key: "http_port", kind: "int" },
+  { id: "http-token", key: "http_token" },
+  { id: "pipe-output", key: "pipe_output" },
+];
 
 const STATUS_MAP = {
   idle: { class: "idle", text: "Gotowy" },
@@ -19,42 +103,587 @@ function updateUI(status) {
   statusText.textContent = s.text;
 }
 
+function showMissingTools(missing) {
+  document.getElementById("missing-tools").textContent = missing.join(", ");
+  dependencyWarning.classList.toggle("hidden", missing.length === 0);
+}
+
+function readField(el, kind) {
+  if (kind === "bool") return el.checked;
+  if (kind === "int") return Math.max(0, parseInt(el.value, 10) || 0);
+  // 0 stands for `null` (automatic).
+  if (kind === "auto") return Math.max(0, parseInt(el.value, 10) || 0) || null;
+  if (kind === "float") return parseFloat(el.value) || 0;
+  // Empty stands for `null` (the default); 0 is a valid index.
+  if (kind === "index") return el.value === "" ? null : Math.max(0, parseInt(el.value, 10) || 0);
+  if (kind === "channel") {
+    return el.value === "mono" ? "mono" : { channel: parseInt(el.value, 10) };
+  }
+  if (kind === "sampling") {
+    // best_of or beam_size, per strategy.
+    const size = Math.max(1, parseInt(document.getElementById("sampling-size").value, 10) || 1);
+    return el.value === "beam_search"
+      ? { strategy: "beam_search", beam_size: size }
+      : { strategy: "greedy", best_of: size };
+  }
+  return el.value;
+}
+
+function writeField(el, kind, value) {
+  if (kind === "bool") el.checked = value;
+  else if (kind === "sampling") {
+    el.value = value.strategy;
+    document.getElementById("sampling-size").value = value.best_of ?? value.beam_size;
+  }
+  else if (kind === "channel") el.value = value === "mono" ? "mono" : String(value.channel);
+  else if (kind === "auto") el.value = value ?? 0;
+  else if (kind === "index") el.value = value ?? "";
+  else el.value = value;
+}
+
+async function showAudioInfo() {
+  const audioInfo = document.getElementById("audio-info");
+  try {
+    const info = await invoke("get_audio_info");
+    audioInfo.textContent =
+      `Mikrofon: ${info.name}, ${info.sample_rate} Hz, ` +
+      `kanały: ${info.channels}, format: ${info.sample_format}`;
+  } catch (e) {
+    audioInfo.textContent = `Mikrofon: ${e}`;
+  }
+}
+
+const HEALTH_LABELS = {
+  audio_device: "Mikrofon",
+  model: "Plik modelu",
+  transcription: "Transkrypcja",
+  typing: "Wpisywanie tekstu",
+  hotkey: "Skrót klawiszowy",
+};
+
+const BENCHMARK_SOURCES = {
+  last_recording: "Ostatnie nagranie",
+  speech_sample: "Próbka mowy",
+  synthetic: "Nagranie syntetyczne",
+};
+
+async function showHealthReport() {
+  const report = await invoke("health_check");
+  const list = document.getElementById("health-report");
+  list.replaceChildren(
+    ...report.items.map(({ check, ok, detail }) => {
+      const item = document.createElement("li");
+      item.className = ok ? "ok" : "fail";
+      item.textContent = `${ok ? "✔" : "✘"} ${HEALTH_LABELS[check]}: ${detail}`;
+      return item;
+    }),
+  );
+  list.classList.remove("hidden");
+}
+
 async function saveSettings() {
   const settings = await invoke("get_settings");
-  settings.language = langSelect.value;
-  settings.mode = modeSelect.value;
+  for (const { id, key, kind } of FIELDS) {
+    settings[key] = readField(document.getElementById(id), kind);
+  }
   try {
     await invoke("update_settings", { settings });
+    // The input device may have changed.
+    await showAudioInfo();
   } catch (e) {
     console.error("Settings error:", e);
   }
 }
 
 async function init() {
-  const hasModel = await invoke("check_model");
-  if (!hasModel) {
-    modelWarning.classList.remove("hidden");
+  const model = await invoke("validate_model");
+  if (model.status === "invalid") {
+    document.getElementById("model-problem").textContent =
+      `Plik modelu jest uszkodzony (${model.reason}). Pobierz go ponownie:`;
   }
+  modelWarning.classList.toggle("hidden", model.status === "valid");
 
-  const settings = await invoke("get_settings");
-  langSelect.value = settings.language;
-  modeSelect.value = settings.mode;
+  await listen("missing-dependency", (event) => showMissingTools(event.payload));
+  showMissingTools(await invoke("check_dependencies"));
+  document.getElementById("recheck-deps").addEventListener("click", async () => {
+    showMissingTools(await invoke("check_dependencies"));
+  });
 
-  const status = await invoke("get_status");
-  updateUI(status);
+  const inputDevice = document.getElementById("input-device");
+  try {
+    const devices = await invoke("list_input_devices");
+    inputDevice.append(
+      ...devices.map(
+        ({ name, loopback }) => new Option(loopback ? `${name} (dźwięk systemu)` : name, name),
+      ),
+    );
+    if (!devices.some((device) => device.loopback)) {
+      inputDevice.title = "Brak urządzenia do nagrywania dźwięku systemu";
+    }
+  } catch (e) {
+    console.error(e);
+  }
+  await showAudioInfo();
+
+  const showOrphan = (secs) => {
+    recoveryNotice.classList.toggle("hidden", secs == null);
+    if (secs != null) {
+      document.getElementById("orphan-secs").textContent = secs.toFixed(0);
+    }
+  };
+  await listen("orphaned-recording", (event) => showOrphan(event.payload));
+  showOrphan(await invoke("get_orphaned_recording"));
+  document.getElementById("recover").addEventListener("click", async () => {
+    try {
+      const text = await invoke("recover_recording");
+      if (text != null) {
+        transcriptText.textContent = text;
+      }
+      showOrphan(null);
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  document.getElementById("discard-recovery").addEventListener("click", async () => {
+    await invoke("discard_recording");
+    showOrphan(null);
+  });
+
+  const confirmNotice = document.getElementById("confirm-notice");
+  const showPending = (text) => {
+    confirmNotice.classList.toggle("hidden", text == null);
+    document.getElementById("pending-text").textContent = text ?? "";
+  };
+  await listen("transcript-pending", (event) => showPending(event.payload.text));
+  await listen("transcript-confirmed", (event) => {
+    showPending(null);
+    statusText.textContent = event.payload ? "Wpisywanie zatwierdzone" : "Tekst odrzucony";
+  });
+  for (const [id, command] of [["confirm-type", "confirm_type"], ["reject-type", "reject_type"]]) {
+    document.getElementById(id).addEventListener("click", async () => {
+      try {
+        await invoke(command);
+      } catch (e) {
+        // Answered by the timeout already.
+        showPending(null);
+        console.error(e);
+      }
+    });
+  }
+
+  const paused = document.getElementById("paused");
+  paused.checked = !(await invoke("get_enabled"));
+  await listen("enabled-changed", (event) => (paused.checked = !event.payload));
+  paused.addEventListener("change", async () => {
+    try {
+      await invoke("set_enabled", { enabled: !paused.checked });
+    } catch (e) {
+      console.error(e);
+    }
+  });
+
+  const autostart = document.getElementById("autostart");
+  autostart.checked = await invoke("is_autostart_enabled").catch(() => false);
+  autostart.addEventListener("change", async () => {
+    try {
+      const command = autostart.checked ? "enable_autostart" : "disable_autostart";
+      autostart.checked = await invoke(command);
+    } catch (e) {
+      autostart.checked = !autostart.checked;
+      console.error(e);
+    }
+  });
+
+  const showOnLaunch = document.getElementById("show-on-launch");
+  showOnLaunch.checked = await invoke("get_show_on_launch");
+  showOnLaunch.addEventListener("change", async () => {
+    try {
+      await invoke("set_show_on_launch", { show: showOnLaunch.checked });
+    } catch (e) {
+      console.error(e);
+    }
+  });
+
+  const languages = await invoke("list_languages");
+  languages.sort((a, b) => a.name.localeCompare(b.name));
+  document
+    .getElementById("lang-select")
+    .append(...languages.map(({ code, name }) => new Option(`${name} (${code})`, code)));
+  await listen("language-unknown", async (event) => {
+    statusText.textContent = `Nieznany język „${event.payload}” — używam automatycznego wykrywania`;
+    showSettings(await invoke("get_settings"));
+  });
+
+  const modelSelect = document.getElementById("model-select");
+  const showModels = (settings) => {
+    const names = Object.keys(settings.models);
+    document.getElementById("model-choice").classList.toggle("hidden", names.length === 0);
+    modelSelect.replaceChildren(...names.map((name) => new Option(name, name)));
+    modelSelect.value = names.find((name) => settings.models[name] === settings.model_path) ?? "";
+  };
+  modelSelect.addEventListener("change", async () => {
+    try {
+      await invoke("set_active_model", { name: modelSelect.value });
+    } catch (e) {
+      console.error(e);
+    }
+  });
+  const modelFileSelect = document.getElementById("model-file-select");
+  const showModelFiles = async (settings) => {
+    let models = [];
+    try {
+      models = await invoke("list_available_models");
+    } catch (e) {
+      console.error(e);
+    }
+    document.getElementById("model-file-choice").classList.toggle("hidden", models.length === 0);
+    const describe = ({ name, size, multilingual, quantization, bytes }) => {
+      const details = [size ?? "?", multilingual ? "wielojęzyczny" : "tylko angielski"];
+      if (quantization) details.push(quantization);
+      details.push(`${Math.round(bytes / 1e6)} MB`);
+      return `${name} (${details.join(", ")})`;
+    };
+    modelFileSelect.replaceChildren(
+      ...models.map((model) => new Option(describe(model), model.path)),
+    );
+    modelFileSelect.value = models.find((model) => model.path === settings.model_path)?.path ?? "";
+  };
+  modelFileSelect.addEventListener("change", async () => {
+    try {
+      await invoke("set_model_path", { path: modelFileSelect.value });
+    } catch (e) {
+      statusText.textContent = `Błąd: ${e}`;
+    }
+  });
+  const scratchpad = document.getElementById("scratchpad");
+  await listen("own-window-focused", (event) => {
+    const { policy, text } = event.payload;
+    if (policy === "scratchpad") {
+      if (text) {
+        scratchpad.value += text;
+        scratchpad.classList.remove("hidden");
+      }
+    } else {
+      statusText.textContent = "Nie wpisano: aktywne jest okno aplikacji";
+    }
+  });
+  await listen("falling-behind", (event) => {
+    const { tick_ms, interval_ms } = event.payload;
+    statusText.textContent =
+      `Transkrypcja nie nadąża (${tick_ms} ms na ${interval_ms} ms) — wybierz mniejszy model`;
+  });
+  await listen("too-short", (event) => {
+    const { reason, duration_ms, minimum_ms } = event.payload;
+    statusText.textContent =
+      reason === "length"
+        ? `Nagranie za krótkie (${duration_ms} ms, minimum ${minimum_ms} ms) — pominięto`
+        : `Za mało mowy (${duration_ms} ms, minimum ${minimum_ms} ms) — pominięto`;
+  });
+  await listen("clipping-detected", (event) => {
+    const percent = event.payload.percent.toFixed(1);
+    statusText.textContent = `Przesterowane wejście (${percent}% próbek) — zmniejsz głośność mikrofonu`;
+  });
+  await listen("runaway-output", (event) => {
+    const { chars, max_chars, truncated, repeated_phrase, repeats } = event.payload;
+    statusText.textContent = repeated_phrase
+      ? `Transkrypcja się zapętliła („${repeated_phrase}” ×${repeats})`
+      : `Transkrypcja za długa (${chars} znaków)`;
+    if (truncated) statusText.textContent += ` — obcięto do ${max_chars} znaków`;
+  });
+  await listen("model-loading", async (event) => {
+    if (event.payload) {
+      statusText.textContent = "Ładowanie modelu…";
+    } else {
+      updateUI(await invoke("get_status"));
+    }
+  });
+  await listen("model-loaded", (event) => {
+    if (!event.payload.cached) statusText.textContent = "Model wczytany";
+  });
+  await listen("model-load-failed", (event) => {
+    statusText.textContent = `Nie udało się wczytać modelu: ${event.payload.error}`;
+  });
+  await listen("model-switched", (event) => {
+    statusText.textContent = `Model: ${event.payload.name ?? event.payload.path}`;
+    setTimeout(async () => updateUI(await invoke("get_status")), 2000);
+  });
 
+  const showSettings = (settings) => {
+    for (const { id, key, kind } of FIELDS) {
+      writeField(document.getElementById(id), kind, settings[key]);
+    }
+    showModels(settings);
+    showModelFiles(settings);
+  };
+  await listen("settings-changed", (event) => showSettings(event.payload));
+  await listen("settings-reset", (event) => {
+    showSettings(event.payload);
+    statusText.textContent = "Przywrócono ustawienia domyślne";
+  });
+  await listen("settings-imported", (event) => {
+    showSettings(event.payload);
+    statusText.textContent = "Zaimportowano ustawienia";
+  });
   await listen("status-changed", (event) => updateUI(event.payload));
+
+  // Fetched after the listeners are up, so no change falls in between.
+  const state = await invoke("sync_state");
+  showSettings(state.settings);
+  updateUI(state.status);
+  if (state.model_loading) {
+    statusText.textContent = "Ładowanie modelu…";
+  } else if (state.status === "idle" && !state.model_loaded) {
+    statusText.textContent = "Gotowy (model załaduje się przy nagrywaniu)";
+  }
+  if (state.last_error) {
+    console.error(`[${state.last_error.code}] ${state.last_error.message}`);
+  }
+  showPending(state.pending_transcript);
+  await listen("transcribe-progress", (event) => {
+    statusText.textContent = `Transkrypcja... ${event.payload}%`;
+  });
+  await listen("transcript", (event) => {
+    transcriptText.textContent = event.payload.text;
+    transcriptText.classList.toggle("hidden", !event.payload.text);
+  });
+  // Committed text plus whisper's tentative continuation, shown dimmed.
+  await listen("partial-transcript", (event) => {
+    const { committed, tentative } = event.payload;
+    const pending = document.createElement("span");
+    pending.className = "tentative";
+    pending.textContent = tentative;
+    transcriptText.replaceChildren(committed, pending);
+    transcriptText.classList.toggle("hidden", !committed && !tentative);
+  });
+  await listen("gpu-device-unavailable", (event) => {
+    statusText.textContent = `Karta GPU ${event.payload} niedostępna — użyto domyślnej`;
+  });
+  await listen("model-language-unsupported", (event) => {
+    const { language, model_type } = event.payload;
+    statusText.textContent =
+      `Model ${model_type} zna tylko angielski — język „${language}” wymaga modelu wielojęzycznego`;
+  });
+  await listen("toggle-queued", (event) => {
+    statusText.textContent =
+      event.payload === "loading"
+        ? "Nagrywanie zacznie się po załadowaniu modelu"
+        : "Nagrywanie zacznie się po transkrypcji";
+  });
+  await listen("transcription-aborted", () => {
+    statusText.textContent = "Transkrypcja przerwana";
+  });
+  await listen("hotkey-failed", () => {
+    statusText.textContent = "Skrót klawiszowy przestał działać — uruchom aplikację ponownie";
+  });
+  const hotkeyDebugLog = document.getElementById("hotkey-debug-log");
+  await listen("hotkey-debug", (event) => {
+    const { at_ms, input, decisions } = event.payload;
+    const steps = decisions.map((d) => {
+      if (d.kind === "counted") {
+        const gap = d.gap_ms === null ? "" : ` po ${d.gap_ms} ms (okno ${d.window_ms} ms)`;
+        const held = d.held ? d.held.map((k) => `${k}+`).join("") : "";
+        return `${held}${d.key} ×${d.presses}${gap}`;
+      }
+      if (d.kind === "waiting") return `${d.key}: czeka na trzecie`;
+      if (d.kind === "fired") return `${d.key} ×${d.presses} → ${d.action}`;
+      return `rozbrojone: ${d.keys.join(", ")}`;
+    });
+    // The last 20 steps, newest last.
+    const lines = hotkeyDebugLog.textContent.split("\n").filter(Boolean);
+    lines.push(`${at_ms} ms ${input}${steps.length ? ": " + steps.join("; ") : ""}`);
+    hotkeyDebugLog.textContent = lines.slice(-20).join("\n");
+    hotkeyDebugLog.classList.remove("hidden");
+  });
+  await listen("language-cycled", (event) => {
+    statusText.textContent = `Język: ${event.payload}`;
+  });
+  await listen("language-override", (event) => {
+    statusText.textContent = `Język tego nagrania: ${event.payload}`;
+  });
+  await listen("input-switched", (event) => {
+    const { name, sample_rate } = event.payload;
+    statusText.textContent = `Mikrofon zmieniony — nagrywanie trwa dalej: ${name} (${sample_rate} Hz)`;
+  });
+  await listen("permission-required", () => {
+    statusText.textContent = "Brak uprawnień Dostępności (macOS)";
+  });
   await listen("error", (event) => {
-    statusText.textContent = event.payload;
+    const { code, summary, message } = event.payload;
+    // The summary comes in `ui_language`; the raw detail goes to the console.
+    console.error(`[${code}] ${message}`);
+    statusText.textContent = summary;
     setTimeout(() => updateUI("idle"), 3000);
   });
 
+  // While transcribing, a click aborts instead (toggling is ignored then).
   ring.addEventListener("click", async () => {
-    try { await invoke("toggle_recording"); } catch (e) { console.error(e); }
+    try {
+      const transcribing = (await invoke("get_status")) === "transcribing";
+      await invoke(transcribing ? "cancel_recording" : "toggle_recording");
+    } catch (e) {
+      console.error(e);
+    }
   });
 
-  langSelect.addEventListener("change", saveSettings);
-  modeSelect.addEventListener("change", saveSettings);
+  // The checklist shows by itself on the first run only.
+  if (!localStorage.getItem("healthChecked")) {
+    await showHealthReport();
+    localStorage.setItem("healthChecked", "1");
+  }
+  document.getElementById("health-check").addEventListener("click", showHealthReport);
+
+  const typingReport = document.getElementById("typing-report");
+  await listen("typing-test-countdown", (event) => {
+    statusText.textContent = event.payload > 0
+      ? `Ustaw kursor w polu tekstowym… ${event.payload}`
+      : "Wpisywanie testu...";
+  });
+  document.getElementById("test-typing").addEventListener("click", async () => {
+    try {
+      const report = await invoke("test_typing", { sample: "Test voice-to-text ąęł" });
+      typingReport.textContent = [report.backend]
+        .concat(report.steps.map((s) => `${s.ok ? "✔" : "✘"} ${s.step}: ${s.detail}`))
+        .join("\n");
+      typingReport.classList.remove("hidden");
+    } catch (e) {
+      console.error(e);
+    }
+    updateUI(await invoke("get_status"));
+  });
+
+  document.getElementById("play-last").addEventListener("click", async () => {
+    try {
+      const secs = await invoke("play_last_recording");
+      statusText.textContent = `Odtwarzanie (${secs.toFixed(1)} s)`;
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  document.getElementById("reload-model").addEventListener("click", async () => {
+    try {
+      await invoke("preload_model", { reload: true });
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  document.getElementById("reset-settings").addEventListener("click", async () => {
+    try {
+      await invoke("reset_settings");
+      await showAudioInfo();
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  await listen("benchmark-progress", (event) => {
+    statusText.textContent = `Test szybkości ${event.payload.done}/${event.payload.runs}...`;
+  });
+  document.getElementById("benchmark").addEventListener("click", async () => {
+    try {
+      const r = await invoke("benchmark_model");
+      typingReport.textContent = [
+        `${BENCHMARK_SOURCES[r.source] ?? r.source}: ${r.audio_secs.toFixed(1)} s`,
+        `min / mediana / max: ${r.min_ms} / ${r.median_ms} / ${r.max_ms} ms`,
+        `RTF: ${r.realtime_factor.toFixed(2)} — ${r.keeps_up ? "nadąża" : "nie nadąża"} za streamingiem`,
+        `GPU: ${r.gpu_requested ? "tak" : "nie"}`,
+        r.system_info,
+      ].join("\n");
+      typingReport.classList.remove("hidden");
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+    updateUI(await invoke("get_status"));
+  });
+
+  document.getElementById("export-settings").addEventListener("click", async () => {
+    const path = document.getElementById("settings-path").value.trim();
+    if (!path) return;
+    try {
+      await invoke("export_settings", { path });
+      statusText.textContent = "Wyeksportowano ustawienia";
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  document.getElementById("import-settings").addEventListener("click", async () => {
+    const path = document.getElementById("settings-path").value.trim();
+    if (!path) return;
+    try {
+      await invoke("import_settings", { path });
+      await showAudioInfo();
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+
+  const sessionStatus = document.getElementById("session-status");
+  const showSession = (session) => {
+    sessionStatus.classList.toggle("hidden", !session);
+    if (session) {
+      sessionStatus.textContent = `Sesja dyktowania: ${session.recordings} nagrań, ${session.text.length} znaków`;
+    }
+  };
+  showSession(await invoke("get_session"));
+  await listen("session-changed", (event) => showSession(event.payload));
+  document.getElementById("begin-session").addEventListener("click", async () => {
+    try {
+      await invoke("begin_session");
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  document.getElementById("end-session").addEventListener("click", async () => {
+    const path = document.getElementById("session-path").value.trim();
+    try {
+      const session = await invoke("end_session", { path: path || null });
+      transcriptText.textContent = session.text;
+      statusText.textContent = `Sesja zakończona (${session.recordings} nagrań)`;
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+
+  document.getElementById("transcribe-file").addEventListener("click", async () => {
+    const path = document.getElementById("wav-path").value.trim();
+    if (!path) return;
+    try {
+      transcriptText.textContent = await invoke("transcribe_file", { path });
+      transcriptText.classList.remove("hidden");
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+
+  document.getElementById("open-logs").addEventListener("click", async () => {
+    try {
+      await invoke("open_log_dir");
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+  document.getElementById("show-logs").addEventListener("click", async () => {
+    try {
+      typingReport.textContent = await invoke("get_recent_logs", { lines: 100 });
+      typingReport.classList.remove("hidden");
+    } catch (e) {
+      statusText.textContent = e;
+      console.error(e);
+    }
+  });
+
+  for (const { id } of FIELDS) {
+    document.getElementById(id).addEventListener("change", saveSettings);
+  }
 }
 
 init();
@@ -0,0 +1,52 @@
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+const overlay = document.getElementById("overlay");
+const statusText = document.getElementById("overlay-status");
+const text = document.getElementById("overlay-text");
+
+// Roughly two lines; older text is cut so the newest words stay visible.
+const MAX_CHARS = 120;
+
+const STATUS_TEXT = {
+  recording: "Nagrywanie...",
+  transcribing: "Transkrypcja...",
+};
+
+function tail(s) {
+  return s.length > MAX_CHARS ? "…" + s.slice(-MAX_CHARS) : s;
+}
+
+function showStatus(status) {
+  overlay.className = status;
+  statusText.textContent = STATUS_TEXT[status] ?? "";
+}
+
+async function init() {
+  // The window is created on the first status change, so that event may
+  // have come and gone before this page loaded.
+  showStatus(await invoke("get_status"));
+  await listen("status-changed", (event) => {
+    showStatus(event.payload);
+    // A new recording starts with an empty transcript.
+    if (event.payload === "recording") text.replaceChildren();
+  });
+  await listen("transcribe-progress", (event) => {
+    statusText.textContent = `Transkrypcja... ${event.payload}%`;
+  });
+  // Committed text solid, the still changing rest dimmed.
+  await listen("caption-update", (event) => {
+    const { committed, tentative } = event.payload;
+    const pending = document.createElement("span");
+    pending.className = "tentative";
+    const shown = tail(committed + tentative);
+    const solid = shown.slice(0, Math.max(0, shown.length - tentative.length));
+    pending.textContent = shown.slice(solid.length);
+    text.replaceChildren(solid, pending);
+  });
+  await listen("transcript", (event) => {
+    text.textContent = tail(event.payload.text);
+  });
+}
+
+init();